@@ -0,0 +1,34 @@
+//! Renders a small world without opening a window, and saves the image.
+//!
+//! `cargo run --example offscreen [-- <path.png>]`
+
+use doom::{render_to_buffer, RenderMethod};
+use doom::png_saver;
+use doom::prelude::*;
+
+fn main() {
+    let path = std::env::args().nth(1).unwrap_or("offscreen.png".to_string());
+
+    // A few cubes on a floor of stone
+    let stone = TextureHandle::new(Pixelated::stone());
+    let mut world = WorldBuilder::new()
+        .floor(-2..6, -3..4, 1, &stone)
+        .cube(Vector3::newi(2, 0, 0), &YELLOW)
+        .cube(Vector3::newi(3, 1, 0), &ORANGE)
+        .cube(Vector3::newi(3, 1, -1), &YELLOW)
+        .sky(GradientSky::day())
+        .build();
+
+    // The frame can have any size
+    let (width, height) = (640, 360);
+    let mut camera = Camera::default();
+    let mut pose = Pose::new(Vector3::new(-1., 0.5, -1.5), 0.);
+    pose.apply_pitch(-0.3);
+    camera.set_pose(pose);
+    let buffer = render_to_buffer(&mut world, &camera, width, height, RenderMethod::Painter);
+
+    match png_saver::save_frame(&path, &buffer, width, height) {
+        Ok(()) => println!("Image written to {path}"),
+        Err(err) => println!("Could not save the image: {err}"),
+    }
+}
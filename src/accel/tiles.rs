@@ -0,0 +1,131 @@
+use crate::primitives::aabb::Aabb;
+use crate::primitives::cubic_face2::CubicFace2;
+use crate::primitives::projective_coordinates::ProjectionCoordinates;
+use crate::primitives::ray::Ray;
+use crate::primitives::vector::Vector3;
+use crate::tunables::tunables;
+use crate::viewport::Viewport;
+
+/// Side of the tiles, in pixels
+pub const TILE_SIZE: u32 = 16;
+
+/// The face hit by a ray
+pub struct TileHit {
+    /// Index of the face, in the faces given to build the tiles
+    pub index: usize,
+    /// Distance from the origin of the ray, in meters
+    pub distance: f32,
+    pub projection: ProjectionCoordinates,
+}
+
+/// The projected faces bucketed into square tiles of the screen: the ray of a pixel only needs to
+/// be intersected with the faces overlapping its tile.
+///
+/// The faces of each tile are sorted by their smallest possible distance to the camera, so that
+/// the search stops at the first face which cannot be closer than the face already hit.
+pub struct ScreenTiles<'a> {
+    faces: Vec<CubicFace2<'a>>,
+    columns: u32,
+    rows: u32,
+    /// For each tile, the indices of the faces overlapping it and their smallest distance to the
+    /// camera, sorted by distance
+    tiles: Vec<Vec<(usize, f32)>>,
+}
+
+impl<'a> ScreenTiles<'a> {
+    /// Buckets the faces projected on the viewport by a camera at `position`
+    pub fn new(faces: Vec<CubicFace2<'a>>, viewport: Viewport, position: &Vector3) -> Self {
+        let columns = viewport.width.div_ceil(TILE_SIZE).max(1);
+        let rows = viewport.height.div_ceil(TILE_SIZE).max(1);
+        let mut tiles = vec![Vec::new(); (columns * rows) as usize];
+        for (index, face) in faces.iter().enumerate() {
+            let Some(face3) = face.face3().filter(|_| !face.points().is_empty()) else {
+                continue;
+            };
+            let near = Aabb::from_points(&face3.points()).unwrap().closest_point(position).line_to(position).norm();
+            let (xmin, ymin, xmax, ymax) = face.bounding_box();
+            for row in ymin / TILE_SIZE..=(ymax / TILE_SIZE).min(rows - 1) {
+                for column in xmin / TILE_SIZE..=(xmax / TILE_SIZE).min(columns - 1) {
+                    tiles[(row * columns + column) as usize].push((index, near));
+                }
+            }
+        }
+        for tile in &mut tiles {
+            tile.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        }
+        Self { faces, columns, rows, tiles }
+    }
+
+    pub fn face(&self, index: usize) -> &CubicFace2<'a> {
+        &self.faces[index]
+    }
+
+    /// Returns the closest face hit by the ray going through the pixel (x, y)
+    pub fn closest_hit(&self, ray: &Ray, x: u32, y: u32) -> Option<TileHit> {
+        let (column, row) = ((x / TILE_SIZE).min(self.columns - 1), (y / TILE_SIZE).min(self.rows - 1));
+        let scale = tunables().distance_scale;
+        let mut best: Option<TileHit> = None;
+        for &(index, near) in &self.tiles[(row * self.columns + column) as usize] {
+            // The next faces are all behind the face which was hit
+            if best.as_ref().is_some_and(|hit| hit.distance <= near) {
+                break;
+            }
+            let face = self.faces[index].face3().expect("The faces of the tiles come from 3D faces");
+            let Some((distance, projection)) = face.line_projection(&ray.origin, &ray.direction) else {
+                continue;
+            };
+            let distance = distance as f32 / scale;
+            if best.as_ref().is_none_or(|hit| distance < hit.distance) && face.contains_projection(&projection) {
+                best = Some(TileHit { index, distance, projection });
+            }
+        }
+        best
+    }
+
+    /// Returns the number of faces overlapping the tile of the pixel (x, y)
+    pub fn faces_at(&self, x: u32, y: u32) -> usize {
+        let (column, row) = ((x / TILE_SIZE).min(self.columns - 1), (y / TILE_SIZE).min(self.rows - 1));
+        self.tiles[(row * self.columns + column) as usize].len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::accel::tiles::ScreenTiles;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::cubic_face2::CubicFace2;
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::object::Object;
+    use crate::primitives::ray::Ray;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_same_hits_as_all_faces() {
+        let camera = Camera::default();
+        let cubes: Vec<Cube3> = (0..40)
+            .map(|i| Cube3::minecraft_like(Vector3::newi(2 + i % 5, i % 7 - 3, i % 3 - 1), &YELLOW, &YELLOW))
+            .collect();
+        let faces3: Vec<&CubicFace3> = cubes.iter().flat_map(|cube| cube.get_visible_faces(&camera)).collect();
+        let faces: Vec<CubicFace2> = faces3.iter().map(|face| face.projection(&camera)).collect();
+        let count = faces.len();
+        let tiles = ScreenTiles::new(faces, camera.viewport(), camera.pose().position());
+
+        let (origin, du, dv) = camera.ray_direction_derivatives();
+        let viewport = camera.viewport();
+        for y in (0..viewport.height).step_by(7) {
+            for x in (0..viewport.width).step_by(7) {
+                let ray = Ray::new(*camera.pose().position(), origin + du * x as f32 + dv * y as f32);
+                let expected = faces3
+                    .iter()
+                    .filter_map(|face| face.line_projection(&ray.origin, &ray.direction).filter(|(_, p)| face.contains_projection(p)))
+                    .map(|(distance, _)| distance as f32 / 1000.)
+                    .min_by(f32::total_cmp);
+                assert_eq!(expected, tiles.closest_hit(&ray, x, y).map(|hit| hit.distance), "Pixel ({x}, {y})");
+            }
+        }
+        // The corners of the screen only see a part of the faces
+        assert!(tiles.faces_at(0, 0) < count);
+    }
+}
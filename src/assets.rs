@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+
+use crate::primitives::color::Color;
+use crate::primitives::textures::image::ImageTexture;
+use crate::primitives::textures::pixelated::Pixelated;
+use crate::primitives::textures::{Filtering, Texture, TextureHandle, WeakTextureHandle};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// An asset being loaded in the background. The handle can be cloned and resolves once the
+/// asset is loaded.
+pub struct Asset<T> {
+    slot: Arc<OnceLock<Result<T, String>>>,
+}
+
+impl<T> Clone for Asset<T> {
+    fn clone(&self) -> Self {
+        Self { slot: self.slot.clone() }
+    }
+}
+
+impl<T> Asset<T> {
+    /// Returns the asset if it is loaded
+    pub fn get(&self) -> Option<&T> {
+        self.slot.get().and_then(|result| result.as_ref().ok())
+    }
+}
+
+/// Loads assets from files on background threads, so that the game does not wait for them.
+///
+/// Textures are shared: loading the same file again returns the same texture, as long as it is
+/// still used. The loader does not keep them alive, so they are released as soon as no face
+/// uses them anymore (for instance when switching to another scene).
+///
+/// The threads stop once the loader is dropped and all the requested assets are loaded.
+pub struct AssetLoader {
+    jobs: Sender<Job>,
+    workers: Vec<JoinHandle<()>>,
+    textures: Mutex<HashMap<String, WeakTextureHandle>>,
+}
+
+impl AssetLoader {
+    pub fn new(threads: usize) -> Self {
+        let (jobs, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::spawn(move || Self::work(&receiver))
+            })
+            .collect();
+        Self {
+            jobs,
+            workers,
+            textures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn work(receiver: &Mutex<Receiver<Job>>) {
+        loop {
+            // The lock is released before running the job, so that the workers run in parallel
+            let job = receiver.lock().unwrap().recv();
+            match job {
+                Ok(job) => job(),
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// Waits until all the requested assets are loaded (or failed to load)
+    pub fn finish(self) {
+        drop(self.jobs);
+        for worker in self.workers {
+            worker.join().expect("An asset worker panicked");
+        }
+    }
+
+    /// Reads the file and parses it in the background
+    pub fn load<T, F>(&self, path: &str, parse: F) -> Asset<T>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&str) -> Result<T, String> + Send + 'static,
+    {
+        let asset = Asset { slot: Arc::new(OnceLock::new()) };
+        let slot = asset.slot.clone();
+        let path = path.to_string();
+        let job = move || {
+            let result = std::fs::read_to_string(&path).map_err(|err| err.to_string()).and_then(|text| parse(&text));
+            if let Err(err) = &result {
+                println!("Could not load {path}: {err}");
+            }
+            let _ = slot.set(result);
+        };
+        self.jobs.send(Box::new(job)).expect("The asset workers are running as long as the loader exists");
+        asset
+    }
+
+    /// Loads a pixelated texture (see `Pixelated::from_text`), or returns it if it is already
+    /// loaded. The placeholder is displayed until the texture is loaded, or if it can not be loaded.
+    pub fn load_texture(&self, path: &str, placeholder: &TextureHandle) -> TextureHandle {
+        let mut textures = self.textures.lock().unwrap();
+        if let Some(texture) = textures.get(path).and_then(WeakTextureHandle::upgrade) {
+            return texture;
+        }
+        let texture = TextureHandle::new(StreamedTexture {
+            asset: self.load(path, Pixelated::from_text),
+            placeholder: placeholder.clone(),
+        });
+        textures.insert(path.to_string(), texture.downgrade());
+        texture
+    }
+
+    /// Returns the number of textures loaded by this loader which are still in use
+    pub fn textures_in_use(&self) -> usize {
+        let mut textures = self.textures.lock().unwrap();
+        textures.retain(|_, texture| texture.upgrade().is_some());
+        textures.len()
+    }
+}
+
+/// Textures of a world, identified by their name (for instance in scene files).
+///
+/// A texture can also be registered as a file, which is only loaded the first time the texture is
+/// needed: PNG images are loaded as `ImageTexture`, other files as `Pixelated` textures.
+#[derive(Default)]
+pub struct TextureLibrary {
+    textures: HashMap<String, TextureHandle>,
+    /// Path of the textures which are not loaded yet
+    files: HashMap<String, String>,
+}
+
+impl TextureLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names a texture, replacing the previous texture of this name
+    pub fn insert(&mut self, name: &str, texture: &TextureHandle) {
+        self.files.remove(name);
+        self.textures.insert(name.to_string(), texture.clone());
+    }
+
+    /// Names the texture of a file, which is loaded by `get`
+    pub fn insert_file(&mut self, name: &str, path: &str) {
+        self.textures.remove(name);
+        self.files.insert(name.to_string(), path.to_string());
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.textures.contains_key(name) || self.files.contains_key(name)
+    }
+
+    /// Returns the texture of this name, loading it if needed
+    pub fn get(&mut self, name: &str) -> Result<TextureHandle, String> {
+        if let Some(texture) = self.textures.get(name) {
+            return Ok(texture.clone());
+        }
+        let path = self.files.get(name).ok_or_else(|| format!("Unknown texture '{name}'"))?;
+        let texture = Self::load_file(path).map_err(|err| format!("Could not load the texture '{name}' from {path}: {err}"))?;
+        self.insert(name, &texture);
+        Ok(texture)
+    }
+
+    /// Returns the name of the texture, if it has one
+    pub fn name_of(&self, texture: &TextureHandle) -> Option<&str> {
+        self.textures.iter().find(|(_, other)| other.same_as(texture)).map(|(name, _)| name.as_str())
+    }
+
+    fn load_file(path: &str) -> Result<TextureHandle, String> {
+        if path.ends_with(".png") {
+            Ok(TextureHandle::new(ImageTexture::load(path)?))
+        } else {
+            let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+            Ok(TextureHandle::new(Pixelated::from_text(&text)?))
+        }
+    }
+}
+
+/// A texture loaded in the background, which shows a placeholder until it is ready
+pub struct StreamedTexture {
+    asset: Asset<Pixelated>,
+    placeholder: TextureHandle,
+}
+
+impl StreamedTexture {
+    fn current(&self) -> &dyn Texture {
+        match self.asset.get() {
+            Some(texture) => texture,
+            None => &*self.placeholder,
+        }
+    }
+}
+
+impl Texture for StreamedTexture {
+    fn width(&self) -> f32 {
+        self.current().width()
+    }
+
+    fn height(&self) -> f32 {
+        self.current().height()
+    }
+
+    fn color_at(&self, u: f32, v: f32) -> &Color {
+        self.current().color_at(u, v)
+    }
+
+    fn texel_size(&self) -> Option<f32> {
+        self.current().texel_size()
+    }
+
+    fn filtering(&self) -> Option<Filtering> {
+        self.current().filtering()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assets::{AssetLoader, TextureLibrary};
+    use crate::primitives::color::Color;
+    use crate::primitives::textures::colored::{ORANGE, YELLOW};
+    use crate::primitives::textures::Texture;
+
+    #[test]
+    fn test_streamed_texture() {
+        let path = std::env::temp_dir().join("game_engine_test_texture.txt");
+        std::fs::write(&path, "0.5\nkk\nkk\n").unwrap();
+
+        let loader = AssetLoader::new(2);
+        let texture = loader.load_texture(path.to_str().unwrap(), &YELLOW);
+        let missing = loader.load_texture("does/not/exist.txt", &YELLOW);
+        loader.finish();
+
+        assert_eq!(Color::black().rgba(), texture.color_at(0.2, 0.7).rgba());
+        assert_eq!(1., texture.width());
+
+        // The placeholder stays when the file can not be loaded
+        assert_eq!(Color::yellow().rgba(), missing.color_at(0.2, 0.7).rgba());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_textures_released_when_unused() {
+        let loader = AssetLoader::new(1);
+        let texture = loader.load_texture("some/texture.txt", &YELLOW);
+        let same = loader.load_texture("some/texture.txt", &YELLOW);
+        assert!(texture.same_as(&same));
+        assert_eq!(1, loader.textures_in_use());
+
+        drop(texture);
+        assert_eq!(1, loader.textures_in_use());
+        drop(same);
+        assert_eq!(0, loader.textures_in_use());
+    }
+
+    #[test]
+    fn test_texture_library() {
+        let path = std::env::temp_dir().join("game_engine_test_library.txt");
+        std::fs::write(&path, "0.5\nkk\nkk\n").unwrap();
+
+        let mut library = TextureLibrary::new();
+        library.insert("yellow", &YELLOW);
+        library.insert_file("black", path.to_str().unwrap());
+        library.insert_file("missing", "does/not/exist.png");
+        assert!(library.contains("black") && !library.contains("orange"));
+        assert_eq!(Some("yellow"), library.name_of(&YELLOW));
+        assert_eq!(None, library.name_of(&ORANGE));
+
+        // The file is loaded once, when the texture is first needed
+        let black = library.get("black").unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(black.same_as(&library.get("black").unwrap()));
+        assert_eq!(Color::black().rgba(), black.color_at(0.2, 0.7).rgba());
+        assert_eq!(Some("black"), library.name_of(&black));
+
+        assert!(library.get("missing").err().unwrap().contains("Could not load"));
+        assert!(library.get("orange").err().unwrap().contains("Unknown texture"));
+        library.insert("yellow", &ORANGE);
+        assert!(library.get("yellow").unwrap().same_as(&ORANGE));
+    }
+}
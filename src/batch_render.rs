@@ -0,0 +1,95 @@
+use std::path::{Path, PathBuf};
+
+use doom::benchmark::CameraPath;
+use doom::{render_offscreen, RenderMethod};
+use doom::png_saver;
+use doom::viewport::Viewport;
+use doom::worlds::World;
+
+use crate::bookmarks::load_bookmarks;
+
+/// Renders `frames` images with the camera moving along the path, and writes them in the
+/// directory as `frame_0000.png`, `frame_0001.png`... Returns the paths of the images.
+fn render_sequence(world: &mut World, path: &CameraPath, frames: usize, method: RenderMethod, directory: &Path) -> Result<Vec<PathBuf>, String> {
+    std::fs::create_dir_all(directory).map_err(|err| err.to_string())?;
+    let viewport = world.viewport();
+    let mut images = Vec::new();
+    for i in 0..frames {
+        // The first and the last frames are at the ends of the path
+        let progress = if frames > 1 { i as f32 / (frames - 1) as f32 } else { 0. };
+        world.set_camera_pose(path.pose_at(progress));
+        let image = render_offscreen(world, method);
+        let image_path = directory.join(format!("frame_{i:04}.png"));
+        png_saver::save_frame(&image_path.to_string_lossy(), &image, viewport.width, viewport.height).map_err(|err| err.to_string())?;
+        images.push(image_path);
+    }
+    Ok(images)
+}
+
+/// Command line tool: renders an image sequence without any window, such as a turntable of a
+/// scene or the frames of an animation test.
+///
+/// `render [--scene <path>] [--path <bookmarks>] [--frames <n>] [--renderer <painter|bsp|raytracer>]
+/// [--out <directory>] [--resolution <width>x<height>]`
+///
+/// The camera goes through the poses of a bookmarks file (see `Bookmark`), or stays where the
+/// scene places it without path.
+pub fn run_render(args: &[String], mut world: World) -> Result<(), String> {
+    let option = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1));
+    if let Some(scene) = option("--scene") {
+        world.load_scene(scene).map_err(|err| format!("Could not load the scene {scene}: {err}"))?;
+    }
+    let path = match option("--path") {
+        Some(file) => {
+            let poses: Vec<_> = load_bookmarks(file)?.into_iter().map(|bookmark| bookmark.pose).collect();
+            if poses.is_empty() {
+                return Err(format!("There is no pose in {file}"));
+            }
+            CameraPath::new(poses)
+        }
+        None => CameraPath::new(vec![world.camera().pose().clone()]),
+    };
+    let frames = match option("--frames") {
+        Some(text) => text.parse().map_err(|_| format!("Invalid number of frames '{text}'"))?,
+        None => 60,
+    };
+    let method = option("--renderer").map_or(Ok(RenderMethod::Raytracer), |name| name.parse())?;
+    let directory = option("--out").map(String::as_str).unwrap_or("frames");
+    if let Some(resolution) = option("--resolution") {
+        world.set_viewport(Viewport::parse(resolution)?);
+    }
+    let images = render_sequence(&mut world, &path, frames, method, Path::new(directory))?;
+    println!("{} frames written to {directory}", images.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use doom::benchmark::CameraPath;
+    use doom::RenderMethod;
+    use doom::primitives::camera::Camera;
+    use doom::primitives::cube::Cube3;
+    use doom::primitives::position::Pose;
+    use doom::primitives::textures::colored::YELLOW;
+    use doom::primitives::vector::Vector3;
+    use doom::viewport::Viewport;
+    use doom::worlds::World;
+
+    use crate::batch_render::render_sequence;
+
+    #[test]
+    fn test_render_sequence() {
+        let mut world = World::new(Camera::default());
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(3, 0, 0), &YELLOW, &YELLOW));
+        world.set_viewport(Viewport::new(160, 100));
+        let path = CameraPath::new(vec![Pose::new(Vector3::new(0., 0.5, -0.5), 0.), Pose::new(Vector3::new(0., -2., -0.5), 0.3)]);
+        let directory = std::env::temp_dir().join("test_render_sequence");
+        let images = render_sequence(&mut world, &path, 3, RenderMethod::Painter, &directory).unwrap();
+        assert_eq!(3, images.len());
+        assert!(images.iter().all(|image| image.exists()));
+        assert!(images[2].ends_with("frame_0002.png"));
+        // The camera ends at the last pose of the path
+        assert_eq!(Vector3::new(0., -2., -0.5), *world.camera().pose().position());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
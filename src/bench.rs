@@ -0,0 +1,74 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use doom::primitives::textures::pixelated::Pixelated;
+use doom::primitives::textures::Texture;
+
+/// Minimum duration of the measurement of a benchmark
+const MEASUREMENT_TIME: Duration = Duration::from_millis(300);
+/// Number of samples (u, v) used by the texture benchmarks, which is about one frame
+const N_SAMPLES: usize = 960 * 640;
+
+/// Runs a closure many times and measures how long it takes.
+///
+/// This mimics the API of criterion:
+/// ```ignore
+/// let result = bench_function("name", |b| b.iter(|| expensive_function()));
+/// ```
+pub struct Bencher {
+    iterations: u64,
+    elapsed: Duration,
+}
+
+impl Bencher {
+    /// Runs the routine with an increasing number of iterations, until the measurement is long
+    /// enough to be meaningful.
+    pub fn iter<O, F: FnMut() -> O>(&mut self, mut routine: F) {
+        let mut iterations = 1;
+        loop {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                black_box(routine());
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= MEASUREMENT_TIME {
+                self.iterations = iterations;
+                self.elapsed = elapsed;
+                return;
+            }
+            iterations *= 2;
+        }
+    }
+}
+
+/// Benchmarks the function, prints and returns the time per iteration
+pub fn bench_function<F: FnMut(&mut Bencher)>(name: &str, mut f: F) -> Duration {
+    let mut bencher = Bencher {
+        iterations: 0,
+        elapsed: Duration::ZERO,
+    };
+    f(&mut bencher);
+    let time_per_iteration = bencher.elapsed / bencher.iterations.max(1) as u32;
+    println!("{name:<30} time: {time_per_iteration:?} ({} iterations)", bencher.iterations);
+    time_per_iteration
+}
+
+/// Compares the reference and the fast sampling of pixelated textures, for a frame worth of pixels
+pub fn texture_sampling_benchmark() {
+    let texture = Pixelated::soil_side();
+    let samples: Vec<(f32, f32)> = (0..N_SAMPLES)
+        .map(|i| ((i % 960) as f32 * 0.0031, (i / 960) as f32 * 0.0029))
+        .collect();
+
+    let reference = bench_function("pixelated/reference", |b| {
+        b.iter(|| samples.iter().map(|(u, v)| texture.color_at_reference(*u, *v).rgba()[0] as u32).sum::<u32>())
+    });
+    let fast = bench_function("pixelated/lut", |b| {
+        b.iter(|| samples.iter().map(|(u, v)| texture.color_at(*u, *v).rgba()[0] as u32).sum::<u32>())
+    });
+    println!(
+        "Per pixel: reference = {:.2} ns, lut = {:.2} ns",
+        reference.as_nanos() as f64 / N_SAMPLES as f64,
+        fast.as_nanos() as f64 / N_SAMPLES as f64,
+    );
+}
@@ -0,0 +1,211 @@
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::frame::{AbstractFrame, Frame};
+use crate::primitives::cubic_face2::CubicFace2;
+use crate::primitives::point::Point2;
+use crate::primitives::position::Pose;
+use crate::profiler::{FrameProfiler, STAGES};
+use crate::viewport::Viewport;
+use crate::worlds::World;
+
+/// A path followed by the camera, going through a list of poses
+pub struct CameraPath {
+    waypoints: Vec<Pose>,
+}
+
+impl CameraPath {
+    pub fn new(waypoints: Vec<Pose>) -> Self {
+        assert!(!waypoints.is_empty(), "A camera path needs at least one pose");
+        Self { waypoints }
+    }
+
+    pub fn waypoints(&self) -> &[Pose] {
+        &self.waypoints
+    }
+
+    /// Returns the pose at the given progress along the path (between 0 and 1). The camera moves
+    /// along straight lines between the waypoints, spending the same time between each of them.
+    pub fn pose_at(&self, progress: f32) -> Pose {
+        let segments = self.waypoints.len() - 1;
+        let position = progress.clamp(0., 1.) * segments as f32;
+        let i = (position as usize).min(segments.saturating_sub(1));
+        let t = position - i as f32;
+        let from = &self.waypoints[i];
+        let to = &self.waypoints[(i + 1).min(segments)];
+        from.slerp(to, t)
+    }
+}
+
+/// Result of a benchmark flythrough
+pub struct BenchmarkReport {
+    /// Total duration of each frame
+    frame_times: Vec<Duration>,
+    /// Average duration of each stage of the rendering
+    stages: [Duration; STAGES.len()],
+    /// Average number of times each covered pixel is drawn
+    overdraw: f32,
+}
+
+impl BenchmarkReport {
+    /// Frame time below which this fraction of the frames are (zero without frames)
+    fn percentile(&self, q: f32) -> Duration {
+        let mut sorted = self.frame_times.clone();
+        sorted.sort();
+        match sorted.len() {
+            0 => Duration::ZERO,
+            n => sorted[((n - 1) as f32 * q).round() as usize],
+        }
+    }
+
+    fn average(&self) -> Duration {
+        self.frame_times.iter().sum::<Duration>() / self.frame_times.len().max(1) as u32
+    }
+
+    pub fn to_json(&self) -> Value {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.;
+        let stages: serde_json::Map<String, Value> =
+            STAGES.iter().zip(self.stages).map(|(stage, d)| (format!("{stage:?}"), json!(ms(d)))).collect();
+        json!({
+            "frames": self.frame_times.len(),
+            "average_fps": 1. / self.average().as_secs_f64(),
+            "frame_time_ms": {
+                "average": ms(self.average()),
+                "p50": ms(self.percentile(0.5)),
+                "p95": ms(self.percentile(0.95)),
+                "p99": ms(self.percentile(0.99)),
+            },
+            "stages_ms": stages,
+            "overdraw": self.overdraw,
+        })
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.;
+        let mut md = format!("# Benchmark ({} frames)\n\n", self.frame_times.len());
+        md += &format!("* Average FPS: {:.1}\n", 1. / self.average().as_secs_f64());
+        md += &format!("* 1% low FPS: {:.1}\n", 1. / self.percentile(0.99).as_secs_f64());
+        md += &format!("* Overdraw: {:.2}\n\n", self.overdraw);
+        md += "| Frame time | ms |\n|---|---|\n";
+        for (name, d) in [("average", self.average()), ("p50", self.percentile(0.5)), ("p95", self.percentile(0.95)), ("p99", self.percentile(0.99))] {
+            md += &format!("| {name} | {:.2} |\n", ms(d));
+        }
+        md += "\n| Stage | ms |\n|---|---|\n";
+        for (stage, d) in STAGES.iter().zip(self.stages) {
+            md += &format!("| {stage:?} | {:.2} |\n", ms(d));
+        }
+        md
+    }
+}
+
+/// Renders `frames` frames offscreen with the painter's algorithm, moving the camera along the
+/// path, and measures them.
+///
+/// The overdraw is measured with a separate rendering of each frame, so that counting the pixels
+/// does not slow down the measured one.
+pub fn run(world: &mut World, path: &CameraPath, frames: usize) -> BenchmarkReport {
+    let viewport = world.viewport();
+    let mut buffer = vec![0u8; viewport.buffer_len()];
+    let mut profiler = FrameProfiler::new(frames.max(1));
+    let mut frame_times = Vec::with_capacity(frames);
+    let mut counter = OverdrawCounter::new(viewport);
+    for i in 0..frames {
+        world.set_camera_pose(path.pose_at(i as f32 / (frames.max(2) - 1) as f32));
+        let start = Instant::now();
+        world.draw_painter_profiled(&mut Frame::new(&mut buffer, viewport), &mut profiler);
+        frame_times.push(start.elapsed());
+        profiler.end_frame();
+
+        world.draw_painter_profiled(&mut counter, &mut FrameProfiler::new(1));
+        counter.end_frame();
+    }
+
+    BenchmarkReport { frame_times, stages: profiler.average(), overdraw: counter.overdraw() }
+}
+
+/// A frame which counts how many times each pixel is drawn
+struct OverdrawCounter {
+    viewport: Viewport,
+    counts: Vec<u16>,
+    drawn: u64,
+    covered: u64,
+}
+
+impl OverdrawCounter {
+    fn new(viewport: Viewport) -> Self {
+        Self { viewport, counts: vec![0; viewport.pixel_count()], drawn: 0, covered: 0 }
+    }
+
+    fn end_frame(&mut self) {
+        for count in &mut self.counts {
+            if *count > 0 {
+                self.drawn += *count as u64;
+                self.covered += 1;
+                *count = 0;
+            }
+        }
+    }
+
+    fn overdraw(&self) -> f32 {
+        if self.covered == 0 {
+            return 0.;
+        }
+        self.drawn as f32 / self.covered as f32
+    }
+}
+
+impl AbstractFrame for OverdrawCounter {
+    fn draw_one_face(&mut self, face: &CubicFace2) {
+        let (xmin, ymin, xmax, ymax) = face.bounding_box();
+        for y in ymin..ymax {
+            for x in xmin..xmax {
+                if face.contains(&Point2::new(x as f32, y as f32)) && face.raytracing(x as i16, y as i16).is_some() {
+                    self.counts[self.viewport.index(x, y)] += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::benchmark::{run, CameraPath};
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::position::Pose;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+    use crate::worlds::World;
+
+    #[test]
+    fn test_camera_path() {
+        let path = CameraPath::new(vec![Pose::new(Vector3::newi(0, 0, 0), 0.), Pose::new(Vector3::newi(2, 0, 0), 1.), Pose::new(Vector3::newi(2, 4, 0), 0.)]);
+        assert!(path.pose_at(0.25).position() == &Vector3::new(1., 0., 0.));
+        assert!((path.pose_at(0.25).rotation_z() - 0.5).abs() < 1e-6);
+        assert!(path.pose_at(0.75).position() == &Vector3::new(2., 2., 0.));
+        assert!(path.pose_at(1.).position() == &Vector3::new(2., 4., 0.));
+    }
+
+    #[test]
+    fn test_benchmark_report() {
+        // Two cubes in a row: the hidden faces of the second one are drawn for nothing
+        let mut world = World::new(Camera::default());
+        world.add_cube(Cube3::minecraft_like(Vector3::new(2., -0.5, -0.5), &YELLOW, &YELLOW));
+        world.add_cube(Cube3::minecraft_like(Vector3::new(3.5, -0.7, -0.7), &YELLOW, &YELLOW));
+        let path = CameraPath::new(vec![Pose::new(Vector3::empty(), 0.), Pose::new(Vector3::new(-1., 0., 0.), 0.)]);
+        let report = run(&mut world, &path, 3);
+
+        let json = report.to_json();
+        assert_eq!(3, json["frames"]);
+        assert!(report.overdraw > 1.);
+        assert!(report.to_markdown().contains("| Raster |"));
+
+        // Without frames, the report is empty
+        let report = run(&mut world, &path, 0);
+        assert_eq!(0, report.to_json()["frames"]);
+        assert_eq!(Duration::ZERO, report.percentile(0.99));
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use doom::primitives::position::Pose;
+
+/// File where the bookmarks are saved from the game
+pub const BOOKMARKS_PATH: &str = "bookmarks.json";
+
+/// A named pose of the camera, to come back to the same view
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub pose: Pose,
+}
+
+impl Bookmark {
+    pub fn new(name: &str, pose: Pose) -> Self {
+        Self { name: name.to_string(), pose }
+    }
+}
+
+/// Reads a list of bookmarks from a JSON file
+pub fn load_bookmarks(path: &str) -> Result<Vec<Bookmark>, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&text).map_err(|err| format!("Invalid bookmarks: {err}"))
+}
+
+/// Writes a list of bookmarks to a JSON file
+pub fn save_bookmarks(path: &str, bookmarks: &[Bookmark]) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(bookmarks).map_err(|err| err.to_string())?;
+    std::fs::write(path, text).map_err(|err| err.to_string())
+}
+
+/// Adds a bookmark at the end of the file (which is created if needed), named after its position
+/// in the list. Returns the name of the new bookmark.
+pub fn append_bookmark(path: &str, pose: Pose) -> Result<String, String> {
+    let mut bookmarks = if std::path::Path::new(path).exists() { load_bookmarks(path)? } else { Vec::new() };
+    let name = format!("bookmark_{}", bookmarks.len() + 1);
+    bookmarks.push(Bookmark::new(&name, pose));
+    save_bookmarks(path, &bookmarks)?;
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use doom::primitives::position::Pose;
+    use doom::primitives::vector::Vector3;
+
+    use crate::bookmarks::{append_bookmark, load_bookmarks};
+
+    #[test]
+    fn test_append_bookmarks() {
+        let path = std::env::temp_dir().join("test_bookmarks.json");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let mut pose = Pose::new(Vector3::new(1., 2., -1.5), 0.3);
+        pose.apply_pitch(-0.2);
+        assert_eq!("bookmark_1", append_bookmark(path, pose.clone()).unwrap());
+        assert_eq!("bookmark_2", append_bookmark(path, Pose::new(Vector3::empty(), 0.)).unwrap());
+        let bookmarks = load_bookmarks(path).unwrap();
+        assert_eq!(2, bookmarks.len());
+        assert!(bookmarks[0].pose == pose);
+
+        std::fs::write(path, "not json").unwrap();
+        assert!(load_bookmarks(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
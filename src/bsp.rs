@@ -1,11 +1,14 @@
-mod cubic_face_split;
+pub mod cubic_face_split;
+pub(crate) mod merging;
+pub(crate) mod seams;
 pub mod tree;
+mod welding;
 
 #[cfg(test)]
 mod tests {
     use std::ops::Deref;
     use crate::primitives::cubic_face3::CubicFace3;
-    use crate::bsp::cubic_face_split::{bsp_polygon_split, point_in_front_of};
+    use crate::bsp::cubic_face_split::{bsp_polygon_split, bsp_polygon_split_with_epsilon, point_in_front_of};
     use crate::bsp::tree::binary_space_partionning;
     use crate::primitives::textures::colored::YELLOW;
     use crate::primitives::vector::Vector3;
@@ -113,10 +116,10 @@ mod tests {
 
         // The face GH must be split in two faces
         let (r1, r2) = bsp_polygon_split(&face_gh, &face_ab);
-        assert!(r1.is_some());
-        assert!(r2.is_some());
-        let f1 = r1.unwrap();
-        let f2 = r2.unwrap();
+        assert!(!r1.is_empty());
+        assert!(!r2.is_empty());
+        let f1 = r1[0].clone();
+        let f2 = r2[0].clone();
         assert!(f1.points().contains(&Vector3::new(-0.5, 0.0, 0.0)));
         assert!(f1.points().contains(&Vector3::new(-0.5, 0.0, 2.0)));
         assert!(f2.points().contains(&Vector3::new(-0.5, 0.0, 0.0)));
@@ -124,26 +127,26 @@ mod tests {
 
         // The gc face is behind
         let (r1, r2) = bsp_polygon_split(&face_gc, &face_ab);
-        assert!(r2.is_none());
-        assert!(r1.is_some());
+        assert!(r2.is_empty());
+        assert!(!r1.is_empty());
 
         // Same for CP
         let (r1, r2) = bsp_polygon_split(&face_cp, &face_ab);
-        assert!(r2.is_none());
-        assert!(r1.is_some());
+        assert!(r2.is_empty());
+        assert!(!r1.is_empty());
 
         // The gc face is in front
         let (r1, r2) = bsp_polygon_split(&face_hf, &face_ab);
-        assert!(r2.is_some());
-        assert!(r1.is_none());
+        assert!(!r2.is_empty());
+        assert!(r1.is_empty());
 
         // between h and e, there should be an intersection
         let face_eh = CubicFace3::vface_from_line(e, h);
         let (r1, r2) = bsp_polygon_split(&face_eh, &face_ab);
-        assert!(r2.is_some());
-        assert!(r1.is_some());
-        let f1 = r1.unwrap();
-        let f2 = r2.unwrap();
+        assert!(!r2.is_empty());
+        assert!(!r1.is_empty());
+        let f1 = r1[0].clone();
+        let f2 = r2[0].clone();
         println!("f1 = {f1:?}");
         println!("f2 = {f2:?}");
         // We must assert that contains 'near' works
@@ -187,10 +190,113 @@ mod tests {
         assert_eq!(2., splitter.area());
 
         let (r1, r2) = bsp_polygon_split(&to_split, &splitter);
-        let f1 = r1.unwrap();
-        let f2 = r2.unwrap();
+        let f1 = r1[0].clone();
+        let f2 = r2[0].clone();
         assert_eq!(4., f1.area() + f2.area());
         assert!(f1.area() < 4.);
         assert!(f2.area() < 4.);
     }
+
+    #[test]
+    fn test_bsp_polygon_splitting_with_epsilon() {
+        // The splitter is the plane y=0, facing the negative y
+        let splitter = CubicFace3::vface_from_line(Vector3::newi2(0, 0), Vector3::newi2(1, 0));
+
+        // This face ends slightly behind the plane: with a tolerance, it is not split
+        let to_split = CubicFace3::new(
+            [Vector3::newi2(0, -1), Vector3::newi2(1, -1), Vector3::new(1., 0.00005, 0.), Vector3::new(0., 0.00005, 0.)],
+            Vector3::newi(0, 0, -1),
+            &YELLOW
+        );
+        let (r1, r2) = bsp_polygon_split_with_epsilon(&to_split, &splitter, 0.0001);
+        assert!(!r1.is_empty());
+        assert!(r2.is_empty());
+        let (r1, r2) = bsp_polygon_split_with_epsilon(&to_split, &splitter, 0.);
+        assert!(!r1.is_empty());
+        assert!(!r2.is_empty());
+
+        // The plane goes through a corner of this face: it is split at this corner
+        let to_split = CubicFace3::new(
+            [Vector3::newi2(0, -1), Vector3::newi2(1, -1), Vector3::new(2., 0.00005, 0.), Vector3::newi2(1, 1)],
+            Vector3::newi(0, 0, -1),
+            &YELLOW
+        );
+        let (r1, r2) = bsp_polygon_split_with_epsilon(&to_split, &splitter, 0.0001);
+        assert!(!r1.is_empty());
+        assert!(!r2.is_empty());
+    }
+
+    #[test]
+    fn test_bsp_polygon_splitting_preserves_texture_coordinates() {
+        let to_split = CubicFace3::new(
+            [Vector3::newi2(0, 0), Vector3::newi2(2, 0), Vector3::newi2(2, 2), Vector3::newi2(0, 2)],
+            Vector3::newi(0, 0, -1),
+            &YELLOW
+        );
+        for splitter in [
+            CubicFace3::vface_from_line(Vector3::new(0., 0.7, 0.), Vector3::new(1., 0.7, 0.)),
+            CubicFace3::vface_from_line(Vector3::new(1.3, 0., 0.), Vector3::new(1.3, 1., 0.)),
+        ] {
+            let (r1, r2) = bsp_polygon_split(&to_split, &splitter);
+            for fragment in r1.into_iter().chain(r2) {
+                // Cast a vertical ray through a point of the fragment, and compare the texture
+                // coordinates with the ones of the original face
+                let target = fragment.center();
+                let origin = target + Vector3::new(0., 0., -1.);
+                let direction = Vector3::new(0., 0., 1.);
+                let (_, p1) = to_split.line_projection(&origin, &direction).unwrap();
+                let (_, p2) = fragment.line_projection(&origin, &direction).unwrap();
+                let (u1, v1) = to_split.uv_at(&p1);
+                let (u2, v2) = fragment.uv_at(&p2);
+                assert!(f32::abs(u1 - u2) < 0.0001);
+                assert!(f32::abs(v1 - v2) < 0.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bsp_polygon_splitting_triangles_and_corners() {
+        // The splitter is the plane x=1
+        let splitter = CubicFace3::vface_from_line(Vector3::newi2(1, 0), Vector3::newi2(1, 1));
+
+        // A triangle across the plane is split into a triangle and a quadrilateral
+        let triangle = CubicFace3::triangle(
+            [Vector3::newi2(0, 0), Vector3::newi2(2, 0), Vector3::newi2(0, 2)],
+            [(0., 0.), (1., 0.), (0., 1.)],
+            Vector3::newi(0, 0, -1),
+            &YELLOW
+        );
+        let (r1, r2) = bsp_polygon_split(&triangle, &splitter);
+        assert!(!r1.is_empty() && !r2.is_empty());
+        let area: f32 = r1.iter().chain(&r2).map(CubicFace3::area).sum();
+        assert!((area - triangle.area()).abs() < 1e-5);
+        assert!(r1.iter().chain(&r2).all(|f| f.points().iter().all(|p| p.x() < 1. + 1e-5) || f.points().iter().all(|p| p.x() > 1. - 1e-5)));
+
+        // The plane goes through a vertex of this triangle, and its other vertices are on both
+        // sides: each side gets the triangle between the vertex and the crossing point
+        let triangle = CubicFace3::triangle(
+            [Vector3::newi2(1, 0), Vector3::newi2(2, 2), Vector3::newi2(0, 2)],
+            [(0., 0.), (1., 0.), (0., 1.)],
+            Vector3::newi(0, 0, -1),
+            &YELLOW
+        );
+        let (r1, r2) = bsp_polygon_split(&triangle, &splitter);
+        assert_eq!((1, 1), (r1.len(), r2.len()));
+        assert!((r1[0].area() - 1.).abs() < 1e-5 && (r2[0].area() - 1.).abs() < 1e-5);
+        assert!(r1[0].points().iter().all(|p| p.x() > 1. - 1e-5) != r2[0].points().iter().all(|p| p.x() > 1. - 1e-5));
+
+        // The plane cuts a corner of this square: one side gets a triangle, the other one the rest
+        let square = CubicFace3::new(
+            [Vector3::new(0.5, 0., 0.), Vector3::new(1.5, 0., 0.), Vector3::new(1.5, 1., 0.), Vector3::new(0.5, 1., 0.)],
+            Vector3::newi(0, 0, -1),
+            &YELLOW
+        );
+        let splitter = CubicFace3::vface_from_line(Vector3::newi2(1, 0), Vector3::newi2(2, 1));
+        let (r1, r2) = bsp_polygon_split(&square, &splitter);
+        let (corner, rest) = if r1.len() == 1 && r1[0].is_triangle() { (r1, r2) } else { (r2, r1) };
+        assert!(corner[0].is_triangle());
+        assert!((corner[0].area() - 0.125).abs() < 1e-5);
+        let area: f32 = rest.iter().map(CubicFace3::area).sum();
+        assert!((area - 0.875).abs() < 1e-5);
+    }
 }
@@ -1,11 +1,28 @@
 use crate::bsp::cubic_face_split::PointPosition::{Behind, Contained, InFront};
 use crate::primitives::cubic_face3::CubicFace3;
 use crate::primitives::vector::Vector3;
+use crate::tunables::tunables;
+
+/// Default tolerance (in meters) under which a point is considered to lie on a splitting plane
+/// (see `Tunables`).
+pub const DEFAULT_EPSILON: f32 = 1e-4;
+
+/// A fragment is kept as a quadrilateral when its diagonals have the same middle, up to this
+/// fraction of their length
+const PARALLELOGRAM_TOLERANCE: f32 = 1e-4;
+
+/// Fragments whose area (twice the area, in square meters) is smaller are dropped
+const MIN_FRAGMENT_AREA: f32 = 1e-8;
 
 /// Helper function for the binary space partitioning.
 ///
-/// Splits a given polygon into possibly 1 or 2 parts that are strictly
-/// in front another, or behind it.
+/// Splits a given polygon into the fragments that are strictly in front of the plane of another
+/// face, and the fragments behind it. Each side gets no fragment, the whole polygon, or the part
+/// of the polygon on this side.
+///
+/// The parts are cut with the Sutherland-Hodgman algorithm. A part which is a parallelogram is
+/// kept as a single face (this is the case of a face cut parallel to its sides); the other parts
+/// are cut into triangles, since the faces must be parallelograms or triangles.
 ///
 /// Resources
 /// * https://geidav.wordpress.com/2015/03/21/splitting-an-arbitrary-polygon-by-a-line/
@@ -14,82 +31,154 @@ use crate::primitives::vector::Vector3;
 /// * From the book "Graphics Gems 3", the chapter:
 /// "PARTITIONING A 3-D CONVEXARTITIONING A 3-D CONVEXARTITIONING A 3-D CONVEXARTITIONING A 3-D CONVEXARTITIONING A 3-D CONVEX"
 ///
-pub fn bsp_polygon_split(to_split: &CubicFace3, face: &CubicFace3) -> (Option<CubicFace3>, Option<CubicFace3>) {
-    // The algo is very simple : since the polygon are convex and have 4 points, we can diffenriate 3 scenarios
-    // * 1: all the points of `to_split` are in front of `face`
-    // * 2: all the points of `to_split` are behind `face`
-    // * 3: two points are behind, two points are front
-    //   For this configuration, we only support two modes: horizontal split or vertical split.
-
-    // Note that this algorithm is a simplified version of polygon splitting algorithm, which works in my case.
-
-    // Compute the number of points in front of the face
-    let points = to_split.points();
-    let positions: Vec<PointPosition> = to_split.points().iter().map(|p| PointPosition::from(&face, &p)).collect();
+pub fn bsp_polygon_split(to_split: &CubicFace3, face: &CubicFace3) -> (Vec<CubicFace3>, Vec<CubicFace3>) {
+    bsp_polygon_split_with_epsilon(to_split, face, tunables().bsp_epsilon)
+}
+
+/// Same as `bsp_polygon_split`, but points closer than `epsilon` to the plane of `face` are
+/// considered to be contained in this plane.
+///
+/// Contained points do not vote for a side: a face whose other points are all on the same side
+/// of the plane is not split. This avoids creating tiny slivers when the polygon only touches
+/// the plane because of floating point errors. When the plane goes through a corner and the other
+/// points are on both sides, the contained corner belongs to the fragments of both sides.
+pub fn bsp_polygon_split_with_epsilon(to_split: &CubicFace3, face: &CubicFace3, epsilon: f32) -> (Vec<CubicFace3>, Vec<CubicFace3>) {
+    let n = if to_split.is_triangle() { 3 } else { 4 };
+    let points = &to_split.points()[..n];
+    let positions: Vec<PointPosition> = points.iter().map(|p| PointPosition::from(face, p, epsilon)).collect();
     let n_in_front = positions.iter().filter(|p| **p == InFront).count();
-    let n_contained = positions.iter().filter(|p| **p == Contained).count();
-
-    match n_in_front {
-        // all points behind
-        0 => return (None, Some(to_split.clone())),
-        // two points are in front, two points are behind: we need to
-        // split the polygon in two.
-        2 => {
-            // Compute the points in front
-            let in_fronts: Vec<bool> = positions.iter().map(|p| *p == InFront).collect();
-
-            match n_contained {
-                0 => {
-                    enum SplitMode { AfterFirst, AfterSecond }
-                    let mut split_mode = if in_fronts[0] != in_fronts[1] {
-                        SplitMode::AfterFirst
-                    } else {
-                        SplitMode::AfterSecond
-                    };
-
-                    match split_mode {
-                        SplitMode::AfterFirst => {
-                            let x = face.line_intersection(&points[0], &points[1]).unwrap();
-                            let y = face.line_intersection(&points[2], &points[3]).unwrap();
-                            let f1 = CubicFace3::new([points[0], x, y, points[3]], to_split.normal().clone(), to_split.texture().clone());
-                            let f2 = CubicFace3::new([x, points[1], points[2], y], to_split.normal().clone(), to_split.texture().clone());
-                            return (Some(f1), Some(f2));
-                        }
-                        SplitMode::AfterSecond => {
-                            let x = face.line_intersection(&points[1], &points[2]).unwrap();
-                            let y = face.line_intersection(&points[3], &points[0]).unwrap();
-                            let f1 = CubicFace3::new([points[0], points[1], x, y], to_split.normal().clone(), to_split.texture().clone());
-                            let f2 = CubicFace3::new([y, x, points[2], points[3]], to_split.normal().clone(), to_split.texture().clone());
-                            return (Some(f1), Some(f2));
-                        }
+    let n_behind = positions.iter().filter(|p| **p == Behind).count();
+
+    match (n_in_front, n_behind) {
+        // all points behind (or contained). If all the points are contained in the plane,
+        // the face is behind by convention.
+        (0, _) => (Vec::new(), vec![to_split.clone()]),
+        // all the points are in front (or contained)
+        (_, 0) => (vec![to_split.clone()], Vec::new()),
+        // The polygon is cut in two parts. The texture coordinates of the intersections are
+        // interpolated on the split edges, so that the texture of the fragments matches the one
+        // of the original face.
+        _ => {
+            let uvs = to_split.uvs();
+            let (mut in_front, mut behind) = (Vec::new(), Vec::new());
+            for i in 0..n {
+                let j = (i + 1) % n;
+                let corner = (points[i], uvs[i]);
+                match positions[i] {
+                    InFront => in_front.push(corner),
+                    Behind => behind.push(corner),
+                    Contained => {
+                        in_front.push(corner);
+                        behind.push(corner);
                     }
                 }
-                2 => {
-                    // If there are two points contained, we find if the face is either behind or in_fronts
-                    if n_in_front > 0 {
-                        return (Some(to_split.clone()), None)
-                    } else {
-                        return (None, Some(to_split.clone()))
-                    }
+                let crosses = matches!((&positions[i], &positions[j]), (InFront, Behind) | (Behind, InFront));
+                if crosses {
+                    let x = intersection(face, &points[i], &points[j]);
+                    let crossing = (x, to_split.uv_on_edge(i, j, &x));
+                    in_front.push(crossing);
+                    behind.push(crossing);
                 }
-                _ => panic!("We only support 0 or 2 points partially contained in a face")
             }
+            (fragments(to_split, &in_front), fragments(to_split, &behind))
+        }
+    }
+}
+
+/// Point where the segment, with its ends on both sides of the plane of the face, crosses it
+fn intersection(face: &CubicFace3, p1: &Vector3, p2: &Vector3) -> Vector3 {
+    let (d1, d2) = (signed_distance(face, p1), signed_distance(face, p2));
+    *p1 + p1.line_to(p2) * (d1 / (d1 - d2))
+}
+
+/// Turns a convex polygon cut from `original` into faces: a single face if it is a parallelogram,
+/// otherwise a fan of triangles
+fn fragments(original: &CubicFace3, corners: &[(Vector3, (f32, f32))]) -> Vec<CubicFace3> {
+    let normal = *original.normal();
+    let face = |points: &[(Vector3, (f32, f32))]| {
+        let face = match points {
+            [a, b, c] => CubicFace3::triangle([a.0, b.0, c.0], [a.1, b.1, c.1], normal, original.texture()),
+            [a, b, c, d] => CubicFace3::new_with_uvs([a.0, b.0, c.0, d.0], [a.1, b.1, c.1, d.1], normal, original.texture()),
+            _ => unreachable!("Fragments have 3 or 4 corners"),
+        };
+        face.with_receivers(original.receivers())
+    };
+    if let [a, b, c, d] = corners {
+        let scale = a.0.line_to(&c.0).norm().max(b.0.line_to(&d.0).norm());
+        if (a.0 + c.0).line_to(&(b.0 + d.0)).norm() <= PARALLELOGRAM_TOLERANCE * scale {
+            return vec![face(corners)];
         }
-        // all the points are in front
-        4 => return (Some(to_split.clone()), None),
-        _ => { panic!("Unsupported number of points in front of the face: {n_in_front}") }
     }
+    (1..corners.len().saturating_sub(1))
+        .map(|i| [corners[0], corners[i], corners[i + 1]])
+        .filter(|[a, b, c]| (b.0 - a.0).cross(&(c.0 - a.0)).norm() > MIN_FRAGMENT_AREA)
+        .map(|corners| face(&corners))
+        .collect()
+}
+
+/// Returns true if all the points of `to_check` are contained in the plane of `face`.
+pub fn is_coplanar(to_check: &CubicFace3, face: &CubicFace3, epsilon: f32) -> bool {
+    to_check.points().iter().all(|p| PointPosition::from(face, p, epsilon) == Contained)
+}
+
+/// Returns true if `to_check` has points strictly on both sides of the plane of `face`,
+/// which means that it would be split by this plane.
+pub fn crosses_plane(to_check: &CubicFace3, face: &CubicFace3, epsilon: f32) -> bool {
+    let positions: Vec<PointPosition> = to_check.points().iter().map(|p| PointPosition::from(face, p, epsilon)).collect();
+    positions.contains(&InFront) && positions.contains(&Behind)
+}
+
+/// Returns true if no point of `to_check` is behind the plane of `face`
+pub fn lies_in_front(to_check: &CubicFace3, face: &CubicFace3, epsilon: f32) -> bool {
+    to_check.points().iter().all(|p| PointPosition::from(face, p, epsilon) != Behind)
+}
+
+/// Returns true if no point of `to_check` is in front of the plane of `face`
+pub fn lies_behind(to_check: &CubicFace3, face: &CubicFace3, epsilon: f32) -> bool {
+    to_check.points().iter().all(|p| PointPosition::from(face, p, epsilon) != InFront)
+}
+
+/// Cuts `face` along the edges of `region`, a face in the same plane. Returns the parts of the
+/// face outside of the region, and the parts inside of it (none if they do not overlap).
+pub fn clip_by_region(face: &CubicFace3, region: &CubicFace3, epsilon: f32) -> (Vec<CubicFace3>, Vec<CubicFace3>) {
+    let points = region.points();
+    let n = if region.is_triangle() { 3 } else { 4 };
+    let mut normal = *region.normal();
+    normal.normalize();
+    let center = region.center();
+    let mut outside = Vec::new();
+    let mut inside = vec![face.clone()];
+    for i in 0..n {
+        // The plane of the edge, perpendicular to the region, faces the outside of the region
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let mut out = (b - a).cross(&normal);
+        if out.dot(&(center - a)) > 0. {
+            out = out.opposite();
+        }
+        let cutter = CubicFace3::new([a, b, b + normal, a + normal], out, region.texture());
+        let mut rest = Vec::new();
+        for part in &inside {
+            let (parts_outside, parts_inside) = bsp_polygon_split_with_epsilon(part, &cutter, epsilon);
+            outside.extend(parts_outside);
+            rest.extend(parts_inside);
+        }
+        if rest.is_empty() {
+            return (vec![face.clone()], Vec::new());
+        }
+        inside = rest;
+    }
+    (outside, inside)
 }
 
 #[derive(Eq, PartialEq)]
 enum PointPosition { InFront, Contained, Behind }
 
 impl PointPosition {
-    fn from(face: &CubicFace3, point: &Vector3) -> Self {
-        let dot = point.line_to(&face.center()).dot(face.normal());
-        if dot < 0.0 {
+    fn from(face: &CubicFace3, point: &Vector3, epsilon: f32) -> Self {
+        let distance = signed_distance(face, point);
+        if distance > epsilon {
             InFront
-        } else if dot > 0.0 {
+        } else if distance < -epsilon {
             Behind
         } else {
             Contained
@@ -97,6 +186,12 @@ impl PointPosition {
     }
 }
 
+/// Returns the distance between the point and the plane of the face, which is positive
+/// when the point is in front of the face.
+fn signed_distance(face: &CubicFace3, point: &Vector3) -> f32 {
+    face.center().line_to(point).dot(face.normal()) / face.normal().norm()
+}
+
 /// Returns true if the given point is in front of the plane, false otherwise.
 pub fn point_in_front_of(face: &CubicFace3, point: &Vector3) -> bool {
     let to_center = point.line_to(&face.center());
@@ -0,0 +1,136 @@
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::vector::Vector3;
+
+/// Merges the adjacent faces of the list into larger rectangles, as long as possible.
+///
+/// Two faces are merged when
+/// * they are coplanar, with the same normal and the same texture
+/// * they share a full edge, and the union of the two faces is again a parallelogram
+/// * their texture coordinates agree on the shared edge (so that the texture stays seamless)
+pub fn merge_coplanar_faces(mut faces: Vec<CubicFace3>, epsilon: f32) -> Vec<CubicFace3> {
+    let mut merged_any = true;
+    while merged_any {
+        merged_any = false;
+        'search: for i in 0..faces.len() {
+            for j in (i + 1)..faces.len() {
+                if let Some(merged) = try_merge(&faces[i], &faces[j], epsilon) {
+                    faces[i] = merged;
+                    faces.swap_remove(j);
+                    merged_any = true;
+                    break 'search;
+                }
+            }
+        }
+    }
+    faces
+}
+
+/// Returns the union of the two faces, if it can be represented as a single face.
+pub fn try_merge(f1: &CubicFace3, f2: &CubicFace3, epsilon: f32) -> Option<CubicFace3> {
+    if f1.is_triangle() || f2.is_triangle() {
+        return None;
+    }
+    if !f1.texture().same_as(f2.texture()) || f1.receivers() != f2.receivers() || !near(*f1.normal(), *f2.normal(), epsilon) {
+        return None;
+    }
+
+    let p1 = f1.points();
+    let p2 = f2.points();
+    let uv1 = f1.uvs();
+    let uv2 = f2.uvs();
+
+    // Find a rotation of the points of f1 = [a, b, c, d] and of f2 = [c, b, e, f] such that
+    // (b, c) is the shared edge. The merged face is then [a, e, f, d].
+    for r in 0..4 {
+        let [a, b, c, d] = [0, 1, 2, 3].map(|k| (r + k) % 4);
+        for s in 0..4 {
+            let [c2, b2, e, f] = [0, 1, 2, 3].map(|k| (s + k) % 4);
+            let shares_edge = near(p1[b], p2[b2], epsilon) && near(p1[c], p2[c2], epsilon);
+            if shares_edge
+                && near_uv(uv1[b], uv2[b2], epsilon)
+                && near_uv(uv1[c], uv2[c2], epsilon)
+                && is_continued(p1[a], p1[b], p2[e], epsilon)
+                && is_continued(p1[d], p1[c], p2[f], epsilon)
+            {
+                return Some(CubicFace3::new_with_uvs(
+                    [p1[a], p2[e], p2[f], p1[d]],
+                    [uv1[a], uv2[e], uv2[f], uv1[d]],
+                    *f1.normal(),
+                    f1.texture(),
+                ).with_receivers(f1.receivers()));
+            }
+        }
+    }
+    None
+}
+
+fn near(p1: Vector3, p2: Vector3, epsilon: f32) -> bool {
+    p1.line_to(&p2).norm() <= epsilon
+}
+
+fn near_uv(uv1: (f32, f32), uv2: (f32, f32), epsilon: f32) -> bool {
+    f32::abs(uv1.0 - uv2.0) <= epsilon && f32::abs(uv1.1 - uv2.1) <= epsilon
+}
+
+/// Returns true if the segment from `from` to `to` goes straight through `through`.
+fn is_continued(from: Vector3, through: Vector3, to: Vector3, epsilon: f32) -> bool {
+    let v1 = from.line_to(&through);
+    let v2 = through.line_to(&to);
+    v1.cross(&v2).norm() <= epsilon * v1.norm() * v2.norm() && v1.dot(&v2) > 0.
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bsp::cubic_face_split::bsp_polygon_split;
+    use crate::bsp::merging::{merge_coplanar_faces, try_merge};
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::textures::colored::{PURPLE, YELLOW};
+    use crate::primitives::vector::Vector3;
+
+    fn floor() -> CubicFace3 {
+        CubicFace3::new(
+            [Vector3::newi2(0, 0), Vector3::newi2(2, 0), Vector3::newi2(2, 2), Vector3::newi2(0, 2)],
+            Vector3::newi(0, 0, -1),
+            &YELLOW,
+        )
+    }
+
+    #[test]
+    fn test_merge_split_fragments() {
+        let splitter = CubicFace3::vface_from_line(Vector3::new(0., 0.7, 0.), Vector3::new(1., 0.7, 0.));
+        let (r1, r2) = bsp_polygon_split(&floor(), &splitter);
+        let (f1, f2) = (r1[0].clone(), r2[0].clone());
+
+        let merged = try_merge(&f1, &f2, 0.0001).unwrap();
+        assert_eq!(4., merged.area());
+        assert!(try_merge(&f2, &f1, 0.0001).is_some());
+
+        // Faces with a different texture are not merged
+        let mut other = f2.clone();
+        other.set_texture(&PURPLE);
+        assert!(try_merge(&f1, &other, 0.0001).is_none());
+    }
+
+    #[test]
+    fn test_merge_row_of_faces() {
+        // 4 faces in a row, with a continuous texture
+        let faces: Vec<CubicFace3> = (0..4)
+            .map(|i| {
+                let x = i as f32;
+                CubicFace3::new_with_uvs(
+                    [Vector3::new(x, 0., 0.), Vector3::new(x + 1., 0., 0.), Vector3::new(x + 1., 1., 0.), Vector3::new(x, 1., 0.)],
+                    [(x, 0.), (x + 1., 0.), (x + 1., 1.), (x, 1.)],
+                    Vector3::newi(0, 0, -1),
+                    &YELLOW,
+                )
+            })
+            .collect();
+        let merged = merge_coplanar_faces(faces.clone(), 0.0001);
+        assert_eq!(1, merged.len());
+        assert_eq!(4., merged[0].area());
+
+        // With a texture restarting on each face, there is nothing to merge
+        let faces: Vec<CubicFace3> = faces.iter().map(|f| CubicFace3::new(f.points(), *f.normal(), &YELLOW)).collect();
+        assert_eq!(4, merge_coplanar_faces(faces, 0.0001).len());
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::bsp::welding::VertexWelder;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::vector::Vector3;
+
+/// A defect between adjacent faces, through which the background can leak when they are drawn
+#[derive(Debug, PartialEq)]
+pub enum Seam {
+    /// A vertex of a face lies inside an edge of another face (the edge from point `edge` to the
+    /// next one), instead of being one of its ends: the rasterized edges do not exactly follow
+    /// each other.
+    TJunction { face: usize, edge: usize, point: Vector3 },
+    /// Two vertices are closer than epsilon but not equal: there is a hairline gap between the
+    /// faces.
+    Gap { point: Vector3, other: Vector3 },
+}
+
+/// Returns the indices of the points of each edge of the face
+fn edges(face: &CubicFace3) -> Vec<(usize, usize)> {
+    if face.is_triangle() {
+        vec![(0, 1), (1, 2), (2, 0)]
+    } else {
+        vec![(0, 1), (1, 2), (2, 3), (3, 0)]
+    }
+}
+
+/// Returns the distinct vertices of the face
+fn vertices(face: &CubicFace3) -> Vec<Vector3> {
+    let points = face.points();
+    let count = if face.is_triangle() { 3 } else { 4 };
+    points[..count].to_vec()
+}
+
+/// If `point` lies strictly inside the segment [a, b], returns its position along the segment
+/// (between 0 and 1).
+fn position_on_edge(a: Vector3, b: Vector3, point: Vector3, epsilon: f32) -> Option<f32> {
+    let edge = a.line_to(&b);
+    let length = edge.norm();
+    let t = a.line_to(&point).dot(&edge) / (length * length);
+    let projected = a + edge * t;
+    let inside = t * length > epsilon && (1. - t) * length > epsilon;
+    (inside && projected.line_to(&point).norm() <= epsilon).then_some(t)
+}
+
+/// Exact coordinates of a point, to index it
+fn point_key(p: &Vector3) -> [u32; 3] {
+    [p.x().to_bits(), p.y().to_bits(), p.z().to_bits()]
+}
+
+/// Edges of the faces, stored by the cells of a grid that they cross (once grown by epsilon), so
+/// that the edges going through a point are found without testing all of them
+struct EdgeIndex {
+    cell_size: f32,
+    cells: HashMap<(i64, i64, i64), Vec<(usize, usize)>>,
+}
+
+impl EdgeIndex {
+    /// Indexes the edges of the faces, as (face, first point of the edge)
+    fn new(faces: &[CubicFace3], epsilon: f32) -> Self {
+        // Cells of the size of an average edge: each edge crosses a few cells
+        let lengths: Vec<f32> = faces.iter().flat_map(|face| edges(face).into_iter().map(|(a, b)| face.points()[a].line_to(&face.points()[b]).norm())).collect();
+        let average = lengths.iter().sum::<f32>() / lengths.len().max(1) as f32;
+        let mut index = Self { cell_size: average.max(4. * epsilon), cells: HashMap::new() };
+        for (i, face) in faces.iter().enumerate() {
+            let points = face.points();
+            for (a, b) in edges(face) {
+                let (a1, a2) = (index.cell_of(&points[a], -epsilon), index.cell_of(&points[a], epsilon));
+                let (b1, b2) = (index.cell_of(&points[b], -epsilon), index.cell_of(&points[b], epsilon));
+                for x in a1.0.min(b1.0)..=a2.0.max(b2.0) {
+                    for y in a1.1.min(b1.1)..=a2.1.max(b2.1) {
+                        for z in a1.2.min(b1.2)..=a2.2.max(b2.2) {
+                            index.cells.entry((x, y, z)).or_default().push((i, a));
+                        }
+                    }
+                }
+            }
+        }
+        index
+    }
+
+    fn cell_of(&self, p: &Vector3, margin: f32) -> (i64, i64, i64) {
+        let cell = |c: f32| ((c + margin) / self.cell_size).floor() as i64;
+        (cell(p.x()), cell(p.y()), cell(p.z()))
+    }
+
+    /// Returns the edges which may go through the point
+    fn near(&self, p: &Vector3) -> &[(usize, usize)] {
+        self.cells.get(&self.cell_of(p, 0.)).map_or(&[], |edges| edges.as_slice())
+    }
+}
+
+/// Lists the seams between the faces: the gaps (vertices which should be welded) and the
+/// T-junctions (typically left by merging the faces, see `merge_coplanar_faces`).
+#[cfg(test)]
+pub fn find_seams(faces: &[CubicFace3], epsilon: f32) -> Vec<Seam> {
+    find_seams_with(faces, &[], epsilon)
+}
+
+/// Lists the seams of the faces, with the faces themselves and with the vertices of `neighbours`
+/// (which are left as they are)
+fn find_seams_with(faces: &[CubicFace3], neighbours: &[CubicFace3], epsilon: f32) -> Vec<Seam> {
+    let own: HashSet<[u32; 3]> = faces.iter().flat_map(vertices).map(|p| point_key(&p)).collect();
+    let mut welder = VertexWelder::new(epsilon);
+    let mut all_vertices: Vec<Vector3> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut seams = Vec::new();
+    for point in neighbours.iter().chain(faces).flat_map(vertices) {
+        if !seen.insert(point_key(&point)) {
+            continue;
+        }
+        let other = welder.weld(point);
+        if other != point && own.contains(&point_key(&point)) {
+            seams.push(Seam::Gap { point, other });
+        }
+        all_vertices.push(point);
+    }
+    let index = EdgeIndex::new(faces, epsilon);
+    for point in &all_vertices {
+        for &(i, a) in index.near(point) {
+            let points = faces[i].points();
+            let b = edges(&faces[i])[a].1;
+            if position_on_edge(points[a], points[b], *point, epsilon).is_some() {
+                seams.push(Seam::TJunction { face: i, edge: a, point: *point });
+            }
+        }
+    }
+    seams
+}
+
+/// Splits the face in two, along a line going through the point `t` of the edge starting at
+/// point `edge`. The line is parallel to the adjacent edges, so that both parts remain
+/// parallelograms (or triangles).
+fn split_at(face: &CubicFace3, edge: usize, t: f32) -> [CubicFace3; 2] {
+    let points = face.points();
+    let uvs = face.uvs();
+    let lerp = |p: Vector3, q: Vector3| p + p.line_to(&q) * t;
+    let lerp_uv = |u: (f32, f32), v: (f32, f32)| (u.0 + (v.0 - u.0) * t, u.1 + (v.1 - u.1) * t);
+    let normal = *face.normal();
+    if face.is_triangle() {
+        let [a, b, c] = [edge, (edge + 1) % 3, (edge + 2) % 3];
+        let (m, uv_m) = (lerp(points[a], points[b]), lerp_uv(uvs[a], uvs[b]));
+        [
+            CubicFace3::triangle([points[a], m, points[c]], [uvs[a], uv_m, uvs[c]], normal, face.texture()),
+            CubicFace3::triangle([m, points[b], points[c]], [uv_m, uvs[b], uvs[c]], normal, face.texture()),
+        ]
+        .map(|f| f.with_receivers(face.receivers()))
+    } else {
+        let [a, b, c, d] = [0, 1, 2, 3].map(|k| (edge + k) % 4);
+        let (m, uv_m) = (lerp(points[a], points[b]), lerp_uv(uvs[a], uvs[b]));
+        let (n, uv_n) = (lerp(points[d], points[c]), lerp_uv(uvs[d], uvs[c]));
+        [
+            CubicFace3::new_with_uvs([points[a], m, n, points[d]], [uvs[a], uv_m, uv_n, uvs[d]], normal, face.texture()),
+            CubicFace3::new_with_uvs([m, points[b], points[c], n], [uv_m, uvs[b], uvs[c], uv_n], normal, face.texture()),
+        ]
+        .map(|f| f.with_receivers(face.receivers()))
+    }
+}
+
+/// Removes the seams between the faces: the close vertices are welded together, then the faces
+/// are split at their T-junctions, so that adjacent faces share their edges exactly.
+pub fn fix_seams(faces: Vec<CubicFace3>, epsilon: f32) -> Vec<CubicFace3> {
+    fix_seams_with(faces, &[], epsilon)
+}
+
+/// Removes the seams of the faces, as `fix_seams`, and the seams with the adjacent `neighbours`
+/// (for instance the faces of the neighbouring chunks of a mesh), which are not modified.
+pub fn fix_seams_with(faces: Vec<CubicFace3>, neighbours: &[CubicFace3], epsilon: f32) -> Vec<CubicFace3> {
+    let mut faces = VertexWelder::new(epsilon).weld_faces(&faces);
+    loop {
+        let seams = find_seams_with(&faces, neighbours, epsilon);
+        if seams.is_empty() {
+            return faces;
+        }
+        // All the gaps are closed at once, then each face is split at one of its T-junctions
+        // (splitting a face changes its edges, which are listed again)
+        let mut welded: HashMap<[u32; 3], Vector3> = HashMap::new();
+        let mut split: HashSet<usize> = HashSet::new();
+        for seam in &seams {
+            if let Seam::Gap { point, other } = seam {
+                welded.insert(point_key(point), *other);
+            }
+        }
+        if !welded.is_empty() {
+            for face in &mut faces {
+                *face = face.map_points(|p| welded.get(&point_key(&p)).copied().unwrap_or(p));
+            }
+            continue;
+        }
+        for seam in seams {
+            let Seam::TJunction { face, edge, point } = seam else {
+                continue;
+            };
+            if !split.insert(face) {
+                continue;
+            }
+            let points = faces[face].points();
+            let next = edges(&faces[face])[edge].1;
+            let t = position_on_edge(points[edge], points[next], point, epsilon).expect("The point is on the edge");
+            let [first, second] = split_at(&faces[face], edge, t);
+            faces[face] = first;
+            faces.push(second);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bsp::merging::merge_coplanar_faces;
+    use crate::bsp::seams::{find_seams, fix_seams, Seam};
+    use crate::compare::{render_offscreen, RenderMethod};
+    use crate::frame::BACKGROUND_COLOR;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::position::Pose;
+    use crate::primitives::textures::colored::{ORANGE, YELLOW};
+    use crate::primitives::vector::{Vector3, UNIT_Z};
+    use crate::worlds::World;
+    use crate::{HEIGHT, WIDTH};
+
+    const EPSILON: f32 = 1e-4;
+
+    /// Floor of unit tiles, with runs of 3 tiles of the same color shifted on each row: merging
+    /// them creates long strips, whose edges have T-junctions with the strips of the next row.
+    fn checker_floor(size: i32) -> Vec<CubicFace3> {
+        let mut faces = Vec::new();
+        for i in 0..size {
+            for j in 0..size {
+                let texture = if ((i + j) / 3) % 2 == 0 { &YELLOW } else { &ORANGE };
+                let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                faces.push(CubicFace3::new_with_uvs(
+                    corners.map(|(x, y)| Vector3::newi(x, y, 0)),
+                    corners.map(|(x, y)| (x as f32, y as f32)),
+                    UNIT_Z.opposite(),
+                    texture,
+                ));
+            }
+        }
+        faces
+    }
+
+    /// Counts the background pixels surrounded by faces: they leak through a seam
+    fn seam_leaks(image: &[u8]) -> usize {
+        let (width, height) = (WIDTH as usize, HEIGHT as usize);
+        let is_background = |x: usize, y: usize| image[4 * (y * width + x)..4 * (y * width + x + 1)] == BACKGROUND_COLOR;
+        (1..height - 1)
+            .flat_map(|y| (1..width - 1).map(move |x| (x, y)))
+            .filter(|(x, y)| {
+                is_background(*x, *y)
+                    && !is_background(x - 1, *y)
+                    && !is_background(x + 1, *y)
+                    && !is_background(*x, y - 1)
+                    && !is_background(*x, y + 1)
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_find_and_fix_seams() {
+        let merged = merge_coplanar_faces(checker_floor(6), EPSILON);
+        assert!(merged.len() < 36);
+        let seams = find_seams(&merged, EPSILON);
+        assert!(!seams.is_empty());
+        assert!(seams.iter().all(|seam| matches!(seam, Seam::TJunction { .. })));
+
+        let fixed = fix_seams(merged, EPSILON);
+        assert!(find_seams(&fixed, EPSILON).is_empty());
+        assert_eq!(36., fixed.iter().map(|f| f.area()).sum::<f32>());
+
+        // Two faces which almost share an edge
+        let left = CubicFace3::vface_from_line(Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.));
+        let right = CubicFace3::vface_from_line(Vector3::new(1.00005, 0., 0.), Vector3::new(2., 0., 0.));
+        let faces = [left, right];
+        assert_eq!(2, find_seams(&faces, EPSILON).iter().filter(|seam| matches!(seam, Seam::Gap { .. })).count());
+        assert!(find_seams(&fix_seams(faces.to_vec(), EPSILON), EPSILON).is_empty());
+    }
+
+    #[test]
+    fn test_no_background_through_seams() {
+        let mut world = World::new(Camera::default());
+        for face in checker_floor(12) {
+            world.add_face(face);
+        }
+        world.compute_bsp();
+        world.merge_bsp_faces();
+        for k in 0..6 {
+            let mut pose = Pose::new(Vector3::new(6., 6.3, -3.), k as f32 * 0.53);
+            pose.apply_pitch(-1.3);
+            world.set_camera_pose(pose);
+            let image = render_offscreen(&mut world, RenderMethod::Bsp);
+            assert_eq!(0, seam_leaks(&image), "The background leaks at rotation {k}");
+        }
+    }
+}
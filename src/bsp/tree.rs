@@ -1,15 +1,71 @@
-use crate::bsp::cubic_face_split::{bsp_polygon_split, point_in_front_of};
+use crate::bsp::cubic_face_split::{bsp_polygon_split_with_epsilon, crosses_plane, clip_by_region, is_coplanar, lies_behind, lies_in_front, point_in_front_of};
+use crate::bsp::merging::merge_coplanar_faces;
+use crate::bsp::seams::fix_seams;
+use crate::bsp::welding::VertexWelder;
 use crate::frame::AbstractFrame;
 use crate::primitives::camera::Camera;
 use crate::primitives::cubic_face3::CubicFace3;
+use crate::tunables::tunables;
 
 use std::ops::Deref;
 
+/// Parameters of the construction of the BSP
+pub struct BspBuildOptions {
+    /// Tolerance (in meters) of the polygon splitting
+    pub epsilon: f32,
+    /// Maximum number of faces that can be stored in a leaf of the tree.
+    /// A group of faces can be stored in a leaf only if none of these faces would be split by
+    /// the plane of another face of the group. Use 1 to have a single face per node.
+    pub max_leaf_faces: usize,
+    /// Maximum number of faces of a convex leaf, whose faces can not hide each other (such as
+    /// the walls of a room, or the sides of a box) and are drawn without being sorted
+    pub max_convex_leaf_faces: usize,
+    /// Number of faces of a node tried as its splitting plane, evenly spread in the list of faces.
+    /// The plane with the lowest cost is kept (see `split_weight`). Use 1 to always split with the
+    /// first face, which is faster to build but gives unbalanced trees with many splits.
+    pub candidates: usize,
+    /// Cost of splitting a face, compared to the cost of each face of difference between the two
+    /// sides of the plane
+    pub split_weight: f32,
+}
+
+impl Default for BspBuildOptions {
+    fn default() -> Self {
+        Self {
+            epsilon: tunables().bsp_epsilon,
+            max_leaf_faces: 6,
+            max_convex_leaf_faces: 32,
+            candidates: 8,
+            split_weight: 4.,
+        }
+    }
+}
+
+/// Statistics about the shape of a BSP tree
+#[derive(Debug, PartialEq)]
+pub struct BspStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub convex_leaves: usize,
+    pub faces: usize,
+    pub depth: usize,
+}
+
 /// Binary Space Partionning
 ///
 /// This class represents a binary tree in rust
 pub struct BSPNode {
+    /// The faces contained in the splitting plane of this node.
+    /// For a leaf, this is the group of faces (not necessarily coplanar) held by the leaf.
     faces: Vec<CubicFace3>,
+    /// The face defining the splitting plane (None for a leaf). It is kept when the faces of the
+    /// node are removed.
+    plane: Option<CubicFace3>,
+    /// A leaf holds a small group of faces which are sorted by depth when rendered
+    is_leaf: bool,
+    /// The faces of a convex leaf are drawn in any order, since they never overlap once the faces
+    /// which do not face the camera are culled
+    is_convex: bool,
     in_front: Option<Box<BSPNode>>,
     behind: Option<Box<BSPNode>>,
     /// List of faces that needs to be processed by this node
@@ -22,6 +78,9 @@ impl BSPNode {
     fn new() -> Self {
         Self {
             faces: Vec::new(),
+            plane: None,
+            is_leaf: false,
+            is_convex: false,
             in_front: None,
             behind: None,
             to_process: Vec::new(),
@@ -33,7 +92,7 @@ impl BSPNode {
     }
 
     fn get_plane(&self) -> &CubicFace3 {
-        &self.faces[0]
+        self.plane.as_ref().expect("Only the leaves do not have a plane")
     }
 
     fn set_to_process(&mut self, to_process: Vec<CubicFace3>) {
@@ -43,10 +102,15 @@ impl BSPNode {
     // Public methods to visit the tree
 
     pub fn debug(&self, indent: usize) {
+        if self.is_leaf {
+            let kind = if self.is_convex { "Convex leaf" } else { "Leaf" };
+            println!("{:indent$}{kind} with {} faces", "", self.faces.len(), indent = indent);
+            return;
+        }
         println!(
             "{:indent$}Node from face: {:?}",
             "",
-            self.faces[0],
+            self.get_plane(),
             indent = indent
         );
         if let Some(node) = &self.in_front {
@@ -73,6 +137,131 @@ impl BSPNode {
         }
     }
 
+    /// Merges the adjacent coplanar faces stored in each node of the tree into larger faces.
+    /// This is an optional post-processing of the tree, which reduces the number of faces
+    /// to rasterize at each frame. The seams left between the faces are then fixed (see
+    /// `fix_seams`).
+    pub fn merge_coplanar_faces(&mut self, epsilon: f32) {
+        let faces = std::mem::take(&mut self.faces);
+        self.faces = fix_seams(merge_coplanar_faces(faces, epsilon), epsilon);
+        if let Some(node) = &mut self.in_front {
+            node.merge_coplanar_faces(epsilon);
+        }
+        if let Some(node) = &mut self.behind {
+            node.merge_coplanar_faces(epsilon);
+        }
+    }
+
+    /// Returns statistics about the tree starting from here
+    pub fn stats(&self) -> BspStats {
+        let mut stats = BspStats {
+            nodes: 1,
+            leaves: usize::from(self.is_leaf),
+            convex_leaves: usize::from(self.is_convex),
+            faces: self.faces.len(),
+            depth: 1,
+        };
+        for child in [&self.in_front, &self.behind].into_iter().flatten() {
+            let child_stats = child.stats();
+            stats.nodes += child_stats.nodes;
+            stats.leaves += child_stats.leaves;
+            stats.convex_leaves += child_stats.convex_leaves;
+            stats.faces += child_stats.faces;
+            stats.depth = stats.depth.max(child_stats.depth + 1);
+        }
+        stats
+    }
+
+    /// Return the number of faces starting from here
+    #[allow(dead_code)]
+    fn face_count(&self) -> usize {
+        self.faces.len()
+            + self.in_front.as_ref().map_or(0, |n| n.face_count())
+            + self.behind.as_ref().map_or(0, |n| n.face_count())
+    }
+
+    /// Adds a face to the tree, without building it again: the face is split by the planes of
+    /// the nodes, and only the leaves receiving a fragment are built again.
+    pub fn insert(&mut self, face: CubicFace3, options: &BspBuildOptions) {
+        if self.is_leaf {
+            let mut faces = std::mem::take(&mut self.faces);
+            faces.push(face);
+            *self = binary_space_partionning_with_options(&faces, options);
+            return;
+        }
+        if is_coplanar(&face, self.get_plane(), options.epsilon) {
+            self.faces.push(face);
+            return;
+        }
+        let (in_front, behind) = bsp_polygon_split_with_epsilon(&face, self.get_plane(), options.epsilon);
+        for (child, fragment) in [(&mut self.in_front, in_front), (&mut self.behind, behind)] {
+            match child {
+                Some(node) => fragment.into_iter().for_each(|fragment| node.insert(fragment, options)),
+                None if !fragment.is_empty() => *child = Some(Box::new(binary_space_partionning_with_options(&fragment, options))),
+                None => {}
+            }
+        }
+    }
+
+    /// Removes the parts of the faces of the tree covered by the face which face the same side,
+    /// and returns their area. The parts of the faces which were merged with the face (see
+    /// `merge_coplanar_faces`) and are outside of it are kept.
+    ///
+    /// The area is the one of the face if it was in the tree, but it is smaller if the face was
+    /// removed as a duplicate when building the tree, or if the splits were not exact.
+    ///
+    /// The nodes left without faces are pruned (see `prune`), so that the tree does not keep
+    /// growing when objects move.
+    pub fn remove(&mut self, face: &CubicFace3, epsilon: f32) -> f32 {
+        let mut removed = 0.;
+        for f in std::mem::take(&mut self.faces) {
+            if !is_coplanar(&f, face, epsilon) || f.normal().dot(face.normal()) <= 0. {
+                self.faces.push(f);
+                continue;
+            }
+            let (outside, inside) = clip_by_region(&f, face, epsilon);
+            removed += inside.iter().map(CubicFace3::area).sum::<f32>();
+            self.faces.extend(outside);
+        }
+        if self.is_leaf || is_coplanar(face, self.get_plane(), epsilon) {
+            return removed;
+        }
+        // The fragments are on the sides of the plane where the face extends
+        let (in_front, behind) = (!lies_behind(face, self.get_plane(), epsilon), !lies_in_front(face, self.get_plane(), epsilon));
+        for (extends, child) in [(in_front, &mut self.in_front), (behind, &mut self.behind)] {
+            if let (true, Some(node)) = (extends, child) {
+                removed += node.remove(face, epsilon);
+            }
+        }
+        self.prune();
+        removed
+    }
+
+    /// Returns true if the node and its children hold no face
+    fn is_empty(&self) -> bool {
+        self.faces.is_empty() && self.in_front.is_none() && self.behind.is_none()
+    }
+
+    /// Removes the empty children of the node, and replaces the node by its only child when it
+    /// holds no face: the plane of the node does not order anything anymore.
+    fn prune(&mut self) {
+        for child in [&mut self.in_front, &mut self.behind] {
+            if child.as_ref().is_some_and(|node| node.is_empty()) {
+                *child = None;
+            }
+        }
+        if self.is_leaf || !self.faces.is_empty() {
+            return;
+        }
+        match (self.in_front.take(), self.behind.take()) {
+            (Some(child), None) | (None, Some(child)) => *self = *child,
+            (in_front, behind) => {
+                self.in_front = in_front;
+                self.behind = behind;
+            }
+        }
+    }
+
     #[allow(dead_code)]
     fn in_front(&self) -> &Option<Box<BSPNode>> {
         &self.in_front
@@ -87,14 +276,31 @@ impl BSPNode {
 /// Implementation of the rendering using the BSP
 impl BSPNode {
     fn render(&self, camera: &Camera, drawer: &mut dyn AbstractFrame) {
-        let face3d = self.get_plane();
-        if face3d.is_visible_from(&camera) {
-            let face2d = face3d.projection(camera);
-            drawer.draw_one_face(&face2d);
+        // All the faces of the node are in the same plane, so they can be drawn in any order.
+        for face3d in &self.faces {
+            if face3d.is_visible_from(camera) {
+                let face2d = face3d.projection(camera);
+                drawer.draw_one_face(&face2d);
+            }
+        }
+    }
+
+    /// Renders the faces of a leaf, from the farthest to the closest one unless the leaf is convex
+    fn render_leaf(&self, camera: &Camera, drawer: &mut dyn AbstractFrame) {
+        let mut faces: Vec<&CubicFace3> = self.faces.iter().filter(|f| f.is_visible_from(camera)).collect();
+        if !self.is_convex {
+            faces.sort_by(|f1, f2| f2.distance_to(camera).total_cmp(&f1.distance_to(camera)));
+        }
+        for face3d in faces {
+            drawer.draw_one_face(&face3d.projection(camera));
         }
     }
 
     pub fn painter_algorithm_traversal(&self, camera: &Camera, drawer: &mut dyn AbstractFrame) {
+        if self.is_leaf {
+            self.render_leaf(camera, drawer);
+            return;
+        }
         // TODO handle collinear faces
         if point_in_front_of(self.get_plane(), camera.pose().position()) {
             // draw in the following order: behind, current, in-fronts
@@ -119,60 +325,138 @@ impl BSPNode {
 }
 
 /// Builds a binary space partitioning of the provided list of polygons.
-pub fn binary_space_partionning(faces: &Vec<CubicFace3>) -> BSPNode {
+pub fn binary_space_partionning(faces: &[CubicFace3]) -> BSPNode {
+    binary_space_partionning_with_options(faces, &BspBuildOptions::default())
+}
+
+/// Builds a binary space partitioning of the provided list of polygons, using the given options.
+///
+/// After each split, the vertices of the fragments are welded together so that nearly-identical
+/// vertices and faces do not accumulate in the deep levels of the tree.
+pub fn binary_space_partionning_with_options(faces: &[CubicFace3], options: &BspBuildOptions) -> BSPNode {
+    /// Returns true if none of the faces would be split by the plane of another face
+    fn can_be_leaf(faces: &[CubicFace3], epsilon: f32) -> bool {
+        faces.iter().enumerate().all(|(i, plane)| {
+            faces.iter().enumerate().all(|(j, f)| i == j || !crosses_plane(f, plane, epsilon))
+        })
+    }
+
+    /// Returns true if each face is in front of the planes of all the others (such as the walls of
+    /// a room), or behind them (such as the sides of a box)
+    fn is_convex_set(faces: &[CubicFace3], epsilon: f32) -> bool {
+        let all_pairs = |relation: fn(&CubicFace3, &CubicFace3, f32) -> bool| {
+            faces.iter().enumerate().all(|(i, plane)| faces.iter().enumerate().all(|(j, f)| i == j || relation(f, plane, epsilon)))
+        };
+        all_pairs(lies_in_front) || all_pairs(lies_behind)
+    }
+
     /// Recursive function in charge of building the BSP.
     /// The function uses the attributes `to_process` as the list of faces that this node
     /// as to classify.
-    fn recursive_construction(node: &mut BSPNode) {
-        // Select the first face in the list as the main face of the node
-        // This is an arbitrary decision.
+    fn recursive_construction(node: &mut BSPNode, welder: &mut VertexWelder, options: &BspBuildOptions) {
+        // Small groups of faces that do not overlap are kept together in a leaf
+        if node.to_process.len() > 1
+            && node.to_process.len() <= options.max_leaf_faces
+            && can_be_leaf(&node.to_process, options.epsilon)
+        {
+            node.is_convex = is_convex_set(&node.to_process, options.epsilon);
+            node.faces = std::mem::take(&mut node.to_process);
+            node.is_leaf = true;
+            return;
+        }
+        if node.to_process.len() > 1
+            && node.to_process.len() <= options.max_convex_leaf_faces
+            && is_convex_set(&node.to_process, options.epsilon)
+        {
+            node.faces = std::mem::take(&mut node.to_process);
+            node.is_leaf = true;
+            node.is_convex = true;
+            return;
+        }
+
+        // The best candidate becomes the main face of the node
+        let best = best_splitter(&node.to_process, options);
+        node.to_process.swap(0, best);
+        node.plane = Some(node.to_process[0].clone());
         node.add_face(node.to_process[0].clone());
 
         // Split all the other polygon in the list so that they are either stricly in_front or behind
         // the current frame.
         let mut in_fronts = vec![];
         let mut behinds = vec![];
-        for i in 1..node.to_process.len() {
-            let f = node.to_process[i].clone();
-            match bsp_polygon_split(&f, node.get_plane()) {
-                (Some(in_front), None) => in_fronts.push(in_front),
-                (None, Some(behind)) => behinds.push(behind),
-                (Some(in_front), Some(behind)) => {
-                    in_fronts.push(in_front);
-                    behinds.push(behind);
-                }
-                (_, _) => {
-                    panic!("Not supported : the face necessarly belongs to the remaining space.")
-                }
+        let mut coplanars = vec![];
+        for f in &node.to_process[1..] {
+            // Faces contained in the plane of the node are stored in the node itself
+            if is_coplanar(f, node.get_plane(), welder.epsilon()) {
+                coplanars.push(f.clone());
+                continue;
             }
+            let (in_front, behind) = bsp_polygon_split_with_epsilon(f, node.get_plane(), welder.epsilon());
+            in_fronts.extend(in_front);
+            behinds.extend(behind);
         }
+        node.faces.append(&mut coplanars);
+        let in_fronts = welder.weld_faces(&in_fronts);
+        let behinds = welder.weld_faces(&behinds);
 
         // Apply this algorithm to the two newly constructed list
-        if in_fronts.len() > 0 {
+        if !in_fronts.is_empty() {
             let mut new_node = BSPNode::new();
             new_node.set_to_process(in_fronts);
-            recursive_construction(&mut new_node);
+            recursive_construction(&mut new_node, welder, options);
             node.in_front = Some(Box::new(new_node));
         }
 
-        if behinds.len() > 0 {
+        if !behinds.is_empty() {
             let mut new_node = BSPNode::new();
             new_node.set_to_process(behinds);
-            recursive_construction(&mut new_node);
+            recursive_construction(&mut new_node, welder, options);
             node.behind = Some(Box::new(new_node));
         }
     }
 
+    /// Returns the index of the candidate face whose plane splits the fewest faces while sharing
+    /// them evenly between its two sides
+    fn best_splitter(faces: &[CubicFace3], options: &BspBuildOptions) -> usize {
+        let candidates = options.candidates.clamp(1, faces.len());
+        let cost = |plane: &CubicFace3| {
+            let (mut splits, mut in_front, mut behind) = (0, 0i32, 0i32);
+            for face in faces {
+                if crosses_plane(face, plane, options.epsilon) {
+                    splits += 1;
+                } else if is_coplanar(face, plane, options.epsilon) {
+                    continue;
+                } else if point_in_front_of(plane, &face.center()) {
+                    in_front += 1;
+                } else {
+                    behind += 1;
+                }
+            }
+            options.split_weight * splits as f32 + (in_front - behind).abs() as f32
+        };
+        (0..candidates)
+            .map(|i| i * faces.len() / candidates)
+            .map(|i| (cost(&faces[i]), i))
+            .min_by(|(c1, _), (c2, _)| c1.total_cmp(c2))
+            .map_or(0, |(_, i)| i)
+    }
+
+    let mut welder = VertexWelder::new(options.epsilon);
     let mut root = BSPNode::new();
-    root.set_to_process(faces.clone());
-    recursive_construction(&mut root);
-    return root;
+    root.set_to_process(welder.weld_faces(faces));
+    // All the faces may be degenerated, such as the slivers of a split
+    if root.to_process.is_empty() {
+        root.is_leaf = true;
+        return root;
+    }
+    recursive_construction(&mut root, &mut welder, options);
+    root
 }
 
 #[cfg(test)]
 mod tests {
     use crate::bsp::cubic_face_split::point_in_front_of;
-    use crate::bsp::tree::binary_space_partionning;
+    use crate::bsp::tree::{binary_space_partionning, binary_space_partionning_with_options, BspBuildOptions, BspStats};
     use crate::drawable::Drawable;
     use crate::frame::AbstractFrame;
     use crate::primitives::camera::Camera;
@@ -182,6 +466,7 @@ mod tests {
     use crate::primitives::point::Point2;
     use crate::primitives::textures::colored::YELLOW;
     use crate::primitives::vector::{UNIT_Z, Vector3};
+    use crate::voxels::VoxelWorld;
     use crate::worlds::World;
     use std::f32::consts::PI;
     use std::time::Instant;
@@ -207,26 +492,93 @@ mod tests {
         assert!(point_in_front_of(&face_ab, &c));
         assert!(!point_in_front_of(&face_ab, &h));
 
-        let bsp = binary_space_partionning(&vec![face_ab.clone(), face_gh.clone()]);
+        let one_face_per_node = BspBuildOptions { max_leaf_faces: 1, candidates: 1, ..Default::default() };
+        let bsp = binary_space_partionning_with_options(&[face_ab.clone(), face_gh.clone()], &one_face_per_node);
         bsp.debug(0);
         assert_eq!(3, bsp.len());
         assert_eq!(1, bsp.in_front().as_ref().unwrap().len());
         assert_eq!(1, bsp.behind().as_ref().unwrap().len());
 
-        let bsp = binary_space_partionning(&vec![
+        let faces = [
             face_ab.clone(),
             face_gh.clone(),
             face_cp.clone(),
             face_ce.clone(),
-        ]);
+        ];
+        let bsp = binary_space_partionning_with_options(&faces, &one_face_per_node);
         // bsp.debug(0);
         assert_eq!(5, bsp.len());
         assert_eq!(3, bsp.in_front().as_ref().unwrap().len());
         assert_eq!(1, bsp.behind().as_ref().unwrap().len());
+
+        // With leaves, the 3 faces in front of AB do not overlap and are stored in a single leaf
+        let bsp = binary_space_partionning_with_options(&faces, &BspBuildOptions { candidates: 1, ..Default::default() });
+        assert_eq!(3, bsp.len());
+        assert_eq!(1, bsp.in_front().as_ref().unwrap().len());
+        assert_eq!(3, bsp.in_front().as_ref().unwrap().face_count());
+    }
+
+    #[test]
+    fn test_bsp_splitter_heuristic() {
+        // The plane of the first face crosses all the other faces, whose planes do not cross it
+        let mut faces = vec![CubicFace3::vface_from_line(Vector3::newi2(0, 5), Vector3::newi2(0, 6))];
+        faces.extend((1..=4).map(|k| CubicFace3::vface_from_line(Vector3::newi2(-1, k), Vector3::newi2(1, k))));
+        let options = BspBuildOptions { max_leaf_faces: 1, ..Default::default() };
+        let first_face = binary_space_partionning_with_options(&faces, &BspBuildOptions { candidates: 1, ..options });
+        let best_face = binary_space_partionning_with_options(&faces, &options);
+        assert_eq!(9, first_face.stats().faces);
+        assert_eq!(5, best_face.stats().faces);
+    }
+
+    #[test]
+    fn test_bsp_convex_leaves() {
+        // The 10 faces around two blocks can not hide each other
+        let mut voxels = VoxelWorld::new(0..1);
+        let block = voxels.add_block_kind(&YELLOW, &YELLOW);
+        voxels.set(0, 0, 0, Some(block));
+        voxels.set(1, 0, 0, Some(block));
+        let bsp = binary_space_partionning(&voxels.chunk_faces((0, 0)));
+        assert_eq!(BspStats { nodes: 1, leaves: 1, convex_leaves: 1, faces: 10, depth: 1 }, bsp.stats());
+
+        // The two boxes are separated without splitting any face
+        let mut faces = voxels.chunk_faces((0, 0));
+        faces.extend(voxels.chunk_faces((0, 0)).iter().map(|face| face.map_points(|p| p + Vector3::newi(3, 0, 0))));
+        let stats = binary_space_partionning(&faces).stats();
+        assert_eq!((2, 20), (stats.convex_leaves, stats.faces));
+    }
+
+    #[test]
+    fn test_bsp_construction_removes_duplicated_faces() {
+        let face = CubicFace3::vface_from_line(Vector3::newi2(0, 0), Vector3::newi2(1, 0));
+        let nearly_same = CubicFace3::vface_from_line(Vector3::new(0.00001, 0., 0.), Vector3::newi2(1, 0));
+        let bsp = binary_space_partionning(&[face.clone(), face, nearly_same]);
+        assert_eq!(1, bsp.len());
+    }
+
+    #[test]
+    fn test_bsp_merge_coplanar_faces() {
+        // A row of coplanar faces with a continuous texture, and a wall
+        let mut faces: Vec<CubicFace3> = (0..4)
+            .map(|i| {
+                let x = i as f32;
+                CubicFace3::new_with_uvs(
+                    [Vector3::new(x, 0., 0.), Vector3::new(x + 1., 0., 0.), Vector3::new(x + 1., 1., 0.), Vector3::new(x, 1., 0.)],
+                    [(x, 0.), (x + 1., 0.), (x + 1., 1.), (x, 1.)],
+                    Vector3::newi(0, 0, -1),
+                    &YELLOW,
+                )
+            })
+            .collect();
+        faces.push(CubicFace3::vface_from_line(Vector3::newi2(0, 2), Vector3::newi2(4, 2)));
+
+        let mut bsp = binary_space_partionning(&faces);
+        assert_eq!(5, bsp.face_count());
+        bsp.merge_coplanar_faces(0.0001);
+        assert_eq!(2, bsp.face_count());
     }
 
     struct DummyFrame {
-        faces: Vec<[Point2; 4]>,
+        faces: Vec<Vec<Point2>>,
     }
 
     impl DummyFrame {
@@ -246,7 +598,7 @@ mod tests {
 
     impl AbstractFrame for DummyFrame {
         fn draw_one_face(&mut self, face: &CubicFace2) {
-            self.faces.push(face.points());
+            self.faces.push(face.points().to_vec());
         }
     }
 
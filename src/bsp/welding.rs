@@ -0,0 +1,130 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::vector::Vector3;
+
+/// Snaps vertices which are closer than `epsilon` from each other onto a single canonical vertex.
+///
+/// When a plane splits several faces, the intersection points are computed independently for
+/// each face and end up being "near" but not exactly equal. Welding them makes the fragments
+/// share their vertices exactly, which then allows to detect degenerated and duplicated faces
+/// before they accumulate in the deep levels of the tree.
+pub struct VertexWelder {
+    epsilon: f32,
+    /// Canonical vertices, stored by the cell of the grid (of size epsilon) that contains them
+    cells: HashMap<(i64, i64, i64), Vec<Vector3>>,
+}
+
+impl VertexWelder {
+    pub fn new(epsilon: f32) -> Self {
+        Self {
+            epsilon,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn epsilon(&self) -> f32 {
+        self.epsilon
+    }
+
+    fn cell_of(&self, p: &Vector3) -> (i64, i64, i64) {
+        (
+            (p.x() / self.epsilon).floor() as i64,
+            (p.y() / self.epsilon).floor() as i64,
+            (p.z() / self.epsilon).floor() as i64,
+        )
+    }
+
+    /// Returns the canonical vertex associated with `p`.
+    /// If no vertex was registered close enough to `p`, then `p` becomes a canonical vertex.
+    pub fn weld(&mut self, p: Vector3) -> Vector3 {
+        let (i, j, k) = self.cell_of(&p);
+        // A vertex closer than epsilon is necessarily in one of the adjacent cells
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    if let Some(vertices) = self.cells.get(&(i + di, j + dj, k + dk)) {
+                        if let Some(v) = vertices.iter().find(|v| p.line_to(v).norm() <= self.epsilon) {
+                            return *v;
+                        }
+                    }
+                }
+            }
+        }
+        self.cells.entry((i, j, k)).or_default().push(p);
+        p
+    }
+
+    /// Returns a copy of the face where all the points were welded
+    pub fn weld_face(&mut self, face: &CubicFace3) -> CubicFace3 {
+        face.map_points(|p| self.weld(p))
+    }
+
+    /// Welds all the provided faces and removes
+    /// * the degenerated faces (two of their points were welded together)
+    /// * the duplicated faces (same points and same normal)
+    pub fn weld_faces(&mut self, faces: &[CubicFace3]) -> Vec<CubicFace3> {
+        let mut seen = HashSet::new();
+        let mut welded = Vec::with_capacity(faces.len());
+        for face in faces {
+            let face = self.weld_face(face);
+            if !is_degenerated(&face) && seen.insert(face_key(&face)) {
+                welded.push(face);
+            }
+        }
+        welded
+    }
+}
+
+/// Returns true if two consecutive points of the face are identical
+/// (apart from the last point of a triangle, which is repeated)
+fn is_degenerated(face: &CubicFace3) -> bool {
+    let points = face.points();
+    let n = if face.is_triangle() { 3 } else { 4 };
+    (0..n).any(|i| points[i] == points[(i + 1) % n])
+}
+
+/// Key identifying a face: its exact points and its (rounded) normal.
+/// This is only meaningful once the face has been welded.
+fn face_key(face: &CubicFace3) -> ([u32; 12], [i32; 3]) {
+    let mut points = [0; 12];
+    for (i, p) in face.points().iter().enumerate() {
+        points[3 * i] = p.x().to_bits();
+        points[3 * i + 1] = p.y().to_bits();
+        points[3 * i + 2] = p.z().to_bits();
+    }
+    let n = face.normal();
+    let normal = [n.x(), n.y(), n.z()].map(|c| (c * 1000.).round() as i32);
+    (points, normal)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bsp::welding::VertexWelder;
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_weld_near_vertices() {
+        let mut welder = VertexWelder::new(0.001);
+        let p = Vector3::new(0.5, 0.0, 0.0);
+        assert_eq!(p, welder.weld(p));
+        // Points nearby are snapped, even across the cells of the grid
+        assert_eq!(p, welder.weld(Vector3::new(0.5004, 0.0, 0.0)));
+        assert_eq!(p, welder.weld(Vector3::new(0.4996, -0.0003, 0.0002)));
+        // Points further away are kept
+        let q = Vector3::new(0.502, 0.0, 0.0);
+        assert_eq!(q, welder.weld(q));
+    }
+
+    #[test]
+    fn test_weld_faces_removes_duplicates_and_degenerated() {
+        let mut welder = VertexWelder::new(0.001);
+        let f1 = CubicFace3::vface_from_line(Vector3::newi2(0, 0), Vector3::newi2(1, 0));
+        let f2 = CubicFace3::vface_from_line(Vector3::new(0.0002, 0., 0.), Vector3::new(1.0, 0.0003, 0.));
+        let sliver = CubicFace3::vface_from_line(Vector3::newi2(2, 0), Vector3::new(2.0005, 0., 0.));
+        let faces = welder.weld_faces(&[f1.clone(), f2, sliver]);
+        assert_eq!(1, faces.len());
+        assert_eq!(f1.points(), faces[0].points());
+    }
+}
@@ -0,0 +1,191 @@
+use std::f32::consts::FRAC_PI_2;
+
+use crate::primitives::position::Pose;
+use crate::primitives::vector::{Vector3, UNIT_Z};
+
+/// How the rig places the camera relative to its target
+#[derive(Clone, Debug, PartialEq)]
+pub enum RigMode {
+    /// The camera stays at the offset from the target, given in the frame of the target (x in
+    /// front of it, y on its right and z down), and turns with it
+    Follow { offset: Vector3 },
+    /// The camera turns around the target at a distance, and always looks at it. The angles are
+    /// relative to the world, and the pitch is positive above the target.
+    Orbit { distance: f32, yaw: f32, pitch: f32 },
+}
+
+/// Moves a camera after a target (the player, a vehicle...) instead of setting its pose directly:
+/// the camera catches up with the pose given by the mode at a speed set by the stiffness, which
+/// smooths the jerky motions of the target.
+pub struct CameraRig {
+    mode: RigMode,
+    /// How fast the camera catches up with the target, in 1/s. Each second, the distance to the
+    /// wanted pose is divided by e^stiffness; an infinite stiffness disables the smoothing.
+    stiffness: f32,
+    /// Whether the camera looks at the target instead of taking its orientation (orbiting cameras
+    /// always look at their target)
+    look_at: bool,
+    /// Height above the position of the target at which the camera looks
+    look_at_height: f32,
+    /// Current pose of the camera, None until the first update
+    pose: Option<Pose>,
+}
+
+impl CameraRig {
+    pub fn new(mode: RigMode) -> Self {
+        Self { mode, stiffness: 8., look_at: false, look_at_height: 0., pose: None }
+    }
+
+    /// A camera behind the target and above it, looking at it
+    pub fn third_person(distance: f32, height: f32) -> Self {
+        Self::new(RigMode::Follow { offset: Vector3::new(-distance, 0., -height) }).with_look_at(true)
+    }
+
+    pub fn orbit(distance: f32) -> Self {
+        Self::new(RigMode::Orbit { distance, yaw: 0., pitch: 0. })
+    }
+
+    pub fn with_stiffness(mut self, stiffness: f32) -> Self {
+        self.stiffness = stiffness;
+        self
+    }
+
+    pub fn with_look_at(mut self, look_at: bool) -> Self {
+        self.look_at = look_at;
+        self
+    }
+
+    /// Looks at a point above the target (the head of a character) instead of its position
+    pub fn with_look_at_height(mut self, height: f32) -> Self {
+        self.look_at_height = height;
+        self
+    }
+
+    pub fn mode(&self) -> &RigMode {
+        &self.mode
+    }
+
+    /// Changes the mode: the camera moves smoothly to its new place
+    pub fn set_mode(&mut self, mode: RigMode) {
+        self.mode = mode;
+    }
+
+    /// Turns the camera around the target (in orbit mode), keeping it between the poles
+    pub fn orbit_by(&mut self, yaw: f32, pitch: f32) {
+        if let RigMode::Orbit { yaw: current_yaw, pitch: current_pitch, .. } = &mut self.mode {
+            *current_yaw += yaw;
+            *current_pitch = (*current_pitch + pitch).clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+        }
+    }
+
+    /// Moves the camera closer to the target (negative amount) or further, in orbit mode
+    pub fn zoom_by(&mut self, amount: f32) {
+        if let RigMode::Orbit { distance, .. } = &mut self.mode {
+            *distance = (*distance + amount).max(0.1);
+        }
+    }
+
+    /// The current pose of the camera, None before the first update
+    pub fn pose(&self) -> Option<&Pose> {
+        self.pose.as_ref()
+    }
+
+    /// Moves the camera right to its pose, as after a teleport of the target
+    pub fn snap(&mut self, target: &Pose) -> &Pose {
+        self.pose.insert(self.wanted_pose(target))
+    }
+
+    /// The pose the camera moves to, for this pose of the target
+    pub fn wanted_pose(&self, target: &Pose) -> Pose {
+        let focus = *target.position() - UNIT_Z * self.look_at_height;
+        match &self.mode {
+            RigMode::Follow { offset } => {
+                let forward = target.orientation();
+                let right = Vector3::new(-forward.y(), forward.x(), 0.);
+                let position = *target.position() + forward * offset.x() + right * offset.y() + UNIT_Z * offset.z();
+                let mut pose = target.clone();
+                pose.set_position(position);
+                if self.look_at {
+                    pose.look_at(&focus);
+                }
+                pose
+            }
+            RigMode::Orbit { distance, yaw, pitch } => {
+                // Placed on the opposite side of the direction it looks at
+                let direction = Vector3::new(yaw.cos() * pitch.cos(), -yaw.sin() * pitch.cos(), pitch.sin());
+                let mut pose = Pose::new(focus - direction * *distance, *yaw);
+                pose.look_at(&focus);
+                pose
+            }
+        }
+    }
+
+    /// Moves the camera towards its pose for the target, `dt` seconds after the previous update,
+    /// and returns its new pose. The first update places the camera right away.
+    pub fn update(&mut self, target: &Pose, dt: f32) -> &Pose {
+        let wanted = self.wanted_pose(target);
+        let Some(current) = self.pose.take() else {
+            return self.pose.insert(wanted);
+        };
+        // Does not depend on the frame rate: two updates of dt move as much as one of 2 * dt
+        let t = if self.stiffness.is_finite() { 1. - (-self.stiffness * dt).exp() } else { 1. };
+        self.pose.insert(current.slerp(&wanted, t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::camera_rig::{CameraRig, RigMode};
+    use crate::primitives::position::Pose;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_follow_target() {
+        let mut rig = CameraRig::third_person(4., 1.).with_stiffness(5.);
+        let mut target = Pose::new(Vector3::empty(), 0.);
+        assert_eq!(Vector3::new(-4., 0., -1.), *rig.update(&target, 0.1).position());
+        // The camera looks down at the target
+        assert!(rig.pose().unwrap().pitch() < 0.);
+
+        // The camera catches up with the target smoothly, whatever the frame rate
+        target.set_position(Vector3::new(10., 0., 0.));
+        let x = rig.update(&target, 0.1).position().x();
+        assert!(x > -4. && x < 6., "{x}");
+        let mut other = CameraRig::third_person(4., 1.).with_stiffness(5.);
+        other.snap(&Pose::new(Vector3::empty(), 0.));
+        other.update(&target, 0.05);
+        assert!((other.update(&target, 0.05).position().x() - x).abs() < 1e-4);
+        for _ in 0..50 {
+            rig.update(&target, 0.1);
+        }
+        assert!(rig.pose().unwrap().position().line_to(&Vector3::new(6., 0., -1.)).norm() < 1e-3);
+
+        // Turning the target turns the camera around it, along the shortest arc
+        target.set_rotation(PI / 2.);
+        let pose = rig.snap(&target);
+        assert!(pose.position().line_to(&Vector3::new(10., 4., -1.)).norm() < 1e-4, "{:?}", pose.position());
+        let mut rig = CameraRig::new(RigMode::Follow { offset: Vector3::empty() }).with_stiffness(1.);
+        rig.snap(&Pose::new(Vector3::empty(), 0.1));
+        let yaw = rig.update(&Pose::new(Vector3::empty(), 2. * PI - 0.1), 0.1).rotation_z();
+        assert!(yaw > -0.1 && yaw < 0.1, "{yaw}");
+    }
+
+    #[test]
+    fn test_orbit() {
+        let mut rig = CameraRig::orbit(5.).with_stiffness(f32::INFINITY);
+        let target = Pose::new(Vector3::new(1., 2., 0.), 0.);
+        rig.orbit_by(PI / 2., 0.);
+        let pose = rig.update(&target, 0.1).clone();
+        assert!(pose.position().line_to(&Vector3::new(1., 7., 0.)).norm() < 1e-4, "{:?}", pose.position());
+        assert!(pose.orientation().line_to(&Vector3::new(0., -1., 0.)).norm() < 1e-4);
+
+        // Seen from above, the camera looks down
+        rig.orbit_by(0., 1.);
+        rig.zoom_by(-10.);
+        let pose = rig.update(&target, 0.1);
+        assert!(pose.pitch() < -0.9);
+        assert_eq!(&RigMode::Orbit { distance: 0.1, yaw: PI / 2., pitch: 1. }, rig.mode());
+    }
+}
@@ -0,0 +1,291 @@
+use std::str::FromStr;
+
+use crate::drawable::Drawable;
+use crate::frame::Frame;
+use crate::png_saver;
+use crate::primitives::camera::Camera;
+use crate::primitives::cube::Cube3;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::mesh::Mesh;
+use crate::primitives::tessellation::Patch;
+use crate::primitives::textures::colored::{ORANGE, PURPLE, YELLOW};
+use crate::primitives::textures::pixelated::Pixelated;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::vector::Vector3;
+use crate::ui::palette::DebugPalette;
+use crate::viewport::Viewport;
+use crate::worlds::World;
+
+/// Default maximum difference between two channels of a pixel for them to be considered equal
+pub const DEFAULT_TOLERANCE: u8 = 8;
+/// Percentage of pixels which may differ between two renderers, because the edges of the
+/// faces are not rasterized in the exact same way.
+pub const MAX_MISMATCH_PERCENTAGE: f32 = 0.5;
+
+/// The different ways to render a world
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMethod {
+    /// Painter algorithm, sorting the faces by depth
+    Painter,
+    /// Painter algorithm, using the binary space partitioning of the world
+    Bsp,
+    Raytracer,
+}
+
+impl FromStr for RenderMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "painter" => Ok(Self::Painter),
+            "bsp" => Ok(Self::Bsp),
+            "raytracer" => Ok(Self::Raytracer),
+            _ => Err(format!("Unknown renderer '{s}' (expected painter, bsp or raytracer)")),
+        }
+    }
+}
+
+/// Renders the world in a new buffer, without any window
+pub fn render_offscreen(world: &mut World, method: RenderMethod) -> Vec<u8> {
+    let viewport = world.viewport();
+    let mut buffer: Vec<u8> = vec![0; viewport.buffer_len()];
+    world.draw_sky(&mut buffer);
+    match method {
+        // The BSP is kept, for the next images drawn with it
+        RenderMethod::Painter => world.draw_painter_sorted(&mut Frame::new(&mut buffer, viewport)),
+        RenderMethod::Bsp => {
+            if world.bsp().is_none() {
+                world.compute_bsp();
+            }
+            world.draw_painter(&mut Frame::new(&mut buffer, viewport));
+        }
+        RenderMethod::Raytracer => world.draw_raytracing(&mut buffer),
+    }
+    buffer
+}
+
+/// Renders the world seen by the camera in a new buffer of `width` x `height` pixels, without any
+/// window. The camera of the world is put back afterwards.
+pub fn render_to_buffer(world: &mut World, camera: &Camera, width: u32, height: u32, method: RenderMethod) -> Vec<u8> {
+    let mut camera = camera.clone();
+    camera.set_viewport(Viewport::new(width, height));
+    let previous = world.replace_camera(camera);
+    let image = render_offscreen(world, method);
+    world.replace_camera(previous);
+    image
+}
+
+/// Result of the per-pixel comparison of two images
+pub struct ImageDiff {
+    pub mismatched: usize,
+    pub total: usize,
+    /// Visualisation of the differences: the first image in grayscale, with the mismatched
+    /// pixels colored by the heat of the palette (red by default, the brighter, the larger the
+    /// difference)
+    pub image: Vec<u8>,
+}
+
+impl ImageDiff {
+    pub fn mismatch_percentage(&self) -> f32 {
+        100. * self.mismatched as f32 / self.total as f32
+    }
+}
+
+/// Compares two RGBA images of the same size. Pixels are mismatched when one of their color
+/// channels differ by more than `tolerance`.
+pub fn diff_images(a: &[u8], b: &[u8], tolerance: u8) -> ImageDiff {
+    diff_images_with_palette(a, b, tolerance, DebugPalette::Standard)
+}
+
+/// Same as `diff_images`, with the mismatched pixels of the visualisation colored by the palette
+pub fn diff_images_with_palette(a: &[u8], b: &[u8], tolerance: u8, palette: DebugPalette) -> ImageDiff {
+    assert_eq!(a.len(), b.len(), "The images must have the same size");
+    let mut mismatched = 0;
+    let mut image = Vec::with_capacity(a.len());
+    for (pa, pb) in a.chunks_exact(4).zip(b.chunks_exact(4)) {
+        let difference = (0..3).map(|c| pa[c].abs_diff(pb[c])).max().unwrap();
+        if difference > tolerance {
+            mismatched += 1;
+            image.extend_from_slice(&palette.heat(difference));
+        } else {
+            let gray = ((pa[0] as u32 + pa[1] as u32 + pa[2] as u32) / 6) as u8;
+            image.extend_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+    ImageDiff {
+        mismatched,
+        total: a.len() / 4,
+        image,
+    }
+}
+
+/// Scenes used to check that the renderers agree, with their name
+pub fn equivalence_scenes() -> Vec<(&'static str, World)> {
+    let stone = &TextureHandle::new(Pixelated::stone());
+    let soil_side = &TextureHandle::new(Pixelated::soil_side());
+    let soil_top = &TextureHandle::new(Pixelated::soil_top());
+    let new_world = |position: Vector3, rotation: f32| {
+        let mut world = World::new(Camera::default());
+        world.set_camera_position(position);
+        world.set_camera_rotation(rotation);
+        world
+    };
+    let facing_y = -std::f32::consts::PI / 2.;
+
+    // A single colored cube, seen from the front
+    let mut single_cube = new_world(Vector3::new(0.5, -2., -0.5), facing_y);
+    single_cube.add_cube(Cube3::minecraft_like(Vector3::newi(0, 0, 0), &YELLOW, &PURPLE));
+
+    // Textured cubes seen from above, with a diagonal orientation
+    let mut textured = new_world(Vector3::new(-2., -3., -2.5), facing_y + 0.6);
+    for i in 0..3 {
+        textured.add_cube(Cube3::minecraft_like(Vector3::newi(i, 0, 0), soil_side, soil_top));
+        textured.add_cube(Cube3::minecraft_like(Vector3::newi(i, 1, 0), stone, stone));
+    }
+
+    // Faces at different distances, which must be ordered correctly
+    let mut layers = new_world(Vector3::new(1., -3., -0.5), facing_y);
+    for (i, texture) in [&YELLOW, &ORANGE, &PURPLE].into_iter().enumerate() {
+        let x = i as f32 * 0.5;
+        let mut face = CubicFace3::vface_from_line(Vector3::new(x, i as f32, 0.), Vector3::new(x + 1., i as f32, 0.));
+        face.set_texture(texture);
+        layers.add_face(face);
+    }
+
+    // A pyramid made of triangles
+    let mut mesh = new_world(Vector3::new(0.5, -2.5, -1.), facing_y);
+    mesh.add_mesh(Mesh::pyramid(Vector3::new(0., -0.5, 0.), 1., 1.2, stone));
+
+    // Curved surfaces, tessellated in flat faces
+    let mut curved = new_world(Vector3::new(1., -3.5, -1.), facing_y);
+    curved.add_patch(Patch::cylinder(Vector3::new(0., 0., 0.), 0.4, 1.5, 12, stone));
+    curved.add_patch(Patch::arch(Vector3::new(2., 0., 0.), 0.6, 0.9, 0.3, 8, soil_side));
+
+    vec![
+        ("single cube", single_cube),
+        ("textured cubes", textured),
+        ("layered faces", layers),
+        ("triangle mesh", mesh),
+        ("curved surfaces", curved),
+    ]
+}
+
+/// Renders each equivalence scene with all the renderers, and checks that they match the
+/// raytracer.
+fn run_suite() -> Result<(), String> {
+    let mut failures = 0;
+    for (name, mut world) in equivalence_scenes() {
+        let reference = render_offscreen(&mut world, RenderMethod::Raytracer);
+        for method in [RenderMethod::Painter, RenderMethod::Bsp] {
+            let diff = diff_images(&reference, &render_offscreen(&mut world, method), DEFAULT_TOLERANCE);
+            let ok = diff.mismatch_percentage() <= MAX_MISMATCH_PERCENTAGE;
+            println!("{name}: {method:?} vs Raytracer = {:.3}% {}", diff.mismatch_percentage(), if ok { "ok" } else { "FAILED" });
+            if !ok {
+                failures += 1;
+            }
+        }
+    }
+    match failures {
+        0 => Ok(()),
+        n => Err(format!("{n} comparisons exceed {MAX_MISMATCH_PERCENTAGE}% of mismatched pixels")),
+    }
+}
+
+/// Command line tool: renders the world with two renderers and writes the difference.
+///
+/// `compare <renderer> <renderer> [--out <path>] [--tolerance <value>]`
+///
+/// `compare --suite` checks all the renderers against the raytracer, on the equivalence scenes.
+pub fn run_compare(args: &[String], mut world: World, palette: DebugPalette) -> Result<(), String> {
+    if args.first().is_some_and(|a| a == "--suite") {
+        return run_suite();
+    }
+    let (first, second) = match args {
+        [first, second, ..] => (first.parse::<RenderMethod>()?, second.parse::<RenderMethod>()?),
+        _ => return Err("Usage: compare <painter|bsp|raytracer> <painter|bsp|raytracer> [--out <path>] [--tolerance <value>]".to_string()),
+    };
+    let option = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1));
+    let out = option("--out").map(String::as_str).unwrap_or("diff.png");
+    let tolerance = match option("--tolerance") {
+        Some(value) => value.parse().map_err(|_| format!("Invalid tolerance '{value}'"))?,
+        None => DEFAULT_TOLERANCE,
+    };
+
+    let image1 = render_offscreen(&mut world, first);
+    let image2 = render_offscreen(&mut world, second);
+    let diff = diff_images_with_palette(&image1, &image2, tolerance, palette);
+    let viewport = world.viewport();
+    png_saver::save_frame(out, &diff.image, viewport.width, viewport.height).map_err(|err| err.to_string())?;
+    println!(
+        "{first:?} vs {second:?}: {} / {} pixels mismatched ({:.3}%), diff written to {out}",
+        diff.mismatched,
+        diff.total,
+        diff.mismatch_percentage()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compare::{diff_images, equivalence_scenes, render_offscreen, render_to_buffer, RenderMethod, DEFAULT_TOLERANCE, MAX_MISMATCH_PERCENTAGE};
+    use crate::frame::BACKGROUND_COLOR;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::color::Color;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::position::Pose;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+    use crate::worlds::World;
+
+    #[test]
+    fn test_renderers_equivalence() {
+        for (name, mut world) in equivalence_scenes() {
+            let raytracer = render_offscreen(&mut world, RenderMethod::Raytracer);
+            let background = diff_images(&raytracer, &BACKGROUND_COLOR.repeat(raytracer.len() / 4), 0);
+            assert!(background.mismatch_percentage() > 2., "Scene '{name}' is almost empty");
+            for method in [RenderMethod::Painter, RenderMethod::Bsp] {
+                let diff = diff_images(&raytracer, &render_offscreen(&mut world, method), DEFAULT_TOLERANCE);
+                println!("{name}: {method:?} vs Raytracer = {:.3}%", diff.mismatch_percentage());
+                assert!(
+                    diff.mismatch_percentage() <= MAX_MISMATCH_PERCENTAGE,
+                    "Scene '{name}': {method:?} differs from the raytracer on {:.3}% of the pixels",
+                    diff.mismatch_percentage()
+                );
+            }
+            // Drawing with the painter algorithm does not throw away the BSP
+            render_offscreen(&mut world, RenderMethod::Painter);
+            assert!(world.bsp().is_some(), "Scene '{name}' lost its BSP");
+        }
+    }
+
+    #[test]
+    fn test_render_to_buffer() {
+        let mut world = World::new(Camera::default());
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(3, 0, 0), &YELLOW, &YELLOW));
+        let viewport = world.viewport();
+
+        // Looking at the cube from the other side, in a smaller frame
+        let mut camera = Camera::default();
+        camera.set_pose(Pose::new(Vector3::new(7., 0.5, 0.5), std::f32::consts::PI));
+        let painter = render_to_buffer(&mut world, &camera, 64, 48, RenderMethod::Painter);
+        assert_eq!(64 * 48 * 4, painter.len());
+        assert!(painter.chunks(4).any(|pixel| pixel == Color::yellow().rgba()));
+        let raytracer = render_to_buffer(&mut world, &camera, 64, 48, RenderMethod::Raytracer);
+        assert!(diff_images(&painter, &raytracer, DEFAULT_TOLERANCE).mismatch_percentage() <= MAX_MISMATCH_PERCENTAGE);
+
+        // The camera of the world did not change
+        assert_eq!(viewport, world.viewport());
+        assert_eq!(Vector3::empty(), *world.camera().pose().position());
+    }
+
+    #[test]
+    fn test_diff_images() {
+        let a = [10, 10, 10, 255, 10, 10, 10, 255, 0, 0, 0, 255, 100, 0, 0, 255];
+        let b = [10, 10, 10, 255, 14, 10, 10, 255, 200, 0, 0, 255, 0, 0, 0, 255];
+        let diff = diff_images(&a, &b, 5);
+        assert_eq!(2, diff.mismatched);
+        assert_eq!(50., diff.mismatch_percentage());
+        assert_eq!([228, 0, 0, 255], diff.image[8..12]);
+        assert_eq!(a.len(), diff.image.len());
+    }
+}
@@ -0,0 +1,302 @@
+use serde::{Deserialize, Serialize};
+
+use crate::input_map::{Action, InputMap};
+use crate::input_map::key_from_name;
+use crate::tunables::Tunables;
+use crate::ui::palette::DebugPalette;
+
+/// Default location of the configuration file
+pub const CONFIG_PATH: &str = "config.toml";
+
+/// Algorithm used to render the world
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RendererMode {
+    /// Painter algorithm, sorting the faces by depth
+    Painter,
+    /// Painter algorithm, using the binary space partitioning of the world
+    Bsp,
+    /// Faces drawn in any order, keeping the closest one for each pixel
+    ZBuffer,
+    Raytracer,
+    /// Experimental ray marching of the signed distance field of the world
+    Raymarching,
+}
+
+impl RendererMode {
+    /// Returns the renderer following this one, to cycle through all of them
+    pub fn next(self) -> Self {
+        match self {
+            RendererMode::Painter => RendererMode::Bsp,
+            RendererMode::Bsp => RendererMode::ZBuffer,
+            RendererMode::ZBuffer => RendererMode::Raytracer,
+            RendererMode::Raytracer => RendererMode::Raymarching,
+            RendererMode::Raymarching => RendererMode::Painter,
+        }
+    }
+}
+
+/// Settings of the game, stored in a TOML file.
+///
+/// Missing entries of the file take their default value, so that old files remain valid.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Size of the window, relative to the size of the frame
+    pub resolution_scale: f32,
+    /// Horizontal field of view, in degrees
+    pub fov: f32,
+    /// Angle (in radians) by which the camera turns at each key press
+    pub sensitivity: f32,
+    /// Angle (in radians) by which the camera turns for each pixel moved by the captured mouse
+    pub mouse_sensitivity: f32,
+    /// Objects further than this distance (in meters) are not drawn
+    pub draw_distance: f32,
+    /// Fades the objects into the fog before they reach the draw distance
+    pub fog: bool,
+    /// Color of the fog (red, green, blue), by default the horizon of the day sky
+    pub fog_color: [u8; 3],
+    pub renderer: RendererMode,
+    /// Colors of the debug visualizations
+    pub debug_palette: DebugPalette,
+    pub key_bindings: KeyBindings,
+    /// Numbers tuning the behavior of the engine
+    pub tunables: Tunables,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            resolution_scale: 1.,
+            fov: 100.,
+            sensitivity: std::f32::consts::PI / 16.,
+            mouse_sensitivity: 0.003,
+            draw_distance: 100.,
+            fog: true,
+            fog_color: [200, 220, 240],
+            renderer: RendererMode::Bsp,
+            debug_palette: DebugPalette::Standard,
+            key_bindings: KeyBindings::default(),
+            tunables: Tunables::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let config: Config = toml::from_str(text).map_err(|err| err.to_string())?;
+        config.key_bindings.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("The configuration can always be serialized")
+    }
+
+    /// Loads the configuration from the file, or returns the default configuration if the file
+    /// does not exist.
+    pub fn load(path: &str) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::from_toml(&text),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_toml())
+    }
+}
+
+/// Keys used for each action, given by their name (for instance `Up` or `W`)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub forward: String,
+    pub backward: String,
+    pub left: String,
+    pub right: String,
+    pub up: String,
+    pub down: String,
+    pub turn_left: String,
+    pub turn_right: String,
+    pub pause: String,
+    pub slow_motion: String,
+    pub single_step: String,
+    pub weather: String,
+    pub look_up: String,
+    pub look_down: String,
+    pub roll_left: String,
+    pub roll_right: String,
+    pub jump: String,
+    pub walk: String,
+    pub toggle_fps: String,
+    pub screenshot: String,
+    pub toggle_ui: String,
+    pub spectate: String,
+    pub zoom_in: String,
+    pub zoom_out: String,
+    pub print_camera: String,
+    pub describe_scene: String,
+    pub cycle_renderer: String,
+    pub timing_graph: String,
+    pub log_movement: String,
+    pub switch_scene: String,
+    pub save_scene: String,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            forward: "Up".to_string(),
+            backward: "Down".to_string(),
+            left: "Left".to_string(),
+            right: "Right".to_string(),
+            up: "K".to_string(),
+            down: "J".to_string(),
+            turn_left: "R".to_string(),
+            turn_right: "E".to_string(),
+            pause: "P".to_string(),
+            slow_motion: "O".to_string(),
+            single_step: "N".to_string(),
+            weather: "T".to_string(),
+            look_up: "Y".to_string(),
+            look_down: "H".to_string(),
+            roll_left: "Z".to_string(),
+            roll_right: "X".to_string(),
+            jump: "Space".to_string(),
+            walk: "G".to_string(),
+            toggle_fps: "F1".to_string(),
+            screenshot: "F5".to_string(),
+            toggle_ui: "F6".to_string(),
+            spectate: "F11".to_string(),
+            zoom_in: "Equals".to_string(),
+            zoom_out: "Minus".to_string(),
+            print_camera: "F2".to_string(),
+            describe_scene: "F3".to_string(),
+            cycle_renderer: "F4".to_string(),
+            timing_graph: "F7".to_string(),
+            log_movement: "F8".to_string(),
+            switch_scene: "F9".to_string(),
+            save_scene: "F10".to_string(),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Returns all the actions: their name, the key they are bound to, and the action itself
+    pub fn actions_mut(&mut self) -> [(&'static str, &mut String, Action); 31] {
+        [
+            ("Forward", &mut self.forward, Action::MoveForward),
+            ("Backward", &mut self.backward, Action::MoveBackward),
+            ("Left", &mut self.left, Action::StrafeLeft),
+            ("Right", &mut self.right, Action::StrafeRight),
+            ("Up", &mut self.up, Action::MoveUp),
+            ("Down", &mut self.down, Action::MoveDown),
+            ("Turn left", &mut self.turn_left, Action::TurnLeft),
+            ("Turn right", &mut self.turn_right, Action::TurnRight),
+            ("Pause", &mut self.pause, Action::Pause),
+            ("Slow motion", &mut self.slow_motion, Action::SlowMotion),
+            ("Single step", &mut self.single_step, Action::SingleStep),
+            ("Weather", &mut self.weather, Action::CycleWeather),
+            ("Look up", &mut self.look_up, Action::LookUp),
+            ("Look down", &mut self.look_down, Action::LookDown),
+            ("Roll left", &mut self.roll_left, Action::RollLeft),
+            ("Roll right", &mut self.roll_right, Action::RollRight),
+            ("Jump", &mut self.jump, Action::Jump),
+            ("Walk", &mut self.walk, Action::ToggleWalk),
+            ("FPS", &mut self.toggle_fps, Action::ToggleFps),
+            ("Screenshot", &mut self.screenshot, Action::Screenshot),
+            ("Settings", &mut self.toggle_ui, Action::ToggleUi),
+            ("Spectate", &mut self.spectate, Action::Spectate),
+            ("Zoom in", &mut self.zoom_in, Action::ZoomIn),
+            ("Zoom out", &mut self.zoom_out, Action::ZoomOut),
+            ("Print camera", &mut self.print_camera, Action::PrintCamera),
+            ("Describe scene", &mut self.describe_scene, Action::DescribeScene),
+            ("Renderer", &mut self.cycle_renderer, Action::CycleRenderer),
+            ("Timing graph", &mut self.timing_graph, Action::TimingGraph),
+            ("Log movement", &mut self.log_movement, Action::LogMovement),
+            ("Switch scene", &mut self.switch_scene, Action::SwitchScene),
+            ("Save scene", &mut self.save_scene, Action::SaveScene),
+        ]
+    }
+
+    /// Returns the map from the actions to their keys, ignoring the unsupported keys
+    pub fn input_map(&self) -> InputMap {
+        let mut map = InputMap::new();
+        for (_, name, action) in self.clone().actions_mut() {
+            if let Some(key) = key_from_name(name) {
+                map.bind(action, key);
+            }
+        }
+        map
+    }
+
+    /// Checks that each action is bound to a supported key, which no other action uses
+    fn validate(&self) -> Result<(), String> {
+        let mut bindings = self.clone();
+        let actions = bindings.actions_mut();
+        for (i, (action, name, _)) in actions.iter().enumerate() {
+            if key_from_name(name).is_none() {
+                return Err(format!("Unsupported key '{name}' for action '{action}'"));
+            }
+            if let Some((other, _, _)) = actions[..i].iter().find(|(_, other, _)| other == name) {
+                return Err(format!("Key '{name}' is bound to both '{other}' and '{action}'"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::VirtualKeyCode;
+
+    use crate::config::{Config, RendererMode};
+    use crate::input_map::Action;
+    use crate::ui::palette::DebugPalette;
+    use crate::tunables::Tunables;
+
+    #[test]
+    fn test_config_roundtrip() {
+        let mut config = Config {
+            fov: 75.,
+            renderer: RendererMode::Raymarching,
+            debug_palette: DebugPalette::ColorBlind,
+            fog: false,
+            fog_color: [10, 20, 30],
+            ..Default::default()
+        };
+        config.key_bindings.forward = "W".to_string();
+        let parsed = Config::from_toml(&config.to_toml()).unwrap();
+        assert_eq!(config, parsed);
+        assert_eq!(Some(Action::MoveForward), parsed.key_bindings.input_map().action(VirtualKeyCode::W));
+    }
+
+    #[test]
+    fn test_partial_config() {
+        let config = Config::from_toml("fov = 60.0\n[key_bindings]\nforward = \"W\"\n[tunables]\nscreen_padding = 4\n").unwrap();
+        assert_eq!(60., config.fov);
+        assert_eq!(RendererMode::Bsp, config.renderer);
+        assert!(config.fog);
+        assert_eq!("W", config.key_bindings.forward);
+        assert_eq!("Down", config.key_bindings.backward);
+        assert_eq!(4, config.tunables.screen_padding);
+        assert_eq!(Tunables::default().distance_scale, config.tunables.distance_scale);
+
+        assert!(Config::from_toml("[key_bindings]\nforward = \"NotAKey\"\n").is_err());
+        // Z is already bound to the roll
+        assert!(Config::from_toml("[key_bindings]\nforward = \"Z\"\n").is_err());
+    }
+
+    #[test]
+    fn test_renderer_cycle() {
+        let mut renderer = RendererMode::Painter;
+        for _ in 0..4 {
+            renderer = renderer.next();
+            assert_ne!(RendererMode::Painter, renderer);
+        }
+        assert_eq!(RendererMode::Painter, renderer.next());
+        assert_eq!(Some(RendererMode::ZBuffer), toml::Value::String("zbuffer".to_string()).try_into().ok());
+    }
+}
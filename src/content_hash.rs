@@ -0,0 +1,110 @@
+use std::hash::Hasher;
+
+use crate::primitives::textures::Texture;
+use crate::primitives::vector::Vector3;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+/// The textures without texels are sampled on a grid of this many points along each side
+const TEXTURE_SAMPLES: usize = 8;
+
+/// Hashes the content of a scene or of a frame (FNV-1a), to detect regressions by comparing the
+/// hashes of two branches instead of images.
+///
+/// Unlike the hasher of the standard library, the hashes do not depend on the version of Rust.
+pub struct ContentHasher {
+    hash: u64,
+}
+
+impl ContentHasher {
+    pub fn new() -> Self {
+        Self { hash: FNV_OFFSET }
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    pub fn write_vector(&mut self, vector: &Vector3) {
+        for value in [vector.x(), vector.y(), vector.z()] {
+            self.write_f32(value);
+        }
+    }
+
+    /// Writes the size of the texture and its colors: the color of each texel, or the colors of
+    /// a grid of points for the textures without texels
+    pub fn write_texture(&mut self, texture: &dyn Texture) {
+        let (width, height) = (texture.width(), texture.height());
+        self.write_f32(width);
+        self.write_f32(height);
+        // The texels are sampled over the largest side, which covers all of them even if the
+        // texture repeats itself along the other side
+        let (samples, step) = match texture.texel_size() {
+            Some(size) => ((width.max(height) / size).round() as usize, (size, size)),
+            None => (TEXTURE_SAMPLES, (width / TEXTURE_SAMPLES as f32, height / TEXTURE_SAMPLES as f32)),
+        };
+        for row in 0..samples {
+            for column in 0..samples {
+                let color = texture.color_at((column as f32 + 0.5) * step.0, (row as f32 + 0.5) * step.1);
+                self.write(&color.rgba());
+            }
+        }
+    }
+}
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for ContentHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash = (self.hash ^ *byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    // The integers are hashed in little endian on all the platforms
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+}
+
+/// Returns the hash of a frame buffer
+pub fn frame_hash(frame: &[u8]) -> u64 {
+    let mut hasher = ContentHasher::new();
+    hasher.write(frame);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use crate::content_hash::{frame_hash, ContentHasher};
+
+    #[test]
+    fn test_content_hash() {
+        // Reference values of FNV-1a
+        assert_eq!(0xcbf29ce484222325, ContentHasher::new().finish());
+        assert_eq!(0xaf63dc4c8601ec8c, frame_hash(b"a"));
+        assert_ne!(frame_hash(&[0, 1]), frame_hash(&[1, 0]));
+
+        let mut hasher = ContentHasher::new();
+        hasher.write_f32(1.);
+        assert_eq!(frame_hash(&1f32.to_bits().to_le_bytes()), hasher.finish());
+    }
+}
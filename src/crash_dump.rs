@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use doom::png_saver;
+use doom::primitives::position::Pose;
+use doom::viewport::Viewport;
+
+/// Directory where the crash dumps are written
+pub const CRASH_DIRECTORY: &str = "crashes";
+
+/// What is known about the game, written to disk if it panics
+#[derive(Default)]
+struct CrashState {
+    /// Last frame which was rendered without panicking
+    frame: Option<Vec<u8>>,
+    /// Size of the last frame
+    viewport: Viewport,
+    pose: Option<Pose>,
+    /// Description of the world (see `World::describe`), recorded when the scene changes
+    description: Option<Value>,
+}
+
+static STATE: Mutex<Option<CrashState>> = Mutex::new(None);
+
+/// Runs `f` on the recorded state. The panic can happen while the state is locked (or the lock
+/// can be poisoned by an earlier panic): the state is then skipped rather than waiting forever.
+fn with_state<R>(f: impl FnOnce(&mut CrashState) -> R) -> Option<R> {
+    let mut state = match STATE.try_lock() {
+        Ok(state) => state,
+        Err(std::sync::TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => return None,
+    };
+    Some(f(state.get_or_insert_with(CrashState::default)))
+}
+
+/// Remembers the last frame which was successfully rendered, and the pose of the camera
+pub fn record_frame(frame: &[u8], viewport: Viewport, pose: &Pose) {
+    with_state(|state| {
+        match &mut state.frame {
+            Some(last) if last.len() == frame.len() => last.copy_from_slice(frame),
+            _ => state.frame = Some(frame.to_vec()),
+        }
+        state.viewport = viewport;
+        state.pose = Some(pose.clone());
+    });
+}
+
+/// Remembers the description of the world, to be called whenever its objects change
+pub fn record_scene(description: Value) {
+    with_state(|state| state.description = Some(description));
+}
+
+/// Installs a panic hook which writes the recorded state to a new directory of
+/// `CRASH_DIRECTORY` before the default hook prints the panic and the program unwinds.
+pub fn install() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let written = with_state(|state| dump(state, &info.to_string(), Path::new(CRASH_DIRECTORY)));
+        match written {
+            Some(Ok(directory)) => eprintln!("Crash dump written to {}", directory.display()),
+            Some(Err(err)) => eprintln!("Could not write the crash dump: {err}"),
+            None => eprintln!("Could not write the crash dump: the state is in use"),
+        }
+        default_hook(info);
+    }));
+}
+
+/// Writes the panic message, the last frame, the pose of the camera and the description of the
+/// world to a new directory, named after the current time. Returns the path of this directory.
+fn dump(state: &CrashState, message: &str, parent: &Path) -> std::io::Result<PathBuf> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let mut directory = parent.join(format!("crash_{}_{:03}", now.as_secs(), now.subsec_millis()));
+    let mut i = 1;
+    while directory.exists() {
+        directory = parent.join(format!("crash_{}_{:03}_{i}", now.as_secs(), now.subsec_millis()));
+        i += 1;
+    }
+    std::fs::create_dir_all(&directory)?;
+
+    std::fs::write(directory.join("panic.txt"), message)?;
+    if let Some(frame) = &state.frame {
+        let path = directory.join("frame.png");
+        png_saver::save_frame(&path.to_string_lossy(), frame, state.viewport.width, state.viewport.height)?;
+    }
+    if let Some(pose) = &state.pose {
+        std::fs::write(directory.join("camera.json"), serde_json::to_string_pretty(pose)?)?;
+    }
+    if let Some(description) = &state.description {
+        std::fs::write(directory.join("scene.json"), serde_json::to_string_pretty(description)?)?;
+    }
+    Ok(directory)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use doom::primitives::position::Pose;
+    use doom::primitives::vector::Vector3;
+    use doom::viewport::Viewport;
+
+    use crate::crash_dump::{dump, CrashState};
+
+    #[test]
+    fn test_dump() {
+        let parent = std::env::temp_dir().join("crash_dump_test");
+        let pose = Pose::new(Vector3::new(1., 2., -3.), 0.5);
+        let state = CrashState {
+            frame: Some(vec![128; 4 * 320 * 240]),
+            viewport: Viewport::new(320, 240),
+            pose: Some(pose.clone()),
+            description: Some(json!({ "objects": [] })),
+        };
+        let first = dump(&state, "index out of bounds", &parent).unwrap();
+        assert_eq!("index out of bounds", std::fs::read_to_string(first.join("panic.txt")).unwrap());
+        assert_eq!(b"\x89PNG", &std::fs::read(first.join("frame.png")).unwrap()[..4]);
+        let camera: Pose = serde_json::from_str(&std::fs::read_to_string(first.join("camera.json")).unwrap()).unwrap();
+        assert!(camera == pose);
+        assert!(first.join("scene.json").exists());
+
+        // Nothing was recorded yet: only the message is written, in another directory
+        let second = dump(&CrashState::default(), "early panic", &parent).unwrap();
+        assert_ne!(first, second);
+        assert!(!second.join("frame.png").exists());
+        std::fs::remove_dir_all(&parent).unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+use crate::frame::Frame;
+use crate::primitives::camera::Camera;
+use crate::primitives::vector::Vector3;
+use crate::tunables::tunables;
+use crate::ui::palette::DebugPalette;
+use crate::viewport::Viewport;
+
+/// Size (in pixels) of the squares drawn on the waypoints
+const WAYPOINT_SIZE: i32 = 4;
+
+/// A path drawn over the world for debugging, such as the route found for an AI or the path
+/// followed by the camera: its waypoints are connected by segments.
+pub struct DebugPath {
+    name: String,
+    points: Vec<Vector3>,
+    visible: bool,
+    /// Category of the debug palette used to draw the path
+    color: usize,
+}
+
+impl DebugPath {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn points(&self) -> &[Vector3] {
+        &self.points
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn draw(&self, frame: &mut Frame, camera: &Camera, color: [u8; 4]) {
+        let forward = camera.screen_to_world_ray(camera.optical_center().0, camera.optical_center().1);
+        let depth = |point: &Vector3| forward.origin.line_to(point).dot(&forward.direction);
+        // The segments are cut at the near plane, since their projection goes to infinity at the
+        // camera
+        let near = tunables().near_distance;
+        for segment in self.points.windows(2) {
+            let (mut a, mut b) = (segment[0], segment[1]);
+            let (depth_a, depth_b) = (depth(&a), depth(&b));
+            if depth_a < near && depth_b < near {
+                continue;
+            }
+            // Only keep the part of the segment in front of the camera
+            if depth_a < near {
+                a = a + (b - a) * ((near - depth_a) / (depth_b - depth_a));
+            } else if depth_b < near {
+                b = b + (a - b) * ((near - depth_b) / (depth_a - depth_b));
+            }
+            let (a, b) = (camera.project(&a), camera.project(&b));
+            if let Some((x0, y0, x1, y1)) = clip_segment(a.x(), a.y(), b.x(), b.y(), frame.viewport()) {
+                frame.draw_line(x0.round() as i32, y0.round() as i32, x1.round() as i32, y1.round() as i32, color);
+            }
+        }
+        for point in &self.points {
+            if let Some(uv) = camera.world_to_screen(point) {
+                let half = WAYPOINT_SIZE / 2;
+                frame.fill_rect(uv.x() as i32 - half, uv.y() as i32 - half, WAYPOINT_SIZE, WAYPOINT_SIZE, color);
+            }
+        }
+    }
+}
+
+/// Returns the part of the segment inside the viewport (Liang-Barsky algorithm), if any. The
+/// projection of a point close to the camera can be very far outside of the frame.
+fn clip_segment(x0: f32, y0: f32, x1: f32, y1: f32, viewport: Viewport) -> Option<(f32, f32, f32, f32)> {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let (mut t_min, mut t_max) = (0f32, 1f32);
+    let (width, height) = ((viewport.width - 1) as f32, (viewport.height - 1) as f32);
+    for (p, q) in [(-dx, x0), (dx, width - x0), (-dy, y0), (dy, height - y0)] {
+        if p == 0. {
+            if q < 0. {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0. {
+            t_min = t_min.max(t);
+        } else {
+            t_max = t_max.min(t);
+        }
+        if t_min > t_max {
+            return None;
+        }
+    }
+    Some((x0 + t_min * dx, y0 + t_min * dy, x0 + t_max * dx, y0 + t_max * dy))
+}
+
+/// Returns a path going along the 12 edges of the view frustum of the camera (some of them twice)
+fn frustum_outline(camera: &Camera, far: f32) -> Vec<Vector3> {
+    let near = camera.frustum_corners(tunables().near_distance);
+    let far = camera.frustum_corners(far);
+    let mut points = vec![near[0], near[1], near[2], near[3], near[0]];
+    for i in 0..4 {
+        points.extend([far[i], far[(i + 1) % 4], near[(i + 1) % 4], far[(i + 1) % 4]]);
+    }
+    points
+}
+
+/// The debug paths of a world, which can be shown or hidden one by one. Each path is drawn with a
+/// color of the debug palette, picked when the path is added so that it does not change when the
+/// other paths are removed.
+#[derive(Default)]
+pub struct DebugPaths {
+    paths: Vec<DebugPath>,
+    /// Palette category of the next path added
+    next_color: usize,
+}
+
+impl DebugPaths {
+    /// Shows a path, replacing the points of the path with the same name (which stays hidden if
+    /// it was)
+    pub fn set(&mut self, name: &str, points: Vec<Vector3>) {
+        match self.paths.iter_mut().find(|path| path.name == name) {
+            Some(path) => path.points = points,
+            None => {
+                self.paths.push(DebugPath { name: name.to_string(), points, visible: true, color: self.next_color });
+                self.next_color += 1;
+            }
+        }
+    }
+
+    /// Shows the view frustum of the camera, up to the distance `far`: the frames seen at the near
+    /// plane and at `far`, connected by their corners
+    pub fn set_frustum(&mut self, name: &str, camera: &Camera, far: f32) {
+        self.set(name, frustum_outline(camera, far));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.paths.retain(|path| path.name != name);
+    }
+
+    /// Shows or hides a path. Returns false if there is no such path.
+    pub fn set_visible(&mut self, name: &str, visible: bool) -> bool {
+        match self.paths.iter_mut().find(|path| path.name == name) {
+            Some(path) => {
+                path.visible = visible;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DebugPath> {
+        self.paths.iter()
+    }
+
+    /// Draws the visible paths on top of the frame, which are not hidden by the objects
+    pub fn draw(&self, frame: &mut Frame, camera: &Camera, palette: DebugPalette) {
+        for path in self.paths.iter().filter(|path| path.visible) {
+            path.draw(frame, camera, palette.category(path.color));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug_paths::{clip_segment, DebugPaths};
+    use crate::frame::Frame;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::vector::Vector3;
+    use crate::ui::palette::DebugPalette;
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn test_clip_segment() {
+        let viewport = Viewport::new(101, 51);
+        assert_eq!(Some((10., 10., 20., 30.)), clip_segment(10., 10., 20., 30., viewport));
+        assert_eq!(Some((0., 25., 100., 25.)), clip_segment(-1000., 25., 1000., 25., viewport));
+        assert_eq!(Some((50., 0., 50., 20.)), clip_segment(50., -44., 50., 20., viewport));
+        assert_eq!(None, clip_segment(-10., -10., 200., -1., viewport));
+    }
+
+    #[test]
+    fn test_draw_paths() {
+        let mut camera = Camera::default();
+        let viewport = Viewport::new(200, 100);
+        camera.set_viewport(viewport);
+        // Number of pixels of the path
+        let drawn = |paths: &DebugPaths| {
+            let mut buffer = vec![0; viewport.buffer_len()];
+            paths.draw(&mut Frame::new(&mut buffer, viewport), &camera, DebugPalette::ColorBlind);
+            buffer.chunks(4).filter(|pixel| *pixel != [0, 0, 0, 0]).count()
+        };
+
+        // A path going from behind the camera to the front: only the part in front is drawn
+        let mut paths = DebugPaths::default();
+        paths.set("route", vec![Vector3::new(-2., 0., 0.5), Vector3::new(3., 0., 0.5), Vector3::new(3., 2., 0.5)]);
+        let visible = drawn(&paths);
+        assert!(visible > 50);
+        assert!(paths.set_visible("route", false));
+        assert_eq!(0, drawn(&paths));
+        assert!(!paths.set_visible("other", true));
+
+        // Updating the path keeps it hidden
+        paths.set("route", vec![Vector3::new(3., 0., 0.5), Vector3::new(3., 2., 0.5)]);
+        assert_eq!(0, drawn(&paths));
+        paths.set_visible("route", true);
+        assert!(drawn(&paths) > 0 && drawn(&paths) < visible);
+        paths.remove("route");
+        assert_eq!(0, paths.iter().count());
+    }
+
+    #[test]
+    fn test_path_colors() {
+        let mut camera = Camera::default();
+        let viewport = Viewport::new(200, 100);
+        camera.set_viewport(viewport);
+        let palette = DebugPalette::ColorBlind;
+        // Colors (without alpha) of the pixels of the paths
+        let colors = |paths: &DebugPaths| {
+            let mut buffer = vec![0; viewport.buffer_len()];
+            paths.draw(&mut Frame::new(&mut buffer, viewport), &camera, palette);
+            let mut colors: Vec<&[u8]> = buffer.chunks(4).filter(|pixel| *pixel != [0, 0, 0, 0]).map(|pixel| &pixel[..3]).collect();
+            colors.sort();
+            colors.dedup();
+            colors.into_iter().map(|color| color.to_vec()).collect::<Vec<_>>()
+        };
+        let category = |index: usize| palette.category(index)[..3].to_vec();
+
+        let mut paths = DebugPaths::default();
+        paths.set("first", vec![Vector3::new(3., -0.2, 0.2), Vector3::new(3., -0.2, -0.2)]);
+        paths.set("second", vec![Vector3::new(3., 0.2, 0.2), Vector3::new(3., 0.2, -0.2)]);
+        assert_eq!(2, colors(&paths).len());
+        assert!(colors(&paths).contains(&category(1)));
+
+        // Removing the first path does not change the color of the second one
+        paths.remove("first");
+        assert_eq!(vec![category(1)], colors(&paths));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::frame::AbstractFrame;
+use crate::primitives::camera::Camera;
+use crate::primitives::cubic_face2::CubicFace2;
+
+/// Fraction of the draw distance around the boundary where objects keep their previous visibility
+const HYSTERESIS: f32 = 0.1;
+
+/// Maximum distance at which objects are drawn.
+///
+/// To avoid objects flickering when the camera moves back and forth around the boundary, a
+/// visible object is only hidden beyond `max * (1 + HYSTERESIS)`, and a hidden object only
+/// reappears within `max * (1 - HYSTERESIS)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawDistance {
+    max: f32,
+}
+
+impl DrawDistance {
+    pub fn new(max: f32) -> Self {
+        Self { max: max.max(0.) }
+    }
+
+    /// Distance beyond which nothing is drawn
+    pub fn outer(&self) -> f32 {
+        self.max * (1. + HYSTERESIS)
+    }
+
+    /// Returns whether an object at this distance is visible, given its previous visibility
+    pub fn is_visible(&self, distance: f32, was_visible: bool) -> bool {
+        if was_visible {
+            distance <= self.outer()
+        } else {
+            distance <= self.max * (1. - HYSTERESIS)
+        }
+    }
+}
+
+/// A frame which only draws the faces closer than a maximum distance to the camera
+pub struct DistanceCulledFrame<'a> {
+    inner: &'a mut dyn AbstractFrame,
+    camera: &'a Camera,
+    max: f32,
+}
+
+impl<'a> DistanceCulledFrame<'a> {
+    pub fn new(inner: &'a mut dyn AbstractFrame, camera: &'a Camera, max: f32) -> Self {
+        Self { inner, camera, max }
+    }
+}
+
+impl<'a> AbstractFrame for DistanceCulledFrame<'a> {
+    fn draw_one_face(&mut self, face: &CubicFace2) {
+        if face.distance_to(self.camera) <= self.max {
+            self.inner.draw_one_face(face);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::draw_distance::DrawDistance;
+
+    #[test]
+    fn test_hysteresis() {
+        let distance = DrawDistance::new(10.);
+        assert!(distance.is_visible(10.5, true));
+        assert!(!distance.is_visible(10.5, false));
+        assert!(!distance.is_visible(11.5, true));
+        assert!(distance.is_visible(8.5, false));
+    }
+}
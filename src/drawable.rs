@@ -1,10 +1,11 @@
 use winit::event::VirtualKeyCode;
 use crate::frame::AbstractFrame;
+use crate::input_map::Action;
 
 /// If an object is drawable, it can be rendered onto the screen
 pub trait Drawable {
-    /// Update the internal state
-    fn update(&mut self);
+    /// Update the internal state, advancing the simulation by `dt` seconds
+    fn update(&mut self, dt: f32);
 
     /// Draw onto pixels
     fn draw_raytracing(&self, frame: &mut [u8]);
@@ -20,4 +21,16 @@ pub trait Drawable {
 
     /// A key as held (maintaining press)
     fn key_held(&mut self, key: VirtualKeyCode);
+
+    /// The key of an action was pressed
+    fn action_pressed(&mut self, action: Action);
+
+    /// The key of an action was held
+    fn action_held(&mut self, action: Action);
+
+    /// The captured mouse moved by (dx, dy) pixels, to look around
+    fn mouse_moved(&mut self, dx: f32, dy: f32);
+
+    /// The mouse wheel turned by `delta` lines (positive when turned away from the user)
+    fn mouse_scrolled(&mut self, delta: f32);
 }
\ No newline at end of file
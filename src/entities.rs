@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+
+use crate::motion_model::fall;
+use crate::primitives::object::ObjectId;
+use crate::primitives::transformation::Transform;
+use crate::primitives::vector::Vector3;
+use crate::updatable::WorldQuery;
+use crate::worlds::World;
+
+/// Identifier of an entity, which stays valid when other entities are spawned or despawned
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntityId(u64);
+
+/// Optional motion of an entity, integrated at each update. The entities do not collide with the
+/// objects of the world.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Physics {
+    /// Velocity in meters per second
+    pub velocity: Vector3,
+    /// Whether the entity falls (the z-axis goes down), as the camera does (see `motion_model`)
+    pub gravity: bool,
+}
+
+impl Physics {
+    pub fn new(velocity: Vector3, gravity: bool) -> Self {
+        Self { velocity, gravity }
+    }
+}
+
+/// A game object: a transform, the object of the world drawn for it (if any) and its physics.
+pub struct Entity {
+    /// Motion of the entity from where its object was added to the world
+    transform: Transform,
+    /// True when the transform changed since it was applied to the object of the world
+    moved: bool,
+    render: Option<ObjectId>,
+    pub physics: Option<Physics>,
+}
+
+impl Entity {
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.moved = true;
+    }
+
+    /// Moves the entity by `offset`, after its current transform
+    pub fn translate(&mut self, offset: &Vector3) {
+        self.set_transform(self.transform.then(&Transform::translation(*offset)));
+    }
+
+    /// The object of the world moved with the entity
+    pub fn render(&self) -> Option<ObjectId> {
+        self.render
+    }
+}
+
+/// The entities, in the order they were spawned
+#[derive(Default)]
+pub struct Entities {
+    entities: BTreeMap<EntityId, Entity>,
+    next_id: u64,
+}
+
+impl Entities {
+    /// Adds an entity drawn as the object of the world (or not drawn, without object)
+    pub fn spawn(&mut self, render: Option<ObjectId>, physics: Option<Physics>) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.entities.insert(id, Entity { transform: Transform::identity(), moved: false, render, physics });
+        id
+    }
+
+    pub fn despawn(&mut self, id: EntityId) -> Option<Entity> {
+        self.entities.remove(&id)
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &Entity)> + '_ {
+        self.entities.iter().map(|(id, entity)| (*id, entity))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut Entity)> + '_ {
+        self.entities.iter_mut().map(|(id, entity)| (*id, entity))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// Gameplay logic run at each update over the entities, such as the behavior of doors or enemies.
+/// As the behaviors of the objects (see `Updatable`), the systems see the world through a
+/// `WorldQuery`. The closures taking the same arguments are systems.
+pub trait System {
+    fn run(&mut self, entities: &mut Entities, world: &WorldQuery, dt: f32);
+}
+
+impl<F: FnMut(&mut Entities, &WorldQuery, f32)> System for F {
+    fn run(&mut self, entities: &mut Entities, world: &WorldQuery, dt: f32) {
+        self(entities, world, dt)
+    }
+}
+
+/// Game objects above the objects of a world: the gameplay logic is attached to entities and run
+/// by systems, instead of living in the main loop. Each world has its layer (see
+/// `World::entities_mut`), updated with the world after the behaviors of the objects.
+///
+/// At each update, the physics of the entities is integrated, then the systems run in the order
+/// they were added, and finally the objects of the moved entities are moved in the world (with the
+/// objects attached to them in the scene graph).
+#[derive(Default)]
+pub struct EntityLayer {
+    entities: Entities,
+    systems: Vec<Box<dyn System>>,
+}
+
+impl EntityLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_system(&mut self, system: impl System + 'static) {
+        self.systems.push(Box::new(system));
+    }
+
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    pub fn entities_mut(&mut self) -> &mut Entities {
+        &mut self.entities
+    }
+
+    pub(crate) fn update(&mut self, world: &mut World, dt: f32) {
+        for (_, entity) in self.entities.iter_mut() {
+            if let Some(physics) = &mut entity.physics {
+                if physics.gravity {
+                    fall(&mut physics.velocity, dt);
+                }
+                let offset = physics.velocity * dt;
+                entity.translate(&offset);
+            }
+        }
+        let query = world.query();
+        for system in &mut self.systems {
+            system.run(&mut self.entities, &query, dt);
+        }
+        for (_, entity) in self.entities.iter_mut().filter(|(_, entity)| entity.moved) {
+            entity.moved = false;
+            if let Some(object) = entity.render {
+                world.set_local_transform(object, entity.transform.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::drawable::Drawable;
+    use crate::entities::{Entities, Physics};
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+    use crate::updatable::WorldQuery;
+    use crate::worlds::World;
+
+    #[test]
+    fn test_entity_layer() {
+        let mut world = World::new(Camera::default());
+        let cube = world.add_cube(Cube3::minecraft_like(Vector3::newi(2, 0, 0), &YELLOW, &YELLOW));
+        let layer = world.entities_mut();
+        let door = layer.entities_mut().spawn(Some(cube), Some(Physics::new(Vector3::new(0., 1., 0.), false)));
+        let marker = layer.entities_mut().spawn(None, None);
+
+        // A system stops the door once it is open
+        layer.add_system(move |entities: &mut Entities, _: &WorldQuery, _: f32| {
+            let door = entities.get_mut(door).unwrap();
+            if door.transform().apply(&Vector3::empty()).y() >= 1. {
+                door.physics = None;
+            }
+        });
+        for _ in 0..20 {
+            world.update(0.1);
+        }
+        let position = world.get(cube).unwrap().get_all_faces()[0].points()[0];
+        assert!((position.y() - 1.).abs() < 1e-4, "{:?}", position);
+        assert_eq!(2, world.entities().entities().len());
+
+        // Nothing moves while the simulation is paused
+        world.entities_mut().entities_mut().get_mut(marker).unwrap().physics = Some(Physics::new(Vector3::empty(), true));
+        world.set_time_scale(0.);
+        world.update(0.1);
+        assert_eq!(Vector3::empty(), world.entities().entities().get(marker).unwrap().transform().apply(&Vector3::empty()));
+        world.set_time_scale(1.);
+        world.update(0.1);
+        assert!(world.entities().entities().get(marker).unwrap().transform().apply(&Vector3::empty()).z() > 0.);
+
+        assert!(world.despawn(door));
+        assert!(world.get(cube).is_none());
+        assert!(world.entities().entities().get(marker).is_some());
+    }
+}
@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::ControlFlow;
+
+/// Time between two frames while the window is not focused
+pub const UNFOCUSED_FRAME_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Follows the focus of the window. While the window is in the background, the game does not
+/// need to be redrawn at full speed: the frames are spaced by `UNFOCUSED_FRAME_INTERVAL`.
+pub struct FocusTracker {
+    focused: bool,
+    last_frame: Option<Instant>,
+}
+
+impl FocusTracker {
+    pub fn new() -> Self {
+        Self { focused: true, last_frame: None }
+    }
+
+    /// Returns the new focus of the window if the event changed it
+    pub fn handle_event<T>(&mut self, event: &Event<T>) -> Option<bool> {
+        match event {
+            Event::WindowEvent { event: WindowEvent::Focused(focused), .. } if *focused != self.focused => {
+                self.focused = *focused;
+                Some(*focused)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether a new frame should be drawn now
+    pub fn should_redraw(&mut self, now: Instant) -> bool {
+        let redraw = self.focused || self.last_frame.is_none_or(|last| now >= last + UNFOCUSED_FRAME_INTERVAL);
+        if redraw {
+            self.last_frame = Some(now);
+        }
+        redraw
+    }
+
+    /// Returns how the event loop should wait for the next events: it keeps running when the
+    /// window is focused, and otherwise sleeps until the next frame is due.
+    pub fn control_flow(&self) -> ControlFlow {
+        match (self.focused, self.last_frame) {
+            (false, Some(last)) => ControlFlow::WaitUntil(last + UNFOCUSED_FRAME_INTERVAL),
+            _ => ControlFlow::Poll,
+        }
+    }
+}
+
+impl Default for FocusTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use winit::event::{Event, WindowEvent};
+    use winit::event_loop::ControlFlow;
+    use winit::window::WindowId;
+
+    use crate::focus::{FocusTracker, UNFOCUSED_FRAME_INTERVAL};
+
+    fn focus_event(focused: bool) -> Event<'static, ()> {
+        // SAFETY: the identifier is only compared, it never reaches the windowing system
+        let window_id = unsafe { WindowId::dummy() };
+        Event::WindowEvent { window_id, event: WindowEvent::Focused(focused) }
+    }
+
+    #[test]
+    fn test_focus_changes() {
+        let mut focus = FocusTracker::new();
+        assert_eq!(None, focus.handle_event(&focus_event(true)));
+        assert_eq!(Some(false), focus.handle_event(&focus_event(false)));
+        assert_eq!(None, focus.handle_event(&focus_event(false)));
+        assert_eq!(Some(true), focus.handle_event(&focus_event(true)));
+    }
+
+    #[test]
+    fn test_redraw_rate() {
+        let mut focus = FocusTracker::new();
+        let start = Instant::now();
+        assert!(focus.should_redraw(start));
+        assert!(focus.should_redraw(start + Duration::from_millis(1)));
+        assert_eq!(ControlFlow::Poll, focus.control_flow());
+
+        // In the background, the frames are spaced out
+        focus.handle_event(&focus_event(false));
+        let start = start + Duration::from_millis(1);
+        assert!(!focus.should_redraw(start + Duration::from_millis(10)));
+        assert_eq!(ControlFlow::WaitUntil(start + UNFOCUSED_FRAME_INTERVAL), focus.control_flow());
+        assert!(focus.should_redraw(start + UNFOCUSED_FRAME_INTERVAL));
+
+        // And back to full speed with the focus
+        focus.handle_event(&focus_event(true));
+        assert!(focus.should_redraw(start + UNFOCUSED_FRAME_INTERVAL + Duration::from_millis(1)));
+        assert_eq!(ControlFlow::Poll, focus.control_flow());
+    }
+}
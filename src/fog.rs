@@ -0,0 +1,105 @@
+use std::hash::Hasher;
+
+use crate::content_hash::ContentHasher;
+use crate::frame::AbstractFrame;
+use crate::primitives::color::Color;
+use crate::primitives::cubic_face2::CubicFace2;
+
+/// Distance fog: the faces fade into the color of the fog with their distance to the camera, so
+/// that far objects do not appear suddenly at full contrast.
+///
+/// The fog starts at `start` (where the faces have their own color) and is opaque at `end`.
+#[derive(Clone, Debug)]
+pub struct Fog {
+    color: [u8; 4],
+    start: f32,
+    end: f32,
+}
+
+impl Fog {
+    pub fn new(color: &Color, start: f32, end: f32) -> Self {
+        Self { color: color.rgba(), start, end: end.max(start) }
+    }
+
+    /// Writes the color and the distances of the fog, to compute the hash of the world
+    pub fn write_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write(&self.color);
+        hasher.write_f32(self.start);
+        hasher.write_f32(self.end);
+    }
+
+    /// Returns the amount of fog (between 0 and 1) in front of a point at this distance
+    pub fn density(&self, distance: f32) -> f32 {
+        if distance >= self.end {
+            return 1.;
+        }
+        ((distance - self.start) / (self.end - self.start)).clamp(0., 1.)
+    }
+
+    /// Returns the color of a point at this distance, seen through the fog. The opacity of the
+    /// color does not change.
+    pub fn apply(&self, color: &Color, distance: f32) -> Color {
+        let density = self.density(distance);
+        let (rgba, fog) = (color.rgba(), self.color);
+        let mix = |c: usize| (rgba[c] as f32 + (fog[c] as f32 - rgba[c] as f32) * density).round() as u8;
+        Color::new(mix(0), mix(1), mix(2), rgba[3])
+    }
+}
+
+/// Frame which adds the fog to the faces before drawing them onto another frame
+pub struct FoggedFrame<'a> {
+    inner: &'a mut dyn AbstractFrame,
+    fog: &'a Fog,
+}
+
+impl<'a> FoggedFrame<'a> {
+    pub fn new(inner: &'a mut dyn AbstractFrame, fog: &'a Fog) -> Self {
+        Self { inner, fog }
+    }
+}
+
+impl<'a> AbstractFrame for FoggedFrame<'a> {
+    fn draw_one_face(&mut self, face: &CubicFace2) {
+        self.inner.draw_one_face(&face.with_fog(self.fog));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fog::Fog;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::color::Color;
+    use crate::primitives::cubic_face3::{CubicFace3, Receivers};
+    use crate::primitives::projective_coordinates::ProjectionCoordinates;
+    use crate::primitives::textures::colored::ColoredTexture;
+    use crate::primitives::textures::TextureHandle;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_fog() {
+        let fog = Fog::new(&Color::new(200, 200, 200, 255), 10., 20.);
+        let red = Color::new(100, 0, 0, 128);
+        assert_eq!([100, 0, 0, 128], fog.apply(&red, 5.).rgba());
+        assert_eq!([150, 100, 100, 128], fog.apply(&red, 15.).rgba());
+        assert_eq!([200, 200, 200, 128], fog.apply(&red, 30.).rgba());
+        // Without any distance between the start and the end, the fog is opaque right away
+        assert_eq!(1., Fog::new(&red, 10., 10.).density(10.));
+    }
+
+    #[test]
+    fn test_face_in_fog() {
+        let fog = Fog::new(&Color::new(255, 255, 255, 255), 0., 4.);
+        let camera = Camera::default();
+        let center = ProjectionCoordinates::new(0.5, 0.5);
+        // A black face 2 meters in front of the camera, halfway into the white fog
+        let mut face = CubicFace3::vface_from_line(Vector3::new(2., 0.5, 0.5), Vector3::new(2., -0.5, 0.5));
+        face.set_texture(&TextureHandle::new(ColoredTexture::new(Color::new(0, 0, 0, 255))));
+        let clear = face.clone().with_receivers(Receivers::ALL.without(Receivers::FOG));
+
+        let texture = face.projection(&camera).color_at_projection(&center).rgba();
+        let fogged = face.projection(&camera).with_fog(&fog).color_at_projection(&center).rgba();
+        assert_eq!([0, 0, 0, 255], texture);
+        assert!(fogged[..3].iter().all(|c| (120..160).contains(c)));
+        assert_eq!(texture, clear.projection(&camera).with_fog(&fog).color_at_projection(&center).rgba());
+    }
+}
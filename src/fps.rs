@@ -4,7 +4,6 @@ use std::time::{Instant, SystemTime};
 pub struct FPSMonitor {
     frames: BoundedVecDeque<f32>,
     last_instant: Option<Instant>,
-    frame_count: usize,
 }
 
 impl FPSMonitor {
@@ -12,7 +11,6 @@ impl FPSMonitor {
         Self {
             frames: BoundedVecDeque::new(50),
             last_instant: None,
-            frame_count: 0,
         }
     }
 
@@ -22,15 +20,14 @@ impl FPSMonitor {
             self.frames.push_back(elapsed.as_secs_f32());
         }
         self.last_instant = Some(at);
-        self.frame_count += 1;
     }
 
-    pub fn log_fps(&mut self) {
-        if self.frame_count > 20 {
-            let mean_elapsed: f32 = self.frames.iter().sum::<f32>() / self.frames.len() as f32;
-            let mean_fps = 1. / mean_elapsed;
-            self.frame_count = 0;
-            println!("FPS= {mean_fps}");
+    /// Returns the average FPS over the last frames, once two frames were added
+    pub fn fps(&self) -> Option<f32> {
+        if self.frames.is_empty() {
+            return None;
         }
+        let mean_elapsed: f32 = self.frames.iter().sum::<f32>() / self.frames.len() as f32;
+        Some(1. / mean_elapsed)
     }
 }
@@ -1,5 +1,10 @@
 use crate::primitives::cubic_face2::CubicFace2;
 use crate::primitives::cubic_face3::CubicFace3;
+use crate::ui::font::{glyph, is_set, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::viewport::{Rect, Viewport};
+
+/// Color of the pixels where nothing is drawn
+pub const BACKGROUND_COLOR: [u8; 4] = [214, 214, 194, 150];
 
 /// A frame is an object able to draw faces
 pub trait AbstractFrame {
@@ -8,17 +13,108 @@ pub trait AbstractFrame {
 }
 
 pub struct Frame<'a> {
-    buffer: &'a mut [u8]
+    buffer: &'a mut [u8],
+    viewport: Viewport,
 }
 
 impl<'a> Frame<'a> {
-    pub fn new(buffer: &'a mut [u8]) -> Self {
-        Self { buffer }
+    /// Wraps an RGBA buffer, holding the pixels of the viewport row after row
+    pub fn new(buffer: &'a mut [u8], viewport: Viewport) -> Self {
+        assert_eq!(viewport.buffer_len(), buffer.len(), "The buffer does not have the size of the viewport");
+        Self { buffer, viewport }
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// Sets the color of a pixel, blending it with the current color using the alpha channel.
+    /// Pixels outside of the screen are ignored.
+    pub fn set_pixel(&mut self, x: i32, y: i32, rgba: [u8; 4]) {
+        if !self.viewport.contains(x, y) {
+            return;
+        }
+        let i = 4 * self.viewport.index(x as u32, y as u32);
+        blend(&mut self.buffer[i..i + 4], rgba);
+    }
+
+    /// Fills a rectangle of the screen, whose top left corner is (x, y)
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, rgba: [u8; 4]) {
+        for j in y..y + height {
+            for i in x..x + width {
+                self.set_pixel(i, j, rgba);
+            }
+        }
+    }
+
+    /// Copies the pixels of a frame of the size of the region into the region, without blending.
+    /// The parts of the region outside of the screen are ignored.
+    pub fn blit(&mut self, region: Rect, pixels: &[u8]) {
+        assert_eq!(region.viewport().buffer_len(), pixels.len(), "The pixels do not have the size of the region");
+        let width = region.width.min(self.viewport.width.saturating_sub(region.x)) as usize;
+        for row in 0..region.height.min(self.viewport.height.saturating_sub(region.y)) {
+            let source = 4 * (row * region.width) as usize;
+            let target = 4 * self.viewport.index(region.x, region.y + row);
+            self.buffer[target..target + 4 * width].copy_from_slice(&pixels[source..source + 4 * width]);
+        }
+    }
+
+    /// Draws the outline of a rectangle, whose top left corner is (x, y)
+    pub fn draw_rect(&mut self, x: i32, y: i32, width: i32, height: i32, rgba: [u8; 4]) {
+        self.fill_rect(x, y, width, 1, rgba);
+        self.fill_rect(x, y + height - 1, width, 1, rgba);
+        self.fill_rect(x, y, 1, height, rgba);
+        self.fill_rect(x + width - 1, y, 1, height, rgba);
+    }
+
+    /// Draws a segment between (x0, y0) and (x1, y1)
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, rgba: [u8; 4]) {
+        let steps = (x1 - x0).abs().max((y1 - y0).abs());
+        if steps > (self.viewport.width + self.viewport.height) as i32 {
+            // Nothing sensible to draw, and too long to iterate over
+            return;
+        }
+        for i in 0..=steps {
+            let t = if steps == 0 { 0. } else { i as f32 / steps as f32 };
+            let x = x0 as f32 + t * (x1 - x0) as f32;
+            let y = y0 as f32 + t * (y1 - y0) as f32;
+            self.set_pixel(x.round() as i32, y.round() as i32, rgba);
+        }
+    }
+
+    /// Writes a single line of text with the bitmap font, each pixel of the font being a square of
+    /// `scale` pixels. (x, y) is the top left corner of the text.
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, rgba: [u8; 4], scale: i32) {
+        for (n, c) in text.chars().enumerate() {
+            let g = glyph(c);
+            let x0 = x + n as i32 * text_advance(scale);
+            for gy in 0..GLYPH_HEIGHT {
+                for gx in 0..GLYPH_WIDTH {
+                    if is_set(&g, gx, gy) {
+                        self.fill_rect(x0 + gx as i32 * scale, y + gy as i32 * scale, scale, scale, rgba);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Blends a color over an RGBA pixel, according to the alpha channel of the color. The alpha
+/// channel of the pixel is kept.
+pub fn blend(pixel: &mut [u8], rgba: [u8; 4]) {
+    let alpha = rgba[3] as u32;
+    for (dst, src) in pixel[..3].iter_mut().zip(rgba) {
+        *dst = ((src as u32 * alpha + *dst as u32 * (255 - alpha)) / 255) as u8;
     }
 }
 
+/// Horizontal distance between two characters written with `draw_text`
+pub fn text_advance(scale: i32) -> i32 {
+    (GLYPH_WIDTH as i32 + 1) * scale
+}
+
 impl<'a> AbstractFrame for Frame<'a> {
     fn draw_one_face(&mut self, face: &CubicFace2) {
         face.draw(self.buffer);
     }
-}
\ No newline at end of file
+}
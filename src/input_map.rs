@@ -0,0 +1,225 @@
+use winit::event::VirtualKeyCode;
+
+use crate::config::KeyBindings;
+
+/// Keys that can be written in an input script (or in the key bindings of the configuration)
+pub const SCRIPTABLE_KEYS: [VirtualKeyCode; 47] = [
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Tab,
+    VirtualKeyCode::Minus,
+    VirtualKeyCode::Equals,
+    VirtualKeyCode::F1,
+    VirtualKeyCode::F2,
+    VirtualKeyCode::F3,
+    VirtualKeyCode::F4,
+    VirtualKeyCode::F5,
+    VirtualKeyCode::F6,
+    VirtualKeyCode::F7,
+    VirtualKeyCode::F8,
+    VirtualKeyCode::F9,
+    VirtualKeyCode::F10,
+    VirtualKeyCode::F11,
+    VirtualKeyCode::F12,
+];
+
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    SCRIPTABLE_KEYS.into_iter().find(|key| format!("{key:?}") == name)
+}
+
+/// What the player can do with the keyboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    MoveUp,
+    MoveDown,
+    TurnLeft,
+    TurnRight,
+    LookUp,
+    LookDown,
+    RollLeft,
+    RollRight,
+    Jump,
+    ToggleWalk,
+    Pause,
+    SlowMotion,
+    SingleStep,
+    CycleWeather,
+    ToggleFps,
+    Screenshot,
+    ToggleUi,
+    Spectate,
+    ZoomIn,
+    ZoomOut,
+    PrintCamera,
+    DescribeScene,
+    CycleRenderer,
+    TimingGraph,
+    LogMovement,
+    SwitchScene,
+    SaveScene,
+}
+
+impl Action {
+    pub const ALL: [Action; 31] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::LookUp,
+        Action::LookDown,
+        Action::RollLeft,
+        Action::RollRight,
+        Action::Jump,
+        Action::ToggleWalk,
+        Action::Pause,
+        Action::SlowMotion,
+        Action::SingleStep,
+        Action::CycleWeather,
+        Action::ToggleFps,
+        Action::Screenshot,
+        Action::ToggleUi,
+        Action::Spectate,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::PrintCamera,
+        Action::DescribeScene,
+        Action::CycleRenderer,
+        Action::TimingGraph,
+        Action::LogMovement,
+        Action::SwitchScene,
+        Action::SaveScene,
+    ];
+
+    /// Returns the action written as `name` in an input script (for instance `MoveForward`)
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| format!("{action:?}") == name)
+    }
+
+    /// Returns true if the action lasts while its key is held, and false if it happens once when
+    /// the key is pressed
+    pub fn is_held(self) -> bool {
+        matches!(
+            self,
+            Action::MoveForward | Action::MoveBackward | Action::StrafeLeft | Action::StrafeRight | Action::MoveUp | Action::MoveDown
+        )
+    }
+
+    /// Returns true if the action is handled by the application (the window, the debug tools...)
+    /// rather than by the world
+    pub fn is_application(self) -> bool {
+        matches!(
+            self,
+            Action::ToggleFps
+                | Action::Screenshot
+                | Action::ToggleUi
+                | Action::Spectate
+                | Action::PrintCamera
+                | Action::DescribeScene
+                | Action::CycleRenderer
+                | Action::TimingGraph
+                | Action::LogMovement
+                | Action::SwitchScene
+                | Action::SaveScene
+        )
+    }
+}
+
+/// Keys bound to the actions. The configuration rejects a key bound to several actions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputMap {
+    bindings: Vec<(Action, VirtualKeyCode)>,
+}
+
+impl InputMap {
+    /// Returns a map without any binding
+    pub fn new() -> Self {
+        Self { bindings: Vec::new() }
+    }
+
+    /// Binds the action to the key, replacing its previous key
+    pub fn bind(&mut self, action: Action, key: VirtualKeyCode) {
+        match self.bindings.iter_mut().find(|(other, _)| *other == action) {
+            Some(binding) => binding.1 = key,
+            None => self.bindings.push((action, key)),
+        }
+    }
+
+    pub fn key(&self, action: Action) -> Option<VirtualKeyCode> {
+        self.bindings.iter().find(|(other, _)| *other == action).map(|(_, key)| *key)
+    }
+
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.bindings.iter().find(|(_, other)| *other == key).map(|(action, _)| *action)
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (Action, VirtualKeyCode)> + '_ {
+        self.bindings.iter().copied()
+    }
+}
+
+impl Default for InputMap {
+    /// The bindings of the default configuration
+    fn default() -> Self {
+        KeyBindings::default().input_map()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::VirtualKeyCode;
+
+    use crate::input_map::{Action, InputMap};
+
+    #[test]
+    fn test_input_map() {
+        let mut map = InputMap::default();
+        assert_eq!(Some(Action::MoveForward), map.action(VirtualKeyCode::Up));
+        assert_eq!(Some(VirtualKeyCode::F5), map.key(Action::Screenshot));
+        assert!(Action::MoveForward.is_held() && !Action::Jump.is_held());
+        assert_eq!(Some(Action::SaveScene), Action::from_name("SaveScene"));
+        assert_eq!(None, Action::from_name("Up"));
+
+        map.bind(Action::MoveForward, VirtualKeyCode::W);
+        assert_eq!(Some(Action::MoveForward), map.action(VirtualKeyCode::W));
+        assert_eq!(None, map.action(VirtualKeyCode::Up));
+        assert_eq!(None, InputMap::new().action(VirtualKeyCode::W));
+    }
+}
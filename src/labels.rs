@@ -0,0 +1,100 @@
+use crate::frame::{text_advance, Frame};
+use crate::primitives::camera::Camera;
+use crate::primitives::vector::Vector3;
+use crate::ui::font::GLYPH_HEIGHT;
+
+/// Labels further than this distance are not drawn
+const MAX_DISTANCE: f32 = 20.;
+/// Labels fade out between this distance and `MAX_DISTANCE`
+const FADE_DISTANCE: f32 = 15.;
+/// Closer than this distance, labels are written with the largest font
+const FULL_SIZE_DISTANCE: f32 = 3.;
+const MAX_SCALE: i32 = 3;
+/// Labels hidden behind an object are still drawn, with their opacity multiplied by this factor
+const OCCLUDED_OPACITY: f32 = 0.3;
+/// Opacity of the dark rectangle behind the text
+const BACKGROUND_OPACITY: f32 = 120.;
+/// Height (in meters) between the top of an object and its label
+pub const LABEL_MARGIN: f32 = 0.2;
+
+/// A line of text floating above an object of the world, such as a name or an annotation
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub text: String,
+    pub color: [u8; 4],
+}
+
+impl Label {
+    pub fn new(text: &str, color: [u8; 4]) -> Self {
+        Self { text: text.to_string(), color }
+    }
+
+    /// Size of the pixels of the font: labels get smaller with the distance
+    fn scale(distance: f32) -> i32 {
+        ((MAX_SCALE as f32 * FULL_SIZE_DISTANCE / distance).ceil() as i32).clamp(1, MAX_SCALE)
+    }
+
+    /// Factor applied to the opacity of the label (0 when it is not drawn)
+    fn opacity(distance: f32, occluded: bool) -> f32 {
+        let fade = ((MAX_DISTANCE - distance) / (MAX_DISTANCE - FADE_DISTANCE)).clamp(0., 1.);
+        if occluded {
+            fade * OCCLUDED_OPACITY
+        } else {
+            fade
+        }
+    }
+
+    /// Draws the label centered above the point `anchor`, when it is in front of the camera
+    pub fn draw(&self, frame: &mut Frame, camera: &Camera, anchor: &Vector3, occluded: bool) {
+        let distance = camera.pose().position().line_to(anchor).norm();
+        let opacity = Self::opacity(distance, occluded);
+        if opacity <= 0. {
+            return;
+        }
+        let Some(uv) = camera.world_to_screen(anchor) else {
+            return;
+        };
+        let scale = Self::scale(distance);
+        let width = self.text.chars().count() as i32 * text_advance(scale) - scale;
+        let height = GLYPH_HEIGHT as i32 * scale;
+        let (x, y) = (uv.x().round() as i32 - width / 2, uv.y().round() as i32 - height);
+        let background = [0, 0, 0, (BACKGROUND_OPACITY * opacity) as u8];
+        frame.fill_rect(x - scale, y - scale, width + 2 * scale, height + 2 * scale, background);
+        let [r, g, b, a] = self.color;
+        frame.draw_text(x, y, &self.text, [r, g, b, (a as f32 * opacity).round() as u8], scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frame::Frame;
+    use crate::labels::{Label, MAX_DISTANCE, MAX_SCALE};
+    use crate::primitives::camera::Camera;
+    use crate::primitives::vector::Vector3;
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn test_draw_label() {
+        assert_eq!(MAX_SCALE, Label::scale(1.));
+        assert_eq!(1, Label::scale(15.));
+        assert!(Label::opacity(5., true) < Label::opacity(5., false));
+        assert_eq!(0., Label::opacity(MAX_DISTANCE, false));
+
+        let mut camera = Camera::default();
+        let viewport = Viewport::new(200, 100);
+        camera.set_viewport(viewport);
+        let label = Label::new("A", [255, 255, 255, 255]);
+        // Returns the brightest red value of the frame once the label is drawn at the point
+        let brightest = |anchor: Vector3, occluded: bool| {
+            let mut buffer = vec![0; viewport.buffer_len()];
+            label.draw(&mut Frame::new(&mut buffer, viewport), &camera, &anchor, occluded);
+            buffer.chunks(4).map(|pixel| pixel[0]).max().unwrap()
+        };
+        assert_eq!(255, brightest(Vector3::new(2., 0., 0.), false));
+        let occluded = brightest(Vector3::new(2., 0., 0.), true);
+        assert!(occluded > 0 && occluded < 255);
+        // Behind the camera, and too far away
+        assert_eq!(0, brightest(Vector3::new(-2., 0., 0.), false));
+        assert_eq!(0, brightest(Vector3::new(MAX_DISTANCE + 1., 0., 0.), false));
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+/// Set of render layers, as a bitmask. Each object of the world belongs to some layers, and each
+/// camera only shows the objects of the layers it can see, so that groups of objects (for
+/// instance the debug geometry) can be shown or hidden together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Layers(u8);
+
+impl Layers {
+    /// The geometry of the game itself
+    pub const GAMEPLAY: Layers = Layers(1);
+    /// Visualizations of the engine, such as the position of the lights
+    pub const DEBUG: Layers = Layers(2);
+    /// Markers only needed while editing the world
+    pub const EDITOR: Layers = Layers(4);
+    /// Interface elements placed in the world
+    pub const WORLD_UI: Layers = Layers(8);
+
+    /// Returns true if the sets have at least one layer in common
+    pub fn intersects(self, other: Layers) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Returns these layers and those of `other`
+    pub fn with(self, other: Layers) -> Layers {
+        Layers(self.0 | other.0)
+    }
+
+    /// Returns these layers, except those of `other`
+    pub fn without(self, other: Layers) -> Layers {
+        Layers(self.0 & !other.0)
+    }
+}
+
+impl Default for Layers {
+    /// Layers seen by a camera during the game: the debug and editor layers are hidden
+    fn default() -> Self {
+        Self::GAMEPLAY.with(Self::WORLD_UI)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layers::Layers;
+
+    #[test]
+    fn test_layers() {
+        let visible = Layers::default();
+        assert!(visible.intersects(Layers::GAMEPLAY) && !visible.intersects(Layers::DEBUG));
+        let marker = Layers::DEBUG.with(Layers::EDITOR);
+        assert!(!visible.intersects(marker));
+        assert!(visible.with(Layers::EDITOR).intersects(marker));
+        assert!(!marker.without(Layers::DEBUG).without(Layers::EDITOR).intersects(marker));
+    }
+}
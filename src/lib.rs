@@ -0,0 +1,62 @@
+//! A 3D engine drawing the world pixel by pixel, without any graphics API.
+//!
+//! A `World` holds the objects (cubes, faces, meshes...) and a `Camera`, and is drawn into a
+//! buffer of RGBA pixels by one of the renderers (see `Drawable` and `renderer::Renderer`). The
+//! window, the input and the scenes of the game are handled by the binary (`main.rs`); see the
+//! `examples` directory to embed the engine in other applications, and `prelude` for the types
+//! needed to build a scene (such as `WorldBuilder`).
+
+pub mod accel;
+pub mod assets;
+pub mod benchmark;
+pub mod bsp;
+pub mod camera_rig;
+pub(crate) mod compare;
+pub mod config;
+pub mod content_hash;
+pub mod debug_paths;
+pub mod draw_distance;
+pub mod drawable;
+pub mod entities;
+pub mod fog;
+pub mod fps;
+pub mod frame;
+pub mod input_map;
+pub mod labels;
+pub mod layers;
+pub mod lights;
+pub mod loaders;
+pub mod map;
+pub mod motion_model;
+pub mod movement;
+pub mod pathfinding;
+pub mod png_saver;
+pub mod prelude;
+pub mod primitives;
+pub mod profiler;
+pub mod renderer;
+pub mod scene_file;
+pub mod scene_graph;
+pub mod scenes;
+pub mod sdf;
+pub mod sky;
+pub mod spatial_hash;
+pub mod timestep;
+pub mod tunables;
+pub mod ui;
+pub mod updatable;
+pub mod viewport;
+pub mod visibility;
+pub mod voxels;
+pub mod weather;
+pub mod world_builder;
+pub mod worlds;
+
+/// Offscreen rendering, used by the examples and the commands of the binary
+pub use compare::{render_offscreen, render_to_buffer, run_compare, RenderMethod};
+
+// For different screen resolution: https://en.wikipedia.org/wiki/Display_resolution
+
+/// Default size of the frames (see `Viewport`)
+pub const WIDTH: u32 = 960;
+pub const HEIGHT: u32 = 640;
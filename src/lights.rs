@@ -0,0 +1,181 @@
+use crate::frame::AbstractFrame;
+use crate::primitives::color::Color;
+use crate::primitives::cubic_face2::CubicFace2;
+use crate::primitives::vector::Vector3;
+
+/// How the light of a point light decreases with the distance
+#[derive(Clone, Copy, Debug)]
+pub enum Falloff {
+    /// Decreases linearly, from full light at the light to no light at its range
+    Linear,
+    /// Decreases faster close to the light, which gives a softer edge at the range
+    Quadratic,
+    /// Given the distance relative to the range (0 at the light, 1 at the range), returns the
+    /// amount of light between 0 and 1
+    Custom(fn(f32) -> f32),
+}
+
+impl Falloff {
+    /// Returns the amount of light (between 0 and 1) received at `distance` from a light which
+    /// reaches up to `range`
+    pub fn attenuation(&self, distance: f32, range: f32) -> f32 {
+        let ratio = distance / range;
+        if ratio >= 1. {
+            return 0.;
+        }
+        match self {
+            Falloff::Linear => 1. - ratio,
+            Falloff::Quadratic => (1. - ratio) * (1. - ratio),
+            Falloff::Custom(f) => f(ratio).clamp(0., 1.),
+        }
+    }
+}
+
+/// A colored light, emitting in all directions from a point
+#[derive(Clone, Debug)]
+pub struct PointLight {
+    position: Vector3,
+    /// Light added to the lit surfaces, for each channel (1 = full channel)
+    color: [f32; 3],
+    /// No light is received further than this distance
+    range: f32,
+    falloff: Falloff,
+}
+
+impl PointLight {
+    pub fn new(position: Vector3, color: &Color, intensity: f32, range: f32) -> Self {
+        let rgba = color.rgba();
+        Self {
+            position,
+            color: [0, 1, 2].map(|c| intensity * rgba[c] as f32 / 255.),
+            range,
+            falloff: Falloff::Quadratic,
+        }
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    /// Returns the light received by a surface at `point`, oriented towards `normal` (normalized)
+    fn light_at(&self, point: &Vector3, normal: &Vector3) -> [f32; 3] {
+        let mut direction = point.line_to(&self.position);
+        let distance = direction.norm();
+        if distance > 0. {
+            direction.normalize();
+        }
+        let amount = self.falloff.attenuation(distance, self.range) * normal.dot(&direction).max(0.);
+        self.color.map(|c| c * amount)
+    }
+}
+
+/// The lights of a world. The color of the textures is multiplied by the light they receive: the
+/// ambient light (received everywhere) and the light of each point light.
+#[derive(Clone, Debug)]
+pub struct Lighting {
+    ambient: [f32; 3],
+    lights: Vec<PointLight>,
+}
+
+impl Lighting {
+    pub fn new(ambient: &Color, intensity: f32) -> Self {
+        let rgba = ambient.rgba();
+        Self {
+            ambient: [0, 1, 2].map(|c| intensity * rgba[c] as f32 / 255.),
+            lights: Vec::new(),
+        }
+    }
+
+    pub fn add_light(&mut self, light: PointLight) {
+        self.lights.push(light);
+    }
+
+    /// Returns the color of a texture, lit at `point` of a surface oriented towards `normal`
+    pub fn shade(&self, color: &Color, point: &Vector3, normal: &Vector3) -> Color {
+        let mut normal = *normal;
+        normal.normalize();
+        let mut light = self.ambient;
+        for point_light in &self.lights {
+            let received = point_light.light_at(point, &normal);
+            (0..3).for_each(|c| light[c] += received[c]);
+        }
+        let rgba = color.rgba();
+        Color::new(
+            (rgba[0] as f32 * light[0]).min(255.) as u8,
+            (rgba[1] as f32 * light[1]).min(255.) as u8,
+            (rgba[2] as f32 * light[2]).min(255.) as u8,
+            rgba[3],
+        )
+    }
+}
+
+/// Frame which lights the faces before drawing them onto another frame
+pub struct LitFrame<'a> {
+    inner: &'a mut dyn AbstractFrame,
+    lighting: &'a Lighting,
+}
+
+impl<'a> LitFrame<'a> {
+    pub fn new(inner: &'a mut dyn AbstractFrame, lighting: &'a Lighting) -> Self {
+        Self { inner, lighting }
+    }
+}
+
+impl<'a> AbstractFrame for LitFrame<'a> {
+    fn draw_one_face(&mut self, face: &CubicFace2) {
+        self.inner.draw_one_face(&face.with_lighting(self.lighting));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lights::{Falloff, Lighting, PointLight};
+    use crate::primitives::camera::Camera;
+    use crate::primitives::color::Color;
+    use crate::primitives::cubic_face3::{CubicFace3, Receivers};
+    use crate::primitives::projective_coordinates::ProjectionCoordinates;
+    use crate::primitives::vector::{Vector3, UNIT_Z};
+
+    #[test]
+    fn test_falloff() {
+        assert_eq!(0.5, Falloff::Linear.attenuation(1., 2.));
+        assert_eq!(0.25, Falloff::Quadratic.attenuation(1., 2.));
+        assert_eq!(1., Falloff::Custom(|_| 3.).attenuation(1., 2.));
+        for falloff in [Falloff::Linear, Falloff::Quadratic, Falloff::Custom(|_| 1.)] {
+            assert_eq!(0., falloff.attenuation(3., 2.));
+        }
+    }
+
+    #[test]
+    fn test_colored_lights() {
+        let white = Color::new(200, 200, 200, 255);
+        let mut lighting = Lighting::new(&Color::new(255, 255, 255, 255), 0.5);
+        // A warm light above the ground (z goes down), and a cold one below it
+        lighting.add_light(PointLight::new(Vector3::new(0., 0., -1.), &Color::new(255, 128, 0, 255), 1., 2.).with_falloff(Falloff::Linear));
+        lighting.add_light(PointLight::new(Vector3::new(0., 0., 1.), &Color::new(0, 0, 255, 255), 1., 2.));
+
+        // The ground faces up: only the warm light reaches it, and tints the texture
+        let up = UNIT_Z.opposite();
+        assert_eq!([200, 150, 100, 255], lighting.shade(&white, &Vector3::new(0., 0., 0.), &up).rgba());
+        // Out of range, only the ambient light remains
+        assert_eq!([100, 100, 100, 255], lighting.shade(&white, &Vector3::new(5., 0., 0.), &up).rgba());
+        // Facing down, only the cold light is received
+        assert_eq!([100, 100, 150, 255], lighting.shade(&white, &Vector3::new(0., 0., 0.), &UNIT_Z).rgba());
+    }
+
+    #[test]
+    fn test_face_without_light() {
+        let lighting = Lighting::new(&Color::new(255, 255, 255, 255), 0.5);
+        let camera = Camera::default();
+        let center = ProjectionCoordinates::new(0.5, 0.5);
+        let lit = CubicFace3::vface_from_line(Vector3::newi2(0, 0), Vector3::newi2(1, 0));
+        let emissive = lit.clone().with_receivers(Receivers::ALL.without(Receivers::LIGHT));
+        assert!(emissive.receivers().contains(Receivers::FOG));
+
+        let texture = lit.projection(&camera).color_at_projection(&center).rgba();
+        let half = texture.map(|c| c / 2);
+        assert_eq!(half[..3], lit.projection(&camera).with_lighting(&lighting).color_at_projection(&center).rgba()[..3]);
+        assert_eq!(texture, emissive.projection(&camera).with_lighting(&lighting).color_at_projection(&center).rgba());
+    }
+}
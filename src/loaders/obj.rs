@@ -0,0 +1,153 @@
+use crate::primitives::mesh::Mesh;
+use crate::primitives::object::Object;
+use crate::primitives::snapping::Grid;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::triangle3::Triangle3;
+use crate::primitives::vector::Vector3;
+use crate::worlds::World;
+
+/// The vertices of the imported meshes are quantized to this grid (in meters), so that the
+/// triangles which share a vertex in the file share exactly the same point.
+pub const IMPORT_GRID_STEP: f32 = 1e-3;
+
+/// Imported meshes with more triangles than this are simplified (see `Mesh::simplified`)
+pub const MAX_IMPORTED_FACES: usize = 2000;
+
+/// Triangles with a smaller area (in square meters) are skipped: they have no normal
+const MIN_TRIANGLE_AREA: f32 = 1e-8;
+
+/// Parses a Wavefront .obj file into a mesh, placed relative to `origin`.
+///
+/// Only the vertices (`v`), texture coordinates (`vt`) and faces (`f`) are read, the other
+/// statements (normals, groups, materials...) are ignored. Polygons are split in triangles
+/// around their first vertex.
+///
+/// The .obj files usually have the y-axis going up, while the z-axis of the engine goes down:
+/// a vertex (x, y, z) of the file becomes (x, z, -y) in the world. The texture coordinates are
+/// fractions of the texture, they are converted to meters using the size of the texture (if it
+/// has one: a color is the same everywhere).
+pub fn parse(text: &str, origin: Vector3, texture: &TextureHandle) -> Result<Mesh, String> {
+    let size = |size: f32| if size < f32::MAX { size } else { 1. };
+    let mut vertices = Vec::new();
+    let mut uvs = Vec::new();
+    let mut triangles = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let error = |message: String| format!("line {}: {message}", n + 1);
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let [x, y, z] = parse_floats::<3>(tokens).map_err(error)?;
+                vertices.push(origin + Vector3::new(x, z, -y));
+            }
+            Some("vt") => {
+                let [u, v] = parse_floats::<2>(tokens).map_err(error)?;
+                uvs.push((u * size(texture.height()), v * size(texture.width())));
+            }
+            Some("f") => {
+                let corners = tokens
+                    .map(|token| parse_corner(token, vertices.len(), uvs.len()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+                if corners.len() < 3 {
+                    return Err(error("A face needs at least 3 vertices".to_string()));
+                }
+                for i in 1..corners.len() - 1 {
+                    let [c0, c1, c2] = [corners[0], corners[i], corners[i + 1]];
+                    let points = [vertices[c0.0], vertices[c1.0], vertices[c2.0]];
+                    if (points[1] - points[0]).cross(&(points[2] - points[0])).norm() / 2. < MIN_TRIANGLE_AREA {
+                        continue;
+                    }
+                    let triangle = match (c0.1, c1.1, c2.1) {
+                        (Some(t0), Some(t1), Some(t2)) => Triangle3::with_uvs(points, [uvs[t0], uvs[t1], uvs[t2]], texture),
+                        _ => Triangle3::new(points, texture),
+                    };
+                    triangles.push(triangle);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(Mesh::new(triangles))
+}
+
+/// Loads a .obj file (see `parse`) and adds its mesh to the world. The vertices are quantized to
+/// `IMPORT_GRID_STEP`, and detailed meshes are simplified down to `MAX_IMPORTED_FACES` triangles,
+/// to keep the renderers fast.
+pub fn load_into(world: &mut World, path: &str, origin: Vector3, texture: &TextureHandle) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+    let mut mesh = parse(&text, origin, texture).map_err(|err| format!("{path}: {err}"))?;
+    mesh.snap(&Grid::new(IMPORT_GRID_STEP));
+    world.add_mesh(mesh.simplified(MAX_IMPORTED_FACES));
+    Ok(())
+}
+
+fn parse_floats<'a, const N: usize>(tokens: impl Iterator<Item = &'a str>) -> Result<[f32; N], String> {
+    let values = tokens
+        .take(N)
+        .map(|token| token.parse::<f32>().map_err(|_| format!("Invalid number '{token}'")))
+        .collect::<Result<Vec<_>, _>>()?;
+    values.try_into().map_err(|_| format!("Expected {N} numbers"))
+}
+
+/// Parses a corner of a face (`v`, `v/vt`, `v//vn` or `v/vt/vn`) into the indices (starting
+/// from 0) of its vertex and texture coordinates. Negative indices are relative to the end.
+fn parse_corner(token: &str, n_vertices: usize, n_uvs: usize) -> Result<(usize, Option<usize>), String> {
+    fn index(value: &str, len: usize) -> Result<usize, String> {
+        let i: i64 = value.parse().map_err(|_| format!("Invalid index '{value}'"))?;
+        let resolved = if i < 0 { len as i64 + i } else { i - 1 };
+        if resolved < 0 || resolved >= len as i64 {
+            return Err(format!("Index {i} out of range"));
+        }
+        Ok(resolved as usize)
+    }
+    let mut parts = token.split('/');
+    let vertex = index(parts.next().unwrap_or_default(), n_vertices)?;
+    let uv = match parts.next() {
+        Some(value) if !value.is_empty() => Some(index(value, n_uvs)?),
+        _ => None,
+    };
+    Ok((vertex, uv))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::loaders::obj::parse;
+    use crate::primitives::object::Object;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::textures::image::ImageTexture;
+    use crate::primitives::textures::TextureHandle;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_parse_obj() {
+        let text = "# a square and a triangle\n\
+                    o model\n\
+                    v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                    vt 0 0\nvt 1 0\nvt 1 1\n\
+                    vn 0 0 1\n\
+                    f 1 2 3 4\n\
+                    f 1/1/1 2/2/1 -2/3/1\n";
+        let mesh = parse(text, Vector3::newi(5, 0, 0), &YELLOW).unwrap();
+        let faces = mesh.get_all_faces();
+        assert_eq!(3, faces.len());
+        // The y-axis of the file goes up, which is -z in the world
+        assert!(faces[0].points()[2] == Vector3::new(6., 0., -1.));
+        assert_eq!(0.5, faces[0].area());
+        assert!(faces[0].normal().y() > 0.99);
+        // The texture coordinates are given in fractions of the texture
+        assert_eq!((1., 1.), faces[2].uvs()[2]);
+        let pixels = TextureHandle::new(ImageTexture::from_rgba(2, 2, &[0; 16]));
+        let mesh = parse(text, Vector3::empty(), &pixels).unwrap();
+        assert_eq!((pixels.height(), pixels.width()), mesh.get_all_faces()[2].uvs()[2]);
+
+        // The faces without area are skipped
+        let flat = "v 0 0 0\nv 1 0 0\nv 2 0 0\nv 2 1 0\nf 1 2 3\nf 1 3 4\n";
+        let mesh = parse(flat, Vector3::empty(), &YELLOW).unwrap();
+        let faces = mesh.get_all_faces();
+        assert_eq!(1, faces.len());
+        assert!(!faces[0].normal().x().is_nan());
+
+        assert!(parse("v 0 0\n", Vector3::empty(), &YELLOW).err().unwrap().starts_with("line 1"));
+        assert!(parse("v 0 0 0\nf 1 2 3\n", Vector3::empty(), &YELLOW).is_err());
+    }
+}
@@ -1,49 +1,234 @@
 use std::f32::consts::PI;
+use std::path::Path;
 use std::time::Instant;
 
 use pixels::{Error, Pixels, SurfaceTexture};
 use winit::dpi::LogicalSize;
-use winit::event::{Event, VirtualKeyCode};
+use winit::event::{DeviceEvent, Event, VirtualKeyCode};
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::WindowBuilder;
+use winit::window::{CursorGrabMode, WindowBuilder};
 use winit_input_helper::WinitInputHelper;
 
-use crate::drawable::Drawable;
-use crate::fps::FPSMonitor;
-use crate::frame::Frame;
-use crate::primitives::camera::Camera;
-use crate::primitives::cube::Cube3;
-use crate::primitives::cubic_face3::CubicFace3;
-use crate::primitives::textures::bw::BWTexture;
-use crate::primitives::textures::colored::{ColoredTexture, ORANGE, PURPLE, YELLOW};
-use crate::primitives::textures::pixelated::Pixelated;
-use crate::primitives::vector::{UNIT_Z, Vector3};
-use crate::worlds::World;
-
-pub mod bsp;
-mod drawable;
-mod fps;
-mod frame;
-mod motion_model;
-mod png_saver;
-mod primitives;
-mod worlds;
-
-// For different screen resolution: https://en.wikipedia.org/wiki/Display_resolution
-
-pub const WIDTH: u32 = 960;
-pub const HEIGHT: u32 = 640;
+use doom::assets::AssetLoader;
+use doom::benchmark::CameraPath;
+use doom::config::{Config, CONFIG_PATH};
+use doom::content_hash::frame_hash;
+use doom::drawable::Drawable;
+use doom::fog::Fog;
+use doom::fps::FPSMonitor;
+use doom::input_map::{Action, SCRIPTABLE_KEYS};
+use doom::labels::Label;
+use doom::layers::Layers;
+use doom::lights::{Falloff, Lighting, PointLight};
+use doom::frame::Frame;
+use doom::primitives::aabb::Aabb;
+use doom::primitives::camera::Camera;
+use doom::primitives::cube::Cube3;
+use doom::primitives::cubic_face3::Receivers;
+use doom::primitives::mesh::Mesh;
+use doom::primitives::object::ObjectId;
+use doom::primitives::position::Pose;
+use doom::primitives::snapping::Grid;
+use doom::primitives::sphere::Sphere;
+use doom::primitives::tessellation::Patch;
+use doom::primitives::textures::bw::BWTexture;
+use doom::primitives::textures::colored::{ColoredTexture, ORANGE, PURPLE, YELLOW};
+use doom::primitives::textures::image::{ImageTexture, Wrap};
+use doom::primitives::textures::pixelated::Pixelated;
+use doom::primitives::textures::TextureHandle;
+use doom::primitives::vector::Vector3;
+use doom::primitives::color::Color;
+use doom::profiler::{FrameProfiler, Stage};
+use doom::renderer::Renderer;
+use doom::scenes::transition::TransitionKind;
+use doom::scenes::SceneManager;
+use doom::sky::SkyKind;
+use doom::timestep::{FixedTimestep, SIMULATION_DT};
+use doom::viewport::Viewport;
+use doom::voxels::meshing::ChunkMesher;
+use doom::voxels::water::WaterSimulation;
+use doom::voxels::VoxelWorld;
+use doom::sdf::{SdfBox, SdfSphere, SdfUnion};
+use doom::ui::debug_hud::DebugHud;
+use doom::ui::settings::SettingsMenu;
+use doom::ui::timing_graph::{draw_timing_graph, GRAPH_FRAMES};
+use doom::ui::{MouseState, Ui};
+use doom::weather::WeatherKind;
+use doom::worlds::World;
+use doom::{run_compare, tunables, benchmark, loaders, png_saver, HEIGHT, WIDTH};
+
+use crate::bookmarks::{append_bookmark, Bookmark, BOOKMARKS_PATH};
+use crate::focus::FocusTracker;
+use crate::replay::{InputEvent, InputScript};
+
+mod batch_render;
+mod bench;
+mod bookmarks;
+mod crash_dump;
+mod focus;
+mod replay;
+mod screenshots;
+
+/// Seed used for the random number generator of the world in deterministic mode
+const DETERMINISTIC_SEED: u64 = 0;
+
+/// Scene file written and read at runtime
+const LEVEL_PATH: &str = "level.json";
+
+/// Number of threads loading the assets
+const ASSET_THREADS: usize = 2;
+
+/// The object edited with the keyboard is the one the camera looks at, up to this distance
+const EDIT_DISTANCE: f32 = 3.;
+
+/// Step of the grid on which the edited objects are snapped
+const EDIT_GRID_STEP: f32 = 0.5;
+
+/// The fog starts at this fraction of the draw distance
+const FOG_START: f32 = 0.6;
 
 fn main() -> Result<(), Error> {
+    // Command line options
+    // * `--deterministic`: fixed time step and seeded randomness; the hashes of the scene and of the
+    //   image are logged at each frame
+    // * `--record <path>`: records the inputs in a script, saved when exiting (implies deterministic)
+    // * `--replay <path>`: replays the inputs of a script instead of the keyboard (implies deterministic)
+    // * `--scene <path>`: replaces the demo world by the content of a scene file
+    // * `--resolution <width>x<height>`: size of the rendered frames (960x640 by default). The frames
+    //   are then resized with the window.
+    // * `--bench`: runs the micro-benchmarks and exits
+    // * `compare <renderer> <renderer>`: writes the difference between two renderers and exits
+    // * `map [--scale <pixels per meter>] [--out <path>]`: saves a top-down map of the world and exits
+    // * `visibility [--out <path>]`: saves the visibility of each face from the spawn point as CSV and exits
+    // * `benchmark [--frames <n>] [--out <path.md|path.json>]`: renders a flythrough of the demo world and
+    //   saves a report of the performance
+    // * `screenshots [--bookmarks <path>] [--renderers <list>] [--out <directory>]`: renders each camera
+    //   bookmark with each renderer, and saves the images with a grid of all of them
+    // * `render [--scene <path>] [--path <bookmarks>] [--frames <n>] [--renderer <name>] [--out <directory>]`:
+    //   renders an image sequence with the camera going through the bookmarks, without any window
+    let args: Vec<String> = std::env::args().collect();
+    if let Ok(config) = Config::load(CONFIG_PATH) {
+        // Before the engine uses them
+        if let Err(err) = tunables::set_tunables(config.tunables) {
+            println!("{err}");
+        }
+    }
+    if args.iter().any(|a| a == "--bench") {
+        bench::texture_sampling_benchmark();
+        return Ok(());
+    }
+    if args.get(1).is_some_and(|a| a == "compare") {
+        // The renderers must see the same textures: wait for all of them to be loaded
+        let loader = AssetLoader::new(ASSET_THREADS);
+        let world = demo_world(&loader);
+        loader.finish();
+        let palette = Config::load(CONFIG_PATH).map(|config| config.debug_palette).unwrap_or_default();
+        if let Err(err) = run_compare(&args[2..], world, palette) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if args.get(1).is_some_and(|a| a == "screenshots") {
+        let loader = AssetLoader::new(ASSET_THREADS);
+        let world = demo_world(&loader);
+        loader.finish();
+        if let Err(err) = screenshots::run_screenshots(&args[2..], world, demo_bookmarks()) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if args.get(1).is_some_and(|a| a == "render") {
+        // The scene files refer to the textures of the demo world by their names
+        let loader = AssetLoader::new(ASSET_THREADS);
+        let world = demo_world(&loader);
+        loader.finish();
+        if let Err(err) = batch_render::run_render(&args[2..], world) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let arg_value = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).cloned();
+    if args.get(1).is_some_and(|a| a == "map") {
+        let loader = AssetLoader::new(ASSET_THREADS);
+        let world = demo_world(&loader);
+        loader.finish();
+        let scale = arg_value("--scale").and_then(|s| s.parse().ok()).unwrap_or(16.);
+        let out = arg_value("--out").unwrap_or("map.png".to_string());
+        let map = world.render_map(scale);
+        if let Err(err) = map.save(&out) {
+            println!("Could not save the map: {err}");
+            std::process::exit(1);
+        }
+        let (width, height) = map.size();
+        println!("Map of {width}x{height} pixels written to {out}");
+        return Ok(());
+    }
+    if args.get(1).is_some_and(|a| a == "visibility") {
+        let loader = AssetLoader::new(ASSET_THREADS);
+        let world = demo_world(&loader);
+        loader.finish();
+        let out = arg_value("--out").unwrap_or("visibility.csv".to_string());
+        let stats = world.visibility_stats(world.camera().pose());
+        println!("{}", stats.summary());
+        if let Err(err) = std::fs::write(&out, stats.to_csv()) {
+            println!("Could not save the statistics: {err}");
+            std::process::exit(1);
+        }
+        println!("Statistics of each face written to {out}");
+        return Ok(());
+    }
+    if args.get(1).is_some_and(|a| a == "benchmark") {
+        let loader = AssetLoader::new(ASSET_THREADS);
+        let mut world = demo_world(&loader);
+        loader.finish();
+        let frames = arg_value("--frames").and_then(|s| s.parse().ok()).unwrap_or(120);
+        if frames == 0 {
+            println!("The benchmark needs at least one frame");
+            std::process::exit(1);
+        }
+        let out = arg_value("--out").unwrap_or("benchmark.md".to_string());
+        let report = benchmark::run(&mut world, &demo_flythrough(), frames);
+        let text = if out.ends_with(".json") {
+            serde_json::to_string_pretty(&report.to_json()).expect("The report can always be serialized")
+        } else {
+            report.to_markdown()
+        };
+        if let Err(err) = std::fs::write(&out, text) {
+            println!("Could not save the report: {err}");
+            std::process::exit(1);
+        }
+        println!("{}", report.to_markdown());
+        return Ok(());
+    }
+    let record_path = arg_value("--record");
+    let replay_script = arg_value("--replay").map(|path| InputScript::load(&path).expect("Could not load the input script"));
+    let deterministic = args.iter().any(|a| a == "--deterministic") || record_path.is_some() || replay_script.is_some();
+
+    let mut config = Config::load(CONFIG_PATH).unwrap_or_else(|err| {
+        println!("Could not load {CONFIG_PATH}, using the default settings: {err}");
+        Config::default()
+    });
+    let mut viewport = match arg_value("--resolution").map(|text| Viewport::parse(&text)) {
+        Some(Ok(viewport)) => viewport,
+        Some(Err(err)) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        None => Viewport::default(),
+    };
+
     let event_loop = EventLoop::new();
     let mut input = WinitInputHelper::new();
 
     let window = {
-        let size = LogicalSize::new(WIDTH as f64, HEIGHT as f64);
+        let size = window_size(viewport, config.resolution_scale);
         WindowBuilder::new()
             .with_title("Hello Pixels")
             .with_inner_size(size)
-            .with_min_inner_size(size)
+            .with_min_inner_size(LogicalSize::new(WIDTH as f64 / 2., HEIGHT as f64 / 2.))
             .build(&event_loop)
             .unwrap()
     };
@@ -51,32 +236,461 @@ fn main() -> Result<(), Error> {
     let mut pixels = {
         let window_size = window.inner_size();
         let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(WIDTH, HEIGHT, surface_texture)?
+        Pixels::new(viewport.width, viewport.height, surface_texture)?
     };
 
-    let supported_keys_pressed = [VirtualKeyCode::R, VirtualKeyCode::E];
+    // Actions bound to the keys, either sent to the world or handled here
+    let mut input_map = config.key_bindings.input_map();
+
+    // Textures are loaded in the background while the game starts
+    let mut scenes = SceneManager::new(
+        AssetLoader::new(ASSET_THREADS),
+        vec![("demo", demo_world), ("texture gallery", texture_gallery), ("voxel terrain", voxel_terrain)],
+    );
+    if let Some(path) = arg_value("--scene") {
+        if let Err(err) = scenes.world_mut().load_scene(&path) {
+            println!("Could not load the scene {path}: {err}");
+        }
+    }
+    apply_config(scenes.world_mut(), &config);
+    scenes.world_mut().set_viewport(viewport);
+    if deterministic {
+        scenes.world_mut().set_seed(DETERMINISTIC_SEED);
+    }
+    crash_dump::install();
+    crash_dump::record_scene(scenes.world_mut().describe());
+    let mut recorded_script = InputScript::new();
+    let mut frame_count: u64 = 0;
 
-    let supported_keys_held = [
-        VirtualKeyCode::Down,
-        VirtualKeyCode::Up,
-        VirtualKeyCode::Left,
-        VirtualKeyCode::Right,
-        VirtualKeyCode::J,
-        VirtualKeyCode::K,
-    ];
+    // Run the main loop
+    let mut fps_monitor = FPSMonitor::new();
+    let mut debug_hud = DebugHud::new();
+    let mut show_hud = false;
+    let mut ui = Ui::new(10, 10);
+    ui.set_viewport(viewport);
+    let mut settings = SettingsMenu::new();
+    let mut show_ui = false;
+    let mut sky_kind = SkyKind::Solid;
+    let mut profiler = FrameProfiler::new(GRAPH_FRAMES);
+    let mut show_timing_graph = false;
+    let mut renderer = Renderer::new();
+    let mut log_movement = false;
+    let mut focus = FocusTracker::new();
+    // While looking with the mouse, the cursor is hidden and kept in the window
+    let mut mouse_look = false;
+    // Raw motion of the mouse since the last update: the position of a grabbed cursor does not
+    // change on all the platforms
+    let mut mouse_motion = (0., 0.);
+    let mut clock = FixedTimestep::new(SIMULATION_DT);
+    event_loop.run(move |event, _, control_flow| {
+        // In the background, the simulation is suspended and the frame rate is reduced. Recordings
+        // and replays keep running, so that they stay in sync with the frames.
+        if !deterministic {
+            if let Some(focused) = focus.handle_event(&event) {
+                clock.set_suspended(!focused);
+                if !focused {
+                    // Give the mouse back to the other windows
+                    mouse_look = false;
+                    if let Err(err) = window.set_cursor_grab(CursorGrabMode::None) {
+                        log_error("window.set_cursor_grab", err);
+                    }
+                    window.set_cursor_visible(true);
+                }
+            }
+        }
+
+        if let Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta: (dx, dy) }, .. } = event {
+            mouse_motion.0 += dx as f32;
+            mouse_motion.1 += dy as f32;
+        }
+
+        if let Event::RedrawRequested(_) = event {
+            let world = scenes.world_mut();
+
+            world.draw_sky(pixels.frame_mut());
+
+            renderer.draw(config.renderer, world, pixels.frame_mut(), &mut profiler);
+
+            // The depth of the faces is only needed to hide the particles behind them
+            if world.weather().kind() != WeatherKind::Clear {
+                let depth = renderer.depth(config.renderer, world);
+                world.draw_weather(&mut Frame::new(pixels.frame_mut(), viewport), depth);
+            }
+            world.draw_labels(&mut Frame::new(pixels.frame_mut(), viewport));
+            world.draw_debug_paths(&mut Frame::new(pixels.frame_mut(), viewport), config.debug_palette);
+            scenes.apply_transition(pixels.frame_mut());
+
+            if show_hud {
+                debug_hud.update(fps_monitor.fps(), scenes.world_mut());
+                debug_hud.draw(&mut Frame::new(pixels.frame_mut(), viewport));
+            }
+
+            if show_timing_graph {
+                draw_timing_graph(&mut Frame::new(pixels.frame_mut(), viewport), &profiler, config.debug_palette);
+            }
+
+            if show_ui {
+                ui.draw(&mut Frame::new(pixels.frame_mut(), viewport));
+            }
+
+            if let Err(err) = profiler.time(Stage::Present, || pixels.render()) {
+                log_error("pixels.render", err);
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            profiler.end_frame();
+            crash_dump::record_frame(pixels.frame(), viewport, scenes.world_mut().camera().pose());
+            // The hashes can be compared across branches to find regressions, without storing images
+            if deterministic {
+                println!("Frame {frame_count}: scene {:016x}, image {:016x}", scenes.world_mut().content_hash(), frame_hash(pixels.frame()));
+            }
+
+            fps_monitor.add_frame(Instant::now());
+        }
+
+        // Handle input events
+        if input.update(&event) {
+            let world = scenes.world_mut();
+            let mut switch_scene = None;
+
+            // Runtime controls
+            if show_ui {
+                let position = input.mouse().map(|mouse| {
+                    let (x, y) = pixels.window_pos_to_pixel(mouse).unwrap_or_else(|pos| pixels.clamp_pixel_pos(pos));
+                    (x as i32, y as i32)
+                });
+                ui.begin(MouseState { position, down: input.mouse_held(0), pressed: input.mouse_pressed(0) });
+                ui.label(&format!("Settings ({})", config.key_bindings.toggle_ui));
+                let mut paused = world.is_paused();
+                if ui.checkbox("Paused", &mut paused) {
+                    world.set_time_scale(if paused { 0. } else { 1. });
+                }
+                let mut layers = world.visible_layers();
+                for (name, layer) in [("Debug geometry", Layers::DEBUG), ("Editor markers", Layers::EDITOR)] {
+                    let mut shown = layers.intersects(layer);
+                    if ui.checkbox(name, &mut shown) {
+                        layers = if shown { layers.with(layer) } else { layers.without(layer) };
+                    }
+                }
+                if layers != world.visible_layers() {
+                    world.set_visible_layers(layers);
+                    renderer.reset();
+                }
+                let paths: Vec<(String, bool)> = world.debug_paths().iter().map(|path| (path.name().to_string(), path.is_visible())).collect();
+                for (name, mut visible) in paths {
+                    if ui.checkbox(&format!("Path: {name}"), &mut visible) {
+                        world.debug_paths_mut().set_visible(&name, visible);
+                    }
+                }
+                if ui.button("Respawn") {
+                    world.respawn();
+                }
+                // Shows the path that an AI would follow from the camera to the spawn point
+                if let Some(spawn) = world.spawn_point().map(|pose| *pose.position()) {
+                    if ui.button("Find path to spawn") {
+                        let from = *world.camera().pose().position();
+                        match world.find_path(from, Vector3::new(spawn.x(), spawn.y(), from.z())) {
+                            Some(path) => world.debug_paths_mut().set("A* to spawn", path),
+                            None => eprintln!("No path found to the spawn point"),
+                        }
+                    }
+                }
+                if ui.button(&format!("Weather: {:?}", world.weather().kind())) {
+                    let kind = world.weather().kind().next();
+                    world.weather_mut().set_kind(kind);
+                }
+                if ui.button(&format!("Sky: {sky_kind:?}")) {
+                    sky_kind = sky_kind.next();
+                    world.set_sky(sky_kind.create());
+                    renderer.reset();
+                }
+                let mut intensity = world.weather().intensity();
+                if ui.slider("Intensity", &mut intensity, 0., 1.) {
+                    world.weather_mut().set_intensity(intensity);
+                }
+                if settings.show(&mut ui, &mut config) {
+                    // Apply the new settings right away
+                    window.set_inner_size(window_size(viewport, config.resolution_scale));
+                    apply_config(world, &config);
+                    renderer.reset();
+                }
+
+                // The next key pressed is bound to the selected action
+                if settings.is_rebinding() {
+                    if let Some(key) = SCRIPTABLE_KEYS.into_iter().find(|key| input.key_pressed(*key)) {
+                        settings.bind_key(&mut config, key);
+                        input_map = config.key_bindings.input_map();
+                        apply_config(world, &config);
+                    }
+                }
+            }
 
-    // Texture library is created in the main
-    let bw_texture = Box::leak(Box::new(BWTexture::new(0.5, 0.5)));
+            // The right button captures the mouse to look around, or releases it
+            if input.mouse_pressed(1) {
+                mouse_look = !mouse_look;
+                let grab = if mouse_look {
+                    // Not all the platforms can confine the cursor, but they can lock it
+                    window.set_cursor_grab(CursorGrabMode::Confined).or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked))
+                } else {
+                    window.set_cursor_grab(CursorGrabMode::None)
+                };
+                if let Err(err) = grab {
+                    log_error("window.set_cursor_grab", err);
+                }
+                window.set_cursor_visible(!mouse_look);
+            }
+            let (dx, dy) = std::mem::take(&mut mouse_motion);
+            if mouse_look && replay_script.is_none() && (dx != 0. || dy != 0.) {
+                world.mouse_moved(dx, dy);
+                recorded_script.record(frame_count, InputEvent::MouseMoved(dx, dy));
+            }
+
+            // The mouse wheel zooms in and out
+            let scroll = input.scroll_diff();
+            if scroll != 0. && replay_script.is_none() && !(show_ui && ui.wants_mouse()) {
+                world.mouse_scrolled(scroll);
+                recorded_script.record(frame_count, InputEvent::MouseScrolled(scroll));
+            }
+
+            // left mouse pressed
+            if input.mouse_pressed(0) && !(show_ui && ui.wants_mouse()) {
+                if let Some(mouse) = input.mouse() {
+                    world.left_mouse_pressed(mouse.0 as i16, mouse.1 as i16)
+                }
+            }
+
+            // Close events
+            if input.key_pressed(VirtualKeyCode::Escape) || input.close_requested() {
+                if let Some(path) = &record_path {
+                    match recorded_script.save(path) {
+                        Ok(()) => println!("Input script saved to {path}"),
+                        Err(err) => log_error("recorded_script.save", err),
+                    }
+                }
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+
+            // Handle some keys to be sent to the world
+            if let Some(script) = &replay_script {
+                script.apply(frame_count, world);
+            } else if !settings.is_rebinding() {
+                for (action, key) in input_map.bindings() {
+                    if !action.is_held() && !action.is_application() && input.key_pressed(key) {
+                        world.action_pressed(action);
+                        recorded_script.record(frame_count, InputEvent::ActionPressed(action));
+                    }
+                }
+            }
+
+            // Debug options
+            let action_pressed = |action| input_map.key(action).is_some_and(|key| input.key_pressed(key));
+            if action_pressed(Action::ToggleFps) {
+                show_hud = !show_hud;
+            } else if action_pressed(Action::PrintCamera) {
+                println!("Cam position = {:?}", world.camera().pose().position());
+                println!(
+                    "Cam orientation = {:?}",
+                    world.camera().pose().orientation()
+                );
+                println!("Cam pitch = {}, roll = {}", world.camera().pose().pitch(), world.camera().pose().roll());
+                println!("Objects = {}", world.objects().count());
+                if let Some(object) = object_in_front(world).and_then(|id| world.get(id)) {
+                    println!("Object in front = {:?} with {} faces", object.kind(), object.get_all_faces().len());
+                }
+            } else if action_pressed(Action::CycleRenderer) {
+                config.renderer = config.renderer.next();
+                println!("Using renderer = {:?}", config.renderer);
+            } else if action_pressed(Action::Screenshot) && input.held_shift() {
+                // Shift + the screenshot key bookmarks the view, to render it with the `screenshots` command
+                match append_bookmark(BOOKMARKS_PATH, world.camera().pose().clone()) {
+                    Ok(name) => println!("Camera pose saved as {name} in {BOOKMARKS_PATH}"),
+                    Err(err) => println!("Could not save the bookmark: {err}"),
+                }
+            } else if action_pressed(Action::Screenshot) {
+                match png_saver::save_screenshot(pixels.frame(), viewport, Path::new(".")) {
+                    Ok(path) => println!("Screenshot saved to {}", path.display()),
+                    Err(err) => println!("Could not save the screenshot: {err}"),
+                }
+            } else if action_pressed(Action::ToggleUi) {
+                show_ui = !show_ui;
+            } else if action_pressed(Action::TimingGraph) {
+                show_timing_graph = !show_timing_graph;
+            } else if action_pressed(Action::LogMovement) {
+                log_movement = !log_movement;
+                println!("Logging movement events = {log_movement}");
+            } else if action_pressed(Action::SwitchScene) {
+                // Shift + the switch key fades to black instead of cross-fading
+                switch_scene = Some(if input.held_shift() { TransitionKind::FadeToBlack } else { TransitionKind::CrossFade });
+            } else if action_pressed(Action::DescribeScene) {
+                match world.save_description("scene.json") {
+                    Ok(()) => println!("Scene description saved to scene.json"),
+                    Err(err) => log_error("world.save_description", err),
+                }
+            } else if action_pressed(Action::SaveScene) {
+                // Shift + the save key reloads the saved scene
+                if input.held_shift() {
+                    match world.load_scene(LEVEL_PATH) {
+                        Ok(()) => {
+                            renderer.reset();
+                            crash_dump::record_scene(world.describe());
+                            println!("Scene loaded from {LEVEL_PATH}");
+                        }
+                        Err(err) => println!("Could not load the scene: {err}"),
+                    }
+                } else {
+                    match world.save_scene(LEVEL_PATH) {
+                        Ok(()) => println!("Scene saved to {LEVEL_PATH}"),
+                        Err(err) => println!("Could not save the scene: {err}"),
+                    }
+                }
+            } else if action_pressed(Action::Spectate) {
+                world.set_spectating(!world.is_spectating());
+                println!("Spectating = {}", world.is_spectating());
+            } else if input.key_pressed(VirtualKeyCode::Delete) {
+                if let Some(id) = object_in_front(world) {
+                    world.remove(id);
+                    renderer.reset();
+                    crash_dump::record_scene(world.describe());
+                }
+            } else if input.key_pressed(VirtualKeyCode::Insert) {
+                if let Some(object) = object_in_front(world).and_then(|id| world.get_mut(id)) {
+                    object.snap(&Grid::new(EDIT_GRID_STEP));
+                    renderer.reset();
+                    crash_dump::record_scene(world.describe());
+                }
+            }
+
+            // Resize the window
+            if let Some(size) = input.window_resized() {
+                if let Err(err) = pixels.resize_surface(size.width, size.height) {
+                    log_error("pixels.resize_surface", err);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                // Each pixel of the frame keeps covering `resolution_scale` pixels of the window:
+                // the frame is resized instead of being stretched
+                let scale = window.scale_factor() * config.resolution_scale as f64;
+                let resized = Viewport::fitting(size.width, size.height, scale);
+                if resized != viewport {
+                    viewport = resized;
+                    if let Err(err) = pixels.resize_buffer(viewport.width, viewport.height) {
+                        log_error("pixels.resize_buffer", err);
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    world.set_viewport(viewport);
+                    ui.set_viewport(viewport);
+                    renderer.reset();
+                }
+            }
+
+            // Update internal state by fixed steps and request a redraw. Recordings and replays
+            // simulate exactly one step per frame, since their inputs are saved for each frame.
+            let steps = if deterministic { 1 } else { clock.advance(Instant::now()) };
+            for _ in 0..steps {
+                // The held keys accelerate the camera at each step, not at each frame
+                if replay_script.is_none() && !settings.is_rebinding() {
+                    for (action, key) in input_map.bindings() {
+                        if action.is_held() && input.key_held(key) {
+                            world.action_held(action);
+                            recorded_script.record(frame_count, InputEvent::ActionHeld(action));
+                        }
+                    }
+                }
+                world.update(SIMULATION_DT);
+                if log_movement {
+                    for event in world.movement_events() {
+                        println!("{event:?}");
+                    }
+                }
+            }
+            world.interpolate_camera(if deterministic { 1. } else { clock.alpha() });
+            frame_count += 1;
+            if focus.should_redraw(Instant::now()) {
+                window.request_redraw();
+            }
+            *control_flow = focus.control_flow();
+
+            if let Some(transition) = switch_scene {
+                // The frame buffer still holds the last frame of the current scene
+                scenes.start_transition(transition, pixels.frame());
+                scenes.next();
+                apply_config(scenes.world_mut(), &config);
+                // The selected sky is kept across the scenes
+                scenes.world_mut().set_sky(sky_kind.create());
+                scenes.world_mut().set_viewport(viewport);
+                renderer.reset();
+                if deterministic {
+                    scenes.world_mut().set_seed(DETERMINISTIC_SEED);
+                }
+                crash_dump::record_scene(scenes.world_mut().describe());
+                println!(
+                    "Switched to scene '{}' ({} streamed textures in use)",
+                    scenes.current_name(),
+                    scenes.loader().textures_in_use()
+                );
+            }
+        }
+    });
+}
+
+/// Returns the object just in front of the camera, if any
+fn object_in_front(world: &World) -> Option<ObjectId> {
+    let camera = world.camera();
+    let (px, py) = camera.optical_center();
+    world.pick(&camera.screen_to_world_ray(px, py), EDIT_DISTANCE)
+}
+
+/// Standard camera path through the demo world, used to compare the performance of the renderers
+fn demo_flythrough() -> CameraPath {
+    let mut waypoints: Vec<Pose> = demo_bookmarks().into_iter().map(|bookmark| bookmark.pose).collect();
+    waypoints.push(waypoints[0].clone());
+    CameraPath::new(waypoints)
+}
+
+/// Views of the demo world, rendered by the `screenshots` command
+fn demo_bookmarks() -> Vec<Bookmark> {
+    vec![
+        Bookmark::new("view_1", Pose::new(Vector3::new(2., -3., -1.5), -PI / 2.)),
+        Bookmark::new("view_2", Pose::new(Vector3::new(-1.5, -2., -1.5), -PI / 4.)),
+        Bookmark::new("view_3", Pose::new(Vector3::new(-1.5, 2., -2.5), PI / 4.)),
+        Bookmark::new("view_4", Pose::new(Vector3::new(5.5, 2., -1.5), 3. * PI / 4.)),
+    ]
+}
+
+/// Creates the world displayed by the game. Its textures may still be loading when it is returned.
+fn demo_world(loader: &AssetLoader) -> World {
+    // Texture library is created here, the faces share the textures through handles
+    let bw_texture = &TextureHandle::new(BWTexture::new(0.5, 0.5));
 
     // Minecraft texture library
-    let soil_side = Box::leak(Box::new(Pixelated::soil_side()));
-    let soil_top = Box::leak(Box::new(Pixelated::soil_top()));
-    let wood = Box::leak(Box::new(Pixelated::wood()));
-    let floor = Box::leak(Box::new(Pixelated::wood_floor()));
-    let stone = Box::leak(Box::new(Pixelated::stone()));
+    let soil_side = &TextureHandle::new(Pixelated::soil_side());
+    let soil_top = &TextureHandle::new(Pixelated::soil_top());
+    let wood = &TextureHandle::new(Pixelated::wood());
+    let floor = &TextureHandle::new(Pixelated::wood_floor());
+    let stone = &TextureHandle::new(Pixelated::stone());
+    let bricks = &loader.load_texture("assets/textures/bricks.txt", stone);
+    let glass = &TextureHandle::new(ColoredTexture::new(Color::new(150, 200, 255, 100)));
 
     // Create a world with a standard camera
     let mut world = World::new(Camera::default());
+    let named_textures = [
+        ("soil_side", soil_side),
+        ("soil_top", soil_top),
+        ("wood", wood),
+        ("stone", stone),
+        ("bricks", bricks),
+        ("glass", glass),
+        ("yellow", &YELLOW),
+        ("orange", &ORANGE),
+        ("purple", &PURPLE),
+    ];
+    for (name, texture) in named_textures {
+        world.register_texture(name, texture);
+    }
+    // Only loaded if a scene file uses it
+    world.textures_mut().insert_file("red_bricks", "assets/textures/red_bricks.png");
 
     // Create many cubes arranged as a sort of maze
     // let c = Color::purple();
@@ -119,103 +733,161 @@ fn main() -> Result<(), Error> {
     world.add_cube(Cube3::minecraft_like(Vector3::newi(0,-1,0), wood, wood));
     world.add_cube(Cube3::minecraft_like(Vector3::newi(0,-3,0), stone, stone));
     world.add_cube(Cube3::minecraft_like(Vector3::newi(1,-3,0), stone, stone));
+    world.add_cube(Cube3::minecraft_like(Vector3::newi(4,0,0), bricks, bricks));
+    // A glass block, through which the other blocks are seen
+    world.add_cube(Cube3::minecraft_like(Vector3::newi(1,-1,0), glass, glass));
+    world.add_mesh(Mesh::pyramid(Vector3::new(2.5, -2., 1.), 1., 1.2, stone));
+    if let Err(err) = loaders::obj::load_into(&mut world, "assets/models/arrow.obj", Vector3::new(4.5, 0.5, 0.), &ORANGE) {
+        println!("Could not load the model: {err}");
+    }
+
+    // A column and a doorway arch, made of tessellated curved surfaces
+    world.add_patch(Patch::cylinder(Vector3::new(-1.5, -2.5, 0.), 0.3, 2., 12, stone));
+    world.add_patch(Patch::arch(Vector3::new(-1., 1.5, 0.), 0.7, 1., 0.5, 10, bricks));
+
+    // A glowing lamp, which is not darkened by the lights
+    let lamp = world.add_cube(Cube3::minecraft_like(Vector3::newi(3,-1,0), &YELLOW, &YELLOW).with_receivers(Receivers::ALL.without(Receivers::LIGHT)));
+    world.set_label(lamp, Label::new("Lamp", [255, 230, 120, 255]));
+
+    // A warm torch next to the lamp, and a cold moonlight from far above
+    let (torch, moon) = (Vector3::new(2., -1., -1.5), Vector3::new(-10., 5., -20.));
+    let mut lighting = Lighting::new(&Color::new(255, 255, 255, 255), 0.6);
+    lighting.add_light(PointLight::new(torch, &Color::new(255, 150, 60, 255), 1.2, 4.));
+    lighting.add_light(PointLight::new(moon, &Color::new(120, 150, 255, 255), 0.5, 40.).with_falloff(Falloff::Linear));
+    world.set_lighting(lighting);
+
+    // The lights are shown (and named) with the debug geometry
+    for (light, size, name) in [(torch, 0.1, "Torch"), (moon, 1., "Moon")] {
+        let half = Vector3::new(size, size, size) * 0.5;
+        let marker = Cube3::from_aabb(Aabb::new(light - half, light + half), &ORANGE, &ORANGE);
+        let id = world.add_cube(marker.with_receivers(Receivers::ALL.without(Receivers::LIGHT)));
+        world.set_layers(id, Layers::DEBUG);
+        world.set_label(id, Label::new(name, [255, 255, 255, 255]));
+    }
+
+    // The path of the benchmark flythrough, hidden until selected in the settings
+    let flythrough = demo_flythrough().waypoints().iter().map(|pose| *pose.position()).collect();
+    world.debug_paths_mut().set("Flythrough", flythrough);
+    world.debug_paths_mut().set_visible("Flythrough", false);
 
     // Sets the camera as looking at the object
-    world.set_camera_position(Vector3::new(2., -3., -1.5));
-    world.set_camera_rotation(-PI / 2.);
+    let spawn = Vector3::new(2., -3., -1.5);
+    world.set_spawn_point(spawn, -PI / 2.);
+    world.respawn();
+
+    // The spawn point is marked on the ground, for the editor only
+    let ground = Vector3::new(spawn.x(), spawn.y(), 0.);
+    let marker = Aabb::new(ground + Vector3::new(-0.2, -0.2, -0.05), ground + Vector3::new(0.2, 0.2, 0.));
+    let id = world.add_cube(Cube3::from_aabb(marker, &PURPLE, &PURPLE));
+    world.set_layers(id, Layers::EDITOR);
 
     // Benchmarking the maze
     // world.set_camera_position(Vector3::new(0.11243102, -23.725393, -6.0802684));
     // world.set_camera_rotation(-PI / 2.);
 
-    // Calling this function will (i) build the BSP tree and (ii) force the renderer to use it
-    // when using the painter algorithm.
+    // Building the BSP tree ahead of time avoids a pause when the BSP renderer is first used
     world.compute_bsp();
+    world.merge_bsp_faces();
+
+    // Smooth shapes for the (experimental) ray marching renderer
+    world.set_sdf(Box::new(SdfUnion::new(
+        vec![
+            Box::new(SdfBox::new(Vector3::new(1.5, 2.5, 0.5), Vector3::new(0.5, 0.5, 0.5), Color::new(120, 80, 40, 255))),
+            Box::new(SdfSphere::new(Vector3::new(1.5, 2.5, -0.4), 0.6, Color::new(200, 60, 60, 255))),
+        ],
+        0.3,
+    )));
+
+    // A sphere, only drawn by the raytracer
+    let stone = TextureHandle::new(Pixelated::stone());
+    world.add_raytrace_object(Box::new(Sphere::new(Vector3::new(-1.5, 2.5, -0.5), 0.5).with_texture(&stone)));
+
+    world
+}
 
-    // Run the main loop
-    let mut fps_monitor = FPSMonitor::new();
-    let mut use_fps_monitor = false;
-    event_loop.run(move |event, _, control_flow| {
-        if let Event::RedrawRequested(_) = event {
-            // Draw the background color
-            let background = [214, 214, 194, 150];
-            for pixel in pixels.frame_mut().chunks_exact_mut(4) {
-                pixel.copy_from_slice(&background);
-            }
-
-            // For using painter algorithm (with or without binary space partitioning)
-            let mut current_frame = Frame::new(pixels.frame_mut());
-            world.draw_painter(&mut current_frame);
-
-            // For using raytracing algorithn:
-            // world.draw_raytracing(pixels.frame_mut());
-
-            if let Err(err) = pixels.render() {
-                log_error("pixels.render", err);
-                *control_flow = ControlFlow::Exit;
-                return;
-            }
-
-            fps_monitor.add_frame(Instant::now());
-
-            if use_fps_monitor {
-                fps_monitor.log_fps();
-            }
+/// Shows each texture on a cube, next to each other
+fn texture_gallery(loader: &AssetLoader) -> World {
+    let stone = &TextureHandle::new(Pixelated::stone());
+    let textures = [
+        TextureHandle::new(Pixelated::soil_side()),
+        TextureHandle::new(Pixelated::soil_top()),
+        TextureHandle::new(Pixelated::wood()),
+        TextureHandle::new(Pixelated::wood_floor()),
+        stone.clone(),
+        loader.load_texture("assets/textures/bricks.txt", stone),
+    ];
+    // The same image, smaller than the faces, with two ways of covering the rest of the faces
+    let images = [Wrap::Mirror, Wrap::Clamp].map(|wrap| match ImageTexture::load("assets/textures/red_bricks.png") {
+        Ok(image) => TextureHandle::new(image.with_tile_width(0.5).with_wrap(wrap)),
+        Err(err) => {
+            println!("Could not load the image: {err}");
+            stone.clone()
         }
+    });
+    let textures: Vec<&TextureHandle> = textures.iter().chain(&images).collect();
 
-        // Handle input events
-        if input.update(&event) {
-            // left mouse pressed
-            if input.mouse_pressed(0) {
-                if let Some(mouse) = input.mouse() {
-                    world.left_mouse_pressed(mouse.0 as i16, mouse.1 as i16)
-                }
-            }
-
-            // Close events
-            if input.key_pressed(VirtualKeyCode::Escape) || input.close_requested() {
-                *control_flow = ControlFlow::Exit;
-                return;
-            }
-
-            // Handle some keys to be sent to the world
-            for key in supported_keys_pressed {
-                if input.key_pressed(key) {
-                    world.key_pressed(key)
-                }
-            }
-            for key in supported_keys_held {
-                if input.key_held(key) {
-                    world.key_held(key)
-                }
-            }
+    let mut world = World::new(Camera::default());
+    for (i, texture) in textures.iter().enumerate() {
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(2 * i as i32, 0, 0), texture, texture));
+    }
+    world.set_spawn_point(Vector3::new(7.5, -8., -1.), -PI / 2.);
+    world.respawn();
+    world
+}
 
-            // Debug options
-            if input.key_pressed(VirtualKeyCode::F1) {
-                use_fps_monitor = !use_fps_monitor;
-                println!("Using FPS monitor = {use_fps_monitor}");
-            } else if input.key_pressed(VirtualKeyCode::F2) {
-                println!("Cam position = {:?}", world.camera().pose().position());
-                println!(
-                    "Cam orientation = {:?}",
-                    world.camera().pose().orientation()
-                );
+/// Creates hills of blocks, built by chunks which only hold the faces between the blocks and the air
+fn voxel_terrain(_loader: &AssetLoader) -> World {
+    let mut voxels = VoxelWorld::new(-4..2);
+    let grass = voxels.add_block_kind(&TextureHandle::new(Pixelated::soil_side()), &TextureHandle::new(Pixelated::soil_top()));
+    let stone = &TextureHandle::new(Pixelated::stone());
+    let stone = voxels.add_block_kind(stone, stone);
+    for x in -24..24 {
+        for y in -24..24 {
+            // The z-axis goes down: the top of the column is above the ground at z = 1
+            let hill = 1.5 * ((x as f32 / 5.).sin() + (y as f32 / 7.).cos() + 1.);
+            let top = 1 - hill.round().max(0.) as i32;
+            voxels.set(x, y, top, Some(grass));
+            for z in top + 1..=1 {
+                voxels.set(x, y, z, Some(stone));
             }
+        }
+    }
+
+    // A spring on the flank of a hill, whose water flows down the slopes
+    let water = voxels.add_block_kind(&TextureHandle::new(ColoredTexture::new(Color::new(40, 90, 200, 140))), &TextureHandle::new(ColoredTexture::new(Color::new(60, 120, 220, 140))));
+    voxels.set_transparent(water, true);
+    let mut simulation = WaterSimulation::new(water);
+    simulation.add_source(&mut voxels, 0, 0, -3);
+    for _ in 0..40 {
+        simulation.step(&mut voxels);
+    }
+
+    // The chunks are meshed in parallel
+    let mut world = World::new(Camera::default());
+    let mut mesher = ChunkMesher::new();
+    mesher.request_all(&voxels);
+    mesher.finish(&mut world);
+    world.set_spawn_point(Vector3::new(0.5, -20., -6.), PI / 2.);
+    world.respawn();
+    world
+}
 
-            // Resize the window
-            if let Some(size) = input.window_resized() {
-                if let Err(err) = pixels.resize_surface(size.width, size.height) {
-                    log_error("pixels.resize_surface", err);
-                    *control_flow = ControlFlow::Exit;
-                    return;
-                }
-            }
+/// Applies the settings which affect the world
+fn apply_config(world: &mut World, config: &Config) {
+    world.set_camera_fov(config.fov);
+    world.set_turn_step(config.sensitivity);
+    world.set_input_map(config.key_bindings.input_map());
+    world.set_mouse_sensitivity(config.mouse_sensitivity);
+    world.set_draw_distance(Some(config.draw_distance));
+    // The objects fade into the fog before reaching the draw distance, instead of popping in
+    let [r, g, b] = config.fog_color;
+    world.set_fog(config.fog.then(|| Fog::new(&Color::new(r, g, b, 255), FOG_START * config.draw_distance, config.draw_distance)));
+}
 
-            // Update internal state and request a redraw
-            world.update();
-            window.request_redraw();
-        }
-    });
+/// Size of the window showing frames of this size, at the given scale
+fn window_size(viewport: Viewport, resolution_scale: f32) -> LogicalSize<f64> {
+    let scale = resolution_scale as f64;
+    LogicalSize::new(viewport.width as f64 * scale, viewport.height as f64 * scale)
 }
 
 fn log_error<E: std::error::Error + 'static>(method_name: &str, err: E) {
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::frame::BACKGROUND_COLOR;
+use crate::png_saver;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::textures::Texture;
+use crate::primitives::vector::{Vector3, UNIT_Z};
+
+/// Number of samples (in each direction) used to compute the average color of a texture
+const TEXTURE_SAMPLES: usize = 8;
+/// Maximum size (in pixels) of each side of the map
+const MAX_SIZE: f32 = 4096.;
+
+/// A top-down view of a world, rendered orthographically.
+///
+/// The x-axis of the world goes to the right of the image and the y-axis goes down. Each pixel
+/// has the average color of the texture of the highest face above it.
+pub struct Map {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Map {
+    /// Renders the faces, with `scale` pixels per meter
+    pub fn render(faces: &[&CubicFace3], scale: f32) -> Self {
+        let points: Vec<Vector3> = faces.iter().flat_map(|f| f.points()).collect();
+        if points.is_empty() {
+            return Self { width: 0, height: 0, pixels: Vec::new() };
+        }
+        let min = |axis: fn(&Vector3) -> f32| points.iter().map(axis).fold(f32::INFINITY, f32::min);
+        let max = |axis: fn(&Vector3) -> f32| points.iter().map(axis).fold(f32::NEG_INFINITY, f32::max);
+        let (xmin, ymin, top) = (min(Vector3::x), min(Vector3::y), min(Vector3::z));
+        let scale = scale.min(MAX_SIZE / (max(Vector3::x) - xmin).max(max(Vector3::y) - ymin).max(1e-3));
+        let width = ((max(Vector3::x) - xmin) * scale).ceil().max(1.) as u32;
+        let height = ((max(Vector3::y) - ymin) * scale).ceil().max(1.) as u32;
+
+        // The faces seen from above are the ones whose normal goes up (the z-axis goes down)
+        let faces: Vec<&CubicFace3> = faces.iter().copied().filter(|f| f.normal().z() < 0.).collect();
+        let mut averages = HashMap::new();
+        let colors: Vec<[u8; 4]> = faces
+            .iter()
+            .map(|f| *averages.entry(&**f.texture() as *const dyn Texture as *const ()).or_insert_with(|| average_color(&**f.texture())))
+            .collect();
+
+        let mut pixels = Vec::with_capacity((4 * width * height) as usize);
+        for j in 0..height {
+            for i in 0..width {
+                // Cast a ray going down from above the world, at the center of the pixel
+                let origin = Vector3::new(xmin + (i as f32 + 0.5) / scale, ymin + (j as f32 + 0.5) / scale, top - 1.);
+                let highest = faces
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, f)| f.line_projection(&origin, &UNIT_Z).filter(|(_, p)| f.contains_projection(p)).map(|(d, _)| (d, k)))
+                    .min();
+                pixels.extend(highest.map_or(BACKGROUND_COLOR, |(_, k)| colors[k]));
+            }
+        }
+        Self { width, height, pixels }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        png_saver::save_frame(path, &self.pixels, self.width, self.height)
+    }
+}
+
+/// Returns the average color of a texture, by sampling it on a regular grid
+fn average_color(texture: &dyn Texture) -> [u8; 4] {
+    let mut sum = [0u32; 4];
+    for i in 0..TEXTURE_SAMPLES {
+        for j in 0..TEXTURE_SAMPLES {
+            let u = (i as f32 + 0.5) / TEXTURE_SAMPLES as f32 * texture.height();
+            let v = (j as f32 + 0.5) / TEXTURE_SAMPLES as f32 * texture.width();
+            for (s, c) in sum.iter_mut().zip(texture.color_at(u, v).rgba()) {
+                *s += c as u32;
+            }
+        }
+    }
+    sum.map(|s| (s / (TEXTURE_SAMPLES * TEXTURE_SAMPLES) as u32) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frame::BACKGROUND_COLOR;
+    use crate::map::Map;
+    use crate::primitives::color::Color;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::object::Object;
+    use crate::primitives::textures::colored::{PURPLE, YELLOW};
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_render_map() {
+        // A purple cube on top of a yellow floor of 2 cubes, in diagonal
+        let floor = [Cube3::minecraft_like(Vector3::newi(0, 0, 0), &YELLOW, &YELLOW), Cube3::minecraft_like(Vector3::newi(1, 1, 0), &YELLOW, &YELLOW)];
+        let top = Cube3::minecraft_like(Vector3::newi(0, 0, -1), &PURPLE, &PURPLE);
+        let faces: Vec<_> = floor.iter().chain([&top]).flat_map(|c| c.get_all_faces()).collect();
+
+        let map = Map::render(&faces, 4.);
+        assert_eq!((8, 8), map.size());
+        let pixel = |i: usize, j: usize| &map.pixels[4 * (j * 8 + i)..4 * (j * 8 + i) + 4];
+        assert_eq!(Color::purple().rgba(), pixel(1, 1));
+        assert_eq!(Color::yellow().rgba(), pixel(6, 6));
+        assert_eq!(BACKGROUND_COLOR, pixel(6, 1));
+        assert_eq!((0, 0), Map::render(&[], 4.).size());
+    }
+}
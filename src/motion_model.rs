@@ -1,23 +1,58 @@
-use crate::primitives::vector::Vector3;
+use crate::primitives::vector::{Vector3, UNIT_Z};
+use crate::tunables::tunables;
 
-const MAX_ACC: f32 = 200.;
-const MIN_ACC: f32 = 10.;
+/// Default acceleration given by the controls (see `Tunables`)
 pub const DEFAULT_ACC: f32 = 100.;
 
+/// Acceleration of the gravity, in m/s². The z-axis of the world points down, so the gravity
+/// pulls along +z.
+pub const GRAVITY: f32 = 9.81;
+
+/// Vertical speed given by a jump, in m/s (which lifts the camera by about 1.3 m)
+pub const JUMP_SPEED: f32 = 5.;
+
+/// Falls are not faster than this speed, in m/s
+const MAX_FALL_SPEED: f32 = 30.;
+
+/// Accelerates the fall of something moving at `velocity` during `dt` seconds, up to the maximum
+/// speed of the falls
+pub fn fall(velocity: &mut Vector3, dt: f32) {
+    velocity[2] = (velocity[2] + GRAVITY * dt).min(MAX_FALL_SPEED);
+}
+
 pub struct MotionModel {
+    /// Motion controlled by the keys, which fades out when they are released
     acc: Vector3,
+    /// Velocity of the physical motion (falling and jumping), in m/s
+    velocity: Vector3,
 }
 
 impl MotionModel {
     pub fn new() -> Self {
         Self {
             acc: Vector3::empty(),
+            velocity: Vector3::empty(),
         }
     }
 
     /// Returns the position updated by the motion model
     pub fn new_pos(&mut self, pos: &Vector3, dt: f32) -> Vector3 {
-        *pos + (self.acc * dt * dt)
+        *pos + (self.acc * dt * dt) + self.velocity * dt
+    }
+
+    /// Accelerates the fall during `dt` seconds
+    pub fn apply_gravity(&mut self, dt: f32) {
+        fall(&mut self.velocity, dt);
+    }
+
+    /// Pushes upwards: to be called only when standing on the ground
+    pub fn jump(&mut self) {
+        self.velocity = UNIT_Z * -JUMP_SPEED;
+    }
+
+    /// Stops the fall when reaching the ground
+    pub fn land(&mut self) {
+        self.velocity[2] = self.velocity[2].min(0.);
     }
 
     pub fn slow_down(&mut self) {
@@ -28,7 +63,7 @@ impl MotionModel {
     }
 
     fn slow_down_axis(&mut self, axis: usize) {
-        if self.acc[axis] > MIN_ACC {
+        if self.acc[axis] > tunables().min_acceleration {
             let correction = if self.acc[axis] > 0. {
                 -self.acc[axis] * 0.3
             } else {
@@ -40,12 +75,20 @@ impl MotionModel {
         }
     }
 
+    /// Stops the motion immediately
+    pub fn stop(&mut self) {
+        self.acc = Vector3::empty();
+        self.velocity = Vector3::empty();
+    }
+
     pub fn apply(&mut self, axis: usize, inc: f32) {
-        self.acc[axis] = (self.acc[axis] + inc).clamp(-MAX_ACC, MAX_ACC)
+        let max = tunables().max_acceleration;
+        self.acc[axis] = (self.acc[axis] + inc).clamp(-max, max)
     }
 
     pub fn increment_direction(&mut self, axis: Vector3, inc: f32) {
         self.acc += axis * inc;
-        self.acc.clamp(-MAX_ACC, MAX_ACC)
+        let max = tunables().max_acceleration;
+        self.acc.clamp(-max, max)
     }
 }
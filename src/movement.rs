@@ -0,0 +1,90 @@
+use std::fmt::{Debug, Formatter};
+
+use crate::primitives::textures::TextureHandle;
+
+/// Horizontal distance travelled on the ground between two steps
+const STEP_LENGTH: f32 = 0.7;
+
+/// Something that happened to the camera while moving, which other systems (sounds,
+/// particles...) can react to. The surface is the texture of the ground below the camera.
+#[derive(Clone)]
+pub enum MovementEvent {
+    /// A step was taken on the ground
+    Step { surface: TextureHandle },
+    /// The camera reached the ground after being in the air
+    Landed { surface: TextureHandle },
+    /// The camera was stopped by an object
+    BumpedWall,
+}
+
+impl Debug for MovementEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MovementEvent::Step { surface } => write!(f, "Step on {:p}", &**surface),
+            MovementEvent::Landed { surface } => write!(f, "Landed on {:p}", &**surface),
+            MovementEvent::BumpedWall => write!(f, "BumpedWall"),
+        }
+    }
+}
+
+/// Turns the motion of the camera into steps and landings
+pub struct FootstepTracker {
+    /// Distance travelled on the ground since the last step
+    distance: f32,
+    on_ground: bool,
+}
+
+impl FootstepTracker {
+    pub fn new() -> Self {
+        Self {
+            distance: 0.,
+            on_ground: true,
+        }
+    }
+
+    /// Updates the tracker with the horizontal distance moved since the last update and the
+    /// surface below the camera (None if the camera is in the air).
+    pub fn update(&mut self, moved: f32, ground: Option<TextureHandle>) -> Option<MovementEvent> {
+        let Some(surface) = ground else {
+            self.on_ground = false;
+            self.distance = 0.;
+            return None;
+        };
+        if !std::mem::replace(&mut self.on_ground, true) {
+            return Some(MovementEvent::Landed { surface });
+        }
+        self.distance += moved;
+        if self.distance >= STEP_LENGTH {
+            self.distance -= STEP_LENGTH;
+            return Some(MovementEvent::Step { surface });
+        }
+        None
+    }
+}
+
+impl Default for FootstepTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::movement::{FootstepTracker, MovementEvent, STEP_LENGTH};
+    use crate::primitives::textures::colored::YELLOW;
+
+    #[test]
+    fn test_footsteps() {
+        let mut tracker = FootstepTracker::new();
+        let steps = (0..20)
+            .filter_map(|_| tracker.update(STEP_LENGTH / 4., Some(YELLOW.clone())))
+            .filter(|event| matches!(event, MovementEvent::Step { .. }))
+            .count();
+        assert_eq!(5, steps);
+
+        // No step while in the air, and landing is reported once
+        assert!(tracker.update(STEP_LENGTH * 2., None).is_none());
+        assert!(matches!(tracker.update(STEP_LENGTH * 2., Some(YELLOW.clone())), Some(MovementEvent::Landed { .. })));
+        assert!(tracker.update(STEP_LENGTH / 2., Some(YELLOW.clone())).is_none());
+    }
+}
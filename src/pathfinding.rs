@@ -0,0 +1,102 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::primitives::vector::Vector3;
+
+/// Cost of a move to a neighbouring cell along an axis, and along a diagonal (about sqrt(2) times
+/// more). The costs are integers so that the open set can be ordered exactly.
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// Moves to the 8 neighbouring cells of the grid
+const NEIGHBOURS: [(i32, i32); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Estimated cost to go from a cell to another one (octile distance), which never overestimates
+/// the real cost
+fn heuristic(from: (i32, i32), to: (i32, i32)) -> u32 {
+    let (dx, dy) = ((from.0 - to.0).unsigned_abs(), (from.1 - to.1).unsigned_abs());
+    STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+}
+
+/// Finds the shortest path between two positions with A*, moving horizontally on a grid of cells
+/// of size `step` centered on `start` (the height of `start` is kept along the path).
+///
+/// A cell can be crossed when `is_free` returns true for its center, and the diagonal moves are
+/// only allowed when both cells along the corner are free. The search gives up after visiting
+/// `max_cells` cells.
+///
+/// Returns the waypoints of the path from `start` to `goal` (both included), or None if the goal
+/// cannot be reached.
+pub fn find_path(start: Vector3, goal: Vector3, step: f32, max_cells: usize, is_free: impl Fn(&Vector3) -> bool) -> Option<Vec<Vector3>> {
+    let position = |(i, j): (i32, i32)| start + Vector3::new(i as f32 * step, j as f32 * step, 0.);
+    let target = (((goal.x() - start.x()) / step).round() as i32, ((goal.y() - start.y()) / step).round() as i32);
+    let free = |cell: (i32, i32)| cell == target || is_free(&position(cell));
+
+    let mut open = BinaryHeap::from([(Reverse(heuristic((0, 0), target)), (0, 0))]);
+    let mut costs = HashMap::from([((0, 0), 0)]);
+    let mut parents: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+    let mut visited = 0;
+    while let Some((Reverse(estimate), cell)) = open.pop() {
+        let cost = costs[&cell];
+        // Entries made obsolete by a cheaper path to the same cell
+        if estimate > cost + heuristic(cell, target) {
+            continue;
+        }
+        if cell == target {
+            let mut cells = vec![cell];
+            while let Some(parent) = parents.get(cells.last().unwrap()) {
+                cells.push(*parent);
+            }
+            let mut path: Vec<Vector3> = cells.into_iter().rev().map(position).collect();
+            *path.last_mut().unwrap() = goal;
+            return Some(path);
+        }
+        visited += 1;
+        if visited > max_cells {
+            return None;
+        }
+        for (di, dj) in NEIGHBOURS {
+            let next = (cell.0 + di, cell.1 + dj);
+            let diagonal = di != 0 && dj != 0;
+            if !free(next) || (diagonal && (!free((cell.0 + di, cell.1)) || !free((cell.0, cell.1 + dj)))) {
+                continue;
+            }
+            let next_cost = cost + if diagonal { DIAGONAL_COST } else { STRAIGHT_COST };
+            if costs.get(&next).is_some_and(|known| *known <= next_cost) {
+                continue;
+            }
+            costs.insert(next, next_cost);
+            parents.insert(next, cell);
+            open.push((Reverse(next_cost + heuristic(next, target)), next));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pathfinding::find_path;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_find_path() {
+        let start = Vector3::new(0., 0., 1.);
+        let goal = Vector3::new(5., 0., 1.);
+
+        // Without obstacles, the path is a straight line
+        let path = find_path(start, goal, 1., 1000, |_| true).unwrap();
+        assert_eq!(6, path.len());
+        assert!(path.iter().all(|point| point.y() == 0. && point.z() == 1.));
+        assert!(path[0] == start && path[5] == goal);
+
+        // A wall between the positions, open from y = 3: the path goes around it
+        let wall = |point: &Vector3| point.x() == 2. && point.y() < 2.5;
+        let path = find_path(start, goal, 1., 1000, |point| !wall(point)).unwrap();
+        assert!(path.iter().all(|point| !wall(point)));
+        assert!(path.iter().any(|point| point.y() >= 3.));
+        assert!(path.windows(2).all(|segment| (segment[1] - segment[0]).norm() < 1.5));
+
+        // A closed wall: the search gives up
+        assert!(find_path(start, goal, 1., 1000, |point| point.x() != 2.).is_none());
+    }
+}
@@ -1,3 +1,8 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::viewport::Viewport;
+
 mod crc32 {
     // https://github.com/ledbettj/crc32/blob/master/rust/src/crc32.rs
     pub struct Crc32 {
@@ -220,6 +225,36 @@ pub fn write<W: ::std::io::Write>(
     Ok(())
 }
 
+/// Writes a frame (whose rows go from top to bottom, as in the buffer of pixels) to a PNG file
+pub fn save_frame(path: &str, frame: &[u8], w: u32, h: u32) -> Result<(), ::std::io::Error> {
+    let flipped: Vec<u8> = frame.chunks_exact(4 * w as usize).rev().flatten().copied().collect();
+    let mut file = std::fs::File::create(path)?;
+    write(&mut file, &flipped, w, h)
+}
+
+/// Returns a path in `directory` for a new screenshot, named after the current time (for instance
+/// `screenshot_1700000000_123.png`). A suffix is added if the file already exists.
+pub fn timestamped_path(directory: &Path) -> PathBuf {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let name = format!("screenshot_{}_{:03}", now.as_secs(), now.subsec_millis());
+    let mut path = directory.join(format!("{name}.png"));
+    let mut i = 1;
+    while path.exists() {
+        path = directory.join(format!("{name}_{i}.png"));
+        i += 1;
+    }
+    path
+}
+
+/// Saves the frame (the pixels where the world was drawn) to a new PNG file in `directory`,
+/// named after the current time. Returns the path of the file.
+pub fn save_screenshot(frame: &[u8], viewport: Viewport, directory: &Path) -> std::io::Result<PathBuf> {
+    let path = timestamped_path(directory);
+    let path_str = path.to_str().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid screenshot path"))?;
+    save_frame(path_str, frame, viewport.width, viewport.height)?;
+    Ok(path)
+}
+
 fn example() {
     let mut f = std::fs::File::create("test.png").unwrap();
 
@@ -241,4 +276,24 @@ fn example() {
         Ok(_) => println!("Written image!"),
         Err(e) => println!("Error {:?}", e),
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::png_saver::save_screenshot;
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn test_save_screenshot() {
+        let viewport = Viewport::new(16, 8);
+        let frame = vec![200; viewport.buffer_len()];
+        let directory = std::env::temp_dir();
+        let first = save_screenshot(&frame, viewport, &directory).unwrap();
+        let second = save_screenshot(&frame, viewport, &directory).unwrap();
+        assert_ne!(first, second);
+        for path in [first, second] {
+            let bytes = std::fs::read(&path).unwrap();
+            assert_eq!(b"\x89PNG", &bytes[..4]);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
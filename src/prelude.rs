@@ -0,0 +1,22 @@
+//! The types needed to build and draw a scene, imported at once with `use doom::prelude::*`.
+
+pub use crate::camera_rig::{CameraRig, RigMode};
+pub use crate::drawable::Drawable;
+pub use crate::fog::Fog;
+pub use crate::lights::{Falloff, Lighting, PointLight};
+pub use crate::primitives::billboard::Billboard;
+pub use crate::primitives::camera::Camera;
+pub use crate::primitives::color::Color;
+pub use crate::primitives::cube::Cube3;
+pub use crate::primitives::cubic_face3::CubicFace3;
+pub use crate::primitives::position::Pose;
+pub use crate::primitives::textures::bw::BWTexture;
+pub use crate::primitives::textures::colored::{ColoredTexture, BLACK, ORANGE, PURPLE, YELLOW};
+pub use crate::primitives::textures::image::{ImageTexture, Wrap};
+pub use crate::primitives::textures::pixelated::Pixelated;
+pub use crate::primitives::textures::TextureHandle;
+pub use crate::primitives::vector::Vector3;
+pub use crate::sky::{GradientSky, SolidSky};
+pub use crate::viewport::Viewport;
+pub use crate::world_builder::WorldBuilder;
+pub use crate::worlds::{RaycastHit, World};
@@ -1,3 +1,5 @@
+pub mod aabb;
+pub mod billboard;
 pub mod camera;
 pub mod color;
 pub mod cube;
@@ -5,10 +7,18 @@ pub mod cubic_face2;
 pub mod cubic_face3;
 pub mod line;
 pub mod matrix3;
+pub mod mesh;
 pub mod object;
 pub mod point;
 pub mod position;
 pub mod projective_coordinates;
+pub mod quaternion;
+pub mod ray;
+pub mod simplification;
+pub mod snapping;
+pub mod sphere;
+pub mod tessellation;
 pub mod textures;
 pub mod transformation;
+pub mod triangle3;
 pub mod vector;
@@ -0,0 +1,128 @@
+use crate::primitives::ray::{Hit, Intersectable, Ray};
+use crate::primitives::vector::Vector3;
+
+/// A box whose faces are aligned with the axes, given by its two extreme corners.
+///
+/// Intersecting a ray with the box only takes a few comparisons per axis (the "slab" test), and
+/// is exact on the edges and the corners, where intersecting the six faces one by one can miss
+/// the hit because of rounding errors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    min: Vector3,
+    max: Vector3,
+}
+
+impl Aabb {
+    /// Creates the box between two opposite corners
+    pub fn new(a: Vector3, b: Vector3) -> Self {
+        Self {
+            min: Vector3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            max: Vector3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }
+    }
+
+    /// Returns the smallest box containing all the points
+    pub fn from_points(points: &[Vector3]) -> Option<Self> {
+        let first = *points.first()?;
+        Some(points.iter().fold(Self::new(first, first), |aabb, p| Self::new(
+            Vector3::new(aabb.min.x().min(p.x()), aabb.min.y().min(p.y()), aabb.min.z().min(p.z())),
+            Vector3::new(aabb.max.x().max(p.x()), aabb.max.y().max(p.y()), aabb.max.z().max(p.z())),
+        )))
+    }
+
+    pub fn min(&self) -> &Vector3 {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Vector3 {
+        &self.max
+    }
+
+    /// Returns true if the point is one of the 8 corners of the box
+    pub fn is_corner(&self, point: &Vector3) -> bool {
+        (0..3).all(|i| point[i] == self.min[i] || point[i] == self.max[i])
+    }
+
+    /// Returns the point of the box closest to `point` (which is `point` itself if it is inside)
+    pub fn closest_point(&self, point: &Vector3) -> Vector3 {
+        Vector3::new(
+            point.x().clamp(self.min.x(), self.max.x()),
+            point.y().clamp(self.min.y(), self.max.y()),
+            point.z().clamp(self.min.z(), self.max.z()),
+        )
+    }
+
+    /// Returns true if a sphere intersects the box
+    pub fn collides_with(&self, center: &Vector3, radius: f32) -> bool {
+        self.closest_point(center).line_to(center).norm() < radius
+    }
+
+    /// Returns the distance from `origin` to the first point of the box along the ray, or None if
+    /// the ray misses the box. The distance is 0 when the origin is inside the box.
+    pub fn ray_intersection(&self, origin: &Vector3, direction: &Vector3) -> Option<f32> {
+        // Along each axis, the ray is between the two planes of the box for t in [near, far]: the
+        // ray is inside the box in the intersection of these three ranges.
+        let mut t_min: f32 = 0.;
+        let mut t_max = f32::INFINITY;
+        for i in 0..3 {
+            if direction[i] == 0. {
+                if origin[i] < self.min[i] || origin[i] > self.max[i] {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (self.min[i] - origin[i]) / direction[i];
+            let t2 = (self.max[i] - origin[i]) / direction[i];
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+            if t_min > t_max {
+                return None;
+            }
+        }
+        Some(t_min * direction.norm())
+    }
+}
+
+impl Intersectable for Aabb {
+    fn intersect(&self, ray: &Ray) -> Option<Hit> {
+        self.ray_intersection(&ray.origin, &ray.direction).map(|distance| Hit::along(ray, distance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::aabb::Aabb;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_ray_intersection() {
+        let aabb = Aabb::new(Vector3::new(1., 1., 1.), Vector3::new(0., 0., 0.));
+        assert_eq!(Vector3::new(0., 0., 0.), *aabb.min());
+
+        // Straight at a face, with a direction which is not normalized
+        assert_eq!(Some(2.), aabb.ray_intersection(&Vector3::new(-2., 0.5, 0.5), &Vector3::new(3., 0., 0.)));
+        // Exactly through an edge and a corner
+        assert_eq!(Some(1.), aabb.ray_intersection(&Vector3::new(-1., 0., 0.5), &Vector3::new(1., 0., 0.)));
+        let diagonal = aabb.ray_intersection(&Vector3::new(-1., -1., -1.), &Vector3::new(1., 1., 1.)).unwrap();
+        assert!((diagonal - 3f32.sqrt()).abs() < 1e-5);
+        // From the inside
+        assert_eq!(Some(0.), aabb.ray_intersection(&Vector3::new(0.5, 0.5, 0.5), &Vector3::new(0., 0., 1.)));
+
+        // Missing the box, going away from it, or parallel to it
+        assert_eq!(None, aabb.ray_intersection(&Vector3::new(-2., 1.5, 0.5), &Vector3::new(1., 0., 0.)));
+        assert_eq!(None, aabb.ray_intersection(&Vector3::new(-2., 0.5, 0.5), &Vector3::new(-1., 0., 0.)));
+        assert_eq!(None, aabb.ray_intersection(&Vector3::new(-2., -1., 0.5), &Vector3::new(1., 1.1, 0.)));
+    }
+
+    #[test]
+    fn test_collisions() {
+        let aabb = Aabb::from_points(&[Vector3::newi(0, 0, 0), Vector3::newi(2, 1, 0), Vector3::newi(1, 0, 1)]).unwrap();
+        assert!(aabb.is_corner(&Vector3::newi(2, 1, 1)));
+        assert!(!aabb.is_corner(&Vector3::newi(1, 1, 1)));
+        assert!(aabb.collides_with(&Vector3::new(2.1, 0.5, 0.5), 0.2));
+        // Close to a corner, but not close enough: a test against the planes of the faces would
+        // wrongly report a collision
+        assert!(!aabb.collides_with(&Vector3::new(2.15, 1.15, 1.15), 0.2));
+        assert!(Aabb::from_points(&[]).is_none());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::primitives::camera::Camera;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::object::{Object, ObjectKind};
+use crate::primitives::snapping::Grid;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::transformation::Transform;
+use crate::primitives::vector::{Vector3, UNIT_X, UNIT_Z};
+
+/// Below this horizontal distance to the camera (in meters), a billboard does not turn
+const MIN_DISTANCE: f32 = 1e-4;
+
+/// A textured rectangle standing upright, which turns around the vertical axis to always face the
+/// camera. Items, particles and vegetation are drawn with a single face instead of a 3D model;
+/// the transparent pixels of the texture let the faces behind show through.
+pub struct Billboard {
+    /// Center of the bottom edge of the rectangle
+    position: Vector3,
+    width: f32,
+    height: f32,
+    face: CubicFace3,
+}
+
+impl Billboard {
+    /// Creates a billboard standing on `position` (the z-axis goes down), facing the x-axis until
+    /// it is turned towards the camera. The whole texture is stretched over the rectangle.
+    pub fn new(position: Vector3, width: f32, height: f32, texture: &TextureHandle) -> Self {
+        let face = Self::build_face(&position, width, height, &UNIT_X.opposite(), texture);
+        Self { position, width, height, face }
+    }
+
+    /// Rebuilds a billboard from its face (as saved in a scene file)
+    pub fn from_face(face: CubicFace3) -> Self {
+        let points = face.points();
+        let position = (points[2] + points[3]) * 0.5;
+        let (width, height) = ((points[1] - points[0]).norm(), (points[3] - points[0]).norm());
+        Self { position, width, height, face }
+    }
+
+    pub fn position(&self) -> &Vector3 {
+        &self.position
+    }
+
+    pub fn face(&self) -> &CubicFace3 {
+        &self.face
+    }
+
+    /// Turns the billboard towards the point, and returns false if it was already facing it
+    pub fn face_towards(&mut self, point: &Vector3) -> bool {
+        let mut normal = Vector3::new(point.x() - self.position.x(), point.y() - self.position.y(), 0.);
+        if normal.norm() < MIN_DISTANCE {
+            return false;
+        }
+        normal.normalize();
+        if self.face.normal().line_to(&normal).norm() < MIN_DISTANCE {
+            return false;
+        }
+        self.rebuild(&normal);
+        true
+    }
+
+    /// Builds the face again around the position, keeping its texture and receivers
+    fn rebuild(&mut self, normal: &Vector3) {
+        let receivers = self.face.receivers();
+        let face = Self::build_face(&self.position, self.width, self.height, normal, self.face.texture());
+        self.face = face.with_receivers(receivers);
+    }
+
+    /// The points go from the top left corner, as seen from the side of the normal
+    fn build_face(position: &Vector3, width: f32, height: f32, normal: &Vector3, texture: &TextureHandle) -> CubicFace3 {
+        let right = Vector3::new(normal.y(), -normal.x(), 0.) * (width / 2.);
+        let up = UNIT_Z.opposite() * height;
+        let (bottom_left, bottom_right) = (*position - right, *position + right);
+        CubicFace3::new_with_uvs(
+            [bottom_left + up, bottom_right + up, bottom_right, bottom_left],
+            [(0., 0.), (texture.width(), 0.), (texture.width(), texture.height()), (0., texture.height())],
+            *normal,
+            texture,
+        )
+    }
+}
+
+impl Object for Billboard {
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Billboard
+    }
+
+    fn get_visible_faces(&self, camera: &Camera) -> Vec<&CubicFace3> {
+        self.face.get_visible_faces(camera)
+    }
+
+    fn get_all_faces(&self) -> Vec<&CubicFace3> {
+        vec![&self.face]
+    }
+
+    fn rotate(&mut self, by: f32) {
+        self.transform(&Transform::rotation_around(&UNIT_Z, by, &Vector3::empty()));
+    }
+
+    fn transform(&mut self, transform: &Transform) {
+        self.position = transform.apply(&self.position);
+        self.face.transform(transform);
+    }
+
+    fn set_faces(&mut self, faces: &[CubicFace3]) {
+        self.face = faces[0].clone();
+        let [_, _, bottom_right, bottom_left] = self.face.points();
+        self.position = (bottom_right + bottom_left) / 2.;
+    }
+
+    fn snap(&mut self, grid: &Grid) {
+        self.position = grid.snap(self.position);
+        let normal = *self.face.normal();
+        self.rebuild(&normal);
+    }
+
+    /// The billboards are not obstacles
+    fn collides_with(&self, _point: &Vector3, _radius: f32) -> bool {
+        false
+    }
+
+    fn face_camera(&mut self, position: &Vector3) -> bool {
+        self.face_towards(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::billboard::Billboard;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::object::Object;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_billboard_faces_camera() {
+        let mut billboard = Billboard::new(Vector3::new(3., 0., 1.), 1., 2., &YELLOW);
+        assert_eq!(1, billboard.get_visible_faces(&Camera::default()).len());
+        assert_eq!(Vector3::new(3., -0.5, -1.), billboard.face().points()[0]);
+
+        // Seen from the side, the billboard turns and keeps standing on its position
+        assert!(billboard.face_towards(&Vector3::new(3., 5., -4.)));
+        assert!(!billboard.face_towards(&Vector3::new(3., 8., 0.)));
+        assert_eq!(Vector3::new(0., 1., 0.), *billboard.face().normal());
+        assert_eq!(Vector3::new(3., 0., 1.), (billboard.face().points()[2] + billboard.face().points()[3]) * 0.5);
+        assert!((billboard.face().area() - 2.).abs() < 1e-5);
+        let copy = Billboard::from_face(billboard.face().clone());
+        assert_eq!(billboard.position(), copy.position());
+    }
+}
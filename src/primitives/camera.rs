@@ -1,16 +1,28 @@
-use crate::{HEIGHT, WIDTH};
+use crate::layers::Layers;
 use crate::primitives::matrix3::Matrix3;
 use crate::primitives::point::Point2;
 use crate::primitives::position::Pose;
+use crate::primitives::ray::Ray;
 use crate::primitives::transformation::Transform;
 use crate::primitives::vector::Vector3;
+use crate::tunables::tunables;
+use crate::viewport::Viewport;
+
+/// Limits of the horizontal field of view when zooming, in degrees
+pub const MIN_FOV: f32 = 10.;
+pub const MAX_FOV: f32 = 150.;
 
 /// A camera is a position and calibration parameters
+#[derive(Clone)]
 pub struct Camera {
     pose: Pose,
     f: f32,
     px: f32,
     py: f32,
+    /// Size of the frames seen by the camera
+    viewport: Viewport,
+    /// Only the objects of these layers are seen by the camera
+    visible_layers: Layers,
 }
 
 impl Camera {
@@ -20,15 +32,20 @@ impl Camera {
             f,
             px,
             py,
+            viewport: Viewport::default(),
+            visible_layers: Layers::default(),
         }
     }
 
     pub fn default() -> Self {
+        let viewport = Viewport::default();
         Self {
             pose: Pose::new(Vector3::empty(), 0.),
             f: 400.,
-            px: WIDTH as f32 / 2.,
-            py: HEIGHT as f32 / 2.
+            px: viewport.width as f32 / 2.,
+            py: viewport.height as f32 / 2.,
+            viewport,
+            visible_layers: Layers::default(),
         }
     }
 
@@ -39,7 +56,35 @@ impl Camera {
         // We must transform `point` in the referential of the camera, and then apply the following
         // formula
         let transform = self.get_transform_world_to_cam();
-        let point_in_cam_frame = transform.apply(point);
+        self.project_from_cam_frame(&transform.apply(point))
+    }
+
+    /// Projects a polygon of the world into pixels, keeping only its part in front of the near plane
+    /// of the camera (at `near_distance` from it). Once projected, the points behind the camera are
+    /// mirrored, and would give a wrong polygon.
+    ///
+    /// The polygon is empty if it is entirely behind the near plane.
+    pub fn project_polygon(&self, points: &[Vector3]) -> Vec<Point2> {
+        let transform = self.get_transform_world_to_cam();
+        let points: Vec<Vector3> = points.iter().map(|p| transform.apply(p)).collect();
+        let near = tunables().near_distance;
+        let mut polygon = Vec::with_capacity(points.len() + 1);
+        for (i, p) in points.iter().enumerate() {
+            let q = &points[(i + 1) % points.len()];
+            if p.x() >= near {
+                polygon.push(self.project_from_cam_frame(p));
+            }
+            // The edge crosses the near plane
+            if (p.x() >= near) != (q.x() >= near) {
+                let t = (near - p.x()) / (q.x() - p.x());
+                polygon.push(self.project_from_cam_frame(&(*p + (*q - *p) * t)));
+            }
+        }
+        polygon
+    }
+
+    /// Projects into pixels a point given in the referential of the camera
+    fn project_from_cam_frame(&self, point_in_cam_frame: &Vector3) -> Point2 {
         // Transform the point in pixels using the formula
         // https://en.wikipedia.org/wiki/Camera_matrix#Normalized_camera_matrix_and_normalized_image_coordinates
         // In our case, the camera' forward direction is the x direction
@@ -55,6 +100,20 @@ impl Camera {
         self.pose.apply_z_rot(rot)
     }
 
+    /// Looks up (positive angle) or down
+    pub fn apply_pitch(&mut self, rot: f32) {
+        self.pose.apply_pitch(rot)
+    }
+
+    pub fn apply_roll(&mut self, rot: f32) {
+        self.pose.apply_roll(rot)
+    }
+
+    /// Removes the pitch and the roll of the camera
+    pub fn level(&mut self) {
+        self.pose.level()
+    }
+
     pub fn set_position(&mut self, position: Vector3) {
         self.pose.set_position(position);
     }
@@ -71,10 +130,62 @@ impl Camera {
         &self.pose
     }
 
+    pub fn set_pose(&mut self, pose: Pose) {
+        self.pose = pose;
+    }
+
     pub fn orientation(&self) -> Vector3 {
         self.pose.orientation()
     }
 
+    /// Returns the focal distance (in pixels)
+    pub fn focal(&self) -> f32 {
+        self.f
+    }
+
+    /// Returns the horizontal field of view, in degrees
+    pub fn fov(&self) -> f32 {
+        2. * f32::atan(self.viewport.width as f32 / 2. / self.f).to_degrees()
+    }
+
+    /// Sets the focal distance to obtain the provided horizontal field of view (in degrees)
+    pub fn set_fov(&mut self, fov: f32) {
+        self.f = self.viewport.width as f32 / 2. / f32::tan(fov.to_radians() / 2.);
+    }
+
+    /// Narrows the field of view by the factor (a factor below 1 zooms out), keeping it between
+    /// `MIN_FOV` and `MAX_FOV`
+    pub fn zoom(&mut self, factor: f32) {
+        self.set_fov((self.fov() / factor).clamp(MIN_FOV, MAX_FOV));
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    pub fn visible_layers(&self) -> Layers {
+        self.visible_layers
+    }
+
+    pub fn set_visible_layers(&mut self, layers: Layers) {
+        self.visible_layers = layers;
+    }
+
+    /// Changes the size of the frames, keeping the horizontal field of view. The optical center
+    /// moves to the center of the new frames.
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        let fov = self.fov();
+        self.viewport = viewport;
+        self.px = viewport.width as f32 / 2.;
+        self.py = viewport.height as f32 / 2.;
+        self.set_fov(fov);
+    }
+
+    /// Returns the optical center (in pixels)
+    pub fn optical_center(&self) -> (f32, f32) {
+        (self.px, self.py)
+    }
+
     /// Returns a vector pointing in the direction of the ray directed by this pixel,
     /// in the camera frame
     pub fn ray_direction(&self, u: i16, v: i16) -> Vector3 {
@@ -86,9 +197,49 @@ impl Camera {
             )
     }
 
+    /// Returns the direction of the ray of the pixel (0, 0), and how it changes when moving by
+    /// one pixel along u and along v: the ray of the pixel (u, v) goes in the direction
+    /// `origin + u * du + v * dv` (the same as `ray_direction`).
+    pub fn ray_direction_derivatives(&self) -> (Vector3, Vector3, Vector3) {
+        let rotation = self.get_rotation_cam_to_world();
+        (
+            &rotation * Vector3::new(1.0, -self.px / self.f, -self.py / self.f),
+            &rotation * Vector3::new(0., 1. / self.f, 0.),
+            &rotation * Vector3::new(0., 0., 1. / self.f),
+        )
+    }
+
+    /// Returns the pixel coordinates of a point of the world, or None if the point is behind the
+    /// camera or outside of the frame.
+    pub fn world_to_screen(&self, point: &Vector3) -> Option<Point2> {
+        let uv = self.project(point);
+        let in_frame = uv.x() >= 0. && uv.y() >= 0. && uv.x() < self.viewport.width as f32 && uv.y() < self.viewport.height as f32;
+        (uv.in_front() && in_frame).then_some(uv)
+    }
+
+    /// Returns the ray going through a point of the frame (in pixels, which do not need to be
+    /// inside the frame): it starts at the position of the camera, and its direction is normalized.
+    pub fn screen_to_world_ray(&self, x: f32, y: f32) -> Ray {
+        let mut direction = self.get_rotation_cam_to_world() * Vector3::new(1.0, (x - self.px) / self.f, (y - self.py) / self.f);
+        direction.normalize();
+        Ray::new(*self.pose.position(), direction)
+    }
+
+    /// Returns the corners of the frame seen at this distance in front of the camera (along its
+    /// optical axis), in the order of the corners of the frame: top left, top right, bottom right
+    /// and bottom left.
+    pub fn frustum_corners(&self, distance: f32) -> [Vector3; 4] {
+        let rotation = self.get_rotation_cam_to_world();
+        let (width, height) = (self.viewport.width as f32, self.viewport.height as f32);
+        [(0., 0.), (width, 0.), (width, height), (0., height)].map(|(u, v)| {
+            let direction = Vector3::new(1.0, (u - self.px) / self.f, (v - self.py) / self.f);
+            *self.pose.position() + &rotation * direction * distance
+        })
+    }
+
     pub fn is_point_visible(&self, point: &Vector3) -> bool {
         let uv = self.project(point);
-        uv.in_front() && (uv.x() >= 0.0 || uv.x() < HEIGHT as f32) && (uv.y() >= 0.0 || uv.y() < WIDTH as f32)
+        uv.in_front() && (uv.x() >= 0.0 || uv.x() < self.viewport.height as f32) && (uv.y() >= 0.0 || uv.y() < self.viewport.width as f32)
     }
 }
 
@@ -97,21 +248,24 @@ impl Camera {
     fn get_transform_world_to_cam(&self) -> Transform {
         Transform::new(
             self.pose.position().opposite(),
-            Matrix3::z_rotation(self.pose.rotation_z()),
+            self.get_rotation_cam_to_world().transpose(),
         )
     }
 
-    /// Returns a rotation matrix from cam coordinates to world coordinates
+    /// Returns a rotation matrix from cam coordinates to world coordinates.
+    /// The roll is applied first, then the pitch and finally the yaw.
     fn get_rotation_cam_to_world(&self) -> Matrix3 {
-        Matrix3::z_rotation(-self.pose.rotation_z())
+        self.pose.quaternion().to_matrix()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::primitives::camera::Camera;
+    use crate::primitives::camera::{Camera, MAX_FOV};
     use crate::primitives::position::Pose;
+    use crate::primitives::ray::Ray;
     use crate::primitives::vector::Vector3;
+    use crate::viewport::Viewport;
     use std::f32::consts::PI;
 
     #[test]
@@ -140,4 +294,133 @@ mod tests {
         let uv = cam.project(&point_w);
         println!("pixels: {uv:?}");
     }
+
+    #[test]
+    fn pitch_and_roll() {
+        let mut cam = Camera::default();
+        let (px, py) = cam.optical_center();
+        // A point 45 degrees above the horizon (the z-axis goes down) is at the center of the
+        // image once the camera looks up by 45 degrees
+        let point = Vector3::new(2., 0., -2.);
+        assert!(cam.project(&point).y() < py);
+        cam.apply_pitch(PI / 4.);
+        let uv = cam.project(&point);
+        assert!((uv.x() - px).abs() < 1e-3 && (uv.y() - py).abs() < 1e-3);
+
+        // The pitch is limited to looking straight up
+        cam.apply_pitch(PI);
+        assert!((cam.pose().pitch() - PI / 2.).abs() < 1e-6);
+
+        // The rays go through the points which project on their pixels
+        cam.level();
+        cam.apply_pitch(-0.3);
+        cam.apply_roll(0.4);
+        cam.apply_z_rot(1.2);
+        let point = Vector3::new(3., -4., 1.);
+        let uv = cam.project(&point);
+        let mut ray = cam.ray_direction(uv.x().round() as i16, uv.y().round() as i16);
+        let mut to_point = point - *cam.pose().position();
+        ray.normalize();
+        to_point.normalize();
+        assert!(ray.dot(&to_point) > 0.9999);
+    }
+
+    #[test]
+    fn test_set_viewport() {
+        let mut cam = Camera::default();
+        let fov = cam.fov();
+        cam.set_viewport(Viewport::new(320, 240));
+        assert_eq!((160., 120.), cam.optical_center());
+        assert!((cam.fov() - fov).abs() < 1e-3);
+        // The ray of the center of the frame still goes straight ahead
+        let ray = cam.ray_direction(160, 120);
+        assert!(ray.y().abs() < 1e-6 && ray.z().abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pose_quaternion() {
+        let mut pose = Pose::new(Vector3::new(1., 2., 3.), 0.7);
+        pose.apply_pitch(-0.4);
+        pose.apply_roll(0.2);
+        let mut cam = Camera::default();
+        cam.set_pose(pose.clone());
+        // The quaternion turns the optical axis of the camera to the ray of the optical center
+        let (px, py) = cam.optical_center();
+        let axis = pose.quaternion().rotate(&Vector3::new(1., 0., 0.));
+        assert!(axis.line_to(&cam.screen_to_world_ray(px, py).direction).norm() < 1e-5);
+
+        let mut copy = Pose::new(*pose.position(), 0.);
+        copy.set_quaternion(&pose.quaternion());
+        assert!((copy.rotation_z() - 0.7).abs() < 1e-5 && (copy.pitch() + 0.4).abs() < 1e-5 && (copy.roll() - 0.2).abs() < 1e-5);
+        // Halfway between two poses
+        let half = pose.slerp(&Pose::new(Vector3::new(3., 2., 3.), 0.7), 0.5);
+        assert!((half.pitch() + 0.2).abs() < 1e-4 && (half.position().x() - 2.).abs() < 1e-6);
+        // The angles are derived from the rotation, so the yaw wraps around
+        assert!((Pose::new(Vector3::empty(), 2. * PI - 0.1).rotation_z() + 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_zoom() {
+        let mut cam = Camera::default();
+        cam.set_fov(90.);
+        assert!((cam.focal() - cam.viewport().width as f32 / 2.).abs() < 1e-3);
+        cam.zoom(2.);
+        assert!((cam.fov() - 45.).abs() < 1e-3);
+        cam.zoom(0.01);
+        assert!((cam.fov() - MAX_FOV).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_world_to_screen() {
+        let mut cam = Camera::default();
+        cam.set_pose(Pose::new(Vector3::new(1., 2., 0.), 0.5));
+        cam.apply_pitch(0.2);
+        let point = Vector3::new(4., 0., -1.);
+        let uv = cam.world_to_screen(&point).unwrap();
+
+        // The ray of this pixel goes back through the point
+        let Ray { origin, direction } = cam.screen_to_world_ray(uv.x(), uv.y());
+        assert_eq!(Vector3::new(1., 2., 0.), origin);
+        assert!((direction.norm() - 1.).abs() < 1e-5);
+        let mut to_point = point - origin;
+        to_point.normalize();
+        assert!(direction.dot(&to_point) > 0.99999);
+
+        // Behind the camera, and outside of the frame
+        assert!(cam.world_to_screen(&(origin - direction)).is_none());
+        let outside = cam.screen_to_world_ray(-10., uv.y());
+        assert!(cam.world_to_screen(&outside.at(1.)).is_none());
+    }
+
+    #[test]
+    fn test_project_polygon() {
+        let cam = Camera::default();
+        let (_, py) = cam.optical_center();
+        // A square of the floor going under the camera: its part behind the camera is cut
+        let floor = [Vector3::new(-1., -1., 0.5), Vector3::new(3., -1., 0.5), Vector3::new(3., 1., 0.5), Vector3::new(-1., 1., 0.5)];
+        let polygon = cam.project_polygon(&floor);
+        assert_eq!(4, polygon.len());
+        // Once projected, all the points are below the horizon (instead of being mirrored above)
+        assert!(polygon.iter().all(|p| p.in_front() && p.y() > py));
+
+        // With only one corner behind the camera, the polygon gets a fifth point
+        let corner = [Vector3::new(-1., -1., 0.5), Vector3::new(3., -1., 0.5), Vector3::new(3., 3., 0.5), Vector3::new(1., 3., 0.5)];
+        assert_eq!(5, cam.project_polygon(&corner).len());
+        assert!(cam.project_polygon(&floor.map(|p| p - Vector3::new(4., 0., 0.))).is_empty());
+    }
+
+    #[test]
+    fn test_frustum_corners() {
+        let mut cam = Camera::default();
+        cam.apply_z_rot(0.3);
+        cam.set_position(Vector3::new(1., 2., 3.));
+        let viewport = cam.viewport();
+        let (width, height) = (viewport.width as f32, viewport.height as f32);
+        // The corners are at the given depth, and are projected on the corners of the frame
+        for (corner, (u, v)) in cam.frustum_corners(5.).iter().zip([(0., 0.), (width, 0.), (width, height), (0., height)]) {
+            let uv = cam.project(corner);
+            assert!((uv.x() - u).abs() < 1e-2 && (uv.y() - v).abs() < 1e-2);
+            assert!((cam.pose().position().line_to(corner).dot(&cam.orientation()) - 5.).abs() < 1e-4);
+        }
+    }
 }
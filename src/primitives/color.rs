@@ -1,6 +1,5 @@
-use std::collections::HashMap;
 use rand::distributions::{Distribution, Uniform};
-use rand::thread_rng;
+use rand::Rng;
 
 #[derive(Clone)]
 pub struct Color {
@@ -19,11 +18,10 @@ impl Color {
     // Randomize one of the axis of this color, between 0 and 255.
     // The axis are: 0=r, 1=g, 2=b, 3=a
     // eg, if axis = 3, randomize the alpha b
-    pub fn randomize_dimension(&self, axis: usize) -> Self {
+    // The random generator is provided by the caller, so that it can be seeded.
+    pub fn randomize_dimension<R: Rng>(&self, axis: usize, rng: &mut R) -> Self {
         let between = Uniform::from(0u8..255);
-        let mut rng = rand::thread_rng();
-        // let i1 = between.sample(&mut rng) as u8;
-        let x1 = between.sample(&mut rng);
+        let x1 = between.sample(rng);
         match axis {
             0 => Self {
                 r: x1,
@@ -182,25 +180,26 @@ impl Color {
         Self::new(127,127,127,255)
     }
 
-    pub fn create_colors_library() -> HashMap<char, Color> {
-        let mut colors = HashMap::new();
-        colors.insert('y', Color::yellow());
-        colors.insert('b', Color::dark_blue());
-        colors.insert('k', Color::black());
-        colors.insert('0', Color::white());
-        colors.insert('t', Color::turquoise());
-        colors.insert('o', Color::orange());
-        // Use for minecraft-like
-        colors.insert('g', Color::green());
-        colors.insert('G', Color::dark_green());
-        colors.insert('w', Color::brown1());
-        colors.insert('W', Color::brown2());
-        colors.insert('1', Color::wood_dark());
-        colors.insert('2', Color::wood());
-        colors.insert('3', Color::wood_light());
-        colors.insert('4', Color::stone_dark());
-        colors.insert('5', Color::stone());
-        colors.insert('6', Color::stone_light());
-        colors
+    /// Returns the colors used to describe pixelated textures, with their symbol
+    pub fn library() -> Vec<(char, Color)> {
+        vec![
+            ('y', Color::yellow()),
+            ('b', Color::dark_blue()),
+            ('k', Color::black()),
+            ('0', Color::white()),
+            ('t', Color::turquoise()),
+            ('o', Color::orange()),
+            // Use for minecraft-like
+            ('g', Color::green()),
+            ('G', Color::dark_green()),
+            ('w', Color::brown1()),
+            ('W', Color::brown2()),
+            ('1', Color::wood_dark()),
+            ('2', Color::wood()),
+            ('3', Color::wood_light()),
+            ('4', Color::stone_dark()),
+            ('5', Color::stone()),
+            ('6', Color::stone_light()),
+        ]
     }
 }
@@ -1,8 +1,11 @@
+use crate::primitives::aabb::Aabb;
 use crate::primitives::camera::Camera;
-use crate::primitives::cubic_face3::CubicFace3;
-use crate::primitives::object::Object;
+use crate::primitives::cubic_face3::{CubicFace3, Receivers};
+use crate::primitives::object::{Object, ObjectKind};
+use crate::primitives::snapping::Grid;
+use crate::primitives::transformation::Transform;
 use crate::primitives::textures::colored::BLACK;
-use crate::primitives::textures::Texture;
+use crate::primitives::textures::TextureHandle;
 use crate::primitives::vector::{UNIT_X, UNIT_Y, UNIT_Z, Vector3};
 
 /// A cube in 3D coordinates.
@@ -10,11 +13,13 @@ use crate::primitives::vector::{UNIT_X, UNIT_Y, UNIT_Z, Vector3};
 /// but it seems to fit the purposes better than using 8 points.
 pub struct Cube3 {
     faces: [CubicFace3; 6],
+    /// When the cube is aligned with the axes, its box is used for the collisions and the rays
+    bounds: Option<Aabb>,
 }
 
 impl Cube3 {
     /// Construct a cube from a bottom face with an extrusion above, strictly on the z-direction
-    pub fn from_face(bottom: CubicFace3, h: f32, texture: &'static dyn Texture) -> Self {
+    pub fn from_face(bottom: CubicFace3, h: f32, texture: &TextureHandle) -> Self {
         // Construct the 4 points of the upper face
         let points = bottom.points();
 
@@ -35,22 +40,27 @@ impl Cube3 {
         let f23 = CubicFace3::new([p2, p3, points[3], points[2]], p2 - p1, texture);
         let f30 = CubicFace3::new([p3, p0, points[0], points[3]], p0 - p1, texture);
 
-        Self {
-            faces: [bottom, top, f01, f12, f23, f30],
-        }
+        Self::from_faces([bottom, top, f01, f12, f23, f30])
     }
 
-    pub fn minecraft_like(from: Vector3, side_tex: &'static dyn Texture, top_tex: &'static dyn Texture) -> Self {
+    pub fn minecraft_like(from: Vector3, side_tex: &TextureHandle, top_tex: &TextureHandle) -> Self {
+        Self::from_aabb(Aabb::new(from, from + UNIT_X + UNIT_Y + UNIT_Z), side_tex, top_tex)
+    }
+
+    /// Construct the box, with the `top_tex` texture on its horizontal faces
+    pub fn from_aabb(aabb: Aabb, side_tex: &TextureHandle, top_tex: &TextureHandle) -> Self {
+        let (min, max) = (aabb.min(), aabb.max());
+
         // Construct the points: b=bottom, t=top
-        let b0 = from;
-        let b1 = from + UNIT_X;
-        let b2 = from + UNIT_Y;
-        let b3 = b2 + UNIT_X;
+        let b0 = *min;
+        let b1 = Vector3::new(max.x(), min.y(), min.z());
+        let b2 = Vector3::new(min.x(), max.y(), min.z());
+        let b3 = Vector3::new(max.x(), max.y(), min.z());
 
-        let t0 = b0 + UNIT_Z;
-        let t1 = b1 + UNIT_Z;
-        let t2 = b2 + UNIT_Z;
-        let t3 = b3 + UNIT_Z;
+        let t0 = Vector3::new(min.x(), min.y(), max.z());
+        let t1 = Vector3::new(max.x(), min.y(), max.z());
+        let t2 = Vector3::new(min.x(), max.y(), max.z());
+        let t3 = *max;
 
         // Construct the faces
         let top = CubicFace3::new([t0, t1, t3, t2], UNIT_Z, top_tex);
@@ -61,14 +71,36 @@ impl Cube3 {
         let f4 = CubicFace3::new([b1, b0, t0, t1], UNIT_Y.opposite(), side_tex);
 
         Self {
-            faces: [bottom, top, f1, f2, f3, f4]
+            faces: [bottom, top, f1, f2, f3, f4],
+            bounds: Some(aabb),
         }
     }
 
+    /// Creates a cube from its 6 faces, which must be ordered as in `get_all_faces`
+    pub fn from_faces(faces: [CubicFace3; 6]) -> Self {
+        let bounds = Self::axis_aligned_bounds(&faces);
+        Self { faces, bounds }
+    }
+
+    /// Returns the box of the cube if its faces are aligned with the axes
+    fn axis_aligned_bounds(faces: &[CubicFace3; 6]) -> Option<Aabb> {
+        let points: Vec<Vector3> = faces.iter().flat_map(|face| face.points()).collect();
+        Aabb::from_points(&points).filter(|aabb| points.iter().all(|p| aabb.is_corner(p)))
+    }
+
+    /// Returns the cube, whose faces receive the given effects of the scene
+    pub fn with_receivers(mut self, receivers: Receivers) -> Self {
+        self.faces = self.faces.map(|face| face.with_receivers(receivers));
+        self
+    }
 
 }
 
 impl Object for Cube3 {
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Cube
+    }
+
     /// Criteria for a face to be seen:
     /// * the dot product between the camera's orientation and the face's normal
     ///   is negative.
@@ -97,6 +129,40 @@ impl Object for Cube3 {
         for face in &mut self.faces {
             face.rotate(by);
         }
+        self.bounds = Self::axis_aligned_bounds(&self.faces);
+    }
+
+    fn transform(&mut self, transform: &Transform) {
+        for face in &mut self.faces {
+            face.transform(transform);
+        }
+        self.bounds = Self::axis_aligned_bounds(&self.faces);
+    }
+
+    fn set_faces(&mut self, faces: &[CubicFace3]) {
+        self.faces.clone_from_slice(faces);
+        self.bounds = Self::axis_aligned_bounds(&self.faces);
+    }
+
+    fn snap(&mut self, grid: &Grid) {
+        for face in &mut self.faces {
+            face.snap(grid);
+        }
+        self.bounds = Self::axis_aligned_bounds(&self.faces);
+    }
+
+    /// When the cube is aligned with the axes, the sphere collides if it is close enough to its
+    /// box. Otherwise, the cube is convex and its normals point outward: the sphere collides if
+    /// its center is at less than `radius` in front of every face.
+    fn collides_with(&self, point: &Vector3, radius: f32) -> bool {
+        if let Some(aabb) = &self.bounds {
+            return aabb.collides_with(point, radius);
+        }
+        self.faces.iter().all(|face| {
+            let mut normal = *face.normal();
+            normal.normalize();
+            face.center().line_to(point).dot(&normal) < radius
+        })
     }
 }
 
@@ -104,11 +170,14 @@ impl Object for Cube3 {
 mod tests {
     use std::f32::consts::PI;
 
+    use crate::primitives::aabb::Aabb;
     use crate::primitives::camera::Camera;
     use crate::primitives::cube::Cube3;
     use crate::primitives::cubic_face3::CubicFace3;
-    use crate::primitives::object::Object;
+    use crate::primitives::object::{faces_ray_intersection, Object};
     use crate::primitives::position::Pose;
+    use crate::primitives::ray::{Intersectable, Ray};
+    use crate::primitives::transformation::Transform;
     use crate::primitives::textures::colored::YELLOW;
     use crate::primitives::vector::Vector3;
 
@@ -157,6 +226,55 @@ mod tests {
         assert_eq!(3, faces.len());
     }
 
+    #[test]
+    fn test_axis_aligned_cube() {
+        let cube = Cube3::minecraft_like(Vector3::newi(0, 0, 0), &YELLOW, &YELLOW);
+        let from_faces = Cube3::from_faces(cube.faces.clone());
+        assert_eq!(cube.bounds, from_faces.bounds);
+
+        // The box gives the same hits as the faces, and also hits the edges exactly
+        let aabb = cube.bounds.as_ref().unwrap();
+        let ray = Ray::new(Vector3::new(-2., 0.3, 0.4), Vector3::new(1., 0.1, 0.2));
+        let faces_hit = faces_ray_intersection(&cube.get_all_faces(), &ray).unwrap();
+        assert!((aabb.intersect(&ray).unwrap().distance - faces_hit).abs() < 1e-2);
+        assert_eq!(Some(2.), aabb.ray_intersection(&Vector3::new(-2., 0., 0.), &Vector3::new(1., 0., 0.)));
+
+        // A cube rotated by 45 degrees is intersected face by face
+        let bottom = CubicFace3::hface_from_line(Vector3::newi(0, 0, 0), Vector3::newi(1, 1, 0));
+        let rotated = Cube3::from_face(bottom, 1., &YELLOW);
+        assert!(rotated.bounds.is_none());
+        let distance = faces_ray_intersection(&rotated.get_all_faces(), &Ray::new(Vector3::new(0.1, -3., 0.5), Vector3::new(0., 1., 0.))).unwrap();
+        assert!((2.8..3.2).contains(&distance));
+    }
+
+    #[test]
+    fn test_rotate_around() {
+        // The rotation around the z-axis is the same as `rotate`
+        let mut rotated = Cube3::minecraft_like(Vector3::newi(1, 0, 0), &YELLOW, &YELLOW);
+        let mut expected = Cube3::minecraft_like(Vector3::newi(1, 0, 0), &YELLOW, &YELLOW);
+        rotated.rotate_around(&Vector3::new(0., 0., 2.), 0.3);
+        expected.rotate(0.3);
+        for (a, b) in rotated.get_all_faces().iter().zip(expected.get_all_faces()) {
+            assert!((0..4).all(|i| (a.points()[i] - b.points()[i]).norm() < 1e-5));
+            assert!((*a.normal() - *b.normal()).norm() < 1e-5);
+        }
+
+        // Tumbling a cube by a quarter turn around an horizontal axis through its center keeps
+        // it in place, and its normals still point outwards
+        let mut cube = Cube3::minecraft_like(Vector3::newi(0, 0, 0), &YELLOW, &YELLOW);
+        let center = Vector3::new(0.5, 0.5, 0.5);
+        cube.transform(&Transform::rotation_around(&Vector3::new(1., 0., 0.), PI / 2., &center));
+        let points: Vec<Vector3> = cube.get_all_faces().iter().flat_map(|face| face.points()).collect();
+        let bounds = Aabb::from_points(&points).unwrap();
+        assert!((*bounds.min() - Vector3::empty()).norm() < 1e-5 && (*bounds.max() - Vector3::new(1., 1., 1.)).norm() < 1e-5);
+        assert!(cube.get_all_faces().iter().all(|face| face.normal().dot(&center.line_to(&face.center())) > 0.));
+
+        // Along a diagonal, it is not aligned with the axes anymore: it is intersected face by face
+        cube.transform(&Transform::rotation_around(&Vector3::new(1., 1., 0.), 0.5, &center));
+        assert!(cube.bounds.is_none());
+        assert!(faces_ray_intersection(&cube.get_all_faces(), &Ray::new(Vector3::new(-2., 0.5, 0.5), Vector3::new(1., 0., 0.))).is_some());
+    }
+
     /// This test was created to solve a bug with side views of some cubes
     #[test]
     fn test_side_faces_with_rotated_camera() {
@@ -1,21 +1,27 @@
 use std::cmp::{max, min};
 use std::fmt::{Debug, Formatter};
 
+use crate::fog::Fog;
+use crate::frame::blend;
+use crate::lights::Lighting;
 use crate::primitives::camera::Camera;
 use crate::primitives::color::Color;
-use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::cubic_face3::{CubicFace3, Receivers};
 use crate::primitives::point::Point2;
 use crate::primitives::projective_coordinates::ProjectionCoordinates;
 use crate::primitives::textures::Texture;
-use crate::{HEIGHT, WIDTH};
+use crate::primitives::vector::Vector3;
+use crate::tunables::tunables;
+use crate::viewport::Viewport;
 
 /// A CubicFace2 is the projection of a CubicFace3 (is an oriented square in space)
 ///
+/// Only the part of the face in front of the near plane of the camera is projected: the polygon
+/// of a face crossing this plane has an additional point, and is empty for a face behind it.
+///
 /// Internal properties:
 /// * face: A 2D face can hold a reference to its referring 3D face.
 /// * the camera that observed this
-/// * norm_a or b: the length of the side of the face. This is helpful to keep it in the class
-///   to avoid.
 ///
 /// The coordinates of the Face2 (the image referential) are defined as
 ///
@@ -36,41 +42,77 @@ use crate::{HEIGHT, WIDTH};
 ///        └─────────────────────────────────┘
 
 pub struct CubicFace2<'a> {
-    points: [Point2; 4],
+    points: Vec<Point2>,
     face3: Option<&'a CubicFace3>,
-    norm_a: f32,
-    norm_b: f32,
     camera: &'a Camera,
+    /// Lights of the world (the texture is drawn as is without them)
+    lighting: Option<&'a Lighting>,
+    fog: Option<&'a Fog>,
 }
 
 impl<'a> Debug for CubicFace2<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "points: {:?}, {:?}, {:?}, {:?} ",
-            self.points[0], self.points[1], self.points[2], self.points[3]
-        )
+        write!(f, "points: {:?} ", self.points)
     }
 }
 
 impl<'a> CubicFace2<'a> {
-    pub fn new(points2d: [Point2; 4], face: &'a CubicFace3, camera: &'a Camera) -> Self {
-        let points = face.points();
-        let a = points[1] - points[0];
-        let b = points[3] - points[0];
+    pub fn new(points2d: Vec<Point2>, face: &'a CubicFace3, camera: &'a Camera) -> Self {
         Self {
             points: points2d,
             face3: Some(face),
-            norm_a: a.norm(),
-            norm_b: b.norm(),
             camera,
+            lighting: None,
+            fog: None,
+        }
+    }
+
+    /// Returns a copy of the face, lit by the given lights
+    pub fn with_lighting<'b>(&self, lighting: &'b Lighting) -> CubicFace2<'b>
+    where
+        'a: 'b,
+    {
+        CubicFace2 {
+            points: self.points.clone(),
+            face3: self.face3,
+            camera: self.camera,
+            lighting: Some(lighting),
+            fog: self.fog,
+        }
+    }
+
+    /// Returns a copy of the face, seen through the given fog
+    pub fn with_fog<'b>(&self, fog: &'b Fog) -> CubicFace2<'b>
+    where
+        'a: 'b,
+    {
+        CubicFace2 {
+            points: self.points.clone(),
+            face3: self.face3,
+            camera: self.camera,
+            lighting: self.lighting,
+            fog: Some(fog),
         }
     }
 
     /// Returns the color at the given projection
-    pub fn color_at_projection(&self, coordinates: &ProjectionCoordinates) -> &Color {
-        let (u, v) = coordinates.to_uv(self.norm_a, self.norm_b);
-        &self.face3.unwrap().texture().color_at(u, v)
+    pub fn color_at_projection(&self, coordinates: &ProjectionCoordinates) -> Color {
+        let face = self.face3.unwrap();
+        let (u, v) = face.uv_at(coordinates);
+        let color = face.texture().sample(u, v);
+        let color = match self.lighting {
+            Some(lighting) if face.receivers().contains(Receivers::LIGHT) => {
+                lighting.shade(&color, &face.point_at(coordinates), face.normal())
+            }
+            _ => color,
+        };
+        match self.fog {
+            Some(fog) if face.receivers().contains(Receivers::FOG) => {
+                let distance = self.camera.pose().position().line_to(&face.point_at(coordinates)).norm();
+                fog.apply(&color, distance)
+            }
+            _ => color,
+        }
     }
 
     /// Returns true if the face contains the given point
@@ -79,23 +121,29 @@ impl<'a> CubicFace2<'a> {
         /// its left.
         /// The logic is done using a cross-product check
         /// https://stackoverflow.com/a/2752753/13219173
-        fn is_left_of_link(points: &[Point2], i: usize, j: usize, point: &Point2) -> bool {
-            let x1 = points[i].x();
-            let x2 = points[j].x();
-            let y1 = points[i].y();
-            let y2 = points[j].y();
+        fn is_left_of_link(p1: &Point2, p2: &Point2, point: &Point2) -> bool {
+            let x1 = p1.x();
+            let x2 = p2.x();
+            let y1 = p1.y();
+            let y2 = p2.y();
             let cross_product = (x2 - x1) * (point.y() - y1) - (point.x() - x1) * (y2 - y1);
             cross_product >= 0.
         }
 
-        // The point is contained inside the face if it is to the left of all segments
-        let c1 = is_left_of_link(&self.points, 0, 1, point);
-        let c2 = is_left_of_link(&self.points, 1, 2, point);
-        let c3 = is_left_of_link(&self.points, 2, 3, point);
-        let c4 = is_left_of_link(&self.points, 3, 0, point);
-
-        // Returns true if all conditions are equals
-        return (c1 == c2) && (c1 == c3) && (c1 == c4);
+        // The point is contained inside the face if it is on the same side of all segments.
+        // The segments without length (the last one of a triangle) are ignored.
+        let mut side = None;
+        for (i, p) in self.points.iter().enumerate() {
+            let q = &self.points[(i + 1) % self.points.len()];
+            if p == q {
+                continue;
+            }
+            let left = is_left_of_link(p, q, point);
+            if *side.get_or_insert(left) != left {
+                return false;
+            }
+        }
+        side.is_some()
     }
 
     /// Returns the raytracing distance (in mm, as u32) between the face and a ray defined as the pixels
@@ -109,7 +157,7 @@ impl<'a> CubicFace2<'a> {
             let direction = self.camera.ray_direction(u, v);
             let c = self.camera.pose().position();
             if let Some(proj) = face.line_projection(&c, &direction) {
-                if proj.1.is_inside_face() {
+                if face.contains_projection(&proj.1) {
                     return Some(proj);
                 }
             }
@@ -123,63 +171,251 @@ impl<'a> CubicFace2<'a> {
 
     /// Returns a bounding box containing the box
     /// format: xmin, ymin, xmax, ymax
-    fn bounding_box(&self) -> (u32, u32, u32, u32) {
-        let mut xmin = self.points[0].x() as u32;
-        let mut ymin = self.points[0].y() as u32;
-        let mut xmax = self.points[0].x() as u32;
-        let mut ymax = self.points[0].y() as u32;
-        for i in 1..self.points.len() {
-            let x = self.points[i].x() as u32;
-            let y = self.points[i].y() as u32;
-            xmin = min(x, xmin);
-            ymin = min(y, ymin);
-            xmax = max(x, xmax);
-            ymax = max(y, ymax);
+    pub fn bounding_box(&self) -> (u32, u32, u32, u32) {
+        bounding_box(&self.points, self.camera.viewport())
+    }
+
+    /// Returns the polygon of the face clipped to the rectangle of the screen (Sutherland-Hodgman
+    /// algorithm), which is empty if the face is outside of the screen.
+    pub fn clip_to_screen(&self) -> Vec<Point2> {
+        let viewport = self.camera.viewport();
+        let (width, height) = (viewport.width as f32, viewport.height as f32);
+        // Each side of the screen: the points inside have `sign * (coordinate - bound) >= 0`,
+        // where the coordinate is x if `vertical`, and y otherwise
+        let sides = [(true, 0., 1.), (true, width, -1.), (false, 0., 1.), (false, height, -1.)];
+        let mut polygon = self.points.clone();
+        for (vertical, bound, sign) in sides {
+            let distance = |p: &Point2| sign * (if vertical { p.x() } else { p.y() } - bound);
+            let input = std::mem::take(&mut polygon);
+            for (i, p) in input.iter().enumerate() {
+                let q = &input[(i + 1) % input.len()];
+                let (dp, dq) = (distance(p), distance(q));
+                if dp >= 0. {
+                    polygon.push(*p);
+                }
+                // The segment crosses the side of the screen
+                if (dp >= 0.) != (dq >= 0.) {
+                    let t = dp / (dp - dq);
+                    polygon.push(if vertical {
+                        Point2::new(bound, p.y() + t * (q.y() - p.y()))
+                    } else {
+                        Point2::new(p.x() + t * (q.x() - p.x()), bound)
+                    });
+                }
+            }
         }
-        (
-            (xmin - 2).clamp(0, WIDTH),
-            (ymin - 2).clamp(0, HEIGHT),
-            (xmax + 2).clamp(0, WIDTH),
-            (ymax + 2).clamp(0, HEIGHT),
-        )
+        polygon
     }
 
-    /// Draws all the pixels of self in the given frame.
-    /// - TODO render proper color when working with textures
-    pub fn draw(&self, frame: &mut [u8]) {
-        /// Given a 2D position (in pixels), returns the index inside the 1D buffer of pixels.
-        fn pos_to_index(x: u32, y: u32) -> usize {
-            4 * (x + y * WIDTH) as usize
+    /// Returns the horizontal extent of the face on the row `y` of pixels, if it crosses it
+    fn span(points: &[Point2], y: f32) -> Option<(f32, f32)> {
+        let mut extent: Option<(f32, f32)> = None;
+        let mut include = |x: f32| {
+            let (left, right) = extent.get_or_insert((x, x));
+            *left = left.min(x);
+            *right = right.max(x);
+        };
+        for (i, p) in points.iter().enumerate() {
+            let q = points[(i + 1) % points.len()];
+            if (y - p.y()) * (y - q.y()) > 0. {
+                continue;
+            }
+            if p.y() == q.y() {
+                include(p.x());
+                include(q.x());
+            } else {
+                include(p.x() + (y - p.y()) * (q.x() - p.x()) / (q.y() - p.y()));
+            }
         }
+        extent
+    }
 
-        let (xmin, ymin, xmax, ymax) = self.bounding_box();
-        let mut x = xmin;
-        let mut y = ymin;
-
-        // go through all the points in the bounding box
-        while y < ymax {
-            while x < xmax {
-                if self.contains(&Point2::new(x as f32, y as f32)) {
-                    if let Some((_, projection)) = self.raytracing(x as i16, y as i16) {
-                        let i = pos_to_index(x, y);
-                        let pixel = &mut frame[i..i + 4];
-                        let c = self.color_at_projection(&projection).rgba();
-                        pixel.copy_from_slice(&c);
+    /// Calls `f` for each pixel covered by the face, with the projective coordinates of the pixel
+    /// on the face.
+    ///
+    /// The rows of pixels are scanned within the edges of the 2D polygon, and the projective
+    /// coordinates are interpolated along the rows (see `ScreenMapping`) instead of casting a ray
+    /// for each pixel. The polygon is clipped to the screen first, so that only the rows and
+    /// columns of its visible part are scanned.
+    fn rasterize(&self, mut f: impl FnMut(u32, u32, ProjectionCoordinates)) {
+        let Some(face) = self.face3 else {
+            return;
+        };
+        let mapping = ScreenMapping::new(face, self.camera);
+        let clipped = self.clip_to_screen();
+        let (xmin, ymin, xmax, ymax) = bounding_box(&clipped, self.camera.viewport());
+        for y in ymin..ymax {
+            // One more pixel on each side: the pixels on the edges are decided in 3D
+            let (start, end) = match Self::span(&clipped, y as f32) {
+                Some((left, right)) => (
+                    (left.floor() - 1.).clamp(xmin as f32, xmax as f32) as u32,
+                    (right.ceil() + 2.).clamp(xmin as f32, xmax as f32) as u32,
+                ),
+                None => continue,
+            };
+            for x in start..end {
+                if let Some(projection) = mapping.projection_at(x as f32, y as f32) {
+                    if face.contains_projection(&projection) {
+                        f(x, y, projection);
                     }
                 }
-                x += 1;
             }
-            x = xmin;
-            y += 1;
         }
     }
 
+    /// Draws all the pixels of self in the given frame. The colors of the texture which are not
+    /// opaque are blended with the frame, so the faces behind must be drawn first.
+    pub fn draw(&self, frame: &mut [u8]) {
+        let viewport = self.camera.viewport();
+        self.rasterize(|x, y, projection| {
+            let i = 4 * viewport.index(x, y);
+            let color = self.color_at_projection(&projection).rgba();
+            if color[3] == 255 {
+                frame[i..i + 4].copy_from_slice(&color);
+            } else {
+                blend(&mut frame[i..i + 4], color);
+            }
+        });
+    }
+
+    /// Same as `draw`, but a pixel is only painted if the face is closer than the distance stored in
+    /// the depth buffer (which is then updated).
+    pub fn draw_depth_tested(&self, frame: &mut [u8], depth: &mut [u32]) {
+        let face = self.face3.unwrap();
+        let camera = self.camera.pose().position();
+        let viewport = self.camera.viewport();
+        let scale = tunables().distance_scale;
+        self.rasterize(|x, y, projection| {
+            let i = viewport.index(x, y);
+            let distance = (camera.line_to(&face.point_at(&projection)).norm() * scale) as u32;
+            if distance < depth[i] {
+                let color = self.color_at_projection(&projection).rgba();
+                // The faces are not sorted: the transparent pixels are blended with the closest
+                // face drawn so far, and do not hide the faces drawn after them
+                if color[3] == 255 {
+                    depth[i] = distance;
+                    frame[4 * i..4 * i + 4].copy_from_slice(&color);
+                } else {
+                    blend(&mut frame[4 * i..4 * i + 4], color);
+                }
+            }
+        });
+    }
+
+    /// Writes the distance of the face in the depth buffer where it is the closest opaque face, as
+    /// `draw_depth_tested` but without drawing it
+    pub fn draw_depth_only(&self, depth: &mut [u32]) {
+        let face = self.face3.unwrap();
+        let camera = self.camera.pose().position();
+        let viewport = self.camera.viewport();
+        let scale = tunables().distance_scale;
+        self.rasterize(|x, y, projection| {
+            let i = viewport.index(x, y);
+            let distance = (camera.line_to(&face.point_at(&projection)).norm() * scale) as u32;
+            if distance < depth[i] && self.color_at_projection(&projection).rgba()[3] == 255 {
+                depth[i] = distance;
+            }
+        });
+    }
+
     // Returns true if the faces are roughly equals.
     pub fn equals_to(&self, other: &CubicFace2) -> bool {
         self.points == other.points
     }
-    pub fn points(&self) -> [Point2; 4] {
-        self.points.clone()
+    /// Returns the 3D face which was projected
+    pub fn face3(&self) -> Option<&'a CubicFace3> {
+        self.face3
+    }
+
+    pub fn points(&self) -> &[Point2] {
+        &self.points
+    }
+}
+
+/// Returns a bounding box of the points, larger by the screen padding and clamped to the viewport
+/// (empty if there is no point)
+/// format: xmin, ymin, xmax, ymax
+fn bounding_box(points: &[Point2], viewport: Viewport) -> (u32, u32, u32, u32) {
+    if points.is_empty() {
+        return (0, 0, 0, 0);
+    }
+    let mut xmin = points[0].x() as u32;
+    let mut ymin = points[0].y() as u32;
+    let mut xmax = points[0].x() as u32;
+    let mut ymax = points[0].y() as u32;
+    for point in &points[1..] {
+        let x = point.x() as u32;
+        let y = point.y() as u32;
+        xmin = min(x, xmin);
+        ymin = min(y, ymin);
+        xmax = max(x, xmax);
+        ymax = max(y, ymax);
+    }
+    let padding = tunables().screen_padding;
+    (
+        xmin.saturating_sub(padding).clamp(0, viewport.width),
+        ymin.saturating_sub(padding).clamp(0, viewport.height),
+        xmax.saturating_add(padding).clamp(0, viewport.width),
+        ymax.saturating_add(padding).clamp(0, viewport.height),
+    )
+}
+
+/// Function of the pixel coordinates of the form `c + cu * u + cv * v`
+struct Linear {
+    c: f32,
+    cu: f32,
+    cv: f32,
+}
+
+impl Linear {
+    /// Returns the function `k . direction(u, v)`, where the direction of the ray of each pixel is
+    /// `origin + u * du + v * dv`
+    fn dot(k: &Vector3, (origin, du, dv): &(Vector3, Vector3, Vector3)) -> Self {
+        Self { c: k.dot(origin), cu: k.dot(du), cv: k.dot(dv) }
+    }
+
+    fn at(&self, u: f32, v: f32) -> f32 {
+        self.c + self.cu * u + self.cv * v
+    }
+}
+
+/// Projective coordinates of the points of a face, as a function of the pixel which sees them.
+///
+/// The ray of the pixel (u, v) hits the face where `C + t * d = P + alpha * a + beta * b` (see
+/// `CubicFace3::line_projection`). Solving this system with Cramer's rule gives alpha, beta and t
+/// as ratios whose numerators and denominator are linear in (u, v), since the direction d is:
+/// this is the perspective-correct interpolation of the coordinates.
+struct ScreenMapping {
+    alpha: Linear,
+    beta: Linear,
+    denominator: Linear,
+    /// Numerator of t (which does not depend on the pixel)
+    t: f32,
+}
+
+impl ScreenMapping {
+    fn new(face: &CubicFace3, camera: &Camera) -> Self {
+        let points = face.points();
+        let (p, a, b) = (points[0], points[1] - points[0], points[3] - points[0]);
+        let r = *camera.pose().position() - p;
+        let directions = camera.ray_direction_derivatives();
+        let normal = a.cross(&b);
+        Self {
+            alpha: Linear::dot(&r.cross(&b), &directions),
+            beta: Linear::dot(&a.cross(&r), &directions),
+            denominator: Linear::dot(&normal, &directions),
+            t: -normal.dot(&r),
+        }
+    }
+
+    /// Returns the projective coordinates of the point seen by the pixel, if the plane of the face
+    /// is in front of the camera there
+    fn projection_at(&self, u: f32, v: f32) -> Option<ProjectionCoordinates> {
+        let denominator = self.denominator.at(u, v);
+        if denominator == 0. || self.t / denominator < 0. {
+            return None;
+        }
+        Some(ProjectionCoordinates::new(self.alpha.at(u, v) / denominator, self.beta.at(u, v) / denominator))
     }
 }
 
@@ -192,21 +428,22 @@ mod tests {
     use crate::primitives::point::Point2;
     use crate::primitives::position::Pose;
     use crate::primitives::textures::colored::{ColoredTexture, YELLOW};
+    use crate::primitives::textures::TextureHandle;
     use crate::primitives::vector::Vector3;
 
     #[test]
     fn contains() {
         let face2 = CubicFace2 {
-            points: [
+            points: vec![
                 Point2::new(0., 0.),
                 Point2::new(1., 0.),
                 Point2::new(1., 1.),
                 Point2::new(0., 1.),
             ],
             face3: None,
-            norm_a: 1.0,
-            norm_b: 1.0,
             camera: &Camera::default(),
+            lighting: None,
+            fog: None,
         };
 
         assert!(face2.contains(&Point2::new(0.5, 0.5)));
@@ -225,16 +462,16 @@ mod tests {
     #[test]
     fn contains2() {
         let face2 = CubicFace2 {
-            points: [
+            points: vec![
                 Point2::new(160., 20.),
                 Point2::new(160., 53.3),
                 Point2::new(193.3, 53.3),
                 Point2::new(210., 20.),
             ],
             face3: None,
-            norm_a: 1.0,
-            norm_b: 1.0,
             camera: &Camera::default(),
+            lighting: None,
+            fog: None,
         };
         assert!(face2.contains(&Point2::new(161., 21.)));
     }
@@ -285,4 +522,84 @@ mod tests {
         assert!(d4 > d1);
         assert!(d5 > d1);
     }
+
+    #[test]
+    /// The colors which are not opaque are blended with the frame
+    fn transparent_face() {
+        let camera = Camera::default();
+        let viewport = camera.viewport();
+        let (px, py) = camera.optical_center();
+        let center = 4 * viewport.index(px as u32, py as u32);
+        let mut face = CubicFace3::vface_from_line(Vector3::new(2., -1., -1.), Vector3::new(2., 1., -1.));
+        face.set_texture(&TextureHandle::new(ColoredTexture::new(Color::new(0, 0, 255, 51))));
+
+        let mut frame = [200, 100, 0, 255].repeat(viewport.pixel_count());
+        face.projection(&camera).draw(&mut frame);
+        assert_eq!([160, 80, 51, 255], frame[center..center + 4]);
+        // The pixels outside of the face are not modified
+        assert_eq!([200, 100, 0, 255], frame[0..4]);
+    }
+
+    #[test]
+    fn clip_to_screen() {
+        let camera = Camera::default();
+        let viewport = camera.viewport();
+        let (width, height) = (viewport.width as f32, viewport.height as f32);
+        let face2 = |points: [(f32, f32); 4]| CubicFace2 {
+            points: points.map(|(x, y)| Point2::new(x, y)).to_vec(),
+            face3: None,
+            camera: &camera,
+            lighting: None,
+            fog: None,
+        };
+
+        // A face inside of the screen is not changed
+        let inside = [(10., 10.), (50., 10.), (50., 40.), (10., 40.)];
+        assert_eq!(inside.map(|(x, y)| Point2::new(x, y)).to_vec(), face2(inside).clip_to_screen());
+
+        // A face much larger than the screen is cut to the screen
+        let clipped = face2([(-1e6, -1e6), (1e6, -1e6), (1e6, 1e6), (-1e6, 1e6)]).clip_to_screen();
+        for point in &clipped {
+            assert!((0. ..=width).contains(&point.x()) && (0. ..=height).contains(&point.y()));
+        }
+        assert_eq!((0, 0, viewport.width, viewport.height), super::bounding_box(&clipped, viewport));
+
+        // A triangle with a corner outside of the screen gets two points on its side instead
+        let clipped = face2([(10., 10.), (2. * width - 10., 10.), (10., 30.), (10., 30.)]).clip_to_screen();
+        assert_eq!(2, clipped.iter().filter(|p| p.x() == width).count());
+        assert!(face2([(-30., 10.), (-10., 10.), (-10., 40.), (-30., 40.)]).clip_to_screen().is_empty());
+    }
+
+    #[test]
+    /// The rows of the rasterizer find the same pixels and coordinates as raytracing each pixel
+    fn rasterize() {
+        let camera = Camera::default();
+        let face = CubicFace3::new(
+            [
+                Vector3::new(3., -1., -1.),
+                Vector3::new(4., 1., -1.5),
+                Vector3::new(4., 1., 0.5),
+                Vector3::new(3., -1., 1.),
+            ],
+            Vector3::new(-1., 0.5, 0.),
+            &YELLOW,
+        );
+        let projection = face.projection(&camera);
+        let mut rasterized = Vec::new();
+        projection.rasterize(|u, v, coordinates| rasterized.push((u, v, coordinates)));
+        assert!(rasterized.len() > 1000);
+
+        let (xmin, ymin, xmax, ymax) = projection.bounding_box();
+        let raytraced = (ymin..ymax)
+            .flat_map(|v| (xmin..xmax).map(move |u| (u, v)))
+            .filter(|(u, v)| projection.raytracing(*u as i16, *v as i16).is_some())
+            .count();
+        assert!(raytraced.abs_diff(rasterized.len()) <= rasterized.len() / 100);
+        for (u, v, coordinates) in rasterized.iter().step_by(37) {
+            if let Some((_, expected)) = projection.raytracing(*u as i16, *v as i16) {
+                assert!((expected.alpha() - coordinates.alpha()).abs() < 1e-3);
+                assert!((expected.beta() - coordinates.beta()).abs() < 1e-3);
+            }
+        }
+    }
 }
@@ -1,23 +1,59 @@
 use std::fmt::{Debug, Formatter};
 
+use serde::{Deserialize, Serialize};
+
 use crate::primitives::camera::Camera;
 use crate::primitives::cubic_face2::CubicFace2;
 use crate::primitives::matrix3::Matrix3;
-use crate::primitives::object::Object;
+use crate::primitives::object::{Object, ObjectKind};
 use crate::primitives::projective_coordinates::ProjectionCoordinates;
+use crate::primitives::ray::{Hit, Intersectable, Ray};
+use crate::primitives::snapping::Grid;
+use crate::primitives::transformation::Transform;
 use crate::primitives::textures::colored::{ColoredTexture, YELLOW};
-use crate::primitives::textures::Texture;
+use crate::primitives::textures::TextureHandle;
 use crate::primitives::vector::{UNIT_X, UNIT_Y, UNIT_Z, Vector3};
+use crate::tunables::tunables;
+
+/// Effects of the scene that a face receives (all of them by default). For instance, emissive
+/// signs or skybox faces should not be darkened by the lighting.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Receivers(u8);
+
+impl Receivers {
+    pub const LIGHT: Receivers = Receivers(1);
+    pub const FOG: Receivers = Receivers(2);
+    pub const SHADOWS: Receivers = Receivers(4);
+    pub const ALL: Receivers = Receivers(Self::LIGHT.0 | Self::FOG.0 | Self::SHADOWS.0);
+
+    /// Returns true if all the effects of `other` are received
+    pub fn contains(self, other: Receivers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns these effects, except those of `other`
+    pub fn without(self, other: Receivers) -> Receivers {
+        Receivers(self.0 & !other.0)
+    }
+}
 
 /// A cubic face is an oriented rectangle in space.
 /// This class represents the basic geometric element of the engine.
 /// It is very important that all the points associated with such a face belong to
 /// the same hyper-plane, otherwise the renderer will not work.
+///
+/// A face can also be a triangle (see `CubicFace3::triangle`): its last point is then repeated,
+/// and only the half of the parallelogram on the side of the first point is part of the face.
 #[derive(Clone)]
 pub struct CubicFace3 {
     points: [Vector3; 4],
+    /// Texture coordinates (in meters) of each point.
+    /// They are interpolated over the face to find the color of the texture.
+    uvs: [(f32, f32); 4],
     normal: Vector3,
-    texture: &'static dyn Texture,
+    texture: TextureHandle,
+    is_triangle: bool,
+    receivers: Receivers,
 }
 
 impl Debug for CubicFace3 {
@@ -45,11 +81,7 @@ impl CubicFace3 {
         let rotated = Vector3::new(0., 0., 2.0);
         let p3 = p2 + rotated;
         let p4 = p1 + rotated;
-        Self {
-            points: [p1, p2, p3, p4],
-            normal: normal,
-            texture: &YELLOW,
-        }
+        Self::new([p1, p2, p3, p4], normal, &YELLOW)
     }
 
     /// Creates a horizontal face from a line, creating a square
@@ -58,30 +90,75 @@ impl CubicFace3 {
         let rotated = v.anticlockwise();
         let p3 = p2 + rotated;
         let p4 = p1 + rotated;
-        Self {
-            points: [p1, p2, p3, p4],
-            normal: Vector3::new(0.0, 0.0, -1.0),
-            texture: &YELLOW,
-        }
+        Self::new([p1, p2, p3, p4], Vector3::new(0.0, 0.0, -1.0), &YELLOW)
     }
 
-    pub fn minecraft_like(from: Vector3, side_tex: &'static dyn Texture) -> Self {
+    pub fn minecraft_like(from: Vector3, side_tex: &TextureHandle) -> Self {
         let b0 = from;
         let b1 = from + UNIT_X;
         let b2 = from + UNIT_Y;
         let b3 = b2 + UNIT_X;
+        Self::new([b0, b1, b3, b2], Vector3::new(0.0, 0.0, -1.0), side_tex)
+    }
+
+    /// Creates a face whose texture is anchored on its first point, with the u-axis going to
+    /// the second point and the v-axis going to the last point.
+    pub fn new(points: [Vector3; 4], normal: Vector3, texture: &TextureHandle) -> Self {
+        let norm_a = (points[1] - points[0]).norm();
+        let norm_b = (points[3] - points[0]).norm();
+        let uvs = [(0., 0.), (norm_a, 0.), (norm_a, norm_b), (0., norm_b)];
+        Self::new_with_uvs(points, uvs, normal, texture)
+    }
+
+    /// Creates a face with explicit texture coordinates for each of its points.
+    pub fn new_with_uvs(points: [Vector3; 4], uvs: [(f32, f32); 4], normal: Vector3, texture: &TextureHandle) -> Self {
         Self {
-            points: [b0, b1, b3, b2],
-            normal: Vector3::new(0.0, 0.0, -1.0),
-            texture: side_tex,
+            points,
+            uvs,
+            normal,
+            texture: texture.clone(),
+            is_triangle: false,
+            receivers: Receivers::ALL,
         }
     }
 
-    pub fn new(points: [Vector3; 4], normal: Vector3, texture: &'static dyn Texture) -> Self {
+    /// Creates a triangular face, with explicit texture coordinates for each of its points.
+    pub fn triangle(points: [Vector3; 3], uvs: [(f32, f32); 3], normal: Vector3, texture: &TextureHandle) -> Self {
         Self {
-            points,
+            points: [points[0], points[1], points[2], points[2]],
+            uvs: [uvs[0], uvs[1], uvs[2], uvs[2]],
             normal,
-            texture,
+            texture: texture.clone(),
+            is_triangle: true,
+            receivers: Receivers::ALL,
+        }
+    }
+
+    /// Returns a copy of the face where each point was transformed by `f`.
+    pub fn map_points(&self, f: impl FnMut(Vector3) -> Vector3) -> Self {
+        Self {
+            points: self.points.map(f),
+            ..self.clone()
+        }
+    }
+
+    /// Moves each point of the face to the closest node of the grid. The normal and the texture
+    /// coordinates are kept: the points only move by a fraction of the step of the grid.
+    pub fn snap(&mut self, grid: &Grid) {
+        self.points = self.points.map(|p| grid.snap(p));
+    }
+
+    pub fn is_triangle(&self) -> bool {
+        self.is_triangle
+    }
+
+    /// Returns true if the projective coordinates of a point of the plane of the face are
+    /// inside the face.
+    pub fn contains_projection(&self, projection: &ProjectionCoordinates) -> bool {
+        if self.is_triangle {
+            projection.is_inside_triangle()
+        } else {
+            projection.is_inside_face()
         }
     }
 
@@ -89,24 +166,51 @@ impl CubicFace3 {
         self.points
     }
 
+    pub fn uvs(&self) -> [(f32, f32); 4] {
+        self.uvs
+    }
+
+    /// Returns the texture coordinates of a point of the face, given its projective coordinates.
+    /// Since the face is a parallelogram, the texture coordinates are an affine function of
+    /// (alpha, beta).
+    pub fn uv_at(&self, projection: &ProjectionCoordinates) -> (f32, f32) {
+        projection.to_uv(&self.uvs)
+    }
+
+    /// Returns the texture coordinates of a point located on the edge between the points `i` and `j`.
+    pub fn uv_on_edge(&self, i: usize, j: usize, point: &Vector3) -> (f32, f32) {
+        let edge = self.points[i].line_to(&self.points[j]);
+        let t = self.points[i].line_to(point).dot(&edge) / edge.dot(&edge);
+        let (ui, vi) = self.uvs[i];
+        let (uj, vj) = self.uvs[j];
+        (ui + t * (uj - ui), vi + t * (vj - vi))
+    }
+
     pub fn normal(&self) -> &Vector3 {
         &self.normal
     }
 
     pub fn projection<'a>(&'a self, camera: &'a Camera) -> CubicFace2 {
-        let points2 = self.points.map(|p| camera.project(&p));
-        CubicFace2::new(points2, self, camera)
+        CubicFace2::new(camera.project_polygon(&self.points), self, camera)
     }
 
     pub fn center(&self) -> Vector3 {
         // TODO for efficiency, this could be computed upon creation
+        if self.is_triangle {
+            return (self.points[0] + self.points[1] + self.points[2]) / 3.;
+        }
         (self.points[0] + self.points[1] + self.points[2] + self.points[3]) / 4.
     }
 
     pub fn area(&self) -> f32 {
         let v1 = self.points[1] - self.points[0];
         let v2 = self.points[3] - self.points[0];
-        v1.cross(&v2).norm()
+        let area = v1.cross(&v2).norm();
+        if self.is_triangle {
+            area / 2.
+        } else {
+            area
+        }
     }
 
     pub fn rotate(&mut self, by: f32) {
@@ -119,6 +223,19 @@ impl CubicFace3 {
         self.normal = mat.clone() * self.normal;
     }
 
+    /// Moves the points of the face with the transform, and rotates its normal
+    pub fn transform(&mut self, transform: &Transform) {
+        self.points = self.points.map(|p| transform.apply(&p));
+        self.normal = transform.apply_rotation(&self.normal);
+    }
+
+    /// Returns a copy of the face moved by the transform
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        let mut face = self.clone();
+        face.transform(transform);
+        face
+    }
+
     pub fn is_visible_from(&self, camera: &Camera) -> bool {
         let cam_to_center = self.center() - *camera.pose().position();
         let dot2 = self.normal().dot(&cam_to_center);
@@ -165,10 +282,16 @@ impl CubicFace3 {
         (points[1] - p, points[3] - p, p)
     }
 
+    /// Returns the point of the face's plane at the given projective coordinates
+    pub fn point_at(&self, projection: &ProjectionCoordinates) -> Vector3 {
+        let (a, b, p) = self.get_projective_base();
+        p + a * projection.alpha() + b * projection.beta()
+    }
+
     /// Computes the intersection between a line and self.
     ///
     /// The computed intersection is not necessarily contained in the face. If you want to validate
-    /// that it is contained within the face, you can check `ProjectionCoordinates::is_inside_face`.
+    /// that it is contained within the face, you can check `CubicFace3::contains_projection`.
     /// This mean that the projection can be outside the polygon boundaries, but it will always
     /// be inside the polygon's plane.
     ///
@@ -213,7 +336,7 @@ impl CubicFace3 {
             let t = solution.z();
             if t >= 0. {
                 return Some((
-                    (t * direction.norm() * 1000.) as u32,
+                    (t * direction.norm() * tunables().distance_scale) as u32,
                     ProjectionCoordinates::new(alpha, beta),
                 ));
             }
@@ -233,12 +356,22 @@ impl CubicFace3 {
         }
         return None;
     }
-    pub fn set_texture(&mut self, texture: &'static dyn Texture) {
-        self.texture = texture;
+    pub fn set_texture(&mut self, texture: &TextureHandle) {
+        self.texture = texture.clone();
+    }
+
+    pub fn texture(&self) -> &TextureHandle {
+        &self.texture
+    }
+
+    pub fn receivers(&self) -> Receivers {
+        self.receivers
     }
 
-    pub fn texture(&self) -> &'static dyn Texture {
-        self.texture
+    /// Returns the face, with the given effects of the scene
+    pub fn with_receivers(mut self, receivers: Receivers) -> Self {
+        self.receivers = receivers;
+        self
     }
 }
 
@@ -261,7 +394,22 @@ fn distance_to_line(p1: &Vector3, p2: &Vector3, from: &Vector3) -> f32 {
     }
 }
 
+impl Intersectable for CubicFace3 {
+    fn intersect(&self, ray: &Ray) -> Option<Hit> {
+        let (_, projection) = self.line_projection(&ray.origin, &ray.direction)?;
+        if !self.contains_projection(&projection) {
+            return None;
+        }
+        let point = self.point_at(&projection);
+        Some(Hit { distance: ray.origin.line_to(&point).norm(), point })
+    }
+}
+
 impl Object for CubicFace3 {
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Face
+    }
+
     fn get_visible_faces(&self, camera: &Camera) -> Vec<&CubicFace3> {
         if self.is_visible_from(camera) {
             vec![self]
@@ -278,6 +426,40 @@ impl Object for CubicFace3 {
     fn rotate(&mut self, by: f32) {
         self.rotate(by);
     }
+
+    fn transform(&mut self, transform: &Transform) {
+        self.transform(transform);
+    }
+
+    fn set_faces(&mut self, faces: &[CubicFace3]) {
+        *self = faces[0].clone();
+    }
+
+    fn snap(&mut self, grid: &Grid) {
+        self.snap(grid);
+    }
+
+    /// A face is a thin object: the sphere collides if it crosses the plane of the face
+    /// above the polygon.
+    fn collides_with(&self, point: &Vector3, radius: f32) -> bool {
+        let mut normal = self.normal;
+        normal.normalize();
+        let distance = self.center().line_to(point).dot(&normal);
+        if distance.abs() > radius {
+            return false;
+        }
+        // Find where the point projects on the face
+        let projected = *point - normal * distance;
+        let (a, b, p) = self.get_projective_base();
+        let to_point = p.line_to(&projected);
+        // Solve to_point = alpha * a + beta * b (the axes are not orthogonal for triangles)
+        let (aa, ab, bb) = (a.dot(&a), a.dot(&b), b.dot(&b));
+        let (pa, pb) = (to_point.dot(&a), to_point.dot(&b));
+        let det = aa * bb - ab * ab;
+        let alpha = (pa * bb - pb * ab) / det;
+        let beta = (pb * aa - pa * ab) / det;
+        self.contains_projection(&ProjectionCoordinates::new(alpha, beta))
+    }
 }
 
 #[cfg(test)]
@@ -350,11 +532,7 @@ mod tests {
         let b1 = from + UNIT_X;
         let b2 = from + UNIT_Y;
         let b3 = b2 + UNIT_X;
-        let face = CubicFace3 {
-            points: [b0, b1, b2, b3],
-            normal: UNIT_Z,
-            texture: &YELLOW,
-        };
+        let face = CubicFace3::new([b0, b1, b2, b3], UNIT_Z, &YELLOW);
 
         // Create a camera
         let mut cam = Camera::default();
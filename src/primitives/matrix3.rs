@@ -1,6 +1,11 @@
 use crate::primitives::vector::Vector3;
 use std::ops::Mul;
 
+/// A matrix is considered singular when its determinant is this small relative to the product of
+/// the norms of its columns (the volume of the parallelepiped they span, compared to the volume
+/// of a box with sides of the same lengths)
+const SINGULAR_EPSILON: f32 = 1e-6;
+
 #[derive(Clone, Debug)]
 pub struct Matrix3 {
     a11: f32,
@@ -38,6 +43,24 @@ impl Mul<Vector3> for &Matrix3 {
     }
 }
 
+impl Mul<&Matrix3> for &Matrix3 {
+    type Output = Matrix3;
+
+    fn mul(self, rhs: &Matrix3) -> Self::Output {
+        let row = |a1: f32, a2: f32, a3: f32| {
+            [
+                a1 * rhs.a11 + a2 * rhs.a21 + a3 * rhs.a31,
+                a1 * rhs.a12 + a2 * rhs.a22 + a3 * rhs.a32,
+                a1 * rhs.a13 + a2 * rhs.a23 + a3 * rhs.a33,
+            ]
+        };
+        let [a11, a12, a13] = row(self.a11, self.a12, self.a13);
+        let [a21, a22, a23] = row(self.a21, self.a22, self.a23);
+        let [a31, a32, a33] = row(self.a31, self.a32, self.a33);
+        Matrix3 { a11, a12, a13, a21, a22, a23, a31, a32, a33 }
+    }
+}
+
 impl Mul<f32> for Matrix3 {
     type Output = Matrix3;
 
@@ -125,6 +148,50 @@ impl Matrix3 {
         }
     }
 
+    /// Create a rotation matrix around the y-axis
+    pub fn y_rotation(theta_y: f32) -> Self {
+        Self {
+            a11: f32::cos(theta_y),
+            a21: 0.0,
+            a31: -f32::sin(theta_y),
+            a12: 0.0,
+            a22: 1.0,
+            a32: 0.0,
+            a13: f32::sin(theta_y),
+            a23: 0.0,
+            a33: f32::cos(theta_y),
+        }
+    }
+
+    /// Create a rotation matrix around the x-axis
+    pub fn x_rotation(theta_x: f32) -> Self {
+        Self {
+            a11: 1.0,
+            a21: 0.0,
+            a31: 0.0,
+            a12: 0.0,
+            a22: f32::cos(theta_x),
+            a32: f32::sin(theta_x),
+            a13: 0.0,
+            a23: -f32::sin(theta_x),
+            a33: f32::cos(theta_x),
+        }
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self {
+            a11: self.a11,
+            a12: self.a21,
+            a13: self.a31,
+            a21: self.a12,
+            a22: self.a22,
+            a23: self.a32,
+            a31: self.a13,
+            a32: self.a23,
+            a33: self.a33,
+        }
+    }
+
     /// Create a rotation matrix around any axis of an angle theta
     ///
     /// https://en.wikipedia.org/wiki/Transformation_matrix#Rotation_2
@@ -140,7 +207,7 @@ impl Matrix3 {
             a21: y * x * (1. - c) - z * s,
             a31: z * x * (1. - c) + y * s,
             a12: x * y * (1. - c) + z * s,
-            a22: y * y * (1. - x) + c,
+            a22: y * y * (1. - c) + c,
             a32: z * y * (1. - c) - x * s,
             a13: x * z * (1. - c) - y * s,
             a23: y * z * (1. - c) + x * s,
@@ -150,18 +217,13 @@ impl Matrix3 {
 
     /// Solve the linear equation
     /// A x = rhs
-    /// If there are no solution, returns none
+    /// If there is no single solution (the matrix is singular, or so close to it that the
+    /// solution would be meaningless), returns none
     pub fn linear_solve(&self, rhs: Vector3) -> Option<Vector3> {
-        if self.invertible() {
-            Some(self.inverse() * rhs)
-        } else {
-            None
-        }
+        self.inverse().map(|inverse| inverse * rhs)
     }
-}
 
-impl Matrix3 {
-    fn determinant(&self) -> f32 {
+    pub fn determinant(&self) -> f32 {
         self.a11 * self.a22 * self.a33
             + self.a12 * self.a23 * self.a31
             + self.a13 * self.a21 * self.a32
@@ -170,11 +232,20 @@ impl Matrix3 {
             - self.a11 * self.a23 * self.a32
     }
 
-    fn invertible(&self) -> bool {
-        self.determinant() != 0.
+    /// Returns false for singular matrices, and for matrices close to singular: for instance
+    /// when solving the intersection of a face with a ray almost parallel to it.
+    /// The test does not depend on the scale of the matrix.
+    pub fn invertible(&self) -> bool {
+        let scale = (0..3).map(|i| self.col(i).norm()).product::<f32>();
+        scale > 0. && self.determinant().abs() > SINGULAR_EPSILON * scale
     }
 
-    fn inverse(&self) -> Matrix3 {
+    /// Returns the inverse of the matrix, or None if it is not invertible
+    pub fn inverse(&self) -> Option<Matrix3> {
+        if !self.invertible() {
+            return None;
+        }
+
         // reference : https://semath.info/src/inverse-cofactor-ex3.html
         let a11 = self.a11;
         let a22 = self.a22;
@@ -188,7 +259,7 @@ impl Matrix3 {
 
         let inv = 1. / self.determinant();
 
-        Matrix3 {
+        Some(Matrix3 {
             a11: (a22 * a33 - a23 * a32) * inv,
             a12: -(a12 * a33 - a13 * a32) * inv,
             a13: (a12 * a23 - a13 * a22) * inv,
@@ -198,10 +269,13 @@ impl Matrix3 {
             a31: (a21 * a32 - a22 * a31) * inv,
             a32: -(a11 * a32 - a12 * a31) * inv,
             a33: (a11 * a22 - a12 * a21) * inv,
-        }
+        })
     }
+}
 
-    fn col(&self, i: usize) -> Vector3 {
+impl Matrix3 {
+    /// Returns the column i (from 0 to 2)
+    pub fn col(&self, i: usize) -> Vector3 {
         match i {
             0 => Vector3::new(self.a11, self.a21, self.a31),
             1 => Vector3::new(self.a12, self.a22, self.a32),
@@ -213,6 +287,9 @@ impl Matrix3 {
 
 #[cfg(test)]
 mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
     use crate::primitives::matrix3::Matrix3;
     use crate::primitives::vector::Vector3;
 
@@ -239,20 +316,54 @@ mod tests {
 
         assert_near(Matrix3::rotation_around(pi / 2.0, vz) * vx, vy.opposite());
         assert_near(Matrix3::rotation_around(pi / 2.0, vz) * vy, vx);
+        assert_near(Matrix3::rotation_around(pi, vy) * vy, vy);
     }
 
     #[test]
     fn inverse_simple_matrix() {
         let m1 = Matrix3::identity();
-        let m2 = m1.inverse();
+        let m2 = m1.inverse().unwrap();
         assert_near(Vector3::new(1., 0., 0.), m2.col(0));
         assert_near(Vector3::new(0., 1., 0.), m2.col(1));
         assert_near(Vector3::new(0., 0., 1.), m2.col(2));
 
         let m3 = Matrix3::identity() * 2.;
-        let m4 = m3.inverse();
+        let m4 = m3.inverse().unwrap();
         assert_near(Vector3::new(0.5, 0., 0.), m4.col(0));
         assert_near(Vector3::new(0., 0.5, 0.), m4.col(1));
         assert_near(Vector3::new(0., 0., 0.5), m4.col(2));
     }
+
+    #[test]
+    fn singular_matrices() {
+        // Two equal columns, and a third column almost in the plane of the others
+        assert!(Matrix3::from_columns([1., 2., 3.], [1., 2., 3.], [0., 1., 0.]).linear_solve(Vector3::new(1., 1., 1.)).is_none());
+        assert!(Matrix3::from_columns([1., 0., 0.], [0., 1., 0.], [1., 1., 1e-8]).inverse().is_none());
+        assert_eq!(1., Matrix3::identity().determinant());
+        // The test does not depend on the scale: tiny faces are still solved
+        assert!((Matrix3::identity() * 1e-4).linear_solve(Vector3::new(1., 1., 1.)).is_some());
+        assert!((Matrix3::identity() * 0.).inverse().is_none());
+    }
+
+    #[test]
+    fn solve_random_systems() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut random = || rng.gen_range(-10.0..10.0);
+        for _ in 0..1000 {
+            let m = Matrix3::new(random(), random(), random(), random(), random(), random(), random(), random(), random());
+            let rhs = Vector3::new(random(), random(), random());
+            let Some(inverse) = m.inverse() else {
+                continue;
+            };
+            // The solution satisfies the system, and the inverse gives back the identity
+            let x = m.linear_solve(rhs).unwrap();
+            let error = (&m * x).line_to(&rhs).norm();
+            assert!(error < 1e-3 * rhs.norm(), "{m:?} {error}");
+            let product = &m * &inverse;
+            for (i, column) in [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]].into_iter().enumerate() {
+                let error = product.col(i).line_to(&Vector3::new(column[0], column[1], column[2])).norm();
+                assert!(error < 1e-2, "{m:?} {error}");
+            }
+        }
+    }
 }
@@ -0,0 +1,103 @@
+use crate::primitives::camera::Camera;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::object::{Object, ObjectKind};
+use crate::primitives::snapping::Grid;
+use crate::primitives::transformation::Transform;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::triangle3::Triangle3;
+use crate::primitives::vector::Vector3;
+
+/// An object made of triangles, to represent arbitrary geometry.
+pub struct Mesh {
+    triangles: Vec<Triangle3>,
+}
+
+impl Mesh {
+    pub fn new(triangles: Vec<Triangle3>) -> Self {
+        Self { triangles }
+    }
+
+    /// Creates a mesh from a list of vertices and the indices of the vertices of each triangle.
+    /// The normal of each triangle follows the order of its indices (see `Triangle3::new`).
+    pub fn from_indices(vertices: &[Vector3], indices: &[[usize; 3]], texture: &TextureHandle) -> Self {
+        let triangles = indices
+            .iter()
+            .map(|[i, j, k]| Triangle3::new([vertices[*i], vertices[*j], vertices[*k]], texture))
+            .collect();
+        Self::new(triangles)
+    }
+
+    /// Creates a pyramid with a square base of the given size, whose tip is `height` meters
+    /// above the center of the base (the z-axis goes down).
+    pub fn pyramid(base: Vector3, size: f32, height: f32, texture: &TextureHandle) -> Self {
+        let vertices = [
+            base,
+            base + Vector3::new(size, 0., 0.),
+            base + Vector3::new(size, size, 0.),
+            base + Vector3::new(0., size, 0.),
+            base + Vector3::new(size / 2., size / 2., -height),
+        ];
+        let indices = [[1, 0, 4], [2, 1, 4], [3, 2, 4], [0, 3, 4], [0, 1, 2], [0, 2, 3]];
+        Self::from_indices(&vertices, &indices, texture)
+    }
+}
+
+impl Object for Mesh {
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Mesh
+    }
+
+    fn get_visible_faces(&self, camera: &Camera) -> Vec<&CubicFace3> {
+        self.triangles.iter().map(|t| t.face()).filter(|f| f.is_visible_from(camera)).collect()
+    }
+
+    fn get_all_faces(&self) -> Vec<&CubicFace3> {
+        self.triangles.iter().map(|t| t.face()).collect()
+    }
+
+    fn rotate(&mut self, by: f32) {
+        for triangle in &mut self.triangles {
+            triangle.rotate(by);
+        }
+    }
+
+    fn transform(&mut self, transform: &Transform) {
+        for triangle in &mut self.triangles {
+            triangle.transform(transform);
+        }
+    }
+
+    fn set_faces(&mut self, faces: &[CubicFace3]) {
+        for (triangle, face) in self.triangles.iter_mut().zip(faces) {
+            triangle.set_faces(std::slice::from_ref(face));
+        }
+    }
+
+    fn snap(&mut self, grid: &Grid) {
+        for triangle in &mut self.triangles {
+            triangle.snap(grid);
+        }
+    }
+
+    fn collides_with(&self, point: &Vector3, radius: f32) -> bool {
+        self.triangles.iter().any(|t| t.collides_with(point, radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::mesh::Mesh;
+    use crate::primitives::object::Object;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_pyramid_normals_point_outwards() {
+        let pyramid = Mesh::pyramid(Vector3::empty(), 2., 1., &YELLOW);
+        assert_eq!(6, pyramid.get_all_faces().len());
+        let center = Vector3::new(1., 1., -0.3);
+        for face in pyramid.get_all_faces() {
+            assert!(center.line_to(&face.center()).dot(face.normal()) > 0.);
+        }
+    }
+}
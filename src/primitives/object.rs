@@ -1,9 +1,85 @@
+use serde::{Deserialize, Serialize};
+
 use crate::primitives::camera::Camera;
 use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::ray::{Intersectable, Ray};
+use crate::primitives::snapping::Grid;
+use crate::primitives::transformation::Transform;
+use crate::primitives::vector::Vector3;
+use crate::updatable::Updatable;
+
+/// The kinds of objects, which tell how to rebuild an object from its faces (see `scene_file`)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectKind {
+    Cube,
+    Face,
+    Mesh,
+    Patch,
+    Billboard,
+}
+
+/// Identifier of an object of a world, which stays valid when other objects are added or removed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectId(u64);
+
+impl ObjectId {
+    pub fn new(id: u64) -> Self {
+        Self(id)
+    }
+}
 
 /// An object is a 3D element which can be part of the world
 pub trait Object {
+    fn kind(&self) -> ObjectKind;
     fn get_visible_faces(&self, camera: &Camera) -> Vec<&CubicFace3>;
     fn get_all_faces(&self) -> Vec<&CubicFace3>;
     fn rotate(&mut self, by: f32);
+
+    /// Rotates the object by `angle` around the axis going through the origin, counterclockwise
+    /// when the axis points towards the viewer: `rotate` is the rotation around the z-axis.
+    fn rotate_around(&mut self, axis: &Vector3, angle: f32) {
+        self.transform(&Transform::rotation_around(axis, angle, &Vector3::empty()));
+    }
+
+    /// Moves all the points of the object with the transform, which must be a rigid motion (the
+    /// normals are only rotated)
+    fn transform(&mut self, transform: &Transform);
+
+    /// Replaces the faces of the object with moved copies of them, given in the order of
+    /// `get_all_faces`
+    fn set_faces(&mut self, faces: &[CubicFace3]);
+
+    /// Moves each vertex of the object to the closest node of the grid
+    fn snap(&mut self, grid: &Grid);
+
+    /// Returns true if a sphere centered at `point` with the given radius intersects the object.
+    fn collides_with(&self, point: &Vector3, radius: f32) -> bool;
+
+    /// Returns the behavior of the object, for the objects updated at each step of the simulation
+    fn as_updatable(&mut self) -> Option<&mut dyn Updatable> {
+        None
+    }
+
+    /// Turns the object towards the camera at `position`, before drawing a frame. Returns true if
+    /// the object moved (only the billboards do).
+    fn face_camera(&mut self, _position: &Vector3) -> bool {
+        false
+    }
+
+    /// Returns a sphere (center, radius) containing the whole object
+    fn bounding_sphere(&self) -> (Vector3, f32) {
+        let points: Vec<Vector3> = self.get_all_faces().iter().flat_map(|f| f.points()).collect();
+        if points.is_empty() {
+            return (Vector3::empty(), 0.);
+        }
+        let center = points.iter().fold(Vector3::empty(), |acc, p| acc + *p) / points.len() as f32;
+        let radius = points.iter().map(|p| center.line_to(p).norm()).fold(0., f32::max);
+        (center, radius)
+    }
+}
+
+/// Returns the distance from the origin of the ray to the first face it hits, if any
+pub fn faces_ray_intersection(faces: &[&CubicFace3], ray: &Ray) -> Option<f32> {
+    faces.iter().filter_map(|face| face.intersect(ray)).map(|hit| hit.distance).min_by(f32::total_cmp)
 }
@@ -1,9 +1,16 @@
-use crate::primitives::vector::Vector3;
+use std::f32::consts::FRAC_PI_2;
 
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::quaternion::Quaternion;
+use crate::primitives::vector::{Vector3, UNIT_X, UNIT_Y, UNIT_Z};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Pose {
     pos: Vector3,
-    // for now, we only assume that there is a rotation in the z-axis
-    rotz: f32,
+    /// Rotation from the frame of the camera to the world. The angles of the pose are derived
+    /// from it.
+    rotation: Quaternion,
 }
 
 impl Pose {
@@ -11,20 +18,48 @@ impl Pose {
         &self.pos
     }
 
+    /// Rotation around the z-axis (yaw), between -pi and pi
     pub fn rotation_z(&self) -> f32 {
-        self.rotz
+        self.angles().0
+    }
+
+    /// Rotation around the y-axis of the camera, positive when looking up
+    pub fn pitch(&self) -> f32 {
+        self.angles().1
     }
 
+    /// Rotation around the x-axis of the camera (the direction it looks at)
+    pub fn roll(&self) -> f32 {
+        self.angles().2
+    }
+
+    /// Returns the horizontal direction of the pose (the pitch and the roll are ignored), which
+    /// is the direction of the motion when walking.
     pub fn orientation(&self) -> Vector3 {
-        Vector3::new(f32::cos(self.rotz), -f32::sin(self.rotz), 0.0)
+        let rotz = self.rotation_z();
+        Vector3::new(f32::cos(rotz), -f32::sin(rotz), 0.0)
     }
 
     pub fn new(pos: Vector3, rotz: f32) -> Self {
-        Self { pos, rotz }
+        Self { pos, rotation: Quaternion::from_axis_angle(&UNIT_Z, -rotz) }
     }
 
     pub fn apply_z_rot(&mut self, rot: f32) {
-        self.rotz += rot;
+        self.rotation = (Quaternion::from_axis_angle(&UNIT_Z, -rot) * self.rotation).normalized();
+    }
+
+    pub fn apply_pitch(&mut self, rot: f32) {
+        let (rotz, pitch, roll) = self.angles();
+        self.set_angles(rotz, (pitch + rot).clamp(-FRAC_PI_2, FRAC_PI_2), roll);
+    }
+
+    pub fn apply_roll(&mut self, rot: f32) {
+        self.rotation = (self.rotation * Quaternion::from_axis_angle(&UNIT_X, rot)).normalized();
+    }
+
+    /// Removes the pitch and the roll, to look at the horizon
+    pub fn level(&mut self) {
+        self.set_angles(self.rotation_z(), 0., 0.);
     }
 
     pub fn translate(&mut self, by: &Vector3) {
@@ -36,6 +71,71 @@ impl Pose {
     }
 
     pub fn set_rotation(&mut self, rot: f32) {
-        self.rotz = rot;
+        let (_, pitch, roll) = self.angles();
+        self.set_angles(rot, pitch, roll);
+    }
+
+    pub fn set_pitch(&mut self, pitch: f32) {
+        let (rotz, _, roll) = self.angles();
+        self.set_angles(rotz, pitch.clamp(-FRAC_PI_2, FRAC_PI_2), roll);
+    }
+
+    pub fn set_roll(&mut self, roll: f32) {
+        let (rotz, pitch, _) = self.angles();
+        self.set_angles(rotz, pitch, roll);
+    }
+
+    /// Returns the rotation from the frame of the camera to the world: the roll is applied first,
+    /// then the pitch and finally the yaw
+    pub fn quaternion(&self) -> Quaternion {
+        self.rotation
+    }
+
+    /// Turns the pose to any orientation, given as the rotation from the frame of the camera to
+    /// the world
+    pub fn set_quaternion(&mut self, rotation: &Quaternion) {
+        self.rotation = rotation.normalized();
+    }
+
+    /// Returns the yaw, the pitch and the roll of the rotation. Looking straight up or down, the
+    /// roll is turned into yaw.
+    fn angles(&self) -> (f32, f32, f32) {
+        let m = self.rotation.to_matrix();
+        let (c1, c2, c3) = (m.col(0), m.col(1), m.col(2));
+        let pitch = f32::atan2(-c1.z(), c1.x().hypot(c1.y()));
+        let (rotz, roll) = if c1.x().hypot(c1.y()) > 1e-6 {
+            (-f32::atan2(c1.y(), c1.x()), f32::atan2(c2.z(), c3.z()))
+        } else {
+            (-f32::atan2(-c2.x(), c2.y()), 0.)
+        };
+        // Adding zero turns the negative zeros into positive ones
+        (rotz + 0., pitch + 0., roll + 0.)
+    }
+
+    fn set_angles(&mut self, rotz: f32, pitch: f32, roll: f32) {
+        self.rotation = Quaternion::from_axis_angle(&UNIT_Z, -rotz)
+            * Quaternion::from_axis_angle(&UNIT_Y, pitch)
+            * Quaternion::from_axis_angle(&UNIT_X, roll);
+    }
+
+    /// Interpolates between two poses: the position moves along the line between them, and the
+    /// orientation turns along the shortest arc
+    pub fn slerp(&self, other: &Pose, t: f32) -> Pose {
+        let mut pose = Pose::new(self.pos + self.pos.line_to(&other.pos) * t, 0.);
+        pose.set_quaternion(&self.quaternion().slerp(&other.quaternion(), t));
+        pose
+    }
+
+    /// Turns the pose to look at the point, without roll. Nothing changes if the point is the
+    /// position of the pose.
+    pub fn look_at(&mut self, point: &Vector3) {
+        let direction = self.pos.line_to(point);
+        let horizontal = direction.x().hypot(direction.y());
+        if direction.norm() < f32::EPSILON {
+            return;
+        }
+        let rotz = if horizontal > f32::EPSILON { f32::atan2(-direction.y(), direction.x()) } else { self.rotation_z() };
+        // The z-axis goes down
+        self.set_angles(rotz, f32::atan2(-direction.z(), horizontal), 0.);
     }
 }
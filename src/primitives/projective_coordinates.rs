@@ -1,3 +1,8 @@
+/// Margin (relative to the size of the face) by which a point can be outside of a face and still
+/// be considered inside. Without it, the rounding errors can put a pixel on the edge shared by
+/// two faces outside of both of them, and the background leaks through the seam.
+const EDGE_TOLERANCE: f32 = 1e-4;
+
 /// Contains the projected coordinates (alpha, beta) such that a point P belonging to
 /// a parallelogram can be written as
 ///
@@ -25,9 +30,16 @@ impl ProjectionCoordinates {
         }
     }
 
-    /// Returns coordinates in meters
-    pub fn to_uv(&self, norm_a: f32, norm_b: f32) -> (f32, f32) {
-        (self.alpha * norm_a, self.beta * norm_b)
+    /// Returns the texture coordinates (in meters) of this point, given the texture
+    /// coordinates of the 4 points of the parallelogram.
+    pub fn to_uv(&self, uvs: &[(f32, f32); 4]) -> (f32, f32) {
+        let (u0, v0) = uvs[0];
+        let (u1, v1) = uvs[1];
+        let (u3, v3) = uvs[3];
+        (
+            u0 + self.alpha * (u1 - u0) + self.beta * (u3 - u0),
+            v0 + self.alpha * (v1 - v0) + self.beta * (v3 - v0),
+        )
     }
     pub fn alpha(&self) -> f32 {
         self.alpha
@@ -37,6 +49,12 @@ impl ProjectionCoordinates {
     }
 
     pub fn is_inside_face(&self) -> bool {
-        self.alpha >= 0. && self.alpha <= 1. && self.beta >= 0. && self.beta <= 1.
+        let inside = |c: f32| (-EDGE_TOLERANCE..=1. + EDGE_TOLERANCE).contains(&c);
+        inside(self.alpha) && inside(self.beta)
+    }
+
+    /// Returns true if the point is inside the triangle made of P0, P1 and P3
+    pub fn is_inside_triangle(&self) -> bool {
+        self.alpha >= -EDGE_TOLERANCE && self.beta >= -EDGE_TOLERANCE && self.alpha + self.beta <= 1. + EDGE_TOLERANCE
     }
 }
@@ -0,0 +1,201 @@
+use std::ops::Mul;
+
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::matrix3::Matrix3;
+use crate::primitives::vector::Vector3;
+
+/// A rotation in 3D, as a unit quaternion `w + xi + yj + zk`.
+///
+/// Unlike the angles of a `Pose`, quaternions can be composed and interpolated without gimbal lock:
+/// `slerp` turns at a constant speed along the shortest path between two orientations.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quaternion {
+    w: f32,
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+impl Quaternion {
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Self { w, x, y, z }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(1., 0., 0., 0.)
+    }
+
+    /// Rotation of `angle` radians around the axis, counterclockwise when the axis points towards
+    /// the viewer (as `Matrix3::z_rotation`)
+    pub fn from_axis_angle(axis: &Vector3, angle: f32) -> Self {
+        let mut axis = *axis;
+        axis.normalize();
+        let (sin, cos) = (angle / 2.).sin_cos();
+        Self::new(cos, axis.x() * sin, axis.y() * sin, axis.z() * sin)
+    }
+
+    /// Returns the rotation of a rotation matrix
+    ///
+    /// https://www.euclideanspace.com/maths/geometry/rotations/conversions/matrixToQuaternion/
+    pub fn from_matrix(m: &Matrix3) -> Self {
+        let (c1, c2, c3) = (m.col(0), m.col(1), m.col(2));
+        let (m11, m21, m31) = (c1.x(), c1.y(), c1.z());
+        let (m12, m22, m32) = (c2.x(), c2.y(), c2.z());
+        let (m13, m23, m33) = (c3.x(), c3.y(), c3.z());
+        let trace = m11 + m22 + m33;
+        // Divides by the largest of the terms, to stay accurate
+        let q = if trace > 0. {
+            let s = (trace + 1.).sqrt() * 2.;
+            Self::new(s / 4., (m32 - m23) / s, (m13 - m31) / s, (m21 - m12) / s)
+        } else if m11 > m22 && m11 > m33 {
+            let s = (1. + m11 - m22 - m33).sqrt() * 2.;
+            Self::new((m32 - m23) / s, s / 4., (m12 + m21) / s, (m13 + m31) / s)
+        } else if m22 > m33 {
+            let s = (1. + m22 - m11 - m33).sqrt() * 2.;
+            Self::new((m13 - m31) / s, (m12 + m21) / s, s / 4., (m23 + m32) / s)
+        } else {
+            let s = (1. + m33 - m11 - m22).sqrt() * 2.;
+            Self::new((m21 - m12) / s, (m13 + m31) / s, (m23 + m32) / s, s / 4.)
+        };
+        q.normalized()
+    }
+
+    pub fn to_matrix(&self) -> Matrix3 {
+        let Self { w, x, y, z } = *self;
+        Matrix3::new(
+            1. - 2. * (y * y + z * z),
+            2. * (x * y - w * z),
+            2. * (x * z + w * y),
+            2. * (x * y + w * z),
+            1. - 2. * (x * x + z * z),
+            2. * (y * z - w * x),
+            2. * (x * z - w * y),
+            2. * (y * z + w * x),
+            1. - 2. * (x * x + y * y),
+        )
+    }
+
+    pub fn w(&self) -> f32 {
+        self.w
+    }
+
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn y(&self) -> f32 {
+        self.y
+    }
+
+    pub fn z(&self) -> f32 {
+        self.z
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f32 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn norm(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Returns the quaternion scaled to a norm of 1, which is required to represent a rotation
+    pub fn normalized(&self) -> Self {
+        let norm = self.norm();
+        Self::new(self.w / norm, self.x / norm, self.y / norm, self.z / norm)
+    }
+
+    /// The opposite rotation
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn rotate(&self, v: &Vector3) -> Vector3 {
+        let q = *self * Quaternion::new(0., v.x(), v.y(), v.z()) * self.conjugate();
+        Vector3::new(q.x, q.y, q.z)
+    }
+
+    /// Interpolates between two rotations, from `self` (t = 0) to `other` (t = 1), at a constant
+    /// angular speed along the shortest arc
+    pub fn slerp(&self, other: &Quaternion, t: f32) -> Quaternion {
+        // q and -q are the same rotation: the one closest to self gives the shortest arc
+        let mut dot = self.dot(other);
+        let other = if dot < 0. {
+            dot = -dot;
+            Quaternion::new(-other.w, -other.x, -other.y, -other.z)
+        } else {
+            *other
+        };
+        // Almost the same rotation: the linear interpolation is accurate, and does not divide by 0
+        let (a, b) = if dot > 0.9995 {
+            (1. - t, t)
+        } else {
+            let theta = dot.acos();
+            let sin = theta.sin();
+            (((1. - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Quaternion::new(
+            self.w * a + other.w * b,
+            self.x * a + other.x * b,
+            self.y * a + other.y * b,
+            self.z * a + other.z * b,
+        )
+        .normalized()
+    }
+}
+
+/// Composition of rotations: `a * b` applies `b` first, then `a` (as for matrices)
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Self::Output {
+        Quaternion::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::primitives::matrix3::Matrix3;
+    use crate::primitives::quaternion::Quaternion;
+    use crate::primitives::vector::{Vector3, UNIT_X, UNIT_Y, UNIT_Z};
+
+    fn assert_near(left: Vector3, right: Vector3) {
+        assert!(left.line_to(&right).norm() < 1e-5, "{left:?} vs {right:?}");
+    }
+
+    #[test]
+    fn test_matrix_conversions() {
+        let q = Quaternion::from_axis_angle(&UNIT_Z, PI / 2.);
+        assert_near(UNIT_Y, q.rotate(&UNIT_X));
+        assert_near(UNIT_Y, q.to_matrix() * UNIT_X);
+
+        // Composing the quaternions is multiplying the matrices
+        let rotations = [Matrix3::z_rotation(0.3), Matrix3::y_rotation(-1.2), Matrix3::x_rotation(2.9)];
+        let matrix = &(&rotations[0] * &rotations[1]) * &rotations[2];
+        let q = rotations.iter().map(Quaternion::from_matrix).fold(Quaternion::identity(), |q, r| q * r);
+        let v = Vector3::new(1., -2., 0.5);
+        assert_near(&matrix * v, q.rotate(&v));
+        assert_near(&matrix * v, Quaternion::from_matrix(&matrix).to_matrix() * v);
+        // Half a turn, where the trace is negative
+        assert_near(UNIT_X.opposite(), Quaternion::from_matrix(&Matrix3::y_rotation(PI)).rotate(&UNIT_X));
+    }
+
+    #[test]
+    fn test_slerp() {
+        let from = Quaternion::from_axis_angle(&UNIT_Z, 0.2);
+        let to = Quaternion::from_axis_angle(&UNIT_Z, 1.8);
+        assert_near(Quaternion::from_axis_angle(&UNIT_Z, 0.6).rotate(&UNIT_X), from.slerp(&to, 0.25).rotate(&UNIT_X));
+        assert_near(to.rotate(&UNIT_Y), from.slerp(&to, 1.).rotate(&UNIT_Y));
+        // -q is the same rotation as q, and the interpolation takes the shortest arc through 0
+        let to = Quaternion::from_axis_angle(&UNIT_Z, 2. * PI - 0.2);
+        assert_near(UNIT_X, from.slerp(&to, 0.5).rotate(&UNIT_X));
+    }
+}
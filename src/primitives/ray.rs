@@ -0,0 +1,98 @@
+use crate::content_hash::ContentHasher;
+use crate::primitives::color::Color;
+use crate::primitives::vector::Vector3;
+
+/// A half-line starting at `origin`. The direction does not need to be normalized: the distances
+/// along the ray are always in meters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3,
+    pub direction: Vector3,
+}
+
+impl Ray {
+    pub fn new(origin: Vector3, direction: Vector3) -> Self {
+        Self { origin, direction }
+    }
+
+    /// Returns the ray going from `from` through `to`
+    pub fn between(from: Vector3, to: Vector3) -> Self {
+        Self::new(from, from.line_to(&to))
+    }
+
+    /// Returns the point of the ray at this distance from its origin
+    pub fn at(&self, distance: f32) -> Vector3 {
+        self.origin + self.direction * (distance / self.direction.norm())
+    }
+}
+
+/// The first point where a ray hits a shape
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// Distance from the origin of the ray, in meters
+    pub distance: f32,
+    pub point: Vector3,
+}
+
+impl Hit {
+    /// Returns the hit of the ray at this distance
+    pub fn along(ray: &Ray, distance: f32) -> Self {
+        Self { distance, point: ray.at(distance) }
+    }
+}
+
+/// Shapes which can be hit by rays, such as faces, boxes, spheres, or a whole hierarchy of them.
+///
+/// The same queries then serve the raytracer, the picking of objects, or the line of sight.
+pub trait Intersectable {
+    /// Returns the first point of the shape hit by the ray, if any
+    fn intersect(&self, ray: &Ray) -> Option<Hit>;
+
+    /// Returns true if the ray hits the shape before this distance
+    fn hits_before(&self, ray: &Ray, max_distance: f32) -> bool {
+        self.intersect(ray).is_some_and(|hit| hit.distance <= max_distance)
+    }
+}
+
+/// Shapes drawn by the raytracer only, next to the faces of the objects
+pub trait RaytraceObject: Intersectable + Send + Sync {
+    /// Returns the color of the texture at a point of the surface
+    fn color_at(&self, point: &Vector3) -> Color;
+
+    /// Returns the unit normal of the surface at this point, pointing outwards
+    fn normal_at(&self, point: &Vector3) -> Vector3;
+
+    /// Writes the shape and the texture of the object, to compute the hash of the world
+    fn write_hash(&self, hasher: &mut ContentHasher);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::aabb::Aabb;
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::ray::{Intersectable, Ray};
+    use crate::primitives::sphere::Sphere;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_intersectables() {
+        let ray = Ray::new(Vector3::new(-2., 0.5, 0.5), Vector3::new(2., 0., 0.));
+        assert_eq!(Vector3::new(1., 0.5, 0.5), ray.at(3.));
+
+        // A face, a box and a sphere behind each other along the x axis
+        let face = CubicFace3::vface_from_line(Vector3::new(0., 0., 0.), Vector3::new(0., 1., 0.));
+        let aabb = Aabb::new(Vector3::new(2., 0., 0.), Vector3::new(3., 1., 1.));
+        let sphere = Sphere::new(Vector3::new(5., 0.5, 0.5), 0.5);
+        let shapes: [&dyn Intersectable; 3] = [&face, &aabb, &sphere];
+        let distances: Vec<f32> = shapes.iter().map(|shape| shape.intersect(&ray).unwrap().distance).collect();
+        for (distance, expected) in distances.iter().zip([2., 4., 6.5]) {
+            assert!((distance - expected).abs() < 1e-5);
+        }
+        assert!((sphere.intersect(&ray).unwrap().point - Vector3::new(4.5, 0.5, 0.5)).norm() < 1e-5);
+        assert!(sphere.hits_before(&ray, 7.) && !sphere.hits_before(&ray, 6.));
+
+        // Going the other way, nothing is hit
+        let back = Ray::new(ray.origin, ray.direction.opposite());
+        assert!(shapes.iter().all(|shape| shape.intersect(&back).is_none()));
+    }
+}
@@ -0,0 +1,358 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::primitives::cubic_face3::{CubicFace3, Receivers};
+use crate::primitives::mesh::Mesh;
+use crate::primitives::object::Object;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::triangle3::Triangle3;
+use crate::primitives::vector::Vector3;
+
+/// Vertices closer than this distance (in meters) are merged into a single vertex
+const WELD_DISTANCE: f32 = 1e-4;
+
+/// How much more expensive it is to collapse an edge in a curved region than in a flat one
+const CURVATURE_WEIGHT: f32 = 10.;
+
+/// A collapse is refused if it turns a triangle by more than 60°
+const MIN_NORMAL_ALIGNMENT: f32 = 0.5;
+
+struct Triangle {
+    vertices: [usize; 3],
+    uvs: [(f32, f32); 3],
+    texture: TextureHandle,
+    receivers: Receivers,
+}
+
+impl Triangle {
+    fn position_of(&self, vertex: usize) -> Option<usize> {
+        self.vertices.iter().position(|v| *v == vertex)
+    }
+
+    fn same_material(&self, other: &Triangle) -> bool {
+        self.texture.same_as(&other.texture) && self.receivers == other.receivers
+    }
+}
+
+/// A possible collapse in the queue of the `Simplifier`. It is outdated when the triangles around
+/// its vertex `from` changed since its cost was computed.
+struct Candidate {
+    cost: f32,
+    from: usize,
+    to: usize,
+    stamp: u32,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The cheapest candidate is the greatest, to be at the top of the `BinaryHeap`
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then((other.from, other.to).cmp(&(self.from, self.to)))
+    }
+}
+
+/// Reduces the number of triangles of a mesh by collapsing its edges, one at a time: one end of
+/// the edge is moved onto the other one, which removes the triangles on both sides of the edge.
+///
+/// The cheapest collapse is always done first: short edges in flat regions go first, whereas the
+/// edges where the surface bends (and the borders of the mesh) are kept as long as possible.
+///
+/// The possible collapses are kept in a priority queue. A collapse only changes the triangles
+/// around the two ends of the edge, so only the costs of the collapses starting from their
+/// neighbours are computed again.
+struct Simplifier {
+    vertices: Vec<Vector3>,
+    triangles: Vec<Option<Triangle>>,
+    /// For each vertex, the indices of the triangles which use it
+    around: Vec<Vec<usize>>,
+    /// For each vertex, incremented each time the triangles around it change
+    stamps: Vec<u32>,
+    count: usize,
+}
+
+impl Simplifier {
+    fn new(mesh: &Mesh) -> Self {
+        let mut simplifier = Self { vertices: Vec::new(), triangles: Vec::new(), around: Vec::new(), stamps: Vec::new(), count: 0 };
+        let mut welded = HashMap::new();
+        for face in mesh.get_all_faces() {
+            let points = face.points();
+            let uvs = face.uvs();
+            let vertices = [0, 1, 2].map(|i| {
+                let key = [0, 1, 2].map(|c| (points[i][c] / WELD_DISTANCE).round() as i64);
+                *welded.entry(key).or_insert_with(|| {
+                    simplifier.vertices.push(points[i]);
+                    simplifier.vertices.len() - 1
+                })
+            });
+            // The triangles without area (or whose corners were welded together) are dropped
+            if simplifier.normal(vertices).is_none() {
+                continue;
+            }
+            simplifier.triangles.push(Some(Triangle {
+                vertices,
+                uvs: [uvs[0], uvs[1], uvs[2]],
+                texture: face.texture().clone(),
+                receivers: face.receivers(),
+            }));
+        }
+        simplifier.around = vec![Vec::new(); simplifier.vertices.len()];
+        simplifier.stamps = vec![0; simplifier.vertices.len()];
+        for (i, triangle) in simplifier.triangles.iter().enumerate() {
+            if let Some(triangle) = triangle {
+                triangle.vertices.iter().for_each(|v| simplifier.around[*v].push(i));
+            }
+        }
+        simplifier.count = simplifier.triangles.len();
+        simplifier
+    }
+
+    /// Normal of the triangle formed by the given vertices, or `None` if it is degenerate
+    fn normal(&self, vertices: [usize; 3]) -> Option<Vector3> {
+        let [p0, p1, p2] = vertices.map(|v| self.vertices[v]);
+        let mut normal = (p1 - p0).cross(&(p2 - p0));
+        if normal.norm() < WELD_DISTANCE * WELD_DISTANCE {
+            return None;
+        }
+        normal.normalize();
+        Some(normal)
+    }
+
+    fn triangles_around(&self, vertex: usize) -> impl Iterator<Item = &Triangle> + '_ {
+        self.around[vertex].iter().filter_map(|t| self.triangles[*t].as_ref())
+    }
+
+    /// Returns the vertices sharing a triangle with the vertex
+    fn neighbours(&self, vertex: usize) -> Vec<usize> {
+        let mut neighbours: Vec<usize> = self.triangles_around(vertex).flat_map(|t| t.vertices).filter(|v| *v != vertex).collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours
+    }
+
+    /// Returns the neighbours of the vertex along the border of the mesh (the border is made of the
+    /// edges which belong to a single triangle), none if the vertex is inside the mesh.
+    fn border_neighbours(&self, vertex: usize) -> Vec<usize> {
+        let mut edges: HashMap<usize, usize> = HashMap::new();
+        for triangle in self.triangles_around(vertex) {
+            triangle.vertices.iter().filter(|v| **v != vertex).for_each(|v| *edges.entry(*v).or_default() += 1);
+        }
+        edges.into_iter().filter(|(_, count)| *count == 1).map(|(v, _)| v).collect()
+    }
+
+    /// A vertex at the border can only slide along a straight part of the border, otherwise the
+    /// outline of the mesh would change.
+    fn slides_along_border(&self, from: usize, to: usize) -> bool {
+        let border = self.border_neighbours(from);
+        if border.is_empty() {
+            return true;
+        }
+        let mut direction = self.vertices[from].line_to(&self.vertices[to]);
+        direction.normalize();
+        border.contains(&to)
+            && border.iter().filter(|n| **n != to).all(|n| {
+                let mut other = self.vertices[from].line_to(&self.vertices[*n]);
+                other.normalize();
+                other.dot(&direction) < -1. + 1e-4
+            })
+    }
+
+    /// Returns the cost of moving the vertex `from` onto the vertex `to`, or `None` if this
+    /// collapse would damage the mesh (flip a triangle or make it degenerate).
+    fn collapse_cost(&self, from: usize, to: usize) -> Option<f32> {
+        if !self.slides_along_border(from, to) {
+            return None;
+        }
+        let triangles: Vec<&Triangle> = self.triangles_around(from).collect();
+        let reference = triangles.iter().find(|t| t.position_of(to).is_some())?;
+        let reference_normal = self.normal(reference.vertices)?;
+        let mut curvature: f32 = 0.;
+        for triangle in &triangles {
+            if !triangle.same_material(reference) {
+                return None;
+            }
+            let normal = self.normal(triangle.vertices)?;
+            curvature = curvature.max(1. - normal.dot(&reference_normal));
+            if triangle.position_of(to).is_none() {
+                let moved = triangle.vertices.map(|v| if v == from { to } else { v });
+                if self.normal(moved)?.dot(&normal) < MIN_NORMAL_ALIGNMENT {
+                    return None;
+                }
+            }
+        }
+        let length = self.vertices[from].line_to(&self.vertices[to]).norm();
+        Some(length * (1. + CURVATURE_WEIGHT * curvature))
+    }
+
+    /// Adds to the queue the possible collapses of the vertex onto its neighbours
+    fn push_candidates(&self, from: usize, queue: &mut BinaryHeap<Candidate>) {
+        for to in self.neighbours(from) {
+            if let Some(cost) = self.collapse_cost(from, to) {
+                queue.push(Candidate { cost, from, to, stamp: self.stamps[from] });
+            }
+        }
+    }
+
+    /// Moves the vertex `from` onto the vertex `to`, and returns the vertices whose triangles changed
+    fn collapse(&mut self, from: usize, to: usize) -> Vec<usize> {
+        let mut changed = self.neighbours(from);
+        changed.extend(self.neighbours(to));
+        changed.push(to);
+        // The texture coordinates of the moved corners are shifted as much as the edge, so that
+        // the texture is not distorted.
+        let shift = self
+            .triangles_around(from)
+            .find_map(|t| {
+                let (i, j) = (t.position_of(from)?, t.position_of(to)?);
+                Some((t.uvs[j].0 - t.uvs[i].0, t.uvs[j].1 - t.uvs[i].1))
+            })
+            .unwrap_or((0., 0.));
+        for index in std::mem::take(&mut self.around[from]) {
+            let Some(triangle) = &mut self.triangles[index] else { continue };
+            let i = triangle.position_of(from).expect("The triangles around a vertex use it");
+            if triangle.position_of(to).is_some() {
+                let vertices = triangle.vertices;
+                for v in vertices.into_iter().filter(|v| *v != from) {
+                    self.around[v].retain(|t| *t != index);
+                }
+                self.triangles[index] = None;
+                self.count -= 1;
+            } else {
+                triangle.vertices[i] = to;
+                triangle.uvs[i] = (triangle.uvs[i].0 + shift.0, triangle.uvs[i].1 + shift.1);
+                self.around[to].push(index);
+            }
+        }
+        changed.sort_unstable();
+        changed.dedup();
+        changed.retain(|v| *v != from);
+        for v in &changed {
+            self.stamps[*v] += 1;
+        }
+        self.stamps[from] += 1;
+        changed
+    }
+
+    /// Collapses the cheapest edges until the mesh has at most `max_faces` triangles, or no
+    /// collapse is possible anymore
+    fn simplify(&mut self, max_faces: usize) {
+        let mut queue = BinaryHeap::new();
+        for vertex in 0..self.vertices.len() {
+            self.push_candidates(vertex, &mut queue);
+        }
+        while self.count > max_faces {
+            let Some(candidate) = queue.pop() else {
+                break;
+            };
+            if candidate.stamp != self.stamps[candidate.from] {
+                continue;
+            }
+            for vertex in self.collapse(candidate.from, candidate.to) {
+                self.push_candidates(vertex, &mut queue);
+            }
+        }
+    }
+
+    fn into_mesh(self) -> Mesh {
+        let triangles = self
+            .triangles
+            .iter()
+            .flatten()
+            .filter_map(|t| {
+                let points = t.vertices.map(|v| self.vertices[v]);
+                let normal = self.normal(t.vertices)?;
+                Some(Triangle3::from_face(CubicFace3::triangle(points, t.uvs, normal, &t.texture).with_receivers(t.receivers)))
+            })
+            .collect();
+        Mesh::new(triangles)
+    }
+}
+
+impl Mesh {
+    /// Returns a simplified copy of the mesh, with at most `max_faces` triangles: small triangles
+    /// and flat regions are merged first (see `Simplifier`). The triangles without area are
+    /// dropped.
+    ///
+    /// Collapses which would flip a triangle or shrink the border of the mesh are never done, so
+    /// the result may have more triangles than asked.
+    pub fn simplified(&self, max_faces: usize) -> Mesh {
+        let mut simplifier = Simplifier::new(self);
+        simplifier.simplify(max_faces);
+        simplifier.into_mesh()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::mesh::Mesh;
+    use crate::primitives::object::Object;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+
+    /// Square grid of `n` x `n` cells on the ground, with the given height at each vertex
+    fn grid(n: usize, height: impl Fn(usize, usize) -> f32) -> Mesh {
+        let vertices: Vec<Vector3> = (0..=n)
+            .flat_map(|i| (0..=n).map(move |j| (i, j)))
+            .map(|(i, j)| Vector3::new(i as f32, j as f32, -height(i, j)))
+            .collect();
+        let index = |i: usize, j: usize| i * (n + 1) + j;
+        let indices: Vec<[usize; 3]> = (0..n)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .flat_map(|(i, j)| [[index(i, j), index(i, j + 1), index(i + 1, j + 1)], [index(i, j), index(i + 1, j + 1), index(i + 1, j)]])
+            .collect();
+        Mesh::from_indices(&vertices, &indices, &YELLOW)
+    }
+
+    fn area(mesh: &Mesh) -> f32 {
+        mesh.get_all_faces()
+            .iter()
+            .map(|f| {
+                let [p0, p1, p2, _] = f.points();
+                (p1 - p0).cross(&(p2 - p0)).norm() / 2.
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_flat_mesh() {
+        let mesh = grid(4, |_, _| 0.);
+        assert_eq!(32, mesh.get_all_faces().len());
+        let simplified = mesh.simplified(2);
+        assert_eq!(2, simplified.get_all_faces().len());
+        // The border is preserved, and no triangle was flipped
+        assert!((area(&simplified) - 16.).abs() < 1e-3);
+        let normal = *mesh.get_all_faces()[0].normal();
+        assert!(simplified.get_all_faces().iter().all(|f| (f.normal().dot(&normal) - 1.).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_degenerate_triangles() {
+        // A flat triangle, and a triangle whose corners are welded together
+        let vertices = [Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.), Vector3::new(2., 0., 0.), Vector3::new(0., 1., 0.), Vector3::new(0., 1., 1e-6)];
+        let mesh = Mesh::from_indices(&vertices, &[[0, 1, 2], [0, 3, 4], [0, 1, 3]], &YELLOW);
+        let simplified = mesh.simplified(1);
+        assert_eq!(1, simplified.get_all_faces().len());
+        assert!((area(&simplified) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_curved_regions_are_kept() {
+        let peak = Vector3::new(2., 2., -1.);
+        let mesh = grid(4, |i, j| if (i, j) == (2, 2) { 1. } else { 0. });
+        let simplified = mesh.simplified(12);
+        assert!(simplified.get_all_faces().len() <= 12);
+        assert!(simplified.get_all_faces().iter().any(|f| f.points()[..3].contains(&peak)));
+    }
+}
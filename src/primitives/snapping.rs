@@ -0,0 +1,74 @@
+use crate::primitives::vector::Vector3;
+
+/// A regular grid, to which the vertices of the objects can be snapped.
+///
+/// The faces are built with floats: two faces which should share an edge can end up a tiny
+/// distance away from each other, which shows as hairline cracks when they are rendered. Snapping
+/// the vertices to a common grid makes the shared points exactly equal again.
+#[derive(Clone, Copy, Debug)]
+pub struct Grid {
+    step: f32,
+}
+
+impl Grid {
+    /// Creates a grid whose cells are `step` meters wide, aligned on the origin
+    pub fn new(step: f32) -> Self {
+        assert!(step > 0., "The step of the grid must be positive");
+        Self { step }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Returns the node of the grid closest to the value, on a single axis
+    pub fn snap_value(&self, value: f32) -> f32 {
+        (value / self.step).round() * self.step
+    }
+
+    /// Returns the node of the grid closest to the point
+    pub fn snap(&self, point: Vector3) -> Vector3 {
+        Vector3::new(self.snap_value(point.x()), self.snap_value(point.y()), self.snap_value(point.z()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::object::Object;
+    use crate::primitives::snapping::Grid;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_snap_point() {
+        let grid = Grid::new(0.25);
+        assert!(grid.snap(Vector3::new(0.13, -0.37, 1.01)) == Vector3::new(0.25, -0.25, 1.));
+        assert_eq!(0.1, Grid::new(0.1).snap_value(0.1 + 0.2 - 0.2));
+    }
+
+    #[test]
+    fn test_snapped_faces_share_edges() {
+        // The second face is placed with some accumulated float error
+        let offset = (0..10).fold(0., |acc, _| acc + 0.1);
+        assert_ne!(1., offset);
+        let mut left = CubicFace3::vface_from_line(Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.));
+        let mut right = CubicFace3::vface_from_line(Vector3::new(offset, 0., 0.), Vector3::new(2., 0., 0.));
+        assert!(left.points()[1] != right.points()[0]);
+
+        let grid = Grid::new(0.01);
+        left.snap(&grid);
+        right.snap(&grid);
+        assert!(left.points()[1] == right.points()[0]);
+        assert!(left.points()[2] == right.points()[3]);
+
+        // Snapping an object snaps all its faces
+        let mut cube = Cube3::minecraft_like(Vector3::new(0.3333, 0.6667, 0.), &YELLOW, &YELLOW);
+        cube.snap(&Grid::new(1. / 3.));
+        let snapped = cube.get_all_faces().iter().flat_map(|f| f.points()).all(|p| {
+            [p.x(), p.y(), p.z()].iter().all(|c| (c * 3. - (c * 3.).round()).abs() < 1e-5)
+        });
+        assert!(snapped);
+    }
+}
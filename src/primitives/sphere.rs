@@ -0,0 +1,112 @@
+use std::f32::consts::PI;
+
+use crate::content_hash::ContentHasher;
+use crate::primitives::color::Color;
+use crate::primitives::ray::{Hit, Intersectable, Ray, RaytraceObject};
+use crate::primitives::textures::colored::YELLOW;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::vector::Vector3;
+
+/// A sphere, given by its center and its radius
+#[derive(Clone)]
+pub struct Sphere {
+    center: Vector3,
+    radius: f32,
+    /// The u-coordinate goes around the vertical axis and the v-coordinate from the top to the
+    /// bottom, both in meters along the surface.
+    texture: TextureHandle,
+}
+
+impl Sphere {
+    pub fn new(center: Vector3, radius: f32) -> Self {
+        Self { center, radius, texture: YELLOW.clone() }
+    }
+
+    pub fn with_texture(mut self, texture: &TextureHandle) -> Self {
+        self.texture = texture.clone();
+        self
+    }
+
+    pub fn center(&self) -> &Vector3 {
+        &self.center
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn texture(&self) -> &TextureHandle {
+        &self.texture
+    }
+
+    /// Returns the texture coordinates of a point of the surface
+    pub fn uv_at(&self, point: &Vector3) -> (f32, f32) {
+        let local = self.center.line_to(point);
+        let longitude = local.y().atan2(local.x()) + PI;
+        // The z-axis goes down
+        let latitude = (local.z() / self.radius).clamp(-1., 1.).acos();
+        (longitude * self.radius, (PI - latitude) * self.radius)
+    }
+}
+
+impl Intersectable for Sphere {
+    /// When the origin of the ray is inside the sphere, the hit is where the ray leaves it
+    fn intersect(&self, ray: &Ray) -> Option<Hit> {
+        // Solves |origin + t * direction - center|² = radius², with t >= 0
+        let to_origin = self.center.line_to(&ray.origin);
+        let a = ray.direction.dot(&ray.direction);
+        let half_b = to_origin.dot(&ray.direction);
+        let c = to_origin.dot(&to_origin) - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if a == 0. || discriminant < 0. {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let t = [(-half_b - root) / a, (-half_b + root) / a].into_iter().find(|t| *t >= 0.)?;
+        Some(Hit::along(ray, t * a.sqrt()))
+    }
+}
+
+impl RaytraceObject for Sphere {
+    fn color_at(&self, point: &Vector3) -> Color {
+        let (u, v) = self.uv_at(point);
+        self.texture.sample(u, v)
+    }
+
+    fn normal_at(&self, point: &Vector3) -> Vector3 {
+        self.center.line_to(point) * (1. / self.radius)
+    }
+
+    fn write_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write_vector(&self.center);
+        hasher.write_f32(self.radius);
+        hasher.write_texture(&*self.texture);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::color::Color;
+    use crate::primitives::ray::RaytraceObject;
+    use crate::primitives::sphere::Sphere;
+    use crate::primitives::textures::colored::ColoredTexture;
+    use crate::primitives::textures::TextureHandle;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_sphere_surface() {
+        let red = TextureHandle::new(ColoredTexture::new(Color::new(255, 0, 0, 255)));
+        let sphere = Sphere::new(Vector3::new(1., 1., 1.), 2.).with_texture(&red);
+        let side = Vector3::new(3., 1., 1.);
+        assert_eq!([255, 0, 0, 255], sphere.color_at(&side).rgba());
+        assert_eq!(Vector3::new(1., 0., 0.), sphere.normal_at(&side));
+
+        // The v-coordinate goes from the top (z = -1) to the bottom (z = 3) of the sphere
+        let (_, top) = sphere.uv_at(&Vector3::new(1., 1., -1.));
+        let (_, equator) = sphere.uv_at(&side);
+        let (_, bottom) = sphere.uv_at(&Vector3::new(1., 1., 3.));
+        // Half of the circumference of the sphere
+        let meridian = std::f32::consts::PI * 2.;
+        assert!(top.abs() < 1e-5 && (equator - meridian / 2.).abs() < 1e-5 && (bottom - meridian).abs() < 1e-5);
+    }
+}
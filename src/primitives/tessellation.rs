@@ -0,0 +1,198 @@
+use std::f32::consts::PI;
+
+use crate::primitives::camera::Camera;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::object::{Object, ObjectKind};
+use crate::primitives::snapping::Grid;
+use crate::primitives::transformation::Transform;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::vector::{Vector3, UNIT_Y, UNIT_Z};
+
+/// A curved surface, approximated by a ring of flat quads (and triangles for the parts which
+/// are not parallelograms). The more segments, the smoother the surface.
+///
+/// The texture coordinates follow the surface: around a cylinder, u is the distance along the
+/// circumference, so that the texture is not stretched.
+pub struct Patch {
+    faces: Vec<CubicFace3>,
+}
+
+impl Patch {
+    pub fn new(faces: Vec<CubicFace3>) -> Self {
+        Self { faces }
+    }
+
+    /// Creates a vertical cylinder, closed at both ends, standing on `base` (the center of its
+    /// bottom disk). The z-axis goes down, so the top is `height` meters above the base.
+    pub fn cylinder(base: Vector3, radius: f32, height: f32, segments: usize, texture: &TextureHandle) -> Self {
+        assert!(segments >= 3, "A cylinder needs at least 3 segments");
+        let up = UNIT_Z.opposite() * height;
+        let top = base + up;
+        let angle = |i: usize| 2. * PI * i as f32 / segments as f32;
+        let point = |i: usize| base + radial(angle(i)) * radius;
+        let side = 2. * radius * (PI / segments as f32).sin();
+        let mut faces = Vec::with_capacity(3 * segments);
+        for i in 0..segments {
+            let (b0, b1) = (point(i), point(i + 1));
+            let (u0, u1) = (i as f32 * side, (i + 1) as f32 * side);
+            // The normal of a quad points towards the middle of its segment
+            let normal = radial((angle(i) + angle(i + 1)) / 2.);
+            faces.push(CubicFace3::new_with_uvs(
+                [b0, b1, b1 + up, b0 + up],
+                [(u0, 0.), (u1, 0.), (u1, height), (u0, height)],
+                normal,
+                texture,
+            ));
+            // The disks are made of triangles going to their center
+            let planar = |p: Vector3| (p.x() - base.x(), p.y() - base.y());
+            faces.push(CubicFace3::triangle([base, b1, b0], [planar(base), planar(b1), planar(b0)], UNIT_Z, texture));
+            faces.push(CubicFace3::triangle([top, b0 + up, b1 + up], [planar(top), planar(b0), planar(b1)], UNIT_Z.opposite(), texture));
+        }
+        Self::new(faces)
+    }
+
+    /// Creates a semicircular arch, in the vertical plane going along the x-axis. `center` is the
+    /// center of the circle at the bottom of the arch, on its front side: the arch goes from
+    /// `center.y` to `center.y + depth`.
+    pub fn arch(center: Vector3, inner_radius: f32, outer_radius: f32, depth: f32, segments: usize, texture: &TextureHandle) -> Self {
+        assert!(segments >= 1, "An arch needs at least 1 segment");
+        assert!(inner_radius < outer_radius, "The inner radius must be smaller than the outer radius");
+        // Direction of the point of the arch at the given angle (0 = along x, PI / 2 = up)
+        let direction = |i: usize| {
+            let angle = PI * i as f32 / segments as f32;
+            Vector3::new(angle.cos(), 0., -angle.sin())
+        };
+        let back = UNIT_Y * depth;
+        let mut faces = Vec::with_capacity(6 * segments + 2);
+        for i in 0..segments {
+            let (d0, d1) = (direction(i), direction(i + 1));
+            let (i0, i1) = (center + d0 * inner_radius, center + d1 * inner_radius);
+            let (o0, o1) = (center + d0 * outer_radius, center + d1 * outer_radius);
+            let middle = (d0 + d1) / 2.;
+            // Inner surface (facing the opening), then outer surface
+            for (p0, p1, normal) in [(i1, i0, middle.opposite()), (o0, o1, middle)] {
+                let side = (p1 - p0).norm();
+                let u = i as f32 * side;
+                faces.push(CubicFace3::new_with_uvs(
+                    [p0, p1, p1 + back, p0 + back],
+                    [(u, 0.), (u + side, 0.), (u + side, depth), (u, depth)],
+                    normal,
+                    texture,
+                ));
+            }
+            // The front and the back are trapezoids, split in two triangles
+            let planar = |p: Vector3| (p.x() - center.x(), center.z() - p.z());
+            for (offset, normal) in [(Vector3::empty(), UNIT_Y.opposite()), (back, UNIT_Y)] {
+                let [a, b, c, d] = [i0, o0, o1, i1].map(|p| p + offset);
+                let uvs = [i0, o0, o1, i1].map(planar);
+                faces.push(CubicFace3::triangle([a, b, c], [uvs[0], uvs[1], uvs[2]], normal, texture));
+                faces.push(CubicFace3::triangle([a, c, d], [uvs[0], uvs[2], uvs[3]], normal, texture));
+            }
+        }
+        // The feet of the arch, facing down
+        for d in [direction(0), direction(segments)] {
+            let (inner, outer) = (center + d * inner_radius, center + d * outer_radius);
+            let width = outer_radius - inner_radius;
+            faces.push(CubicFace3::new_with_uvs(
+                [inner, outer, outer + back, inner + back],
+                [(0., 0.), (width, 0.), (width, depth), (0., depth)],
+                UNIT_Z,
+                texture,
+            ));
+        }
+        Self::new(faces)
+    }
+}
+
+/// Horizontal unit vector at the given angle from the x-axis
+fn radial(angle: f32) -> Vector3 {
+    Vector3::new(angle.cos(), angle.sin(), 0.)
+}
+
+impl Object for Patch {
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Patch
+    }
+
+    fn get_visible_faces(&self, camera: &Camera) -> Vec<&CubicFace3> {
+        self.faces.iter().filter(|f| f.is_visible_from(camera)).collect()
+    }
+
+    fn get_all_faces(&self) -> Vec<&CubicFace3> {
+        self.faces.iter().collect()
+    }
+
+    fn rotate(&mut self, by: f32) {
+        for face in &mut self.faces {
+            face.rotate(by);
+        }
+    }
+
+    fn transform(&mut self, transform: &Transform) {
+        for face in &mut self.faces {
+            face.transform(transform);
+        }
+    }
+
+    fn set_faces(&mut self, faces: &[CubicFace3]) {
+        self.faces.clone_from_slice(faces);
+    }
+
+    fn snap(&mut self, grid: &Grid) {
+        for face in &mut self.faces {
+            face.snap(grid);
+        }
+    }
+
+    /// The surface is not necessarily convex: the sphere collides if it touches any face.
+    fn collides_with(&self, point: &Vector3, radius: f32) -> bool {
+        self.faces.iter().any(|f| f.collides_with(point, radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::object::Object;
+    use crate::primitives::tessellation::Patch;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+
+    #[test]
+    fn test_cylinder() {
+        let base = Vector3::new(1., 2., 0.);
+        let cylinder = Patch::cylinder(base, 0.5, 2., 8, &YELLOW);
+        let faces = cylinder.get_all_faces();
+        assert_eq!(24, faces.len());
+
+        // All the normals point outward
+        let middle = base + Vector3::new(0., 0., -1.);
+        assert!(faces.iter().all(|f| middle.line_to(&f.center()).dot(f.normal()) > 0.));
+
+        // The texture continues from one side to the next one
+        let sides: Vec<_> = faces.iter().filter(|f| !f.is_triangle()).collect();
+        for pair in sides.windows(2) {
+            assert!((pair[0].uvs()[1].0 - pair[1].uvs()[0].0).abs() < 1e-5);
+        }
+        // Next to the middle of the first side
+        let side = Vector3::new(0.6 * 0.924, 0.6 * 0.383, -1.);
+        assert!(cylinder.collides_with(&(base + side), 0.2));
+        assert!(!cylinder.collides_with(&(base + side * 2.), 0.2));
+    }
+
+    #[test]
+    fn test_arch() {
+        let center = Vector3::new(0., 0., 0.);
+        let arch = Patch::arch(center, 1., 1.5, 0.5, 6, &YELLOW);
+        let faces = arch.get_all_faces();
+        assert_eq!(38, faces.len());
+
+        // The faces of the opening look towards the center, the outer ones away from it (the feet
+        // of the arch are on the ground)
+        let axis = Vector3::new(0., 0.25, 0.);
+        for face in faces.iter().filter(|f| !f.is_triangle() && f.center().z() < -0.01) {
+            let to_face = axis.line_to(&face.center());
+            let outward = to_face.dot(face.normal()) > 0.;
+            assert_eq!(to_face.norm() > 1.25, outward, "{face:?}");
+        }
+    }
+}
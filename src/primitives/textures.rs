@@ -1,14 +1,155 @@
+use std::ops::Deref;
+use std::sync::{Arc, Weak};
+
+use serde::{Deserialize, Serialize};
+
 use crate::primitives::color::Color;
+use crate::tunables::tunables;
 
 pub mod bw;
 pub mod colored;
+pub mod image;
+pub mod palette;
 pub mod pixelated;
 
+/// How a texture made of texels is sampled between the centers of its texels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Filtering {
+    /// The color of the texel under the point: sharp, but shimmering when seen from afar
+    #[default]
+    Nearest,
+    /// The colors of the 4 closest texels, weighted by their distance to the point
+    Bilinear,
+}
+
 /// A texture is an interface that defines how to be rendered on the screen
-pub trait Texture {
+pub trait Texture: Send + Sync {
     fn width(&self) -> f32;
     fn height(&self) -> f32;
     /// Returns the color at the provided pixel coordinates, where
     /// u and v are expressed in the polygon's reference frame
     fn color_at(&self, u: f32, v: f32) -> &Color;
+
+    /// Size of the texels (in meters), for the textures made of texels
+    fn texel_size(&self) -> Option<f32> {
+        None
+    }
+
+    /// Filtering selected for this texture, instead of the one of the tunables
+    fn filtering(&self) -> Option<Filtering> {
+        None
+    }
+
+    /// Returns the color at the coordinates, filtered as selected by the texture (or else by the
+    /// tunables). The coordinates of the faces come from their perspective projection, so the
+    /// filtering is perspective correct.
+    fn sample(&self, u: f32, v: f32) -> Color {
+        let filtering = self.filtering().unwrap_or(tunables().texture_filtering);
+        let Some(size) = self.texel_size().filter(|_| filtering == Filtering::Bilinear) else {
+            return self.color_at(u, v).clone();
+        };
+        // Position of the point between the centers of the 4 closest texels
+        let (x, y) = (u / size - 0.5, v / size - 0.5);
+        let (tx, ty) = (x - x.floor(), y - y.floor());
+        let (u0, v0) = ((x.floor() + 0.5) * size, (y.floor() + 0.5) * size);
+        let mut rgba = [0.; 4];
+        for (du, dv, weight) in [(0., 0., (1. - tx) * (1. - ty)), (size, 0., tx * (1. - ty)), (0., size, (1. - tx) * ty), (size, size, tx * ty)] {
+            for (channel, value) in rgba.iter_mut().zip(self.color_at(u0 + du, v0 + dv).rgba()) {
+                *channel += weight * value as f32;
+            }
+        }
+        Color::from_rgba(rgba.map(|channel| channel.round() as u8))
+    }
+}
+
+/// A shared texture. Cloning the handle does not copy the texture, which is dropped with the
+/// last handle referring to it.
+#[derive(Clone)]
+pub struct TextureHandle(Arc<dyn Texture>);
+
+impl TextureHandle {
+    pub fn new(texture: impl Texture + 'static) -> Self {
+        Self(Arc::new(texture))
+    }
+
+    /// Returns true if both handles refer to the same texture
+    pub fn same_as(&self, other: &TextureHandle) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Returns a handle which does not keep the texture alive
+    pub fn downgrade(&self) -> WeakTextureHandle {
+        WeakTextureHandle(Arc::downgrade(&self.0))
+    }
+}
+
+/// A reference to a texture which does not prevent it from being dropped
+pub struct WeakTextureHandle(Weak<dyn Texture>);
+
+impl WeakTextureHandle {
+    /// Returns the texture, if it is still used somewhere
+    pub fn upgrade(&self) -> Option<TextureHandle> {
+        self.0.upgrade().map(TextureHandle)
+    }
+}
+
+impl Deref for TextureHandle {
+    type Target = dyn Texture;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use crate::primitives::color::Color;
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::textures::{Texture, TextureHandle};
+    use crate::primitives::vector::Vector3;
+
+    struct DropTracker {
+        color: Color,
+        dropped: Arc<AtomicBool>,
+    }
+
+    impl Texture for DropTracker {
+        fn width(&self) -> f32 {
+            1.
+        }
+
+        fn height(&self) -> f32 {
+            1.
+        }
+
+        fn color_at(&self, _u: f32, _v: f32) -> &Color {
+            &self.color
+        }
+    }
+
+    impl Drop for DropTracker {
+        fn drop(&mut self) {
+            self.dropped.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_texture_dropped_with_last_face() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let texture = TextureHandle::new(DropTracker { color: Color::black(), dropped: dropped.clone() });
+        let mut face = CubicFace3::vface_from_line(Vector3::newi2(0, 0), Vector3::newi2(1, 0));
+        face.set_texture(&texture);
+        let copy = face.clone();
+        assert!(copy.texture().same_as(&texture));
+
+        drop(texture);
+        drop(face);
+        assert!(!dropped.load(Ordering::SeqCst));
+        drop(copy);
+        assert!(dropped.load(Ordering::SeqCst));
+    }
 }
@@ -1,5 +1,7 @@
+use std::sync::LazyLock;
+
 use crate::primitives::color::Color;
-use crate::primitives::textures::Texture;
+use crate::primitives::textures::{Texture, TextureHandle};
 
 /// A simple texture which onl displays 1 color
 #[derive(Clone)]
@@ -27,8 +29,8 @@ impl Texture for ColoredTexture {
     }
 }
 
-// Define most basic textures as static variables
-pub static YELLOW: ColoredTexture = ColoredTexture::new(Color::yellow());
-pub static BLACK: ColoredTexture = ColoredTexture::new(Color::black());
-pub static PURPLE: ColoredTexture = ColoredTexture::new(Color::purple());
-pub static ORANGE: ColoredTexture = ColoredTexture::new(Color::orange());
+// Define most basic textures as static variables, shared by all the faces using them
+pub static YELLOW: LazyLock<TextureHandle> = LazyLock::new(|| TextureHandle::new(ColoredTexture::new(Color::yellow())));
+pub static BLACK: LazyLock<TextureHandle> = LazyLock::new(|| TextureHandle::new(ColoredTexture::new(Color::black())));
+pub static PURPLE: LazyLock<TextureHandle> = LazyLock::new(|| TextureHandle::new(ColoredTexture::new(Color::purple())));
+pub static ORANGE: LazyLock<TextureHandle> = LazyLock::new(|| TextureHandle::new(ColoredTexture::new(Color::orange())));
@@ -0,0 +1,172 @@
+use crate::primitives::color::Color;
+
+use super::{Filtering, Texture};
+
+/// How the texture coordinates outside of the image are mapped to its pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wrap {
+    /// The image is tiled
+    Repeat,
+    /// The image is tiled, every other tile being mirrored, so that the tiles are seamless
+    Mirror,
+    /// The pixels of the border of the image are extended
+    Clamp,
+}
+
+/// A texture showing an image (PNG or JPEG).
+///
+/// As for `Pixelated`, the u-coordinate goes along the rows of the image (from left to right)
+/// and the v-coordinate goes along its columns (from top to bottom).
+pub struct ImageTexture {
+    rows: usize,
+    cols: usize,
+    /// Size of a pixel of the image, in meters
+    pixel_size: f32,
+    wrap: Wrap,
+    /// Filtering of this image, instead of the one of the tunables
+    filtering: Option<Filtering>,
+    /// Color of each pixel, row after row
+    colors: Vec<Color>,
+}
+
+impl ImageTexture {
+    /// Creates a texture from RGBA pixels, row after row. By default, the image is repeated and
+    /// is 1 meter wide.
+    pub fn from_rgba(width: usize, height: usize, pixels: &[u8]) -> Self {
+        assert_eq!(4 * width * height, pixels.len(), "The image must have 4 bytes per pixel");
+        Self {
+            rows: height,
+            cols: width,
+            pixel_size: 1. / width as f32,
+            wrap: Wrap::Repeat,
+            filtering: None,
+            colors: pixels.chunks_exact(4).map(|p| Color::new(p[0], p[1], p[2], p[3])).collect(),
+        }
+    }
+
+    /// Decodes an image file (PNG or JPEG)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let image = image::load_from_memory(bytes).map_err(|err| err.to_string())?.to_rgba8();
+        if image.width() == 0 || image.height() == 0 {
+            return Err("The image is empty".to_string());
+        }
+        Ok(Self::from_rgba(image.width() as usize, image.height() as usize, image.as_raw()))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|err| format!("{path}: {err}"))?;
+        Self::from_bytes(&bytes).map_err(|err| format!("{path}: {err}"))
+    }
+
+    /// Sets the width (in meters) of the image on the faces, its aspect ratio being kept
+    pub fn with_tile_width(mut self, width: f32) -> Self {
+        self.pixel_size = width / self.cols as f32;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_filtering(mut self, filtering: Filtering) -> Self {
+        self.filtering = Some(filtering);
+        self
+    }
+
+    /// Returns the index of the pixel at this coordinate (in pixels), along an axis of `n` pixels
+    fn wrap_index(&self, coordinate: f32, n: usize) -> usize {
+        let i = coordinate.floor() as i64;
+        let n = n as i64;
+        let wrapped = match self.wrap {
+            Wrap::Repeat => i.rem_euclid(n),
+            Wrap::Mirror => {
+                let i = i.rem_euclid(2 * n);
+                if i < n { i } else { 2 * n - 1 - i }
+            }
+            Wrap::Clamp => i.clamp(0, n - 1),
+        };
+        wrapped as usize
+    }
+}
+
+impl Texture for ImageTexture {
+    fn width(&self) -> f32 {
+        (self.rows as f32) * self.pixel_size
+    }
+
+    fn height(&self) -> f32 {
+        (self.cols as f32) * self.pixel_size
+    }
+
+    fn color_at(&self, u: f32, v: f32) -> &Color {
+        let i = self.wrap_index(v / self.pixel_size, self.rows);
+        let j = self.wrap_index(u / self.pixel_size, self.cols);
+        &self.colors[i * self.cols + j]
+    }
+
+    fn texel_size(&self) -> Option<f32> {
+        Some(self.pixel_size)
+    }
+
+    fn filtering(&self) -> Option<Filtering> {
+        self.filtering
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::png_saver;
+    use crate::primitives::textures::image::{ImageTexture, Wrap};
+    use crate::primitives::textures::{Filtering, Texture};
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    #[test]
+    fn test_wrap_modes() {
+        // One row of 3 pixels, 3 meters wide
+        let pixels = [RED, GREEN, BLUE].concat();
+        let texture = ImageTexture::from_rgba(3, 1, &pixels).with_tile_width(3.);
+        assert_eq!(3., texture.height());
+        assert_eq!(GREEN, texture.color_at(1.5, 0.).rgba());
+        assert_eq!(RED, texture.color_at(3.5, 0.).rgba());
+
+        let texture = texture.with_wrap(Wrap::Mirror);
+        assert_eq!(BLUE, texture.color_at(3.5, 0.).rgba());
+        assert_eq!(RED, texture.color_at(-0.5, 0.).rgba());
+
+        let texture = texture.with_wrap(Wrap::Clamp);
+        assert_eq!(BLUE, texture.color_at(10., 0.).rgba());
+        assert_eq!(RED, texture.color_at(-10., 0.).rgba());
+    }
+
+    #[test]
+    fn test_bilinear_filtering() {
+        // One row of 2 pixels, 2 meters wide: the centers of the pixels are at 0.5 and 1.5
+        let pixels = [RED, BLUE].concat();
+        let texture = ImageTexture::from_rgba(2, 1, &pixels).with_tile_width(2.).with_wrap(Wrap::Clamp);
+        assert_eq!(RED, texture.sample(0.75, 0.5).rgba());
+
+        let texture = texture.with_filtering(Filtering::Bilinear);
+        assert_eq!(RED, texture.sample(0.5, 0.5).rgba());
+        assert_eq!([128, 0, 128, 255], texture.sample(1., 0.5).rgba());
+        assert_eq!([64, 0, 191, 255], texture.sample(1.25, 0.2).rgba());
+        // Beyond the centers of the border pixels, the clamped image is not blended
+        assert_eq!(BLUE, texture.sample(1.9, 0.5).rgba());
+    }
+
+    #[test]
+    fn test_decode_png() {
+        // 2x2 image, whose top row is red and green (the encoder takes the rows from the bottom)
+        let mut png = Vec::new();
+        png_saver::write(&mut png, &[BLUE, BLUE, RED, GREEN].concat(), 2, 2).unwrap();
+        let texture = ImageTexture::from_bytes(&png).unwrap();
+        assert_eq!(RED, texture.color_at(0.1, 0.1).rgba());
+        assert_eq!(GREEN, texture.color_at(0.6, 0.1).rgba());
+        assert_eq!(BLUE, texture.color_at(0.6, 0.6).rgba());
+
+        assert!(ImageTexture::from_bytes(b"not an image").is_err());
+    }
+}
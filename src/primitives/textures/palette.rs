@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::primitives::color::Color;
+
+/// Name of the palette used by the minecraft-like textures
+pub const DEFAULT_PALETTE: &str = "default";
+
+/// A list of colors, each of them associated with the symbol used to describe textures.
+///
+/// Palettes are shared between textures, which only store the index of the colors they use.
+pub struct Palette {
+    symbols: Vec<char>,
+    colors: Vec<Color>,
+}
+
+impl Palette {
+    pub fn new(entries: Vec<(char, Color)>) -> Self {
+        assert!(entries.len() <= u8::MAX as usize + 1, "A palette holds at most 256 colors");
+        let (symbols, colors) = entries.into_iter().unzip();
+        Self { symbols, colors }
+    }
+
+    /// Returns the index of the color associated with the symbol
+    pub fn index_of(&self, symbol: char) -> Option<u8> {
+        self.symbols.iter().position(|s| *s == symbol).map(|i| i as u8)
+    }
+
+    pub fn color(&self, index: u8) -> &Color {
+        &self.colors[index as usize]
+    }
+}
+
+fn registry() -> &'static Mutex<HashMap<String, Arc<Palette>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, Arc<Palette>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Registers a palette under the given name, so that it can be shared by all the textures.
+/// If a palette was already registered with this name, it is returned instead.
+pub fn register_palette(name: &str, palette: Palette) -> Arc<Palette> {
+    registry().lock().unwrap().entry(name.to_string()).or_insert_with(|| Arc::new(palette)).clone()
+}
+
+pub fn palette(name: &str) -> Option<Arc<Palette>> {
+    registry().lock().unwrap().get(name).cloned()
+}
+
+/// Returns the palette of the minecraft-like textures
+pub fn default_palette() -> Arc<Palette> {
+    palette(DEFAULT_PALETTE).unwrap_or_else(|| register_palette(DEFAULT_PALETTE, Palette::new(Color::library())))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::primitives::color::Color;
+    use crate::primitives::textures::palette::{default_palette, palette, register_palette, Palette};
+
+    #[test]
+    fn test_palette_registry() {
+        let p = register_palette("test", Palette::new(vec![('a', Color::red()), ('b', Color::green())]));
+        assert_eq!(Some(1), p.index_of('b'));
+        assert_eq!(None, p.index_of('c'));
+        assert_eq!(Color::green().rgba(), p.color(1).rgba());
+
+        // The registered palette is shared
+        assert!(Arc::ptr_eq(&p, &palette("test").unwrap()));
+        assert!(Arc::ptr_eq(&p, &register_palette("test", Palette::new(vec![]))));
+        assert!(Arc::ptr_eq(&default_palette(), &default_palette()));
+    }
+}
@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::sync::Arc;
 use std::usize;
 use crate::primitives::color::Color;
+use crate::primitives::textures::palette::{default_palette, Palette};
 
 use super::Texture;
 
@@ -8,29 +9,77 @@ pub struct Pixelated {
     rows: usize,
     cols: usize,
     pixel_size: f32,
-    /// Holds the pattern of colors to use
-    pixels: Vec<Vec<char>>,
-    /// Holds the colors used by this texture
-    colors: HashMap<char, Color>
+    /// Inverse of the pixel size, to avoid divisions when sampling
+    inv_pixel_size: f32,
+    /// Holds the pattern of colors to use, as indices in the palette (row after row)
+    texels: Vec<u8>,
+    /// Holds the colors used by this texture (shared with other textures)
+    palette: Arc<Palette>,
+    /// Color of each texel (row after row), resolved once from the palette for fast sampling
+    lut: Vec<Color>,
 }
 
 impl Pixelated {
 
     pub fn new(lines: Vec<String>, pixel_size: f32) -> Self {
+        Self::new_with_palette(lines, pixel_size, default_palette())
+    }
+
+    /// Creates a texture where each character of the lines is the symbol of a color of the palette
+    pub fn new_with_palette(lines: Vec<String>, pixel_size: f32, palette: Arc<Palette>) -> Self {
         let rows = lines.len();
         let cols = lines[0].len();
-        let mut pixels = vec![];
+        let mut texels = Vec::with_capacity(rows * cols);
         for line in lines {
-            let row = line.chars().collect();
-            pixels.push(row);
+            assert_eq!(cols, line.chars().count(), "All the lines of a texture must have the same length");
+            for c in line.chars() {
+                texels.push(palette.index_of(c).unwrap_or_else(|| panic!("Unknown color symbol '{c}'")));
+            }
         }
+        let lut = texels.iter().map(|i| palette.color(*i).clone()).collect();
         Self {
             rows,
             cols,
             pixel_size,
-            pixels,
-            colors: Color::create_colors_library()
+            inv_pixel_size: 1. / pixel_size,
+            texels,
+            palette,
+            lut,
+        }
+    }
+
+    /// Parses a texture file: the first line is the size of the pixels, and each following line
+    /// is a row of texels, given by the symbols of the colors of the default palette.
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let pixel_size: f32 = lines
+            .next()
+            .and_then(|line| line.parse().ok())
+            .ok_or("The first line must be the size of the pixels")?;
+        let lines: Vec<String> = lines.map(str::to_string).collect();
+        let cols = lines.first().ok_or("The texture has no pixels")?.chars().count();
+        if lines.iter().any(|line| line.chars().count() != cols) {
+            return Err("All the lines of a texture must have the same length".to_string());
+        }
+        let palette = default_palette();
+        if let Some(c) = lines.iter().flat_map(|line| line.chars()).find(|c| palette.index_of(*c).is_none()) {
+            return Err(format!("Unknown color symbol '{c}'"));
         }
+        Ok(Self::new_with_palette(lines, pixel_size, palette))
+    }
+
+    /// Reference implementation of the sampling, going through the palette with float modulos.
+    /// `color_at` is the fast path, this one is kept to compare against it.
+    pub fn color_at_reference(&self, u: f32, v: f32) -> &Color {
+        // Compute the coordinates inside the primitive rectangle: v goes along the rows, and u
+        // along the columns
+        let x = v.rem_euclid(self.rows as f32 * self.pixel_size);
+        let y = u.rem_euclid(self.cols as f32 * self.pixel_size);
+        // Compute the index in the array of pixels
+        let i = ((x / self.pixel_size) as usize).min(self.rows - 1);
+        let j = ((y / self.pixel_size) as usize).min(self.cols - 1);
+        // Color matching
+        self.palette.color(self.texels[i * self.cols + j])
     }
 }
 
@@ -43,15 +92,15 @@ impl Texture for Pixelated {
         (self.cols as f32) * self.pixel_size
     }
 
-    fn color_at(&self, u: f32, v: f32) -> &crate::primitives::color::Color {
-        // Compute the coordinates inside the primitive square
-        let x = v % self.width();
-        let y = u % self.height();
-        // Compute the index in the array of pixels
-        let i = (x / self.pixel_size) as usize;
-        let j = (y / self.pixel_size) as usize;
-        // Color matching
-        self.colors.get(&self.pixels[i][j]).unwrap()
+    fn color_at(&self, u: f32, v: f32) -> &Color {
+        // Compute the index of the texel with integer maths: the texture repeats itself
+        let i = ((v * self.inv_pixel_size).floor() as i32).rem_euclid(self.rows as i32) as usize;
+        let j = ((u * self.inv_pixel_size).floor() as i32).rem_euclid(self.cols as i32) as usize;
+        &self.lut[i * self.cols + j]
+    }
+
+    fn texel_size(&self) -> Option<f32> {
+        Some(self.pixel_size)
     }
 }
 
@@ -138,4 +187,31 @@ impl Pixelated {
         ];
         return Pixelated::new(lines, 0.1);
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::primitives::textures::pixelated::Pixelated;
+    use crate::primitives::textures::Texture;
+
+    #[test]
+    fn test_fast_sampling_matches_reference() {
+        let texture = Pixelated::stone();
+        for i in 0..200 {
+            for j in 0..200 {
+                // Stay away from the borders of the texels, where rounding differs
+                let u = j as f32 * 0.0137 + 0.0001;
+                let v = i as f32 * 0.0113 + 0.0001;
+                assert_eq!(texture.color_at_reference(u, v).rgba(), texture.color_at(u, v).rgba());
+            }
+        }
+
+        // More rows than columns, sampled on both sides of the origin
+        let texture = Pixelated::from_text("0.1\nyb\nby\nyy").unwrap();
+        for i in -20..20 {
+            for j in -20..20 {
+                let (u, v) = (j as f32 * 0.1 + 0.05, i as f32 * 0.1 + 0.05);
+                assert_eq!(texture.color_at_reference(u, v).rgba(), texture.color_at(u, v).rgba(), "({u}, {v})");
+            }
+        }
+    }
+}
@@ -2,7 +2,7 @@ use crate::primitives::matrix3::Matrix3;
 use crate::primitives::vector::Vector3;
 
 /// Represent an homogenous transformation of the 3D space to the 3D space
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Transform {
     translation: Vector3,
     // how to represent the rotation ?
@@ -17,7 +17,48 @@ impl Transform {
         }
     }
 
+    pub fn identity() -> Self {
+        Self::translation(Vector3::empty())
+    }
+
+    pub fn translation(t: Vector3) -> Self {
+        Self::new(t, Matrix3::identity())
+    }
+
+    /// Rotation by `angle` around the axis going through `pivot`. Following the right-hand rule,
+    /// the rotation is counterclockwise when the axis points towards the viewer (as
+    /// `Matrix3::z_rotation` around the z-axis).
+    pub fn rotation_around(axis: &Vector3, angle: f32, pivot: &Vector3) -> Self {
+        // `Matrix3::rotation_around` turns the other way
+        let rotation = Matrix3::rotation_around(-angle, *axis);
+        // R * (p + t) = R * (p - pivot) + pivot, for t = R^T * pivot - pivot
+        let translation = rotation.transpose() * *pivot - *pivot;
+        Self { translation, rotation }
+    }
+
     pub fn apply(&self, vec: &Vector3) -> Vector3 {
         self.rotation.clone() * (vec + &self.translation)
     }
+
+    /// Applies the rotation only, to a direction such as a normal
+    pub fn apply_rotation(&self, direction: &Vector3) -> Vector3 {
+        &self.rotation * *direction
+    }
+
+    /// Returns the transform applying this one, then `other`
+    pub fn then(&self, other: &Transform) -> Self {
+        // R2 * (R1 * (p + t1) + t2) = R2 * R1 * (p + t1 + R1^T * t2)
+        Self {
+            translation: self.translation + self.rotation.transpose() * other.translation,
+            rotation: &other.rotation * &self.rotation,
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        // p = R^T * p' - t = R^T * (p' - R * t)
+        Self {
+            translation: (&self.rotation * self.translation).opposite(),
+            rotation: self.rotation.transpose(),
+        }
+    }
 }
@@ -0,0 +1,113 @@
+use crate::primitives::camera::Camera;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::object::{Object, ObjectKind};
+use crate::primitives::snapping::Grid;
+use crate::primitives::transformation::Transform;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::vector::Vector3;
+
+/// A triangle in space.
+///
+/// The triangle is rendered as a triangular `CubicFace3`, so that it goes through the same
+/// pipeline as all the other faces.
+#[derive(Clone)]
+pub struct Triangle3 {
+    face: CubicFace3,
+}
+
+impl Triangle3 {
+    /// Creates a triangle whose normal follows the right-hand rule: (p1 - p0) x (p2 - p0).
+    ///
+    /// As for the faces, the texture is anchored on the first point, with the u-axis going to
+    /// the second point.
+    pub fn new(points: [Vector3; 3], texture: &TextureHandle) -> Self {
+        let a = points[1] - points[0];
+        let b = points[2] - points[0];
+        let mut u_axis = a;
+        u_axis.normalize();
+        let u2 = b.dot(&u_axis);
+        let v2 = (b - u_axis * u2).norm();
+        Self::with_uvs(points, [(0., 0.), (a.norm(), 0.), (u2, v2)], texture)
+    }
+
+    /// Creates a triangle with explicit texture coordinates (in meters) for each of its points.
+    pub fn with_uvs(points: [Vector3; 3], uvs: [(f32, f32); 3], texture: &TextureHandle) -> Self {
+        let mut normal = (points[1] - points[0]).cross(&(points[2] - points[0]));
+        normal.normalize();
+        Self {
+            face: CubicFace3::triangle(points, uvs, normal, texture),
+        }
+    }
+
+    /// Creates a triangle from a triangular face (see `CubicFace3::triangle`)
+    pub fn from_face(face: CubicFace3) -> Self {
+        assert!(face.is_triangle(), "The face must be a triangle");
+        Self { face }
+    }
+
+    pub fn face(&self) -> &CubicFace3 {
+        &self.face
+    }
+}
+
+impl Object for Triangle3 {
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Mesh
+    }
+
+    fn get_visible_faces(&self, camera: &Camera) -> Vec<&CubicFace3> {
+        self.face.get_visible_faces(camera)
+    }
+
+    fn get_all_faces(&self) -> Vec<&CubicFace3> {
+        vec![&self.face]
+    }
+
+    fn rotate(&mut self, by: f32) {
+        self.face.rotate(by);
+    }
+
+    fn transform(&mut self, transform: &Transform) {
+        self.face.transform(transform);
+    }
+
+    fn set_faces(&mut self, faces: &[CubicFace3]) {
+        self.face = faces[0].clone();
+    }
+
+    fn snap(&mut self, grid: &Grid) {
+        self.face.snap(grid);
+    }
+
+    fn collides_with(&self, point: &Vector3, radius: f32) -> bool {
+        self.face.collides_with(point, radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::object::Object;
+    use crate::primitives::projective_coordinates::ProjectionCoordinates;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::triangle3::Triangle3;
+    use crate::primitives::vector::{Vector3, UNIT_Z};
+
+    #[test]
+    fn test_triangle_geometry() {
+        let triangle = Triangle3::new([Vector3::newi(0, 0, 0), Vector3::newi(2, 0, 0), Vector3::newi(0, 2, 0)], &YELLOW);
+        let face = triangle.face();
+        assert!(face.is_triangle());
+        assert_eq!(2., face.area());
+        assert_eq!(UNIT_Z, *face.normal());
+
+        // Only the half of the parallelogram close to the first point belongs to the triangle
+        assert!(face.contains_projection(&ProjectionCoordinates::new(0.3, 0.6)));
+        assert!(!face.contains_projection(&ProjectionCoordinates::new(0.6, 0.6)));
+        assert!(triangle.collides_with(&Vector3::new(0.5, 0.5, 0.1), 0.2));
+        assert!(!triangle.collides_with(&Vector3::new(1.5, 1.5, 0.1), 0.2));
+
+        // The texture coordinates are in meters
+        let (u, v) = face.uv_at(&ProjectionCoordinates::new(0.5, 0.5));
+        assert!((u - 1.).abs() < 1e-6 && (v - 1.).abs() < 1e-6);
+    }
+}
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use crate::primitives::matrix3::Matrix3;
 use std::fmt::{Debug, Formatter};
 use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, Sub};
@@ -6,7 +8,7 @@ use winit::event::VirtualKeyCode::V;
 /// A vector in 3 coordinates
 ///
 /// Mathematically, it can represent equally a 3d vector or a 3d point
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Vector3 {
     x: f32,
     y: f32,
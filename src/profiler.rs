@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+use bounded_vec_deque::BoundedVecDeque;
+
+use crate::frame::AbstractFrame;
+use crate::primitives::cubic_face2::CubicFace2;
+
+/// The stages of the rendering of a frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    /// Finding the faces seen by the camera
+    Visibility,
+    /// Projecting the visible faces on the screen
+    Projection,
+    /// Sorting the faces by depth
+    Sort,
+    /// Traversing the BSP, which culls, projects and orders the faces at once
+    Bsp,
+    /// Drawing the faces into the buffer
+    Raster,
+    /// Sending the buffer to the screen
+    Present,
+}
+
+pub const STAGES: [Stage; 6] = [Stage::Visibility, Stage::Projection, Stage::Sort, Stage::Bsp, Stage::Raster, Stage::Present];
+
+/// Duration of each stage of a frame
+pub type FrameTimings = [Duration; STAGES.len()];
+
+/// Records how long each stage of the rendering takes, for the last frames
+pub struct FrameProfiler {
+    current: FrameTimings,
+    history: BoundedVecDeque<FrameTimings>,
+}
+
+impl FrameProfiler {
+    pub fn new(n_frames: usize) -> Self {
+        Self {
+            current: [Duration::ZERO; STAGES.len()],
+            history: BoundedVecDeque::new(n_frames),
+        }
+    }
+
+    pub fn record(&mut self, stage: Stage, duration: Duration) {
+        self.current[stage as usize] += duration;
+    }
+
+    /// Runs the closure and adds its duration to the stage
+    pub fn time<R, F: FnOnce() -> R>(&mut self, stage: Stage, f: F) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.record(stage, start.elapsed());
+        result
+    }
+
+    /// Stores the timings of the current frame, and starts a new one
+    pub fn end_frame(&mut self) {
+        self.history.push_back(self.current);
+        self.current = [Duration::ZERO; STAGES.len()];
+    }
+
+    /// Timings of the last frames, from the oldest to the most recent
+    pub fn history(&self) -> impl Iterator<Item = &FrameTimings> {
+        self.history.iter()
+    }
+
+    /// Average duration of each stage over the last frames
+    pub fn average(&self) -> FrameTimings {
+        let mut total = [Duration::ZERO; STAGES.len()];
+        for timings in &self.history {
+            for (sum, d) in total.iter_mut().zip(timings) {
+                *sum += *d;
+            }
+        }
+        total.map(|d| d / self.history.len().max(1) as u32)
+    }
+
+    /// Returns the average duration of the stages which took some time, such as
+    /// `Visibility 1.20 ms, Raster 8.31 ms`
+    pub fn breakdown(&self) -> String {
+        let average = self.average();
+        STAGES
+            .iter()
+            .filter(|stage| !average[**stage as usize].is_zero())
+            .map(|stage| format!("{stage:?} {:.2} ms", average[*stage as usize].as_secs_f64() * 1000.))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A frame which measures the time spent drawing faces into another frame
+pub struct TimedFrame<'a> {
+    inner: &'a mut dyn AbstractFrame,
+    elapsed: Duration,
+}
+
+impl<'a> TimedFrame<'a> {
+    pub fn new(inner: &'a mut dyn AbstractFrame) -> Self {
+        Self {
+            inner,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+impl<'a> AbstractFrame for TimedFrame<'a> {
+    fn draw_one_face(&mut self, face: &CubicFace2) {
+        let start = Instant::now();
+        self.inner.draw_one_face(face);
+        self.elapsed += start.elapsed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::profiler::{FrameProfiler, Stage};
+
+    #[test]
+    fn test_history() {
+        let mut profiler = FrameProfiler::new(2);
+        for i in 1..=3 {
+            profiler.record(Stage::Raster, Duration::from_millis(i));
+            profiler.record(Stage::Raster, Duration::from_millis(i));
+            assert_eq!(5, profiler.time(Stage::Sort, || 5));
+            profiler.end_frame();
+        }
+        // Only the last frames are kept
+        let raster: Vec<Duration> = profiler.history().map(|t| t[Stage::Raster as usize]).collect();
+        assert_eq!(vec![Duration::from_millis(4), Duration::from_millis(6)], raster);
+        assert_eq!(Duration::from_millis(5), profiler.average()[Stage::Raster as usize]);
+        assert!(profiler.breakdown().contains("Raster 5.00 ms"));
+    }
+}
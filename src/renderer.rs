@@ -0,0 +1,288 @@
+use rayon::prelude::*;
+
+use crate::config::RendererMode;
+use crate::frame::{Frame, BACKGROUND_COLOR};
+use crate::primitives::position::Pose;
+use crate::profiler::{FrameProfiler, Stage};
+use crate::viewport::Viewport;
+use crate::worlds::World;
+
+/// Number of rays traced at each frame by the progressive raytracer
+pub const RAY_BUDGET: usize = 40_000;
+/// Number of samples averaged in each pixel by the progressive raytracer, once the camera stops
+pub const MAX_SAMPLES: u32 = 8;
+/// Size (in pixels) of the blocks previewed by the first samples of the progressive raytracer
+const COARSEST_BLOCK: u32 = 8;
+
+/// Raytracer which only traces a fixed number of rays at each frame, so that it stays responsive.
+///
+/// The samples of each pixel are accumulated over the frames while the camera is still, and
+/// averaged: the rays of the following samples go through other points of the pixels, which
+/// smooths the edges. All is thrown away when the camera moves.
+///
+/// The first sample of the pixels is traced as in the other renderers, coarse to fine: one ray per
+/// block of 8x8 pixels, then the size of the blocks is halved, and the pixels without any sample
+/// show the color of their block meanwhile. The whole image is thus previewed from the first frame.
+pub struct ProgressiveRaytracer {
+    image: Vec<u8>,
+    /// Sum of the colors of the samples of each pixel
+    sums: Vec<[f32; 4]>,
+    /// Number of samples of each pixel
+    samples: Vec<u32>,
+    /// Pose of the camera for which the samples are accumulated
+    pose: Option<Pose>,
+    /// Size of the blocks of the first samples (0 once each pixel has a sample)
+    block: u32,
+    /// Index of the next block of the first samples, or of the next pixel of the following ones
+    next: u32,
+    /// Number of samples that each pixel has after the current pass over them
+    pass: u32,
+}
+
+impl ProgressiveRaytracer {
+    pub fn new() -> Self {
+        Self {
+            image: Vec::new(),
+            sums: Vec::new(),
+            samples: Vec::new(),
+            pose: None,
+            block: COARSEST_BLOCK,
+            next: 0,
+            pass: 1,
+        }
+    }
+
+    /// Restarts the image from scratch at the next frame
+    pub fn reset(&mut self) {
+        self.pose = None;
+    }
+
+    /// Returns true once every pixel has all its samples since the last reset
+    pub fn is_complete(&self) -> bool {
+        self.pass > MAX_SAMPLES
+    }
+
+    /// Traces at most `budget` new rays, and copies the current image to the frame
+    pub fn draw(&mut self, world: &World, frame: &mut [u8], budget: usize) {
+        let viewport = world.viewport();
+        if self.image.len() != viewport.buffer_len() {
+            // The frames were resized
+            self.image = BACKGROUND_COLOR.repeat(viewport.pixel_count());
+            self.sums = vec![[0.; 4]; viewport.pixel_count()];
+            self.samples = vec![0; viewport.pixel_count()];
+            self.pose = None;
+        }
+        if self.pose.as_ref() != Some(world.camera().pose()) {
+            self.pose = Some(world.camera().pose().clone());
+            self.image.chunks_exact_mut(4).for_each(|pixel| pixel.copy_from_slice(&BACKGROUND_COLOR));
+            self.sums.fill([0.; 4]);
+            self.samples.fill(0);
+            self.block = COARSEST_BLOCK;
+            self.next = 0;
+            self.pass = 1;
+        }
+
+        // The rays are listed first, then traced on the threads of the rayon pool
+        let mut rays = Vec::new();
+        while rays.len() < budget && !self.is_complete() {
+            if self.block == 0 {
+                // Each pass adds a sample to all the pixels, at another point of the pixels
+                if self.next == viewport.pixel_count() as u32 {
+                    self.pass += 1;
+                    self.next = 0;
+                    continue;
+                }
+                let (x, y) = (self.next % viewport.width, self.next / viewport.width);
+                self.next += 1;
+                rays.push((x, y, 1, sample_offset(self.pass)));
+                continue;
+            }
+            let columns = viewport.width.div_ceil(self.block);
+            let rows = viewport.height.div_ceil(self.block);
+            if self.next == columns * rows {
+                self.block /= 2;
+                self.next = 0;
+                self.pass += (self.block == 0) as u32;
+                continue;
+            }
+            let (column, row) = (self.next % columns, self.next / columns);
+            self.next += 1;
+
+            // After the coarsest blocks, the blocks at even positions were traced with larger blocks
+            if self.block != COARSEST_BLOCK && column % 2 == 0 && row % 2 == 0 {
+                continue;
+            }
+            rays.push((column * self.block, row * self.block, self.block, (0., 0.)));
+        }
+        let sampler = world.subpixel_raytracing_sampler();
+        let colors: Vec<[u8; 4]> = rays.par_iter().map(|(x, y, _, (dx, dy))| sampler(*x as f32 + dx, *y as f32 + dy)).collect();
+        for ((x, y, block, _), rgba) in rays.into_iter().zip(colors) {
+            let pixel = viewport.index(x, y);
+            let sum = &mut self.sums[pixel];
+            sum.iter_mut().zip(rgba).for_each(|(sum, channel)| *sum += channel as f32);
+            self.samples[pixel] += 1;
+            let average = sum.map(|sum| (sum / self.samples[pixel] as f32).round() as u8);
+            // The first samples are drawn over the larger blocks of the previous ones
+            for py in y..(y + block).min(viewport.height) {
+                for px in x..(x + block).min(viewport.width) {
+                    let i = 4 * viewport.index(px, py);
+                    self.image[i..i + 4].copy_from_slice(&average);
+                }
+            }
+        }
+        frame.copy_from_slice(&self.image);
+    }
+}
+
+/// Returns the offset (in pixels) of the rays of a pass after the first one, from the rays of the
+/// first pass. The points of the successive passes (Halton sequence) cover the pixels evenly.
+fn sample_offset(pass: u32) -> (f32, f32) {
+    let halton = |mut i: u32, base: u32| {
+        let (mut value, mut fraction) = (0., 1.);
+        while i > 0 {
+            fraction /= base as f32;
+            value += fraction * (i % base) as f32;
+            i /= base;
+        }
+        value
+    };
+    (halton(pass - 1, 2) - 0.5, halton(pass - 1, 3) - 0.5)
+}
+
+impl Default for ProgressiveRaytracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Draws a world with any of the renderers, which can be switched between two frames.
+///
+/// The state needed by each renderer is created the first time it is used: the BSP of the world
+/// for the BSP renderer, and the depth buffer for the z-buffer renderer.
+pub struct Renderer {
+    /// Distance of the closest face at each pixel (empty until the z-buffer renderer is used)
+    depth: Vec<u32>,
+    raytracer: Option<ProgressiveRaytracer>,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self { depth: Vec::new(), raytracer: None }
+    }
+
+    /// Must be called when the world or the camera settings change without the camera moving
+    /// (for instance when switching scenes), so that the progressive raytracer starts again.
+    pub fn reset(&mut self) {
+        if let Some(raytracer) = &mut self.raytracer {
+            raytracer.reset();
+        }
+    }
+
+    /// Draws the world on top of the frame, recording the duration of each stage of the rendering.
+    pub fn draw(&mut self, mode: RendererMode, world: &mut World, frame: &mut [u8], profiler: &mut FrameProfiler) {
+        match mode {
+            RendererMode::Painter | RendererMode::Bsp => {
+                world.set_use_bsp(mode == RendererMode::Bsp);
+                world.draw_painter_profiled(&mut Frame::new(frame, world.viewport()), profiler);
+            }
+            RendererMode::ZBuffer => {
+                let depth = self.depth_buffer(world.viewport());
+                profiler.time(Stage::Raster, || world.draw_depth_buffered(frame, depth));
+            }
+            RendererMode::Raytracer => {
+                let raytracer = self.raytracer.get_or_insert_with(ProgressiveRaytracer::new);
+                profiler.time(Stage::Raster, || raytracer.draw(world, frame, RAY_BUDGET));
+            }
+            // Only renders the shapes set with `world.set_sdf`
+            RendererMode::Raymarching => profiler.time(Stage::Raster, || world.draw_raymarching(frame)),
+        }
+        if mode != RendererMode::Raytracer {
+            // The world may change while another renderer is used
+            self.reset();
+        }
+    }
+
+    /// Returns the distance of the closest face at each pixel of the last frame drawn with this
+    /// mode. Only the z-buffer renderer fills it while drawing: it is computed for the others.
+    pub fn depth(&mut self, mode: RendererMode, world: &World) -> &[u32] {
+        if mode != RendererMode::ZBuffer {
+            let depth = self.depth_buffer(world.viewport());
+            world.draw_depth(depth);
+        }
+        &self.depth
+    }
+
+    /// Returns the depth buffer of the size of the viewport, cleared for a new frame
+    fn depth_buffer(&mut self, viewport: Viewport) -> &mut [u32] {
+        if self.depth.len() != viewport.pixel_count() {
+            self.depth = vec![u32::MAX; viewport.pixel_count()];
+        } else {
+            self.depth.fill(u32::MAX);
+        }
+        &mut self.depth
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compare::{diff_images, equivalence_scenes, render_offscreen, RenderMethod, DEFAULT_TOLERANCE, MAX_MISMATCH_PERCENTAGE};
+    use crate::config::RendererMode;
+    use crate::frame::BACKGROUND_COLOR;
+    use crate::profiler::FrameProfiler;
+    use crate::primitives::vector::Vector3;
+    use crate::renderer::{ProgressiveRaytracer, Renderer, MAX_SAMPLES};
+    use crate::{HEIGHT, WIDTH};
+
+    #[test]
+    fn test_switch_renderers() {
+        let (_, mut world) = equivalence_scenes().remove(1);
+        let reference = render_offscreen(&mut world, RenderMethod::Raytracer);
+        let mut renderer = Renderer::new();
+        let mut profiler = FrameProfiler::new(1);
+        for mode in [RendererMode::ZBuffer, RendererMode::Bsp, RendererMode::Painter, RendererMode::ZBuffer] {
+            let mut frame = BACKGROUND_COLOR.repeat((WIDTH * HEIGHT) as usize);
+            renderer.draw(mode, &mut world, &mut frame, &mut profiler);
+            let diff = diff_images(&reference, &frame, DEFAULT_TOLERANCE);
+            assert!(diff.mismatch_percentage() <= MAX_MISMATCH_PERCENTAGE, "{mode:?} differs on {:.3}% of the pixels", diff.mismatch_percentage());
+        }
+        // The BSP was built when first needed, and is kept afterwards
+        assert!(world.bsp().is_some());
+    }
+
+    #[test]
+    fn test_progressive_raytracer() {
+        let (_, mut world) = equivalence_scenes().remove(0);
+        let reference = render_offscreen(&mut world, RenderMethod::Raytracer);
+        let mut raytracer = ProgressiveRaytracer::new();
+        let mut frame = BACKGROUND_COLOR.repeat((WIDTH * HEIGHT) as usize);
+
+        // The first frame already shows a coarse image
+        raytracer.draw(&world, &mut frame, 10_000);
+        assert!(!raytracer.is_complete());
+        let coarse = diff_images(&reference, &frame, DEFAULT_TOLERANCE);
+        assert!(coarse.mismatch_percentage() < 10.);
+
+        // Once each pixel has its first sample, the image is the full raytracing
+        let pixels = (WIDTH * HEIGHT) as usize;
+        raytracer.draw(&world, &mut frame, pixels - 10_000);
+        assert_eq!(reference, frame);
+
+        // While the camera is still, the next samples smooth the edges
+        let frames = (MAX_SAMPLES as usize - 1) * pixels / 100_000 + 1;
+        (0..frames).for_each(|_| raytracer.draw(&world, &mut frame, 100_000));
+        assert!(raytracer.is_complete());
+        assert_ne!(reference, frame);
+        assert!(diff_images(&reference, &frame, DEFAULT_TOLERANCE).mismatch_percentage() <= MAX_MISMATCH_PERCENTAGE);
+
+        // Moving the camera starts again from the coarse image
+        world.set_camera_position(Vector3::new(0.5, -3., -0.5));
+        raytracer.draw(&world, &mut frame, 10_000);
+        assert!(!raytracer.is_complete());
+    }
+}
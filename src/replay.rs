@@ -0,0 +1,163 @@
+use doom::drawable::Drawable;
+use doom::input_map::Action;
+
+/// An input sent to the world. The actions are saved rather than their keys, so that the scripts
+/// still work once the key bindings change.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    ActionPressed(Action),
+    ActionHeld(Action),
+    /// Motion of the captured mouse, in pixels
+    MouseMoved(f32, f32),
+    /// Rotation of the mouse wheel, in lines
+    MouseScrolled(f32),
+}
+
+/// A list of inputs, associated with the frame at which they are sent to the world.
+///
+/// Combined with the deterministic mode of the world, replaying a script produces the exact same
+/// frames as the run during which it was recorded.
+///
+/// The text format has one event per line: `<frame> <pressed|held> <action>`, for instance
+/// `12 held MoveForward`, `<frame> mouse <dx> <dy>` for the motions of the mouse, or `<frame> scroll <delta>`
+/// for its wheel.
+#[derive(Default, Debug, PartialEq)]
+pub struct InputScript {
+    events: Vec<(u64, InputEvent)>,
+}
+
+impl InputScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, frame: u64, event: InputEvent) {
+        self.events.push((frame, event));
+    }
+
+    /// Sends the events of the given frame to the world
+    pub fn apply(&self, frame: u64, world: &mut dyn Drawable) {
+        for (_, event) in self.events.iter().filter(|(f, _)| *f == frame) {
+            match event {
+                InputEvent::ActionPressed(action) => world.action_pressed(*action),
+                InputEvent::ActionHeld(action) => world.action_held(*action),
+                InputEvent::MouseMoved(dx, dy) => world.mouse_moved(*dx, *dy),
+                InputEvent::MouseScrolled(delta) => world.mouse_scrolled(*delta),
+            }
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        self.events
+            .iter()
+            .map(|(frame, event)| match event {
+                InputEvent::ActionPressed(action) => format!("{frame} pressed {action:?}\n"),
+                InputEvent::ActionHeld(action) => format!("{frame} held {action:?}\n"),
+                InputEvent::MouseMoved(dx, dy) => format!("{frame} mouse {dx} {dy}\n"),
+                InputEvent::MouseScrolled(delta) => format!("{frame} scroll {delta}\n"),
+            })
+            .collect()
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut script = Self::new();
+        for (i, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if let [frame, "mouse", dx, dy] = parts[..] {
+                let frame = frame.parse().map_err(|_| format!("line {}: invalid frame '{frame}'", i + 1))?;
+                let motion = dx.parse().and_then(|dx| Ok((dx, dy.parse()?)));
+                let (dx, dy) = motion.map_err(|_| format!("line {}: invalid mouse motion '{dx} {dy}'", i + 1))?;
+                script.record(frame, InputEvent::MouseMoved(dx, dy));
+                continue;
+            }
+            if let [frame, "scroll", delta] = parts[..] {
+                let frame = frame.parse().map_err(|_| format!("line {}: invalid frame '{frame}'", i + 1))?;
+                let delta = delta.parse().map_err(|_| format!("line {}: invalid scroll '{delta}'", i + 1))?;
+                script.record(frame, InputEvent::MouseScrolled(delta));
+                continue;
+            }
+            let (frame, kind, action) = match parts[..] {
+                [frame, kind, action] => (frame, kind, action),
+                _ => return Err(format!("line {}: expected '<frame> <pressed|held> <action>' or '<frame> mouse <dx> <dy>'", i + 1)),
+            };
+            let frame = frame.parse().map_err(|_| format!("line {}: invalid frame '{frame}'", i + 1))?;
+            let action = Action::from_name(action).ok_or(format!("line {}: unknown action '{action}'", i + 1))?;
+            let event = match kind {
+                "pressed" => InputEvent::ActionPressed(action),
+                "held" => InputEvent::ActionHeld(action),
+                _ => return Err(format!("line {}: unknown event '{kind}'", i + 1)),
+            };
+            script.record(frame, event);
+        }
+        Ok(script)
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Self::from_text(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use doom::drawable::Drawable;
+    use doom::frame::Frame;
+    use doom::input_map::Action;
+    use doom::primitives::camera::Camera;
+    use doom::primitives::cube::Cube3;
+    use doom::primitives::textures::colored::YELLOW;
+    use doom::primitives::vector::Vector3;
+    use doom::worlds::World;
+
+    use crate::replay::{InputEvent, InputScript};
+
+    #[test]
+    fn test_script_text_format() {
+        let mut script = InputScript::new();
+        script.record(0, InputEvent::ActionHeld(Action::MoveForward));
+        script.record(3, InputEvent::ActionPressed(Action::TurnLeft));
+        script.record(4, InputEvent::MouseMoved(-2.5, 1.));
+        script.record(5, InputEvent::MouseScrolled(-1.));
+        assert_eq!("0 held MoveForward\n3 pressed TurnLeft\n4 mouse -2.5 1\n5 scroll -1\n", script.to_text());
+        assert_eq!(script, InputScript::from_text(&script.to_text()).unwrap());
+        assert!(InputScript::from_text("1 held Up").is_err());
+        assert!(InputScript::from_text("1 mouse 2").is_err());
+    }
+
+    /// Runs the script on a deterministic world and returns the last frame
+    fn run(script: &InputScript) -> Vec<u8> {
+        let mut world = World::new(Camera::default());
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(0, 0, 0), &YELLOW, &YELLOW));
+        world.set_camera_position(Vector3::new(0.5, -4., -1.));
+        world.set_camera_rotation(-std::f32::consts::PI / 2.);
+        world.set_seed(42);
+
+        let viewport = world.viewport();
+        let mut buffer = vec![0; viewport.buffer_len()];
+        for frame in 0..10 {
+            script.apply(frame, &mut world);
+            world.update(1. / 30.);
+            buffer.fill(0);
+            world.draw_painter(&mut Frame::new(&mut buffer, viewport));
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_replay_is_deterministic() {
+        let mut script = InputScript::new();
+        for frame in 0..5 {
+            script.record(frame, InputEvent::ActionHeld(Action::MoveForward));
+        }
+        script.record(6, InputEvent::ActionPressed(Action::TurnLeft));
+
+        let first = run(&script);
+        assert!(first.iter().any(|c| *c != 0));
+        assert!(first == run(&script));
+        assert!(first != run(&InputScript::new()));
+    }
+}
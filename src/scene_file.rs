@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+use crate::assets::TextureLibrary;
+use crate::layers::Layers;
+use crate::primitives::billboard::Billboard;
+use crate::primitives::cube::Cube3;
+use crate::primitives::cubic_face3::{CubicFace3, Receivers};
+use crate::primitives::mesh::Mesh;
+use crate::primitives::object::{Object, ObjectKind};
+use crate::primitives::position::Pose;
+use crate::primitives::tessellation::Patch;
+use crate::primitives::triangle3::Triangle3;
+use crate::primitives::vector::Vector3;
+
+#[derive(Serialize, Deserialize)]
+struct FaceData {
+    points: [Vector3; 4],
+    uvs: [(f32, f32); 4],
+    normal: Vector3,
+    texture: String,
+    #[serde(default)]
+    triangle: bool,
+    #[serde(default = "all_receivers")]
+    receivers: Receivers,
+}
+
+fn all_receivers() -> Receivers {
+    Receivers::ALL
+}
+
+/// An object of the scene, with the layers it belongs to
+pub type SceneObject = (Box<dyn Object>, Layers);
+
+fn gameplay_layer() -> Layers {
+    Layers::GAMEPLAY
+}
+
+#[derive(Serialize, Deserialize)]
+struct ObjectData {
+    kind: ObjectKind,
+    #[serde(default = "gameplay_layer")]
+    layers: Layers,
+    faces: Vec<FaceData>,
+}
+
+/// Content of a scene file (in JSON): the pose of the camera and the faces and layers of each
+/// object, whose textures are identified by their name. Levels can be written in such files instead of code.
+#[derive(Serialize, Deserialize)]
+pub struct SceneFile {
+    camera: Pose,
+    objects: Vec<ObjectData>,
+}
+
+impl SceneFile {
+    /// Describes the objects, with their layers. All their textures must have a name.
+    pub fn capture(camera: &Pose, objects: &[Box<dyn Object>], layers: &[Layers], textures: &TextureLibrary) -> Result<Self, String> {
+        let objects = objects
+            .iter()
+            .zip(layers)
+            .map(|(object, layers)| {
+                let faces = object
+                    .get_all_faces()
+                    .into_iter()
+                    .map(|face| {
+                        let texture = textures.name_of(face.texture()).ok_or("A face uses a texture without name")?.to_string();
+                        Ok(FaceData {
+                            points: face.points(),
+                            uvs: face.uvs(),
+                            normal: *face.normal(),
+                            texture,
+                            triangle: face.is_triangle(),
+                            receivers: face.receivers(),
+                        })
+                    })
+                    .collect::<Result<Vec<FaceData>, String>>()?;
+                Ok(ObjectData { kind: object.kind(), layers: *layers, faces })
+            })
+            .collect::<Result<Vec<ObjectData>, String>>()?;
+        Ok(Self { camera: camera.clone(), objects })
+    }
+
+    pub fn camera(&self) -> &Pose {
+        &self.camera
+    }
+
+    /// Rebuilds the objects with their layers, finding their textures from their name (and
+    /// loading them if needed)
+    pub fn objects(&self, textures: &mut TextureLibrary) -> Result<Vec<SceneObject>, String> {
+        self.objects.iter().map(|object| Ok((build_object(object, textures)?, object.layers))).collect()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|err| err.to_string())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("The scene can always be serialized")
+    }
+}
+
+fn build_face(face: &FaceData, textures: &mut TextureLibrary) -> Result<CubicFace3, String> {
+    let texture = &textures.get(&face.texture)?;
+    let [p0, p1, p2, p3] = face.points;
+    let [uv0, uv1, uv2, uv3] = face.uvs;
+    let built = if face.triangle {
+        CubicFace3::triangle([p0, p1, p2], [uv0, uv1, uv2], face.normal, texture)
+    } else {
+        CubicFace3::new_with_uvs([p0, p1, p2, p3], [uv0, uv1, uv2, uv3], face.normal, texture)
+    };
+    Ok(built.with_receivers(face.receivers))
+}
+
+fn build_object(object: &ObjectData, textures: &mut TextureLibrary) -> Result<Box<dyn Object>, String> {
+    let mut faces = object
+        .faces
+        .iter()
+        .map(|face| build_face(face, textures))
+        .collect::<Result<Vec<CubicFace3>, String>>()?;
+    match object.kind {
+        ObjectKind::Cube => {
+            let faces: [CubicFace3; 6] = faces.try_into().map_err(|_| "A cube must have 6 faces".to_string())?;
+            Ok(Box::new(Cube3::from_faces(faces)))
+        }
+        ObjectKind::Face if faces.len() == 1 => Ok(Box::new(faces.remove(0))),
+        ObjectKind::Face => Err("A face object must have a single face".to_string()),
+        ObjectKind::Mesh if faces.iter().all(|face| face.is_triangle()) => {
+            Ok(Box::new(Mesh::new(faces.into_iter().map(Triangle3::from_face).collect())))
+        }
+        ObjectKind::Mesh => Err("A mesh must be made of triangles".to_string()),
+        ObjectKind::Patch => Ok(Box::new(Patch::new(faces))),
+        ObjectKind::Billboard if faces.len() == 1 => Ok(Box::new(Billboard::from_face(faces.remove(0)))),
+        ObjectKind::Billboard => Err("A billboard must have a single face".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layers::Layers;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::cubic_face3::{CubicFace3, Receivers};
+    use crate::primitives::mesh::Mesh;
+    use crate::primitives::textures::colored::{ORANGE, YELLOW};
+    use crate::primitives::vector::Vector3;
+    use crate::worlds::World;
+
+    fn world_with_textures() -> World {
+        let mut world = World::new(Camera::default());
+        world.register_texture("yellow", &YELLOW);
+        world.register_texture("orange", &ORANGE);
+        world
+    }
+
+    #[test]
+    fn test_scene_roundtrip() {
+        let mut world = world_with_textures();
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(0, 0, 0), &YELLOW, &ORANGE));
+        world.add_face(CubicFace3::vface_from_line(Vector3::newi2(2, 0), Vector3::newi2(3, 0)).with_receivers(Receivers::ALL.without(Receivers::LIGHT)));
+        world.add_mesh(Mesh::pyramid(Vector3::new(4., 0., 0.), 1., 1., &ORANGE));
+        let marker = world.add_face(CubicFace3::hface_from_line(Vector3::newi2(0, 2), Vector3::newi2(1, 2)));
+        world.set_layers(marker, Layers::EDITOR);
+        world.set_camera_position(Vector3::new(1., -3., -1.));
+        world.set_camera_rotation(0.5);
+        let path = std::env::temp_dir().join("test_scene_roundtrip.json");
+        let path = path.to_str().unwrap();
+        world.save_scene(path).unwrap();
+
+        let mut loaded = world_with_textures();
+        loaded.add_cube(Cube3::minecraft_like(Vector3::newi(9, 9, 9), &YELLOW, &YELLOW));
+        loaded.load_scene(path).unwrap();
+        assert_eq!(world.describe(), loaded.describe());
+        assert!(world.camera().pose() == loaded.camera().pose());
+        let layers: Vec<Layers> = loaded.objects().filter_map(|(id, _)| loaded.layers(id)).collect();
+        assert_eq!(vec![Layers::GAMEPLAY, Layers::GAMEPLAY, Layers::GAMEPLAY, Layers::EDITOR], layers);
+
+        // The textures must be known to load the scene
+        assert!(World::new(Camera::default()).load_scene(path).unwrap_err().contains("Unknown texture"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
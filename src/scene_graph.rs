@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::object::ObjectId;
+use crate::primitives::transformation::Transform;
+
+/// Place of an object in the scene graph
+#[derive(Clone, Debug)]
+struct Node {
+    parent: Option<ObjectId>,
+    /// Motion of the object relative to its parent (or to the world, without parent)
+    local: Transform,
+    /// Transform already applied to the faces of the object
+    applied: Transform,
+    /// Faces of the object at the identity transform, kept from the first time it was placed
+    rest: Option<Vec<CubicFace3>>,
+}
+
+impl Node {
+    fn new() -> Self {
+        Self { parent: None, local: Transform::identity(), applied: Transform::identity(), rest: None }
+    }
+}
+
+/// Hierarchy of the objects of a world: each object moves with its parent, so that articulated
+/// objects (such as a door attached to a wall) or groups of objects move together.
+///
+/// The objects keep their faces in world space, and the transforms are relative to where the
+/// objects were added to the world. The world transform of an object composes its local transform
+/// with the ones of its ancestors, and its faces are placed from a copy of them at the identity
+/// transform (see `place_faces`), so that the rounding errors of the motions do not add up. The
+/// objects which are not in the graph are roots which do not move.
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: HashMap<ObjectId, Node>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parent(&self, id: ObjectId) -> Option<ObjectId> {
+        self.nodes.get(&id).and_then(|node| node.parent)
+    }
+
+    /// Returns the objects attached to this one, in no particular order
+    pub fn children(&self, id: ObjectId) -> Vec<ObjectId> {
+        self.nodes.iter().filter(|(_, node)| node.parent == Some(id)).map(|(child, _)| *child).collect()
+    }
+
+    pub fn local_transform(&self, id: ObjectId) -> Transform {
+        self.nodes.get(&id).map_or(Transform::identity(), |node| node.local.clone())
+    }
+
+    /// Returns the transform from where the object was added to the world to where it is now
+    pub fn world_transform(&self, id: ObjectId) -> Transform {
+        let mut transform = self.local_transform(id);
+        let mut ancestor = self.parent(id);
+        while let Some(parent) = ancestor {
+            transform = transform.then(&self.local_transform(parent));
+            ancestor = self.parent(parent);
+        }
+        transform
+    }
+
+    /// Sets the motion of the object relative to its parent. Its faces and the ones of its
+    /// descendants must then be moved with `place_faces`.
+    pub fn set_local_transform(&mut self, id: ObjectId, local: Transform) {
+        self.node_mut(id).local = local;
+    }
+
+    /// Attaches the object to a parent (or detaches it), without moving it
+    pub fn set_parent(&mut self, id: ObjectId, parent: Option<ObjectId>) -> Result<(), String> {
+        let mut ancestor = parent;
+        while let Some(other) = ancestor {
+            if other == id {
+                return Err(format!("{id:?} can not be attached to itself or to one of its descendants"));
+            }
+            ancestor = self.parent(other);
+        }
+        let world = self.world_transform(id);
+        let parent_world = parent.map_or(Transform::identity(), |parent| self.world_transform(parent));
+        let node = self.node_mut(id);
+        node.parent = parent;
+        node.local = world.then(&parent_world.inverse());
+        Ok(())
+    }
+
+    /// Returns the object and all its descendants, the parents before their children
+    pub fn subtree(&self, id: ObjectId) -> Vec<ObjectId> {
+        let mut ids = vec![id];
+        let mut i = 0;
+        while i < ids.len() {
+            ids.extend(self.children(ids[i]));
+            i += 1;
+        }
+        ids
+    }
+
+    /// Returns the faces of the object at its current world transform, given its current faces
+    /// (which are only read the first time, or after `forget_rest`)
+    pub fn place_faces(&mut self, id: ObjectId, faces: &[&CubicFace3]) -> Vec<CubicFace3> {
+        let world = self.world_transform(id);
+        let node = self.node_mut(id);
+        let inverse = node.applied.inverse();
+        let rest = node.rest.get_or_insert_with(|| faces.iter().map(|face| face.transformed(&inverse)).collect());
+        let placed = rest.iter().map(|face| face.transformed(&world)).collect();
+        node.applied = world;
+        placed
+    }
+
+    /// Must be called when the faces of the object were changed outside of the graph, so that
+    /// they are copied again the next time the object is placed
+    pub fn forget_rest(&mut self, id: ObjectId) {
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.rest = None;
+        }
+    }
+
+    /// Removes the object from the graph: its children stay where they are, without parent
+    pub fn remove(&mut self, id: ObjectId) {
+        for child in self.children(id) {
+            self.set_parent(child, None).expect("Detaching an object always succeeds");
+        }
+        self.nodes.remove(&id);
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    fn node_mut(&mut self, id: ObjectId) -> &mut Node {
+        self.nodes.entry(id).or_insert_with(Node::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::object::ObjectId;
+    use crate::primitives::transformation::Transform;
+    use crate::primitives::vector::{Vector3, UNIT_Z};
+    use crate::scene_graph::SceneGraph;
+
+    #[test]
+    fn test_scene_graph() {
+        let (wall, door, handle) = (ObjectId::new(0), ObjectId::new(1), ObjectId::new(2));
+        let mut graph = SceneGraph::new();
+        let assert_near = |expected: Vector3, point: Vector3| assert!((expected - point).norm() < 1e-5, "{point:?}");
+        graph.set_parent(door, Some(wall)).unwrap();
+        graph.set_parent(handle, Some(door)).unwrap();
+        assert!(graph.set_parent(wall, Some(handle)).is_err());
+        assert_eq!(vec![wall, door, handle], graph.subtree(wall));
+
+        // The door opens around its hinge, and the wall moves the door and its handle
+        let hinge = Vector3::new(1., 0., 0.);
+        graph.set_local_transform(door, Transform::rotation_around(&UNIT_Z, std::f32::consts::FRAC_PI_2, &hinge));
+        graph.set_local_transform(wall, Transform::translation(Vector3::new(0., 0., 5.)));
+        assert_near(Vector3::new(1., 1., 5.), graph.world_transform(handle).apply(&Vector3::new(2., 0., 0.)));
+        // The faces are placed from their first copy, so that many motions do not add up errors
+        let face = CubicFace3::vface_from_line(Vector3::new(2., 0., 0.), Vector3::new(2., 1., 0.));
+        let mut placed = graph.place_faces(handle, &[&face]);
+        assert_near(Vector3::new(1., 1., 5.), placed[0].points()[0]);
+        for i in 0..=1000 {
+            let angle = std::f32::consts::FRAC_PI_2 * (1. - i as f32 / 1000.);
+            graph.set_local_transform(door, Transform::rotation_around(&UNIT_Z, angle, &hinge));
+            placed = graph.place_faces(handle, &[&placed[0]]);
+        }
+        assert_eq!(Vector3::new(2., 0., 5.), placed[0].points()[0]);
+
+        // Without its parent, the handle stays where it is
+        graph.remove(door);
+        assert_eq!(None, graph.parent(handle));
+        assert_near(Vector3::new(2., 0., 5.), graph.world_transform(handle).apply(&Vector3::new(2., 0., 0.)));
+    }
+}
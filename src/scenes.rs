@@ -0,0 +1,116 @@
+use crate::assets::AssetLoader;
+use crate::scenes::transition::{Transition, TransitionKind};
+use crate::worlds::World;
+
+pub mod transition;
+
+/// Creates the world of a scene, loading its assets with the loader
+pub type SceneBuilder = fn(&AssetLoader) -> World;
+
+/// Holds the scenes of the game, and the world of the current one.
+///
+/// The assets are shared between the scenes: when switching, the new scene is built before the
+/// previous one is dropped, so that the textures used by both are not loaded again. The assets
+/// only used by the previous scene are released with it.
+pub struct SceneManager {
+    loader: AssetLoader,
+    scenes: Vec<(&'static str, SceneBuilder)>,
+    current: usize,
+    world: World,
+    transition: Option<Transition>,
+}
+
+impl SceneManager {
+    /// Creates the manager, starting with the first scene
+    pub fn new(loader: AssetLoader, scenes: Vec<(&'static str, SceneBuilder)>) -> Self {
+        let (_, builder) = scenes.first().expect("At least one scene is required");
+        let world = builder(&loader);
+        Self {
+            loader,
+            scenes,
+            current: 0,
+            world,
+            transition: None,
+        }
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    pub fn current_name(&self) -> &'static str {
+        self.scenes[self.current].0
+    }
+
+    pub fn loader(&self) -> &AssetLoader {
+        &self.loader
+    }
+
+    /// Starts a transition from the provided frame (the last frame of the current scene) to the
+    /// frames rendered afterwards. It should be followed by a scene switch.
+    pub fn start_transition(&mut self, kind: TransitionKind, last_frame: &[u8]) {
+        self.transition = Some(Transition::new(kind, last_frame));
+    }
+
+    /// Applies the transition in progress (if any) to a frame of the current scene
+    pub fn apply_transition(&mut self, frame: &mut [u8]) {
+        if let Some(transition) = &self.transition {
+            transition.apply(frame);
+            if transition.progress() >= 1. {
+                self.transition = None;
+            }
+        }
+    }
+
+    /// Replaces the world by a new instance of the scene
+    pub fn switch_to(&mut self, index: usize) {
+        let (_, builder) = self.scenes[index];
+        self.world = builder(&self.loader);
+        self.current = index;
+    }
+
+    /// Switches to the next scene, going back to the first one after the last one
+    pub fn next(&mut self) {
+        self.switch_to((self.current + 1) % self.scenes.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assets::AssetLoader;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+    use crate::scenes::SceneManager;
+    use crate::worlds::World;
+
+    fn scene_with(loader: &AssetLoader, paths: &[&str]) -> World {
+        let mut world = World::new(Camera::default());
+        for (i, path) in paths.iter().enumerate() {
+            let texture = loader.load_texture(path, &YELLOW);
+            world.add_cube(Cube3::minecraft_like(Vector3::newi(i as i32, 0, 0), &texture, &texture));
+        }
+        world
+    }
+
+    #[test]
+    fn test_switch_releases_unused_textures() {
+        let mut scenes = SceneManager::new(
+            AssetLoader::new(1),
+            vec![
+                ("first", |loader| scene_with(loader, &["shared.txt", "first.txt"])),
+                ("second", |loader| scene_with(loader, &["shared.txt"])),
+            ],
+        );
+        assert_eq!(2, scenes.loader().textures_in_use());
+
+        scenes.next();
+        assert_eq!("second", scenes.current_name());
+        assert_eq!(1, scenes.loader().textures_in_use());
+
+        scenes.next();
+        assert_eq!("first", scenes.current_name());
+        assert_eq!(2, scenes.loader().textures_in_use());
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+/// Duration of the transitions between two scenes
+pub const TRANSITION_DURATION: Duration = Duration::from_millis(600);
+
+/// How the previous scene is replaced by the new one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransitionKind {
+    /// The previous scene fades to black, then the new scene fades in
+    FadeToBlack,
+    /// The previous scene progressively becomes transparent over the new one
+    CrossFade,
+}
+
+/// A transition in progress, holding the last frame of the previous scene
+pub struct Transition {
+    kind: TransitionKind,
+    previous: Vec<u8>,
+    start: Instant,
+}
+
+impl Transition {
+    pub fn new(kind: TransitionKind, previous: &[u8]) -> Self {
+        Self {
+            kind,
+            previous: previous.to_vec(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Progress of the transition, between 0 and 1 (once it is over)
+    pub fn progress(&self) -> f32 {
+        (self.start.elapsed().as_secs_f32() / TRANSITION_DURATION.as_secs_f32()).min(1.)
+    }
+
+    /// Applies the transition to the frame of the new scene
+    pub fn apply(&self, frame: &mut [u8]) {
+        blend(self.kind, &self.previous, frame, self.progress());
+    }
+}
+
+/// Blends the frame of the previous scene into the frame of the current one, `t` being the
+/// progress of the transition (0 shows the previous scene, 1 shows the current one).
+pub fn blend(kind: TransitionKind, previous: &[u8], current: &mut [u8], t: f32) {
+    // Weights of the previous and the current frames
+    let (wp, wc) = match kind {
+        TransitionKind::FadeToBlack if t < 0.5 => (1. - 2. * t, 0.),
+        TransitionKind::FadeToBlack => (0., 2. * t - 1.),
+        TransitionKind::CrossFade => (1. - t, t),
+    };
+    for (pixel, previous) in current.chunks_exact_mut(4).zip(previous.chunks_exact(4)) {
+        for (dst, src) in pixel[..3].iter_mut().zip(&previous[..3]) {
+            *dst = (wp * *src as f32 + wc * *dst as f32).round() as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scenes::transition::{blend, TransitionKind};
+
+    #[test]
+    fn test_blend() {
+        let previous = [200, 100, 0, 255];
+        let blended = |kind, t| {
+            let mut current = [0, 100, 200, 255];
+            blend(kind, &previous, &mut current, t);
+            current
+        };
+        assert_eq!([100, 100, 100, 255], blended(TransitionKind::CrossFade, 0.5));
+        assert_eq!([200, 100, 0, 255], blended(TransitionKind::CrossFade, 0.));
+        assert_eq!([100, 50, 0, 255], blended(TransitionKind::FadeToBlack, 0.25));
+        assert_eq!([0, 0, 0, 255], blended(TransitionKind::FadeToBlack, 0.5));
+        assert_eq!([0, 100, 200, 255], blended(TransitionKind::FadeToBlack, 1.));
+    }
+}
@@ -0,0 +1,136 @@
+use std::path::Path;
+
+use doom::{render_offscreen, RenderMethod};
+use doom::png_saver;
+use doom::viewport::Viewport;
+use doom::worlds::World;
+
+use crate::bookmarks::{load_bookmarks, Bookmark};
+
+/// The images of the grid are this many times smaller than the rendered frames
+const GRID_SCALE_DOWN: u32 = 4;
+
+/// Assembles the images (all of the size of the viewport) in a grid, with `columns` images per
+/// row, each of them scaled down by `GRID_SCALE_DOWN`. Returns the image with its width and height.
+fn grid_image(images: &[Vec<u8>], viewport: Viewport, columns: usize) -> (Vec<u8>, u32, u32) {
+    let (cell_width, cell_height) = (viewport.width / GRID_SCALE_DOWN, viewport.height / GRID_SCALE_DOWN);
+    let rows = images.len().div_ceil(columns);
+    let (width, height) = (cell_width * columns as u32, cell_height * rows as u32);
+    let mut grid = vec![0; (4 * width * height) as usize];
+    for (i, image) in images.iter().enumerate() {
+        let (x0, y0) = ((i % columns) as u32 * cell_width, (i / columns) as u32 * cell_height);
+        for y in 0..cell_height {
+            for x in 0..cell_width {
+                // Average of the block of pixels
+                let mut sum = [0u32; 4];
+                for dy in 0..GRID_SCALE_DOWN {
+                    for dx in 0..GRID_SCALE_DOWN {
+                        let k = 4 * viewport.index(x * GRID_SCALE_DOWN + dx, y * GRID_SCALE_DOWN + dy);
+                        (0..4).for_each(|c| sum[c] += image[k + c] as u32);
+                    }
+                }
+                let k = (4 * ((y0 + y) * width + x0 + x)) as usize;
+                (0..4).for_each(|c| grid[k + c] = (sum[c] / (GRID_SCALE_DOWN * GRID_SCALE_DOWN)) as u8);
+            }
+        }
+    }
+    (grid, width, height)
+}
+
+/// Renders the world from each bookmark with each renderer, writes every image as
+/// `<bookmark>_<renderer>.png` in the directory, and a `grid.png` with one row per bookmark and
+/// one column per renderer.
+fn render_matrix(world: &mut World, bookmarks: &[Bookmark], methods: &[RenderMethod], directory: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(directory).map_err(|err| err.to_string())?;
+    let viewport = world.viewport();
+    let mut images = Vec::new();
+    for bookmark in bookmarks {
+        world.set_camera_pose(bookmark.pose.clone());
+        for method in methods {
+            let image = render_offscreen(world, *method);
+            let name = format!("{}_{}.png", bookmark.name, format!("{method:?}").to_lowercase());
+            let path = directory.join(name);
+            png_saver::save_frame(&path.to_string_lossy(), &image, viewport.width, viewport.height).map_err(|err| err.to_string())?;
+            images.push(image);
+        }
+    }
+    let (grid, width, height) = grid_image(&images, viewport, methods.len());
+    let path = directory.join("grid.png");
+    png_saver::save_frame(&path.to_string_lossy(), &grid, width, height).map_err(|err| err.to_string())
+}
+
+/// Command line tool: renders the world from a list of camera bookmarks (see `Bookmark`), with
+/// several renderers.
+///
+/// `screenshots [--bookmarks <path>] [--renderers <painter,bsp,raytracer>] [--out <directory>]
+/// [--resolution <width>x<height>]`
+///
+/// The bookmarks of the demo world are used when no file is given.
+pub fn run_screenshots(args: &[String], mut world: World, default_bookmarks: Vec<Bookmark>) -> Result<(), String> {
+    let option = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1));
+    let bookmarks = match option("--bookmarks") {
+        Some(path) => load_bookmarks(path)?,
+        None => default_bookmarks,
+    };
+    if bookmarks.is_empty() {
+        return Err("There is no bookmark to render".to_string());
+    }
+    let methods = match option("--renderers") {
+        Some(list) => list.split(',').map(str::parse).collect::<Result<Vec<RenderMethod>, String>>()?,
+        None => vec![RenderMethod::Painter, RenderMethod::Bsp, RenderMethod::Raytracer],
+    };
+    let directory = option("--out").map(String::as_str).unwrap_or("screenshots");
+    if let Some(resolution) = option("--resolution") {
+        world.set_viewport(Viewport::parse(resolution)?);
+    }
+    render_matrix(&mut world, &bookmarks, &methods, Path::new(directory))?;
+    println!("{} images and their grid written to {directory}", bookmarks.len() * methods.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use doom::RenderMethod;
+    use doom::primitives::camera::Camera;
+    use doom::primitives::cube::Cube3;
+    use doom::primitives::position::Pose;
+    use doom::primitives::textures::colored::YELLOW;
+    use doom::primitives::vector::Vector3;
+    use doom::viewport::Viewport;
+    use doom::worlds::World;
+
+    use crate::bookmarks::Bookmark;
+    use crate::screenshots::{grid_image, render_matrix, GRID_SCALE_DOWN};
+
+    #[test]
+    fn test_grid_image() {
+        let viewport = Viewport::new(160, 120);
+        let frame = |value: u8| vec![value; viewport.buffer_len()];
+        let (grid, width, height) = grid_image(&[frame(10), frame(20), frame(30)], viewport, 2);
+        assert_eq!((2 * 160 / GRID_SCALE_DOWN, 2 * 120 / GRID_SCALE_DOWN), (width, height));
+        let pixel = |x: u32, y: u32| grid[(4 * (y * width + x)) as usize];
+        assert_eq!(10, pixel(0, 0));
+        assert_eq!(20, pixel(width - 1, 0));
+        assert_eq!(30, pixel(0, height - 1));
+        // There is no fourth image
+        assert_eq!(0, pixel(width - 1, height - 1));
+    }
+
+    #[test]
+    fn test_render_matrix() {
+        let mut world = World::new(Camera::default());
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(3, 0, 0), &YELLOW, &YELLOW));
+        // The images have the size of the viewport of the world
+        world.set_viewport(Viewport::new(320, 200));
+        let bookmarks = [
+            Bookmark::new("front", Pose::new(Vector3::new(0., 0.5, -0.5), 0.)),
+            Bookmark::new("side", Pose::new(Vector3::new(3.5, -3., -0.5), std::f32::consts::FRAC_PI_2)),
+        ];
+        let directory = std::env::temp_dir().join("test_screenshot_matrix");
+        render_matrix(&mut world, &bookmarks, &[RenderMethod::Painter, RenderMethod::Bsp], &directory).unwrap();
+        for name in ["front_painter", "front_bsp", "side_painter", "side_bsp", "grid"] {
+            assert!(directory.join(format!("{name}.png")).exists(), "{name} is missing");
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,258 @@
+use std::hash::Hasher;
+
+use crate::content_hash::ContentHasher;
+use crate::primitives::camera::Camera;
+use crate::primitives::color::Color;
+use crate::primitives::vector::Vector3;
+
+/// Maximum number of steps along a ray
+const MAX_STEPS: usize = 128;
+/// Distance under which the ray is considered to hit the surface
+const HIT_DISTANCE: f32 = 0.001;
+/// Distance after which the ray is considered to hit nothing
+const MAX_DISTANCE: f32 = 100.;
+/// Step used to estimate the normal of the surface with finite differences
+const NORMAL_EPSILON: f32 = 0.0005;
+/// Direction towards the light used for shading (z goes down, so the light comes from above)
+const LIGHT_DIRECTION: Vector3 = Vector3::newi(0, 0, -1);
+/// Minimum amount of light received by surfaces facing away from the light
+const AMBIENT: f32 = 0.3;
+
+/// A shape described by a signed distance function: the distance from any point to the surface
+/// of the shape, negative inside of the shape.
+pub trait SignedDistance {
+    fn distance(&self, p: &Vector3) -> f32;
+
+    /// Color of the surface near the point `p`
+    fn color(&self, p: &Vector3) -> Color;
+
+    /// Writes the parameters of the shape, to compute the hash of the world
+    fn write_hash(&self, hasher: &mut ContentHasher);
+}
+
+pub struct SdfSphere {
+    center: Vector3,
+    radius: f32,
+    color: Color,
+}
+
+impl SdfSphere {
+    pub fn new(center: Vector3, radius: f32, color: Color) -> Self {
+        Self { center, radius, color }
+    }
+}
+
+impl SignedDistance for SdfSphere {
+    fn distance(&self, p: &Vector3) -> f32 {
+        self.center.line_to(p).norm() - self.radius
+    }
+
+    fn color(&self, _p: &Vector3) -> Color {
+        self.color.clone()
+    }
+
+    fn write_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write_vector(&self.center);
+        hasher.write_f32(self.radius);
+        hasher.write(&self.color.rgba());
+    }
+}
+
+/// Axis aligned box
+pub struct SdfBox {
+    center: Vector3,
+    half_size: Vector3,
+    color: Color,
+}
+
+impl SdfBox {
+    pub fn new(center: Vector3, half_size: Vector3, color: Color) -> Self {
+        Self { center, half_size, color }
+    }
+}
+
+impl SignedDistance for SdfBox {
+    fn distance(&self, p: &Vector3) -> f32 {
+        let d = self.center.line_to(p);
+        let q = Vector3::new(
+            d.x().abs() - self.half_size.x(),
+            d.y().abs() - self.half_size.y(),
+            d.z().abs() - self.half_size.z(),
+        );
+        let outside = Vector3::new(q.x().max(0.), q.y().max(0.), q.z().max(0.)).norm();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.);
+        outside + inside
+    }
+
+    fn color(&self, _p: &Vector3) -> Color {
+        self.color.clone()
+    }
+
+    fn write_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write_vector(&self.center);
+        hasher.write_vector(&self.half_size);
+        hasher.write(&self.color.rgba());
+    }
+}
+
+/// Union of several shapes. When `smoothness` is positive, the shapes are blended together
+/// over this distance instead of meeting at a sharp edge.
+pub struct SdfUnion {
+    shapes: Vec<Box<dyn SignedDistance>>,
+    smoothness: f32,
+}
+
+impl SdfUnion {
+    pub fn new(shapes: Vec<Box<dyn SignedDistance>>, smoothness: f32) -> Self {
+        Self { shapes, smoothness }
+    }
+}
+
+impl SignedDistance for SdfUnion {
+    fn distance(&self, p: &Vector3) -> f32 {
+        let k = self.smoothness;
+        self.shapes
+            .iter()
+            .map(|s| s.distance(p))
+            .reduce(|d1, d2| {
+                if k > 0. {
+                    // Polynomial smooth minimum
+                    let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0., 1.);
+                    d2 + (d1 - d2) * h - k * h * (1. - h)
+                } else {
+                    d1.min(d2)
+                }
+            })
+            .unwrap_or(f32::MAX)
+    }
+
+    fn color(&self, p: &Vector3) -> Color {
+        self.shapes
+            .iter()
+            .min_by(|s1, s2| s1.distance(p).total_cmp(&s2.distance(p)))
+            .map(|s| s.color(p))
+            .unwrap_or(Color::new(0, 0, 0, 255))
+    }
+
+    fn write_hash(&self, hasher: &mut ContentHasher) {
+        hasher.write_usize(self.shapes.len());
+        for shape in &self.shapes {
+            shape.write_hash(hasher);
+        }
+        hasher.write_f32(self.smoothness);
+    }
+}
+
+/// Marches along the ray until the surface of the shape is reached.
+/// Returns the distance along the ray to the hit point.
+pub fn raymarch(shape: &dyn SignedDistance, origin: &Vector3, direction: &Vector3) -> Option<f32> {
+    let mut t = 0.;
+    for _ in 0..MAX_STEPS {
+        let d = shape.distance(&(*origin + *direction * t));
+        if d < HIT_DISTANCE {
+            return Some(t);
+        }
+        t += d;
+        if t > MAX_DISTANCE {
+            break;
+        }
+    }
+    None
+}
+
+/// Estimates the normal of the surface at `p`, using the gradient of the distance function
+fn normal_at(shape: &dyn SignedDistance, p: &Vector3) -> Vector3 {
+    let gradient = |axis: Vector3| {
+        shape.distance(&(*p + axis * NORMAL_EPSILON)) - shape.distance(&(*p - axis * NORMAL_EPSILON))
+    };
+    let mut normal = Vector3::new(
+        gradient(Vector3::newi(1, 0, 0)),
+        gradient(Vector3::newi(0, 1, 0)),
+        gradient(Vector3::newi(0, 0, 1)),
+    );
+    normal.normalize();
+    normal
+}
+
+/// Renders the shape from the point of view of the camera, one ray per pixel.
+/// Pixels where no surface is found are left untouched.
+pub fn draw_raymarching(shape: &dyn SignedDistance, camera: &Camera, frame: &mut [u8]) {
+    let origin = *camera.pose().position();
+    let width = camera.viewport().width as usize;
+    for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+        let x = (i % width) as i16;
+        let y = (i / width) as i16;
+        let mut direction = camera.ray_direction(x, y);
+        direction.normalize();
+
+        if let Some(t) = raymarch(shape, &origin, &direction) {
+            let hit = origin + direction * t;
+            let light = normal_at(shape, &hit).dot(&LIGHT_DIRECTION).max(0.);
+            let shading = AMBIENT + (1. - AMBIENT) * light;
+            let rgba = shape.color(&hit).rgba();
+            pixel.copy_from_slice(&[
+                (rgba[0] as f32 * shading) as u8,
+                (rgba[1] as f32 * shading) as u8,
+                (rgba[2] as f32 * shading) as u8,
+                rgba[3],
+            ]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::camera::Camera;
+    use crate::primitives::color::Color;
+    use crate::primitives::vector::Vector3;
+    use crate::sdf::{draw_raymarching, raymarch, SdfBox, SdfSphere, SdfUnion, SignedDistance};
+    use crate::{HEIGHT, WIDTH};
+
+    fn red() -> Color {
+        Color::new(255, 0, 0, 255)
+    }
+
+    #[test]
+    fn test_distances() {
+        let sphere = SdfSphere::new(Vector3::empty(), 1., red());
+        assert_eq!(1., sphere.distance(&Vector3::new(2., 0., 0.)));
+        assert_eq!(-1., sphere.distance(&Vector3::empty()));
+
+        let cube = SdfBox::new(Vector3::empty(), Vector3::new(1., 1., 1.), red());
+        assert_eq!(1., cube.distance(&Vector3::new(0., 2., 0.)));
+        assert_eq!(-0.5, cube.distance(&Vector3::new(0., 0., 0.5)));
+        assert!(f32::abs(cube.distance(&Vector3::new(2., 2., 1.)) - f32::sqrt(2.)) < 1e-6);
+
+        let union = SdfUnion::new(vec![Box::new(sphere), Box::new(SdfSphere::new(Vector3::new(5., 0., 0.), 1., red()))], 0.);
+        assert_eq!(1., union.distance(&Vector3::new(3., 0., 0.)));
+        let smooth = SdfUnion::new(
+            vec![Box::new(SdfSphere::new(Vector3::empty(), 1., red())), Box::new(SdfSphere::new(Vector3::new(3., 0., 0.), 1., red()))],
+            1.,
+        );
+        // Blending the shapes fills the gap between them
+        assert!(smooth.distance(&Vector3::new(1.5, 0., 0.)) < union.distance(&Vector3::new(1.5, 0., 0.)));
+    }
+
+    #[test]
+    fn test_raymarch() {
+        let sphere = SdfSphere::new(Vector3::new(5., 0., 0.), 1., red());
+        let t = raymarch(&sphere, &Vector3::empty(), &Vector3::newi(1, 0, 0)).unwrap();
+        assert!(f32::abs(t - 4.) < 0.001);
+        assert!(raymarch(&sphere, &Vector3::empty(), &Vector3::newi(0, 1, 0)).is_none());
+    }
+
+    #[test]
+    fn test_draw_raymarching() {
+        let mut camera = Camera::default();
+        camera.set_position(Vector3::new(-5., 0., 0.));
+        let sphere = SdfSphere::new(Vector3::empty(), 1., red());
+        let mut frame = vec![0; (4 * WIDTH * HEIGHT) as usize];
+        draw_raymarching(&sphere, &camera, &mut frame);
+
+        // The center of the screen is covered by the sphere, the corner is not
+        let center = 4 * (HEIGHT / 2 * WIDTH + WIDTH / 2) as usize;
+        assert!(frame[center] > 0);
+        assert_eq!(0, frame[center + 1]);
+        assert_eq!([0, 0, 0, 0], frame[0..4]);
+    }
+}
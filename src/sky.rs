@@ -0,0 +1,236 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::frame::BACKGROUND_COLOR;
+use crate::primitives::camera::Camera;
+use crate::primitives::textures::image::{ImageTexture, Wrap};
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::vector::Vector3;
+
+/// What is seen in the directions where there is no object: the background of the frame
+pub trait Sky: Send + Sync {
+    /// Returns the color seen along a direction (which does not need to be normalized)
+    fn color(&self, direction: &Vector3) -> [u8; 4];
+
+    /// Fills the whole frame with the sky, as seen by the camera
+    fn draw(&self, camera: &Camera, frame: &mut [u8]) {
+        let (origin, du, dv) = camera.ray_direction_derivatives();
+        frame.par_chunks_exact_mut(4 * camera.viewport().width as usize).enumerate().for_each(|(y, row)| {
+            let start = origin + dv * y as f32;
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                pixel.copy_from_slice(&self.color(&(start + du * x as f32)));
+            }
+        });
+    }
+}
+
+/// The same color in every direction
+pub struct SolidSky(pub [u8; 4]);
+
+impl Default for SolidSky {
+    fn default() -> Self {
+        Self(BACKGROUND_COLOR)
+    }
+}
+
+impl Sky for SolidSky {
+    fn color(&self, _direction: &Vector3) -> [u8; 4] {
+        self.0
+    }
+
+    fn draw(&self, _camera: &Camera, frame: &mut [u8]) {
+        frame.chunks_exact_mut(4).for_each(|pixel| pixel.copy_from_slice(&self.0));
+    }
+}
+
+/// A vertical gradient: the color goes from the horizon to the zenith above the horizon, and
+/// quickly fades to the color of the ground below it.
+pub struct GradientSky {
+    pub zenith: [u8; 4],
+    pub horizon: [u8; 4],
+    pub ground: [u8; 4],
+}
+
+/// Below the horizon, the ground color is reached this far (as the sine of the angle) below it
+const GROUND_FADE: f32 = 0.1;
+
+fn mix(a: [u8; 4], b: [u8; 4], t: f32) -> [u8; 4] {
+    std::array::from_fn(|i| (a[i] as f32 + (b[i] as f32 - a[i] as f32) * t).round() as u8)
+}
+
+impl GradientSky {
+    /// A clear blue sky
+    pub fn day() -> Self {
+        Self {
+            zenith: [70, 120, 210, 255],
+            horizon: [200, 220, 240, 255],
+            ground: [120, 115, 100, 255],
+        }
+    }
+}
+
+impl Sky for GradientSky {
+    fn color(&self, direction: &Vector3) -> [u8; 4] {
+        // The z-axis goes down: the elevation is the sine of the angle above the horizon
+        let elevation = -direction.z() / direction.norm();
+        if elevation >= 0. {
+            mix(self.horizon, self.zenith, elevation)
+        } else {
+            mix(self.horizon, self.ground, (-elevation / GROUND_FADE).min(1.))
+        }
+    }
+}
+
+/// A cube map: the sky is drawn on the inside of a cube centered on the camera, infinitely far
+/// away, so that it only depends on the orientation of the camera.
+///
+/// The faces are, in order: +x, -x, +y, -y, +z (below) and -z (above). On the four side faces the
+/// v-coordinate of the textures goes down, and the u-coordinate goes right when looking at the
+/// face; the bottom and top faces are seen with +x at the top of the view.
+pub struct Skybox {
+    faces: [TextureHandle; 6],
+}
+
+/// For each face of the skybox (in the order of `Skybox`), the axis and the sign of the world
+/// coordinate along which u increases, then the same for v
+const SKYBOX_AXES: [(usize, f32, usize, f32); 6] = [
+    (1, 1., 2, 1.),
+    (1, -1., 2, 1.),
+    (0, -1., 2, 1.),
+    (0, 1., 2, 1.),
+    (1, 1., 0, -1.),
+    (1, 1., 0, 1.),
+];
+
+/// Number of stars on each face of the night skybox
+const STARS_PER_FACE: usize = 150;
+
+impl Skybox {
+    pub fn new(faces: [TextureHandle; 6]) -> Self {
+        Self { faces }
+    }
+
+    /// A dark night sky, with randomly placed stars
+    pub fn starry_night(size: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Self::new(std::array::from_fn(|_| {
+            let mut pixels = [10, 15, 40, 255].repeat(size * size);
+            for _ in 0..STARS_PER_FACE {
+                let i = rng.gen_range(0..size * size);
+                let brightness = rng.gen_range(150..=255);
+                pixels[4 * i..4 * i + 3].copy_from_slice(&[brightness, brightness, brightness]);
+            }
+            TextureHandle::new(ImageTexture::from_rgba(size, size, &pixels).with_wrap(Wrap::Clamp))
+        }))
+    }
+
+    /// Returns the index of the face seen along the direction, and the coordinates (between 0 and
+    /// 1) of the direction on this face
+    fn face_coordinates(direction: &Vector3) -> (usize, f32, f32) {
+        let axis = (0..3).fold(0, |best, i| if direction[i].abs() > direction[best].abs() { i } else { best });
+        let face = 2 * axis + (direction[axis] < 0.) as usize;
+        let (u_axis, u_sign, v_axis, v_sign) = SKYBOX_AXES[face];
+        let scale = direction[axis].abs();
+        let u = (u_sign * direction[u_axis] / scale + 1.) / 2.;
+        let v = (v_sign * direction[v_axis] / scale + 1.) / 2.;
+        (face, u.clamp(0., 1.), v.clamp(0., 1.))
+    }
+}
+
+impl Sky for Skybox {
+    fn color(&self, direction: &Vector3) -> [u8; 4] {
+        let (face, u, v) = Self::face_coordinates(direction);
+        let texture = &self.faces[face];
+        texture.color_at(u * texture.width(), v * texture.height()).rgba()
+    }
+}
+
+/// The skies which can be selected while playing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SkyKind {
+    Solid,
+    Gradient,
+    Night,
+}
+
+impl SkyKind {
+    /// Returns the next kind of sky, to cycle through all of them
+    pub fn next(self) -> Self {
+        match self {
+            SkyKind::Solid => SkyKind::Gradient,
+            SkyKind::Gradient => SkyKind::Night,
+            SkyKind::Night => SkyKind::Solid,
+        }
+    }
+
+    pub fn create(self) -> Box<dyn Sky> {
+        match self {
+            SkyKind::Solid => Box::new(SolidSky::default()),
+            SkyKind::Gradient => Box::new(GradientSky::day()),
+            SkyKind::Night => Box::new(Skybox::starry_night(256, 0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frame::BACKGROUND_COLOR;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::color::Color;
+    use crate::primitives::textures::colored::ColoredTexture;
+    use crate::primitives::textures::TextureHandle;
+    use crate::primitives::vector::Vector3;
+    use crate::sky::{GradientSky, Sky, Skybox, SolidSky};
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn test_gradient() {
+        let sky = GradientSky::day();
+        assert_eq!(sky.zenith, sky.color(&Vector3::new(0., 0., -2.)));
+        assert_eq!(sky.horizon, sky.color(&Vector3::new(1., 1., 0.)));
+        assert_eq!(sky.ground, sky.color(&Vector3::new(1., 0., 1.)));
+        // Halfway to the zenith
+        let halfway = sky.color(&Vector3::new(3f32.sqrt(), 0., -1.));
+        assert!(halfway[2] > sky.zenith[2] && halfway[2] < sky.horizon[2]);
+
+        // Looking straight ahead, the top of the frame is above the horizon and the bottom below
+        let mut camera = Camera::default();
+        let viewport = Viewport::new(200, 100);
+        camera.set_viewport(viewport);
+        let mut frame = vec![0; viewport.buffer_len()];
+        sky.draw(&camera, &mut frame);
+        let pixel = |x: u32, y: u32| &frame[4 * viewport.index(x, y)..4 * viewport.index(x, y) + 4];
+        assert_eq!(sky.color(&camera.ray_direction(10, 20)), pixel(10, 20));
+        assert!(pixel(0, 0)[2] < sky.horizon[2]);
+        assert_eq!(sky.ground, pixel(0, 99));
+
+        let mut frame = vec![0; viewport.buffer_len()];
+        SolidSky::default().draw(&camera, &mut frame);
+        assert_eq!(BACKGROUND_COLOR.repeat(viewport.pixel_count()), frame);
+    }
+
+    #[test]
+    fn test_skybox() {
+        let colors: [[u8; 4]; 6] = std::array::from_fn(|i| [40 * i as u8, 0, 0, 255]);
+        let sky = Skybox::new(colors.map(|c| TextureHandle::new(ColoredTexture::new(Color::new(c[0], c[1], c[2], c[3])))));
+        let directions = [
+            Vector3::new(2., 1., -1.),
+            Vector3::new(-2., 1., 1.),
+            Vector3::new(0.5, 1., 0.),
+            Vector3::new(0., -1., 0.9),
+            Vector3::new(0.1, 0.2, 1.),
+            Vector3::new(-0.3, 0., -1.),
+        ];
+        for (direction, color) in directions.iter().zip(colors) {
+            assert_eq!(color, sky.color(direction));
+        }
+
+        // The center of the face in front of the camera, and its borders
+        assert_eq!((0, 0.5, 0.5), Skybox::face_coordinates(&Vector3::new(1., 0., 0.)));
+        assert_eq!((0, 1., 0.), Skybox::face_coordinates(&Vector3::new(1., 1., -1.)));
+        // Looking left from +x, the face -y continues on its right side
+        let (face, u, v) = Skybox::face_coordinates(&Vector3::new(1., -1.0001, 0.));
+        assert!(face == 3 && u > 0.999 && v == 0.5);
+    }
+}
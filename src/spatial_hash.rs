@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use crate::primitives::vector::Vector3;
+
+/// Uniform grid of cubic cells, used to quickly find the objects which are close to each other.
+///
+/// Each object is registered (by id) in all the cells overlapped by its bounding sphere. Queries
+/// only visit the cells overlapped by the query sphere, so they do not depend on the total
+/// number of objects. The results are candidates: the exact collision test is left to the caller.
+pub struct SpatialHashGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32, i32), Vec<usize>>,
+    /// Cells in which each object is registered
+    placed: HashMap<usize, Vec<(i32, i32, i32)>>,
+    /// Lowest layer of cells holding an object (the z-axis goes down)
+    lowest: i32,
+}
+
+impl SpatialHashGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            placed: HashMap::new(),
+            lowest: i32::MIN,
+        }
+    }
+
+    /// Removes all the objects from the grid. The allocated cells are kept to be reused.
+    pub fn clear(&mut self) {
+        self.cells.values_mut().for_each(|ids| ids.clear());
+        self.placed.clear();
+        self.lowest = i32::MIN;
+    }
+
+    fn cell_of(&self, p: Vector3) -> (i32, i32, i32) {
+        (
+            (p.x() / self.cell_size).floor() as i32,
+            (p.y() / self.cell_size).floor() as i32,
+            (p.z() / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Returns all the cells overlapped by the bounding box of the sphere
+    fn cells_of(&self, center: Vector3, radius: f32) -> impl Iterator<Item = (i32, i32, i32)> {
+        let r = Vector3::new(radius, radius, radius);
+        let (i0, j0, k0) = self.cell_of(center - r);
+        let (i1, j1, k1) = self.cell_of(center + r);
+        (i0..=i1).flat_map(move |i| (j0..=j1).flat_map(move |j| (k0..=k1).map(move |k| (i, j, k))))
+    }
+
+    /// Registers the object in the cells overlapped by the sphere, instead of its previous cells
+    pub fn insert(&mut self, id: usize, center: Vector3, radius: f32) {
+        self.remove(id);
+        let cells: Vec<(i32, i32, i32)> = self.cells_of(center, radius).collect();
+        for cell in &cells {
+            self.cells.entry(*cell).or_default().push(id);
+            self.lowest = self.lowest.max(cell.2);
+        }
+        self.placed.insert(id, cells);
+    }
+
+    /// Removes the object from the cells in which it is registered
+    pub fn remove(&mut self, id: usize) {
+        for cell in self.placed.remove(&id).unwrap_or_default() {
+            if let Some(ids) = self.cells.get_mut(&cell) {
+                ids.retain(|other| *other != id);
+            }
+        }
+    }
+
+    /// Returns the ids of the objects which may intersect the sphere, sorted and without duplicates
+    pub fn query(&self, center: Vector3, radius: f32) -> Vec<usize> {
+        let mut ids: Vec<usize> = self
+            .cells_of(center, radius)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Returns the ids of the objects which may be below the point (the z-axis goes down), sorted
+    /// and without duplicates
+    pub fn query_below(&self, point: Vector3) -> Vec<usize> {
+        let (i, j, k) = self.cell_of(point);
+        let mut ids: Vec<usize> = (k..=self.lowest).filter_map(|k| self.cells.get(&(i, j, k))).flatten().copied().collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Returns all the pairs of objects sharing at least one cell, as `(smallest id, largest id)`
+    pub fn candidate_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for ids in self.cells.values() {
+            for (n, i) in ids.iter().enumerate() {
+                for j in &ids[n + 1..] {
+                    pairs.push((*i.min(j), *i.max(j)));
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::vector::Vector3;
+    use crate::spatial_hash::SpatialHashGrid;
+
+    #[test]
+    fn test_query() {
+        let mut grid = SpatialHashGrid::new(2.);
+        grid.insert(0, Vector3::new(0.5, 0.5, 0.5), 0.9);
+        grid.insert(1, Vector3::new(10., 0., 0.), 0.5);
+        grid.insert(2, Vector3::new(1.5, 0., 0.), 1.);
+
+        assert_eq!(vec![0, 2], grid.query(Vector3::new(1., 1., 0.), 0.2));
+        assert_eq!(vec![1], grid.query(Vector3::new(9., 0., 0.), 0.2));
+        assert!(grid.query(Vector3::new(-10., 0., 0.), 1.).is_empty());
+
+        // Moving an object only changes its cells
+        grid.insert(2, Vector3::new(9.5, 0., 0.), 1.);
+        assert_eq!(vec![0], grid.query(Vector3::new(1., 1., 0.), 0.2));
+        assert_eq!(vec![1, 2], grid.query(Vector3::new(9., 0., 0.), 0.2));
+        grid.insert(2, Vector3::new(1.5, 0., 0.), 1.);
+        grid.remove(1);
+        assert!(grid.query(Vector3::new(9., 0., 0.), 0.2).is_empty());
+
+        // Only the objects in the column of cells under the point
+        assert_eq!(vec![0, 2], grid.query_below(Vector3::new(1., 1., -20.)));
+        assert!(grid.query_below(Vector3::new(1., 1., 5.)).is_empty());
+        assert!(grid.query_below(Vector3::new(1., 5., -20.)).is_empty());
+
+        grid.clear();
+        assert!(grid.query(Vector3::new(1., 1., 0.), 0.2).is_empty());
+        assert!(grid.query_below(Vector3::new(1., 1., -20.)).is_empty());
+    }
+
+    #[test]
+    fn test_candidate_pairs() {
+        let mut grid = SpatialHashGrid::new(1.);
+        // Object 0 spans several cells, which are all shared with object 1
+        grid.insert(0, Vector3::new(0., 0., 0.), 1.5);
+        grid.insert(1, Vector3::new(0.5, 0.5, 0.5), 1.);
+        grid.insert(2, Vector3::new(20., 0., 0.), 1.);
+        assert_eq!(vec![(0, 1)], grid.candidate_pairs());
+    }
+}
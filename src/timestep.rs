@@ -0,0 +1,85 @@
+use std::time::Instant;
+
+/// Duration of a simulation step: the world is updated at 60 Hz, whatever the frame rate
+pub const SIMULATION_DT: f32 = 1. / 60.;
+
+/// Maximum number of steps simulated before a frame. After a long frame, the simulation is late
+/// but does not try to catch up, otherwise the next frames would take even longer.
+const MAX_STEPS_PER_FRAME: usize = 5;
+
+/// Splits the elapsed wall-clock time into fixed simulation steps, so that the motion does not
+/// depend on the frame rate. The time which does not fill a whole step is kept for the next
+/// frames, and tells how far the frame is between two steps (to interpolate the drawing).
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+    last_time: Option<Instant>,
+    suspended: bool,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32) -> Self {
+        Self { step, accumulator: 0., last_time: None, suspended: false }
+    }
+
+    /// Returns the number of steps to simulate at this time
+    pub fn advance(&mut self, now: Instant) -> usize {
+        let elapsed = self.last_time.map_or(0., |last| now.duration_since(last).as_secs_f32());
+        self.last_time = Some(now);
+        if self.suspended {
+            return 0;
+        }
+        self.accumulator += elapsed;
+        let steps = (self.accumulator / self.step) as usize;
+        self.accumulator -= steps as f32 * self.step;
+        if steps > MAX_STEPS_PER_FRAME {
+            self.accumulator = 0.;
+        }
+        steps.min(MAX_STEPS_PER_FRAME)
+    }
+
+    /// Fraction of a step elapsed since the last simulated step, between 0 and 1
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0., 1.)
+    }
+
+    /// While suspended (for instance when the window loses its focus), the clock is stopped: the
+    /// time elapsed until it is resumed is never simulated.
+    pub fn set_suspended(&mut self, suspended: bool) {
+        self.suspended = suspended;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::timestep::{FixedTimestep, MAX_STEPS_PER_FRAME};
+
+    #[test]
+    fn test_fixed_steps() {
+        let mut clock = FixedTimestep::new(0.01);
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        assert_eq!(0, clock.advance(start));
+
+        // Fast frames accumulate until a step is complete
+        assert_eq!(0, clock.advance(at(4)));
+        assert!((clock.alpha() - 0.4).abs() < 1e-3);
+        assert_eq!(1, clock.advance(at(12)));
+        assert!((clock.alpha() - 0.2).abs() < 1e-3);
+        // Slow frames simulate several steps
+        assert_eq!(3, clock.advance(at(42)));
+
+        // After a very long frame, the simulation does not try to catch up
+        assert_eq!(MAX_STEPS_PER_FRAME, clock.advance(at(1000)));
+        assert_eq!(0., clock.alpha());
+
+        // No time elapses while suspended
+        clock.set_suspended(true);
+        assert_eq!(0, clock.advance(at(5000)));
+        clock.set_suspended(false);
+        assert_eq!(0, clock.advance(at(5005)));
+        assert_eq!(1, clock.advance(at(5012)));
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bsp::cubic_face_split::DEFAULT_EPSILON;
+use crate::motion_model::DEFAULT_ACC;
+use crate::primitives::textures::Filtering;
+
+/// Numbers which tune the behavior of the engine, gathered here so that a project can change
+/// them in the `[tunables]` section of its configuration file instead of editing the engine.
+///
+/// Lengths are in meters, like the coordinates of the world.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tunables {
+    /// Distances compared as integers (for instance in depth buffers) are in 1/`distance_scale`
+    /// meters: the default is millimeters.
+    pub distance_scale: f32,
+    /// Points closer than this distance to a plane are considered to be on it when building the
+    /// BSP tree
+    pub bsp_epsilon: f32,
+    /// Margin (in pixels) added around the projection of a face, so that the pixels of its edges
+    /// are not missed because of rounding
+    pub screen_padding: u32,
+    /// Distance (in meters) of the near plane of the cameras: the parts of the faces closer to
+    /// the camera than this plane are not drawn
+    pub near_distance: f32,
+    /// Acceleration given by each key press of the camera controls
+    pub acceleration: f32,
+    /// The acceleration of the controls never gets larger than this value
+    pub max_acceleration: f32,
+    /// Once the keys are released, the motion stops when the acceleration goes below this value
+    pub min_acceleration: f32,
+    /// Filtering of the textures which do not select their own
+    pub texture_filtering: Filtering,
+}
+
+impl Default for Tunables {
+    fn default() -> Self {
+        Self {
+            distance_scale: 1000.,
+            bsp_epsilon: DEFAULT_EPSILON,
+            screen_padding: 2,
+            near_distance: 0.05,
+            acceleration: DEFAULT_ACC,
+            max_acceleration: 200.,
+            min_acceleration: 10.,
+            texture_filtering: Filtering::Nearest,
+        }
+    }
+}
+
+static TUNABLES: OnceLock<Tunables> = OnceLock::new();
+
+/// Returns the tunables of the engine (the default ones unless `set_tunables` was called first)
+pub fn tunables() -> &'static Tunables {
+    TUNABLES.get_or_init(Tunables::default)
+}
+
+/// Sets the tunables of the engine. They can only be set once, before they are used (usually when
+/// loading the configuration).
+pub fn set_tunables(tunables: Tunables) -> Result<(), String> {
+    TUNABLES.set(tunables).map_err(|_| "The tunables are already in use and can not be changed".to_string())
+}
@@ -0,0 +1,271 @@
+use crate::frame::{text_advance, Frame};
+use crate::ui::font::GLYPH_HEIGHT;
+use crate::viewport::Viewport;
+
+pub mod debug_hud;
+pub mod font;
+pub mod palette;
+pub mod settings;
+pub mod timing_graph;
+
+/// Size of the text of the widgets (each pixel of the font is a square of this size)
+const TEXT_SCALE: i32 = 2;
+/// Size of the widgets, in pixels
+const WIDGET_WIDTH: i32 = 220;
+const WIDGET_HEIGHT: i32 = 22;
+/// Space between two widgets and around the panel
+const SPACING: i32 = 6;
+
+const PANEL_COLOR: [u8; 4] = [30, 30, 30, 180];
+const WIDGET_COLOR: [u8; 4] = [80, 80, 80, 255];
+const HOVERED_COLOR: [u8; 4] = [110, 110, 110, 255];
+const ACTIVE_COLOR: [u8; 4] = [230, 160, 50, 255];
+const TEXT_COLOR: [u8; 4] = [240, 240, 240, 255];
+
+/// The state of the mouse, in pixels of the frame
+#[derive(Clone, Copy, Default)]
+pub struct MouseState {
+    pub position: Option<(i32, i32)>,
+    /// The left button is currently down
+    pub down: bool,
+    /// The left button was pressed since the last frame
+    pub pressed: bool,
+}
+
+enum DrawCommand {
+    Rect { x: i32, y: i32, width: i32, height: i32, rgba: [u8; 4] },
+    Text { x: i32, y: i32, text: String, rgba: [u8; 4] },
+}
+
+/// A minimal immediate-mode UI: widgets are declared at each frame (between `begin` and `draw`),
+/// and each widget call directly returns whether it was interacted with.
+///
+/// ```ignore
+/// ui.begin(mouse);
+/// if ui.button("Respawn") {
+///     world.respawn();
+/// }
+/// ui.slider("FOV", &mut fov, 30., 120.);
+/// ...
+/// ui.draw(&mut frame);
+/// ```
+///
+/// Widgets are laid out vertically, in a panel whose top left corner is provided at creation.
+/// When reaching the bottom of the frame, they continue in a new column.
+pub struct Ui {
+    x: i32,
+    y: i32,
+    mouse: MouseState,
+    /// Position of the next widget
+    cursor_y: i32,
+    /// Column of the next widget
+    column: i32,
+    /// Bottom of the lowest widget of the panel
+    bottom: i32,
+    /// Identifier of the next widget (its index in the declaration order)
+    next_id: usize,
+    /// Widget which is currently being dragged
+    active: Option<usize>,
+    /// Whether the mouse is over the panel
+    hovered: bool,
+    commands: Vec<DrawCommand>,
+    /// Size of the frame the UI is drawn on
+    viewport: Viewport,
+}
+
+impl Ui {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self {
+            x,
+            y,
+            mouse: MouseState::default(),
+            cursor_y: y + SPACING,
+            column: 0,
+            bottom: y + SPACING,
+            next_id: 0,
+            active: None,
+            hovered: false,
+            commands: Vec::new(),
+            viewport: Viewport::default(),
+        }
+    }
+
+    /// Starts a new frame of the UI
+    pub fn begin(&mut self, mouse: MouseState) {
+        // The panel has the size it had at the previous frame
+        let (width, height) = self.panel_size();
+        self.hovered = mouse.position.is_some_and(|(mx, my)| {
+            mx >= self.x && mx < self.x + width && my >= self.y && my < self.y + height
+        });
+        self.mouse = mouse;
+        if !mouse.down {
+            self.active = None;
+        }
+        self.cursor_y = self.y + SPACING;
+        self.column = 0;
+        self.bottom = self.cursor_y;
+        self.next_id = 0;
+        self.commands.clear();
+    }
+
+    /// Must be called when the frame is resized, so that the widgets stay within it
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    /// Returns true if the UI uses the mouse: the mouse events should not be sent to the world.
+    pub fn wants_mouse(&self) -> bool {
+        self.hovered || self.active.is_some()
+    }
+
+    fn panel_size(&self) -> (i32, i32) {
+        ((self.column + 1) * (WIDGET_WIDTH + SPACING) + SPACING, self.bottom - self.y)
+    }
+
+    /// Reserves the space of the next widget and returns (id, x, y, is hovered)
+    fn allocate(&mut self) -> (usize, i32, i32, bool) {
+        if self.cursor_y + WIDGET_HEIGHT + SPACING > self.viewport.height as i32 && self.cursor_y > self.y + SPACING {
+            self.column += 1;
+            self.cursor_y = self.y + SPACING;
+        }
+        let (x, y) = (self.x + SPACING + self.column * (WIDGET_WIDTH + SPACING), self.cursor_y);
+        self.cursor_y += WIDGET_HEIGHT + SPACING;
+        self.bottom = self.bottom.max(self.cursor_y);
+        let id = self.next_id;
+        self.next_id += 1;
+        let hovered = self
+            .mouse
+            .position
+            .is_some_and(|(mx, my)| mx >= x && mx < x + WIDGET_WIDTH && my >= y && my < y + WIDGET_HEIGHT);
+        (id, x, y, hovered)
+    }
+
+    fn text(&mut self, x: i32, y: i32, text: String) {
+        let y = y + (WIDGET_HEIGHT - GLYPH_HEIGHT as i32 * TEXT_SCALE) / 2;
+        self.commands.push(DrawCommand::Text { x, y, text, rgba: TEXT_COLOR });
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let (_, x, y, _) = self.allocate();
+        self.text(x, y, text.to_string());
+    }
+
+    /// Returns true when the button is clicked
+    pub fn button(&mut self, label: &str) -> bool {
+        let (_, x, y, hovered) = self.allocate();
+        let clicked = hovered && self.mouse.pressed;
+        let rgba = if clicked { ACTIVE_COLOR } else if hovered { HOVERED_COLOR } else { WIDGET_COLOR };
+        self.commands.push(DrawCommand::Rect { x, y, width: WIDGET_WIDTH, height: WIDGET_HEIGHT, rgba });
+        let width = label.chars().count() as i32 * text_advance(TEXT_SCALE);
+        self.text(x + (WIDGET_WIDTH - width) / 2, y, label.to_string());
+        clicked
+    }
+
+    /// Returns true when the value is toggled
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let (_, x, y, hovered) = self.allocate();
+        let toggled = hovered && self.mouse.pressed;
+        if toggled {
+            *value = !*value;
+        }
+        let rgba = if hovered { HOVERED_COLOR } else { WIDGET_COLOR };
+        self.commands.push(DrawCommand::Rect { x, y, width: WIDGET_HEIGHT, height: WIDGET_HEIGHT, rgba });
+        if *value {
+            self.commands.push(DrawCommand::Rect { x: x + 5, y: y + 5, width: WIDGET_HEIGHT - 10, height: WIDGET_HEIGHT - 10, rgba: ACTIVE_COLOR });
+        }
+        self.text(x + WIDGET_HEIGHT + SPACING, y, label.to_string());
+        toggled
+    }
+
+    /// Returns true when the value is changed. The slider is dragged while the mouse is down.
+    pub fn slider(&mut self, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let (id, x, y, hovered) = self.allocate();
+        if hovered && self.mouse.pressed {
+            self.active = Some(id);
+        }
+        let mut changed = false;
+        if self.active == Some(id) {
+            if let Some((mx, _)) = self.mouse.position {
+                let ratio = ((mx - x) as f32 / WIDGET_WIDTH as f32).clamp(0., 1.);
+                let new_value = min + ratio * (max - min);
+                changed = new_value != *value;
+                *value = new_value;
+            }
+        }
+        let rgba = if hovered || self.active == Some(id) { HOVERED_COLOR } else { WIDGET_COLOR };
+        self.commands.push(DrawCommand::Rect { x, y, width: WIDGET_WIDTH, height: WIDGET_HEIGHT, rgba });
+        let ratio = ((*value - min) / (max - min)).clamp(0., 1.);
+        let handle = x + (ratio * (WIDGET_WIDTH - 6) as f32) as i32;
+        self.commands.push(DrawCommand::Rect { x: handle, y, width: 6, height: WIDGET_HEIGHT, rgba: ACTIVE_COLOR });
+        self.text(x + SPACING, y, format!("{label}: {:.1}", *value));
+        changed
+    }
+
+    /// Draws the widgets declared since `begin` onto the frame
+    pub fn draw(&self, frame: &mut Frame) {
+        let (width, height) = self.panel_size();
+        frame.fill_rect(self.x, self.y, width, height, PANEL_COLOR);
+        frame.draw_rect(self.x, self.y, width, height, WIDGET_COLOR);
+        for command in &self.commands {
+            match command {
+                DrawCommand::Rect { x, y, width, height, rgba } => frame.fill_rect(*x, *y, *width, *height, *rgba),
+                DrawCommand::Text { x, y, text, rgba } => frame.draw_text(*x, *y, text, *rgba, TEXT_SCALE),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::{MouseState, Ui, SPACING, WIDGET_HEIGHT, WIDGET_WIDTH};
+
+    fn click_at(x: i32, y: i32) -> MouseState {
+        MouseState {
+            position: Some((x, y)),
+            down: true,
+            pressed: true,
+        }
+    }
+
+    #[test]
+    fn test_widgets_interaction() {
+        let mut ui = Ui::new(0, 0);
+        let first_row = SPACING + WIDGET_HEIGHT / 2;
+        let second_row = first_row + WIDGET_HEIGHT + SPACING;
+        let mut checked = false;
+
+        // Clicking on the button does not affect the checkbox
+        ui.begin(click_at(SPACING + 10, first_row));
+        assert!(ui.button("Button"));
+        assert!(!ui.checkbox("Check", &mut checked));
+        assert!(!checked);
+
+        // Clicking on the checkbox toggles it
+        ui.begin(click_at(SPACING + 10, second_row));
+        assert!(!ui.button("Button"));
+        assert!(ui.checkbox("Check", &mut checked));
+        assert!(checked);
+    }
+
+    #[test]
+    fn test_slider_drag() {
+        let mut ui = Ui::new(0, 0);
+        let row = SPACING + WIDGET_HEIGHT / 2;
+        let mut value = 0.;
+
+        ui.begin(click_at(SPACING + WIDGET_WIDTH / 2, row));
+        assert!(ui.slider("Value", &mut value, 0., 10.));
+        assert_eq!(5., value);
+
+        // While the mouse is down, the slider follows it even outside of the widget
+        ui.begin(MouseState { position: Some((SPACING + WIDGET_WIDTH * 2, 200)), down: true, pressed: false });
+        ui.slider("Value", &mut value, 0., 10.);
+        assert_eq!(10., value);
+        assert!(ui.wants_mouse());
+
+        // Once released, it does not move anymore
+        ui.begin(MouseState { position: Some((SPACING, row)), down: false, pressed: false });
+        assert!(!ui.slider("Value", &mut value, 0., 10.));
+        assert_eq!(10., value);
+    }
+}
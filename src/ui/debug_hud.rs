@@ -0,0 +1,90 @@
+use crate::frame::{text_advance, Frame};
+use crate::ui::font::GLYPH_HEIGHT;
+use crate::worlds::World;
+
+const TEXT_COLOR: [u8; 4] = [255, 255, 255, 255];
+const BACKGROUND: [u8; 4] = [0, 0, 0, 140];
+/// Distance between the text and the corner of the screen, and around the text
+const MARGIN: i32 = 4;
+const LINE_HEIGHT: i32 = GLYPH_HEIGHT as i32 + 3;
+
+/// Text drawn in the top right corner of the screen with the state of the engine (FPS, pose of the
+/// camera, number of objects), instead of printing it to the console
+pub struct DebugHud {
+    lines: Vec<String>,
+}
+
+impl DebugHud {
+    pub fn new() -> Self {
+        Self { lines: Vec::new() }
+    }
+
+    /// Updates the text with the state of the current frame
+    pub fn update(&mut self, fps: Option<f32>, world: &World) {
+        let pose = world.camera().pose();
+        let position = pose.position();
+        let objects = world.objects().count();
+        let faces: usize = world.objects().map(|(_, object)| object.get_all_faces().len()).sum();
+        self.lines = vec![
+            fps.map_or("FPS --".to_string(), |fps| format!("FPS {fps:.1}")),
+            format!("POS {:.2} {:.2} {:.2}", position.x(), position.y(), position.z()),
+            format!("YAW {:.2} PITCH {:.2} ROLL {:.2}", pose.rotation_z(), pose.pitch(), pose.roll()),
+            format!("OBJECTS {objects} FACES {faces}"),
+        ];
+        if world.is_spectating() {
+            self.lines.push("SPECTATING".to_string());
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Draws the lines over a dark background, so that they can be read over any scene
+    pub fn draw(&self, frame: &mut Frame) {
+        let width = self.lines.iter().map(|line| line.len() as i32).max().unwrap_or(0) * text_advance(1);
+        let x = frame.viewport().width as i32 - width - 2 * MARGIN;
+        frame.fill_rect(x - MARGIN, MARGIN, width + 2 * MARGIN, self.lines.len() as i32 * LINE_HEIGHT + MARGIN, BACKGROUND);
+        for (i, line) in self.lines.iter().enumerate() {
+            frame.draw_text(x, 2 * MARGIN + i as i32 * LINE_HEIGHT, line, TEXT_COLOR, 1);
+        }
+    }
+}
+
+impl Default for DebugHud {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frame::Frame;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+    use crate::ui::debug_hud::DebugHud;
+    use crate::viewport::Viewport;
+    use crate::worlds::World;
+
+    #[test]
+    fn test_debug_hud() {
+        let mut world = World::new(Camera::default());
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(2, 0, 0), &YELLOW, &YELLOW));
+        world.set_camera_position(Vector3::new(1., -2., 0.5));
+        let mut hud = DebugHud::new();
+        hud.update(Some(59.95), &world);
+        assert_eq!("FPS 60.0", hud.lines()[0]);
+        assert_eq!("POS 1.00 -2.00 0.50", hud.lines()[1]);
+        assert_eq!("OBJECTS 1 FACES 6", hud.lines()[3]);
+
+        // The text is drawn in the top right corner
+        let viewport = Viewport::new(320, 100);
+        let mut buffer = vec![0; viewport.buffer_len()];
+        hud.draw(&mut Frame::new(&mut buffer, viewport));
+        let lit = |x: usize, y: usize| buffer[(y * 320 + x) * 4] > 0;
+        assert!((250..320).any(|x| (0..20).any(|y| lit(x, y))));
+        assert!(!(0..100).any(|x| (0..100).any(|y| lit(x, y))));
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+/// Colors of the debug visualizations (timing graph, differences between renderers...)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugPalette {
+    #[default]
+    Standard,
+    /// Colors which remain distinct with the common color vision deficiencies (from the palette of
+    /// Okabe and Ito), and a blue to yellow ramp instead of shades of red.
+    ColorBlind,
+}
+
+const STANDARD_CATEGORIES: [[u8; 4]; 6] =
+    [[80, 160, 255, 255], [250, 200, 60, 255], [240, 90, 80, 255], [120, 220, 120, 255], [190, 120, 250, 255], [120, 230, 230, 255]];
+
+const COLOR_BLIND_CATEGORIES: [[u8; 4]; 6] =
+    [[86, 180, 233, 255], [240, 228, 66, 255], [213, 94, 0, 255], [0, 158, 115, 255], [204, 121, 167, 255], [230, 159, 0, 255]];
+
+impl DebugPalette {
+    /// Returns the palette following this one, to cycle through all of them
+    pub fn next(self) -> Self {
+        match self {
+            DebugPalette::Standard => DebugPalette::ColorBlind,
+            DebugPalette::ColorBlind => DebugPalette::Standard,
+        }
+    }
+
+    /// Color of the `index`-th category (the colors repeat after a few categories)
+    pub fn category(self, index: usize) -> [u8; 4] {
+        let colors = match self {
+            DebugPalette::Standard => &STANDARD_CATEGORIES,
+            DebugPalette::ColorBlind => &COLOR_BLIND_CATEGORIES,
+        };
+        colors[index % colors.len()]
+    }
+
+    /// Color of an intensity, from 0 (barely visible) to 255 (strongest)
+    pub fn heat(self, value: u8) -> [u8; 4] {
+        match self {
+            DebugPalette::Standard => [128 + value / 2, 0, 0, 255],
+            DebugPalette::ColorBlind => {
+                let t = value as f32 / 255.;
+                let mix = |from: u8, to: u8| (from as f32 + t * (to as f32 - from as f32)).round() as u8;
+                [mix(0, 240), mix(114, 228), mix(178, 66), 255]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::palette::DebugPalette;
+
+    #[test]
+    fn test_palettes() {
+        for palette in [DebugPalette::Standard, DebugPalette::ColorBlind] {
+            // The categories and the ends of the ramp can be told apart
+            let categories: Vec<_> = (0..6).map(|i| palette.category(i)).collect();
+            assert!((1..6).all(|i| !categories[..i].contains(&categories[i])));
+            assert_eq!(categories[0], palette.category(6));
+            assert_ne!(palette.heat(0), palette.heat(255));
+            assert_eq!(palette, palette.next().next());
+        }
+        // The color-blind ramp also changes in brightness, not only in hue
+        let luminance = |[r, g, b, _]: [u8; 4]| 0.3 * r as f32 + 0.59 * g as f32 + 0.11 * b as f32;
+        assert!(luminance(DebugPalette::ColorBlind.heat(255)) > luminance(DebugPalette::ColorBlind.heat(0)) + 80.);
+    }
+}
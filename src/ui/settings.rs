@@ -0,0 +1,116 @@
+use winit::event::VirtualKeyCode;
+
+use crate::config::{Config, CONFIG_PATH};
+use crate::ui::Ui;
+
+/// In-game settings screen, editing the configuration of the game.
+pub struct SettingsMenu {
+    /// Index of the action waiting for a new key
+    rebinding: Option<usize>,
+}
+
+impl SettingsMenu {
+    pub fn new() -> Self {
+        Self { rebinding: None }
+    }
+
+    /// Declares the widgets of the menu. Returns true if the configuration was modified, in which
+    /// case the caller should apply it.
+    pub fn show(&mut self, ui: &mut Ui, config: &mut Config) -> bool {
+        let mut changed = false;
+        changed |= ui.slider("Scale", &mut config.resolution_scale, 0.5, 2.);
+        changed |= ui.slider("FOV", &mut config.fov, 30., 120.);
+        changed |= ui.slider("Turn", &mut config.sensitivity, 0.02, 0.5);
+        changed |= ui.slider("Mouse", &mut config.mouse_sensitivity, 0.0005, 0.01);
+        changed |= ui.slider("Distance", &mut config.draw_distance, 5., 200.);
+        changed |= ui.checkbox("Fog", &mut config.fog);
+        if ui.button(&format!("Renderer: {:?}", config.renderer)) {
+            config.renderer = config.renderer.next();
+            changed = true;
+        }
+
+        // Clicking on an action waits for the next key press to bind it
+        for (i, (action, key, _)) in config.key_bindings.actions_mut().into_iter().enumerate() {
+            let label = if self.rebinding == Some(i) { format!("{action}: ?") } else { format!("{action}: {key}") };
+            if ui.button(&label) {
+                self.rebinding = Some(i);
+            }
+        }
+
+        if ui.button(&format!("Palette: {:?}", config.debug_palette)) {
+            config.debug_palette = config.debug_palette.next();
+        }
+
+        if ui.button("Save") {
+            match config.save(CONFIG_PATH) {
+                Ok(()) => println!("Settings saved to {CONFIG_PATH}"),
+                Err(err) => println!("Could not save the settings: {err}"),
+            }
+        }
+        changed
+    }
+
+    /// Returns true if the menu waits for a key to be pressed
+    pub fn is_rebinding(&self) -> bool {
+        self.rebinding.is_some()
+    }
+
+    /// Binds the key to the action being edited. The action which used this key, if any, takes
+    /// the previous key of the edited action, so that no key triggers two actions.
+    pub fn bind_key(&mut self, config: &mut Config, key: VirtualKeyCode) {
+        let Some(i) = self.rebinding.take() else {
+            return;
+        };
+        let key = format!("{key:?}");
+        let mut actions = config.key_bindings.actions_mut();
+        let Some(previous) = actions.get(i).map(|(_, name, _)| name.to_string()) else {
+            return;
+        };
+        for (j, (_, name, _)) in actions.iter_mut().enumerate() {
+            if j == i {
+                **name = key.clone();
+            } else if **name == key {
+                **name = previous.clone();
+            }
+        }
+    }
+}
+
+impl Default for SettingsMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::VirtualKeyCode;
+
+    use crate::config::Config;
+    use crate::ui::settings::SettingsMenu;
+    use crate::ui::{MouseState, Ui, SPACING, WIDGET_HEIGHT};
+
+    #[test]
+    fn test_rebind_key() {
+        let mut ui = Ui::new(0, 0);
+        let mut menu = SettingsMenu::new();
+        let mut config = Config::default();
+
+        // The first binding (forward) is the 8th widget
+        let row = SPACING + 7 * (WIDGET_HEIGHT + SPACING) + WIDGET_HEIGHT / 2;
+        ui.begin(MouseState { position: Some((SPACING + 10, row)), down: true, pressed: true });
+        menu.show(&mut ui, &mut config);
+        assert!(menu.is_rebinding());
+
+        menu.bind_key(&mut config, VirtualKeyCode::W);
+        assert!(!menu.is_rebinding());
+        assert_eq!("W", config.key_bindings.forward);
+
+        // Binding a used key swaps the keys of the two actions
+        ui.begin(MouseState { position: Some((SPACING + 10, row)), down: true, pressed: true });
+        menu.show(&mut ui, &mut config);
+        menu.bind_key(&mut config, VirtualKeyCode::R);
+        assert_eq!("R", config.key_bindings.forward);
+        assert_eq!("W", config.key_bindings.turn_left);
+    }
+}
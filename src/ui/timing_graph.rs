@@ -0,0 +1,56 @@
+use crate::frame::Frame;
+use crate::profiler::{FrameProfiler, Stage, STAGES};
+use crate::ui::font::GLYPH_HEIGHT;
+use crate::ui::palette::DebugPalette;
+use crate::WIDTH;
+
+/// Width of the bar of one frame, in pixels
+pub const BAR_WIDTH: i32 = 3;
+/// Number of frames displayed by the graph (enough to fill a frame of the default width)
+pub const GRAPH_FRAMES: usize = (WIDTH as i32 / BAR_WIDTH) as usize;
+/// Vertical scale of the graph
+const PIXELS_PER_MS: f32 = 4.;
+/// Duration of a frame at 60 FPS, displayed as a reference line
+const TARGET_FRAME_MS: f32 = 1000. / 60.;
+
+const TARGET_COLOR: [u8; 4] = [255, 255, 255, 160];
+const TEXT_COLOR: [u8; 4] = [255, 255, 255, 255];
+
+/// Opacity of the bars
+const BAR_ALPHA: u8 = 220;
+
+fn stage_color(stage: Stage, palette: DebugPalette) -> [u8; 4] {
+    let [r, g, b, _] = palette.category(stage as usize);
+    [r, g, b, BAR_ALPHA]
+}
+
+/// Draws the timings of the last frames as stacked bars scrolling across the bottom of the
+/// screen, the most recent frame being on the right.
+pub fn draw_timing_graph(frame: &mut Frame, profiler: &FrameProfiler, palette: DebugPalette) {
+    let viewport = frame.viewport();
+    let bottom = viewport.height as i32;
+    let n = profiler.history().count() as i32;
+    for (i, timings) in profiler.history().enumerate() {
+        let x = viewport.width as i32 - (n - i as i32) * BAR_WIDTH;
+        let mut y = bottom;
+        for stage in STAGES {
+            let height = (timings[stage as usize].as_secs_f32() * 1000. * PIXELS_PER_MS).round() as i32;
+            frame.fill_rect(x, y - height, BAR_WIDTH, height, stage_color(stage, palette));
+            y -= height;
+        }
+    }
+
+    // Reference line for 60 FPS
+    let target_y = bottom - (TARGET_FRAME_MS * PIXELS_PER_MS) as i32;
+    frame.fill_rect(0, target_y, viewport.width as i32, 1, TARGET_COLOR);
+    frame.draw_text(4, target_y - GLYPH_HEIGHT as i32 - 2, "16.7 MS", TEXT_COLOR, 1);
+
+    // Legend, with the average duration of each stage
+    let average = profiler.average();
+    for (i, stage) in STAGES.iter().enumerate() {
+        let y = target_y - (STAGES.len() as i32 - i as i32) * (GLYPH_HEIGHT as i32 + 3) - 12;
+        frame.fill_rect(4, y, 7, GLYPH_HEIGHT as i32, stage_color(*stage, palette));
+        let ms = average[*stage as usize].as_secs_f32() * 1000.;
+        frame.draw_text(14, y, &format!("{stage:?} {ms:.1} MS"), TEXT_COLOR, 1);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::primitives::camera::Camera;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::object::{Object, ObjectId, ObjectKind};
+use crate::primitives::snapping::Grid;
+use crate::primitives::transformation::Transform;
+use crate::primitives::vector::Vector3;
+use crate::spatial_hash::SpatialHashGrid;
+
+/// What an object can see of the world while it is updated
+pub struct WorldQuery<'a> {
+    camera: &'a Camera,
+    ids: &'a [ObjectId],
+    grid: &'a SpatialHashGrid,
+}
+
+impl<'a> WorldQuery<'a> {
+    pub(crate) fn new(camera: &'a Camera, ids: &'a [ObjectId], grid: &'a SpatialHashGrid) -> Self {
+        Self { camera, ids, grid }
+    }
+
+    /// The camera of the player
+    pub fn camera(&self) -> &Camera {
+        self.camera
+    }
+
+    /// Returns the objects which may be closer than `radius` to the position (their bounding
+    /// spheres share a cell of the spatial grid with the sphere), including the updated object
+    pub fn objects_around(&self, position: &Vector3, radius: f32) -> Vec<ObjectId> {
+        self.grid.query(*position, radius).into_iter().map(|i| self.ids[i]).collect()
+    }
+}
+
+/// Behavior of an object, run by `World::update` at each step of the simulation (but not while it
+/// is paused). The object can move its faces: the world updates its BSP afterwards.
+pub trait Updatable {
+    fn tick(&mut self, dt: f32, world: &WorldQuery);
+}
+
+/// An object moved by a function of the time since it was added: rotating doors, elevators or
+/// moving platforms. The transform returned by the function is relative to where the object was
+/// at the time 0, and the faces are placed from a copy of them at that time.
+pub struct Animated<O: Object> {
+    object: O,
+    motion: Box<dyn Fn(f32) -> Transform>,
+    time: f32,
+    /// Transform applied to the faces of the object
+    applied: Transform,
+    /// Faces of the object at the time 0
+    rest: Vec<CubicFace3>,
+}
+
+impl<O: Object> Animated<O> {
+    pub fn new(object: O, motion: impl Fn(f32) -> Transform + 'static) -> Self {
+        let rest = object.get_all_faces().into_iter().cloned().collect();
+        Self { object, motion: Box::new(motion), time: 0., applied: Transform::identity(), rest }
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Returns the faces of the object at the time 0, after it was moved from the outside
+    fn rest_of_object(&self) -> Vec<CubicFace3> {
+        let inverse = self.applied.inverse();
+        self.object.get_all_faces().into_iter().map(|face| face.transformed(&inverse)).collect()
+    }
+}
+
+impl<O: Object> Updatable for Animated<O> {
+    fn tick(&mut self, dt: f32, _world: &WorldQuery) {
+        self.time += dt;
+        let transform = (self.motion)(self.time);
+        let faces: Vec<CubicFace3> = self.rest.iter().map(|face| face.transformed(&transform)).collect();
+        self.object.set_faces(&faces);
+        self.applied = transform;
+    }
+}
+
+impl<O: Object> Object for Animated<O> {
+    fn kind(&self) -> ObjectKind {
+        self.object.kind()
+    }
+
+    fn get_visible_faces(&self, camera: &Camera) -> Vec<&CubicFace3> {
+        self.object.get_visible_faces(camera)
+    }
+
+    fn get_all_faces(&self) -> Vec<&CubicFace3> {
+        self.object.get_all_faces()
+    }
+
+    fn rotate(&mut self, by: f32) {
+        self.object.rotate(by);
+        self.rest = self.rest_of_object();
+    }
+
+    fn transform(&mut self, transform: &Transform) {
+        self.object.transform(transform);
+        self.rest = self.rest_of_object();
+    }
+
+    fn set_faces(&mut self, faces: &[CubicFace3]) {
+        self.object.set_faces(faces);
+        self.rest = self.rest_of_object();
+    }
+
+    fn snap(&mut self, grid: &Grid) {
+        self.object.snap(grid);
+        self.rest = self.rest_of_object();
+    }
+
+    fn collides_with(&self, point: &Vector3, radius: f32) -> bool {
+        self.object.collides_with(point, radius)
+    }
+
+    fn as_updatable(&mut self) -> Option<&mut dyn Updatable> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::drawable::Drawable;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::transformation::Transform;
+    use crate::primitives::vector::{Vector3, UNIT_Z};
+    use crate::updatable::Animated;
+    use crate::worlds::World;
+
+    #[test]
+    fn test_animated_objects() {
+        let mut world = World::new(Camera::default());
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(3, 3, 0), &YELLOW, &YELLOW));
+        // A door turning by a quarter of a turn in one second around its hinge, and an elevator
+        let door = CubicFace3::vface_from_line(Vector3::newi2(2, 0), Vector3::newi2(2, 1));
+        let door = world.add_updatable(Animated::new(door, |t| Transform::rotation_around(&UNIT_Z, t.min(1.) * PI / 2., &Vector3::newi2(2, 0))));
+        let cube = Cube3::minecraft_like(Vector3::newi(5, 0, 0), &YELLOW, &YELLOW);
+        let elevator = world.add_updatable(Animated::new(cube, |t| Transform::translation(UNIT_Z * -t)));
+        world.set_use_bsp(true);
+        for _ in 0..20 {
+            world.update(0.1);
+        }
+        // The door turned around its hinge until it was open
+        let [hinge, side, _, _] = world.get(door).unwrap().get_all_faces()[0].points();
+        assert!(hinge.line_to(&Vector3::newi2(2, 0)).norm() < 1e-4, "{hinge:?}");
+        assert!(side.y().abs() < 1e-4 && (side.x() - 2.).abs() > 0.999, "{side:?}");
+        let top = world.get(elevator).unwrap().get_all_faces()[0].points()[0];
+        assert!((top.z() + 2.).abs() < 1e-4, "{top:?}");
+        assert!(world.bsp().is_some());
+
+        // Nothing moves while the simulation is paused
+        world.set_time_scale(0.);
+        world.update(0.1);
+        assert_eq!(top, world.get(elevator).unwrap().get_all_faces()[0].points()[0]);
+
+        // The objects turned from the outside keep turning with their motion
+        world.set_time_scale(1.);
+        world.get_mut(elevator).unwrap().rotate(PI);
+        world.update(0.1);
+        let top = world.get(elevator).unwrap().get_all_faces()[0].points()[0];
+        assert!(top.x() < 0. && (top.z() + 2.1).abs() < 1e-4, "{top:?}");
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{HEIGHT, WIDTH};
+
+/// Size (in pixels) of the frames rendered by a camera
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::new(WIDTH, HEIGHT)
+    }
+}
+
+impl Viewport {
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// Parses a resolution written as `<width>x<height>`, for instance `1280x720`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid resolution '{text}', expected <width>x<height>");
+        let (width, height) = text.split_once('x').ok_or_else(invalid)?;
+        let width: u32 = width.trim().parse().map_err(|_| invalid())?;
+        let height: u32 = height.trim().parse().map_err(|_| invalid())?;
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        Ok(Self::new(width, height))
+    }
+
+    /// Returns the viewport which fills a window of this size (in physical pixels), when each
+    /// pixel of the frame covers `scale` pixels of the window
+    pub fn fitting(window_width: u32, window_height: u32, scale: f64) -> Self {
+        let size = |length: u32| ((length as f64 / scale).round() as u32).max(1);
+        Self::new(size(window_width), size(window_height))
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+
+    /// Length of an RGBA buffer of this size
+    pub fn buffer_len(&self) -> usize {
+        4 * self.pixel_count()
+    }
+
+    /// Index of the pixel in a buffer with one entry per pixel, row after row
+    pub fn index(&self, x: u32, y: u32) -> usize {
+        (x + y * self.width) as usize
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32
+    }
+}
+
+/// A rectangle of pixels of a frame, whose top left corner is (x, y)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub const fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// The rectangle covering the whole viewport
+    pub fn full(viewport: Viewport) -> Self {
+        Self::new(0, 0, viewport.width, viewport.height)
+    }
+
+    /// The viewport of the frames drawn in this rectangle
+    pub fn viewport(&self) -> Viewport {
+        Viewport::new(self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok(Viewport::new(1280, 720)), Viewport::parse("1280x720"));
+        assert!(Viewport::parse("1280").is_err());
+        assert!(Viewport::parse("0x720").is_err());
+        assert!(Viewport::parse("ax720").is_err());
+
+        let viewport = Viewport::fitting(1920, 1081, 2.);
+        assert_eq!(Viewport::new(960, 541), viewport);
+        assert_eq!(4 * 960 * 541, viewport.buffer_len());
+        assert!(viewport.contains(959, 540) && !viewport.contains(960, 0) && !viewport.contains(0, -1));
+    }
+}
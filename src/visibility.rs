@@ -0,0 +1,136 @@
+use crate::primitives::camera::Camera;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::point::Point2;
+use crate::viewport::Viewport;
+
+/// What happened to one face when rendering from a camera pose
+pub struct FaceVisibility {
+    pub object: usize,
+    /// Index of the face in its object
+    pub face: usize,
+    /// The object is within the draw distance
+    pub in_range: bool,
+    /// The face is oriented towards the camera
+    pub front_facing: bool,
+    /// The face overlaps the screen
+    pub in_frustum: bool,
+    /// Number of pixels where the face is the closest one (0 if it is fully occluded)
+    pub pixels: u32,
+}
+
+/// Statistics of the culling stages, face by face, for a camera pose.
+///
+/// The stages are applied in this order: draw distance, backface culling, frustum culling and
+/// occlusion (a face which passed all the previous stages but does not own any pixel was drawn
+/// for nothing by the painter's algorithm).
+pub struct VisibilityStats {
+    faces: Vec<FaceVisibility>,
+}
+
+impl VisibilityStats {
+    /// Computes the statistics of the faces of each object, given whether the object is within
+    /// the draw distance.
+    pub fn compute(objects: &[(Vec<&CubicFace3>, bool)], camera: &Camera) -> Self {
+        let mut faces = Vec::new();
+        // Closest face of each pixel: (distance in mm, index in `faces`)
+        let viewport = camera.viewport();
+        let mut depth: Vec<Option<(u32, usize)>> = vec![None; viewport.pixel_count()];
+        for (object, (object_faces, in_range)) in objects.iter().enumerate() {
+            for (i, face) in object_faces.iter().enumerate() {
+                let front_facing = face.normal().dot(&(face.center() - *camera.pose().position())) < 0.;
+                let projection = face.projection(camera);
+                let in_frustum = overlaps_screen(projection.points(), viewport);
+                if *in_range && front_facing && in_frustum {
+                    let (xmin, ymin, xmax, ymax) = projection.bounding_box();
+                    for y in ymin..ymax {
+                        for x in xmin..xmax {
+                            if !projection.contains(&Point2::new(x as f32, y as f32)) {
+                                continue;
+                            }
+                            if let Some((distance, _)) = projection.raytracing(x as i16, y as i16) {
+                                let pixel = &mut depth[viewport.index(x, y)];
+                                if pixel.is_none_or(|(closest, _)| distance < closest) {
+                                    *pixel = Some((distance, faces.len()));
+                                }
+                            }
+                        }
+                    }
+                }
+                faces.push(FaceVisibility { object, face: i, in_range: *in_range, front_facing, in_frustum, pixels: 0 });
+            }
+        }
+        for (_, face) in depth.into_iter().flatten() {
+            faces[face].pixels += 1;
+        }
+        Self { faces }
+    }
+
+    /// Returns the statistics as CSV, with one line per face
+    pub fn to_csv(&self) -> String {
+        let mut csv = "object,face,in_range,front_facing,in_frustum,pixels\n".to_string();
+        for f in &self.faces {
+            csv += &format!("{},{},{},{},{},{}\n", f.object, f.face, f.in_range, f.front_facing, f.in_frustum, f.pixels);
+        }
+        csv
+    }
+
+    /// Returns the number of faces remaining after each stage
+    pub fn summary(&self) -> String {
+        let in_range = self.faces.iter().filter(|f| f.in_range);
+        let front_facing = in_range.clone().filter(|f| f.front_facing);
+        let in_frustum = front_facing.clone().filter(|f| f.in_frustum);
+        let visible = in_frustum.clone().filter(|f| f.pixels > 0);
+        format!(
+            "{} faces, {} in range, {} front facing, {} in the frustum, {} visible",
+            self.faces.len(),
+            in_range.count(),
+            front_facing.count(),
+            in_frustum.count(),
+            visible.count()
+        )
+    }
+}
+
+/// Returns true if some of the points are in front of the camera, and their bounding box
+/// overlaps the screen
+fn overlaps_screen(points: &[Point2], viewport: Viewport) -> bool {
+    let in_front: Vec<&Point2> = points.iter().filter(|p| p.in_front()).collect();
+    if in_front.is_empty() {
+        return false;
+    }
+    let xmin = in_front.iter().map(|p| p.x()).fold(f32::INFINITY, f32::min);
+    let xmax = in_front.iter().map(|p| p.x()).fold(f32::NEG_INFINITY, f32::max);
+    let ymin = in_front.iter().map(|p| p.y()).fold(f32::INFINITY, f32::min);
+    let ymax = in_front.iter().map(|p| p.y()).fold(f32::NEG_INFINITY, f32::max);
+    xmax >= 0. && xmin < viewport.width as f32 && ymax >= 0. && ymin < viewport.height as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::object::Object;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+    use crate::visibility::VisibilityStats;
+
+    #[test]
+    fn test_visibility_stages() {
+        // Two cubes in a row in front of the camera (the second one is hidden), and one behind it
+        let cubes = [
+            Cube3::minecraft_like(Vector3::new(2., -0.5, -0.5), &YELLOW, &YELLOW),
+            Cube3::minecraft_like(Vector3::new(4., -0.5, -0.5), &YELLOW, &YELLOW),
+            Cube3::minecraft_like(Vector3::new(-3., -0.5, -0.5), &YELLOW, &YELLOW),
+        ];
+        let objects: Vec<_> = cubes.iter().map(|c| (c.get_all_faces(), true)).collect();
+        let stats = VisibilityStats::compute(&objects, &Camera::default());
+
+        assert_eq!(18, stats.faces.len());
+        let visible: Vec<_> = stats.faces.iter().filter(|f| f.pixels > 0).collect();
+        assert_eq!(1, visible.len());
+        assert_eq!(0, visible[0].object);
+        assert!(stats.faces.iter().filter(|f| f.object == 2).all(|f| !f.in_frustum || !f.front_facing));
+        assert!(stats.faces.iter().any(|f| f.object == 1 && f.front_facing && f.in_frustum));
+        assert_eq!(19, stats.to_csv().lines().count());
+    }
+}
@@ -0,0 +1,411 @@
+pub mod impostors;
+pub mod meshing;
+pub mod regions;
+pub mod water;
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::bsp::merging::merge_coplanar_faces;
+use crate::bsp::seams::fix_seams_with;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::object::ObjectId;
+use crate::primitives::tessellation::Patch;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::vector::{Vector3, UNIT_X, UNIT_Y, UNIT_Z};
+use crate::worlds::World;
+
+/// Number of blocks along the x and y sides of a chunk
+pub const CHUNK_SIZE: i32 = 16;
+
+/// Distance under which two vertices of the merged faces are the same
+const MESH_EPSILON: f32 = 1e-4;
+
+/// Kind of a block, given by `VoxelWorld::add_block_kind`
+pub type BlockId = u16;
+
+/// Integer offset along the axes
+type Offset = (i32, i32, i32);
+
+/// The faces of a block, as (direction of the neighbour hiding the face, corners of the face in
+/// a unit cube). The corners are ordered as in `Cube3::from_aabb`, so that the textures look the
+/// same as on the cubes.
+const BLOCK_FACES: [(Offset, [Offset; 4]); 6] = [
+    ((0, 0, -1), [(0, 0, 0), (1, 0, 0), (1, 1, 0), (0, 1, 0)]),
+    ((0, 0, 1), [(0, 0, 1), (1, 0, 1), (1, 1, 1), (0, 1, 1)]),
+    ((-1, 0, 0), [(0, 0, 0), (0, 1, 0), (0, 1, 1), (0, 0, 1)]),
+    ((0, 1, 0), [(0, 1, 0), (1, 1, 0), (1, 1, 1), (0, 1, 1)]),
+    ((1, 0, 0), [(1, 1, 0), (1, 0, 0), (1, 0, 1), (1, 1, 1)]),
+    ((0, -1, 0), [(1, 0, 0), (0, 0, 0), (0, 0, 1), (1, 0, 1)]),
+];
+
+/// Maximum number of kinds of blocks (including the air) in the palette of a chunk
+const PALETTE_SIZE: usize = 256;
+
+/// Storage of the blocks of a chunk, chosen by the chunk from the blocks it holds
+#[derive(Clone)]
+enum Blocks {
+    /// All the blocks are the same, such as the chunks of air above the ground
+    Uniform(Option<BlockId>),
+    /// Each block is an index in the palette of the kinds of blocks in the chunk. The number of
+    /// blocks of each entry is counted, to reuse the entries which are not used anymore.
+    Palette { palette: Vec<Option<BlockId>>, counts: Vec<usize>, indices: Vec<u8> },
+    /// Each block is stored, when the chunk holds too many kinds of blocks for the palette
+    Dense(Vec<Option<BlockId>>),
+}
+
+/// A column of 16x16 blocks, over the heights of its `VoxelWorld`.
+///
+/// Most chunks hold a few kinds of blocks, or only air: instead of storing each block, they store
+/// a single kind when all the blocks are the same, or the index of the kind of each block in a
+/// small palette.
+#[derive(Clone)]
+pub struct VoxelChunk {
+    height: i32,
+    blocks: Blocks,
+}
+
+impl VoxelChunk {
+    pub fn new(height: i32) -> Self {
+        Self { height, blocks: Blocks::Uniform(None) }
+    }
+
+    /// Returns the block at these coordinates in the chunk, if any
+    pub fn get(&self, x: i32, y: i32, z: i32) -> Option<BlockId> {
+        let i = self.index(x, y, z)?;
+        match &self.blocks {
+            Blocks::Uniform(block) => *block,
+            Blocks::Palette { palette, indices, .. } => palette[indices[i] as usize],
+            Blocks::Dense(blocks) => blocks[i],
+        }
+    }
+
+    /// Sets or removes the block at these coordinates, which must be inside the chunk
+    pub fn set(&mut self, x: i32, y: i32, z: i32, block: Option<BlockId>) {
+        let i = self.index(x, y, z).expect("The coordinates must be inside the chunk");
+        let len = self.len();
+        match &mut self.blocks {
+            Blocks::Uniform(current) if *current == block => {}
+            Blocks::Uniform(current) => {
+                let mut indices = vec![0; len];
+                indices[i] = 1;
+                self.blocks = Blocks::Palette { palette: vec![*current, block], counts: vec![len - 1, 1], indices };
+            }
+            Blocks::Palette { palette, counts, indices } => {
+                let previous = indices[i] as usize;
+                let entry = match palette.iter().position(|kind| *kind == block) {
+                    Some(entry) => entry,
+                    None => match counts.iter().position(|count| *count == 0) {
+                        Some(unused) => {
+                            palette[unused] = block;
+                            unused
+                        }
+                        None if palette.len() < PALETTE_SIZE => {
+                            palette.push(block);
+                            counts.push(0);
+                            palette.len() - 1
+                        }
+                        None => {
+                            let mut blocks: Vec<Option<BlockId>> = indices.iter().map(|entry| palette[*entry as usize]).collect();
+                            blocks[i] = block;
+                            self.blocks = Blocks::Dense(blocks);
+                            return;
+                        }
+                    },
+                };
+                counts[previous] -= 1;
+                counts[entry] += 1;
+                indices[i] = entry as u8;
+                if counts[entry] == len {
+                    self.blocks = Blocks::Uniform(block);
+                }
+            }
+            Blocks::Dense(blocks) => blocks[i] = block,
+        }
+    }
+
+    pub fn block_count(&self) -> usize {
+        match &self.blocks {
+            Blocks::Uniform(block) => if block.is_some() { self.len() } else { 0 },
+            Blocks::Palette { palette, counts, .. } => palette.iter().zip(counts).filter(|(kind, _)| kind.is_some()).map(|(_, count)| count).sum(),
+            Blocks::Dense(blocks) => blocks.iter().filter(|block| block.is_some()).count(),
+        }
+    }
+
+    /// Approximate number of bytes used to store the blocks
+    pub fn memory_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + match &self.blocks {
+                Blocks::Uniform(_) => 0,
+                Blocks::Palette { palette, counts, indices } => {
+                    palette.len() * std::mem::size_of::<Option<BlockId>>() + counts.len() * std::mem::size_of::<usize>() + indices.len()
+                }
+                Blocks::Dense(blocks) => blocks.len() * std::mem::size_of::<Option<BlockId>>(),
+            }
+    }
+
+    /// Number of blocks of the chunk, including the air
+    fn len(&self) -> usize {
+        (CHUNK_SIZE * CHUNK_SIZE * self.height) as usize
+    }
+
+    fn index(&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        let inside = (0..CHUNK_SIZE).contains(&x) && (0..CHUNK_SIZE).contains(&y) && (0..self.height).contains(&z);
+        inside.then_some(((z * CHUNK_SIZE + y) * CHUNK_SIZE + x) as usize)
+    }
+}
+
+/// A grid of blocks of side 1, such as the terrains of Minecraft, stored by chunks of 16x16 blocks.
+///
+/// Building each block as a `Cube3` draws many faces which are hidden by the neighbouring blocks.
+/// Instead, each chunk becomes a single object made of the faces between a block and the air.
+/// The block at (x, y, z) fills the same unit cube as `Cube3::minecraft_like(Vector3::newi(x, y, z))`.
+pub struct VoxelWorld {
+    heights: Range<i32>,
+    /// Textures of the kinds of blocks, as (sides, top and bottom)
+    kinds: Vec<(TextureHandle, TextureHandle)>,
+    chunks: HashMap<(i32, i32), VoxelChunk>,
+    /// Kinds of blocks through which the other blocks are seen
+    transparent: HashSet<BlockId>,
+    /// Chunks edited since they were last saved (see `RegionStore`)
+    dirty: HashSet<(i32, i32)>,
+}
+
+impl VoxelWorld {
+    /// Creates an empty world, whose blocks can be at the heights of the range
+    pub fn new(heights: Range<i32>) -> Self {
+        Self { heights, kinds: Vec::new(), transparent: HashSet::new(), chunks: HashMap::new(), dirty: HashSet::new() }
+    }
+
+    pub fn add_block_kind(&mut self, side: &TextureHandle, top: &TextureHandle) -> BlockId {
+        self.kinds.push((side.clone(), top.clone()));
+        (self.kinds.len() - 1) as BlockId
+    }
+
+    /// Sets whether the blocks of this kind (such as water or glass) let the faces of their
+    /// neighbours be seen
+    pub fn set_transparent(&mut self, kind: BlockId, transparent: bool) {
+        if transparent {
+            self.transparent.insert(kind);
+        } else {
+            self.transparent.remove(&kind);
+        }
+    }
+
+    pub fn get(&self, x: i32, y: i32, z: i32) -> Option<BlockId> {
+        let (key, (i, j)) = Self::chunk_of(x, y);
+        self.chunks.get(&key).and_then(|chunk| chunk.get(i, j, z - self.heights.start))
+    }
+
+    /// Sets or removes the block at these coordinates. The height must be in the range of the world.
+    pub fn set(&mut self, x: i32, y: i32, z: i32, block: Option<BlockId>) {
+        assert!(self.heights.contains(&z), "The height {z} is outside of the world");
+        let (key, (i, j)) = Self::chunk_of(x, y);
+        let height = self.heights.len() as i32;
+        self.chunks.entry(key).or_insert_with(|| VoxelChunk::new(height)).set(i, j, z - self.heights.start, block);
+        self.dirty.insert(key);
+    }
+
+    /// Returns the chunks edited since they were last saved
+    pub fn dirty_chunks(&self) -> Vec<(i32, i32)> {
+        self.dirty.iter().copied().collect()
+    }
+
+    /// Returns the chunks edited since they were last saved, which are not dirty anymore
+    pub fn take_dirty_chunks(&mut self) -> Vec<(i32, i32)> {
+        self.dirty.drain().collect()
+    }
+
+    /// Returns the coordinates of the chunks holding blocks
+    pub fn chunks(&self) -> Vec<(i32, i32)> {
+        self.chunks.iter().filter(|(_, chunk)| chunk.block_count() > 0).map(|(key, _)| *key).collect()
+    }
+
+    /// Returns the faces of the blocks of the chunk which are not covered by another block,
+    /// including the blocks of the neighbouring chunks
+    pub fn chunk_faces(&self, key: (i32, i32)) -> Vec<CubicFace3> {
+        let Some(chunk) = self.chunks.get(&key) else {
+            return Vec::new();
+        };
+        let mut faces = Vec::new();
+        for z in self.heights.clone() {
+            for j in 0..CHUNK_SIZE {
+                for i in 0..CHUNK_SIZE {
+                    let Some(block) = chunk.get(i, j, z - self.heights.start) else {
+                        continue;
+                    };
+                    let (x, y) = (key.0 * CHUNK_SIZE + i, key.1 * CHUNK_SIZE + j);
+                    let (side, top) = &self.kinds[block as usize];
+                    for ((dx, dy, dz), corners) in BLOCK_FACES {
+                        // The blocks seen through a transparent neighbour are drawn, but not the
+                        // faces between two blocks of water
+                        if self.get(x + dx, y + dy, z + dz).is_some_and(|other| other == block || !self.transparent.contains(&other)) {
+                            continue;
+                        }
+                        let points = corners.map(|(cx, cy, cz)| Vector3::newi(x + cx, y + cy, z + cz));
+                        let normal = UNIT_X * dx as f32 + UNIT_Y * dy as f32 + UNIT_Z * dz as f32;
+                        faces.push(CubicFace3::new(points, normal, if dz == 0 { side } else { top }));
+                    }
+                }
+            }
+        }
+        faces
+    }
+
+    /// Returns the faces of the chunk merged into larger rectangles (see `merge_coplanar_faces`),
+    /// and split where needed so that they have no seam with each other nor with the merged faces
+    /// of the neighbouring chunks
+    pub fn merged_chunk_faces(&self, key: (i32, i32)) -> Vec<CubicFace3> {
+        let neighbours: Vec<CubicFace3> =
+            [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().flat_map(|(dx, dy)| self.merged_faces((key.0 + dx, key.1 + dy))).collect();
+        fix_seams_with(self.merged_faces(key), &neighbours, MESH_EPSILON)
+    }
+
+    /// Merges the faces of the chunk. Their texture coordinates follow the world, so that the
+    /// textures of adjacent faces agree on their shared edge.
+    fn merged_faces(&self, key: (i32, i32)) -> Vec<CubicFace3> {
+        let faces = self
+            .chunk_faces(key)
+            .iter()
+            .map(|face| {
+                let points = face.points();
+                let (u, v) = (points[0].line_to(&points[1]), points[0].line_to(&points[3]));
+                let (du, dv) = (points[0].dot(&u), points[0].dot(&v));
+                CubicFace3::new_with_uvs(points, face.uvs().map(|(a, b)| (a + du, b + dv)), *face.normal(), face.texture())
+            })
+            .collect();
+        merge_coplanar_faces(faces, MESH_EPSILON)
+    }
+
+    /// Adds each chunk to the world as an object made of its visible faces, and returns them
+    pub fn add_to(&self, world: &mut World) -> Vec<ObjectId> {
+        self.chunks().into_iter().map(|key| world.add_patch(Patch::new(self.chunk_faces(key)))).collect()
+    }
+
+    /// Returns the chunks whose faces change when the block at these coordinates changes: its own
+    /// chunk, and the neighbouring chunks it touches
+    pub fn chunks_around(x: i32, y: i32) -> Vec<(i32, i32)> {
+        let mut keys = vec![Self::chunk_of(x, y).0];
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let key = Self::chunk_of(x + dx, y + dy).0;
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    /// Returns a world holding a copy of the chunk and of its neighbours, which has the same faces
+    /// for this chunk
+    fn snapshot(&self, key: (i32, i32)) -> VoxelWorld {
+        let mut snapshot = VoxelWorld::new(self.heights.clone());
+        snapshot.kinds = self.kinds.clone();
+        snapshot.transparent = self.transparent.clone();
+        for (dx, dy) in [(0, 0), (-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let neighbour = (key.0 + dx, key.1 + dy);
+            if let Some(chunk) = self.chunks.get(&neighbour) {
+                snapshot.chunks.insert(neighbour, chunk.clone());
+            }
+        }
+        snapshot
+    }
+
+    /// Returns the chunk holding the block, and the coordinates of the block in the chunk
+    fn chunk_of(x: i32, y: i32) -> ((i32, i32), (i32, i32)) {
+        ((x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE)), (x.rem_euclid(CHUNK_SIZE), y.rem_euclid(CHUNK_SIZE)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bsp::seams::find_seams;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::textures::colored::{ORANGE, YELLOW};
+    use crate::voxels::{BlockId, VoxelChunk, VoxelWorld, CHUNK_SIZE, MESH_EPSILON};
+    use crate::worlds::World;
+    use std::mem::size_of;
+
+    #[test]
+    fn test_voxel_faces() {
+        let mut voxels = VoxelWorld::new(-2..2);
+        let grass = voxels.add_block_kind(&YELLOW, &ORANGE);
+        // Two blocks side by side, on both sides of the border between two chunks
+        voxels.set(CHUNK_SIZE - 1, -1, 0, Some(grass));
+        voxels.set(CHUNK_SIZE, -1, 0, Some(grass));
+        assert_eq!(Some(grass), voxels.get(CHUNK_SIZE, -1, 0));
+        assert_eq!(None, voxels.get(CHUNK_SIZE, -1, 1));
+        assert_eq!(5, voxels.chunk_faces((0, -1)).len());
+        assert_eq!(5, voxels.chunk_faces((1, -1)).len());
+
+        // A block above hides the top of the block below
+        voxels.set(CHUNK_SIZE, -1, -1, Some(grass));
+        assert_eq!(9, voxels.chunk_faces((1, -1)).len());
+        let mut world = World::new(Camera::default());
+        assert_eq!(2, voxels.add_to(&mut world).len());
+        assert_eq!(14, world.objects().flat_map(|(_, object)| object.get_all_faces()).count());
+
+        voxels.set(CHUNK_SIZE - 1, -1, 0, None);
+        assert_eq!(vec![(1, -1)], voxels.chunks());
+    }
+
+    #[test]
+    fn test_merged_faces_without_seams() {
+        // A floor with stripes of 3 blocks of the same kind, shifted on each row and crossing the
+        // border between two chunks: the merged stripes have T-junctions with the next row
+        let mut voxels = VoxelWorld::new(0..1);
+        let kinds = [voxels.add_block_kind(&YELLOW, &YELLOW), voxels.add_block_kind(&ORANGE, &ORANGE)];
+        for x in 0..2 * CHUNK_SIZE {
+            for y in 0..6 {
+                voxels.set(x, y, 0, Some(kinds[((x + y) / 3 % 2) as usize]));
+            }
+        }
+        let all: usize = [(0, 0), (1, 0)].iter().map(|key| voxels.chunk_faces(*key).len()).sum();
+        let merged: Vec<CubicFace3> = [(0, 0), (1, 0)].iter().flat_map(|key| voxels.merged_faces(*key)).collect();
+        assert!(merged.len() < all);
+        assert!(!find_seams(&merged, MESH_EPSILON).is_empty());
+
+        // The faces are split again where they meet, inside the chunks and across their border
+        let faces: Vec<CubicFace3> = [(0, 0), (1, 0)].iter().flat_map(|key| voxels.merged_chunk_faces(*key)).collect();
+        assert!(find_seams(&faces, MESH_EPSILON).is_empty());
+        let area = |faces: &[CubicFace3]| faces.iter().map(|face| face.area()).sum::<f32>();
+        assert!((area(&faces) - all as f32).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_chunk_storage() {
+        let mut chunk = VoxelChunk::new(4);
+        let empty = chunk.memory_size();
+        let dense = 4 * (CHUNK_SIZE * CHUNK_SIZE) as usize * size_of::<Option<BlockId>>();
+        assert!(empty < 100);
+
+        // A few kinds of blocks take a byte per block
+        chunk.set(1, 2, 3, Some(4));
+        chunk.set(0, 0, 0, Some(7));
+        assert_eq!(Some(4), chunk.get(1, 2, 3));
+        assert_eq!(None, chunk.get(1, 2, 2));
+        assert_eq!(2, chunk.block_count());
+        assert!(chunk.memory_size() < dense / 2);
+
+        // A chunk filled with a single kind of blocks is stored as such
+        for z in 0..4 {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, Some(1));
+                }
+            }
+        }
+        assert_eq!(empty, chunk.memory_size());
+        assert_eq!(4 * (CHUNK_SIZE * CHUNK_SIZE) as usize, chunk.block_count());
+        assert_eq!(Some(1), chunk.get(5, 5, 1));
+
+        // Too many kinds of blocks for the palette
+        for i in 0..300 {
+            chunk.set(i % CHUNK_SIZE, i / CHUNK_SIZE % CHUNK_SIZE, i / (CHUNK_SIZE * CHUNK_SIZE), Some(i as BlockId));
+        }
+        assert_eq!(dense + empty, chunk.memory_size());
+        assert_eq!(Some(299), chunk.get(299 % CHUNK_SIZE, 299 / CHUNK_SIZE % CHUNK_SIZE, 1));
+        assert_eq!(Some(1), chunk.get(5, 5, 3));
+        assert_eq!(4 * (CHUNK_SIZE * CHUNK_SIZE) as usize, chunk.block_count());
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::compare::{render_to_buffer, RenderMethod};
+use crate::primitives::billboard::Billboard;
+use crate::primitives::camera::Camera;
+use crate::primitives::object::ObjectId;
+use crate::primitives::position::Pose;
+use crate::primitives::tessellation::Patch;
+use crate::primitives::textures::image::{ImageTexture, Wrap};
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::vector::Vector3;
+use crate::sky::SolidSky;
+use crate::voxels::{VoxelWorld, CHUNK_SIZE};
+use crate::worlds::World;
+
+/// The chunk is captured from this many times its size, so that the perspective is almost the
+/// same as from far away
+const CAPTURE_DISTANCE: f32 = 10.;
+
+/// Images of a chunk seen from around it, drawn on a billboard instead of its faces
+pub struct Impostor {
+    /// Center of the bottom edge of the billboard
+    position: Vector3,
+    /// Side of the billboard, which covers the chunk from every direction
+    size: f32,
+    /// The chunk seen from the directions spread around it, the first one looking along the x-axis
+    views: Vec<TextureHandle>,
+}
+
+impl Impostor {
+    /// Renders the faces of the chunk from `angles` directions, in square images of `resolution`
+    /// pixels. Returns None if the chunk has no face.
+    pub fn bake(voxels: &VoxelWorld, key: (i32, i32), angles: usize, resolution: u32) -> Option<Self> {
+        let faces = voxels.chunk_faces(key);
+        if faces.is_empty() {
+            return None;
+        }
+        let size = (CHUNK_SIZE as f32 * 2f32.sqrt()).max(voxels.heights.len() as f32);
+        let (x, y) = (chunk_center(key).x(), chunk_center(key).y());
+        // Centered on the middle of the heights of the blocks, the billboard may go under them
+        let center = Vector3::new(x, y, (voxels.heights.start + voxels.heights.end) as f32 / 2.);
+        let position = center + Vector3::new(0., 0., size / 2.);
+
+        // Only the faces are drawn, on a transparent background
+        let mut world = World::new(Camera::default());
+        world.set_sky(Box::new(SolidSky([0, 0, 0, 0])));
+        world.add_patch(Patch::new(faces));
+        let distance = CAPTURE_DISTANCE * size;
+        let mut camera = Camera::default();
+        camera.set_fov(2. * (size / 2. / distance).atan().to_degrees());
+        let views = (0..angles.max(1))
+            .map(|i| {
+                let direction = Self::direction(i, angles.max(1));
+                let mut pose = Pose::new(center - direction * distance, 0.);
+                pose.look_at(&center);
+                camera.set_pose(pose);
+                let image = render_to_buffer(&mut world, &camera, resolution, resolution, RenderMethod::Painter);
+                let texture = ImageTexture::from_rgba(resolution as usize, resolution as usize, &image).with_wrap(Wrap::Clamp);
+                TextureHandle::new(texture)
+            })
+            .collect();
+        Some(Self { position, size, views })
+    }
+
+    /// Returns the billboard showing the chunk as seen from this position
+    pub fn billboard(&self, camera: &Vector3) -> Billboard {
+        Billboard::new(self.position, self.size, self.size, &self.views[self.view_towards(camera)])
+    }
+
+    /// Index of the view captured from the direction closest to the one of the camera
+    pub fn view_towards(&self, camera: &Vector3) -> usize {
+        let angle = (self.position.y() - camera.y()).atan2(self.position.x() - camera.x());
+        let n = self.views.len();
+        ((angle.rem_euclid(2. * PI) / (2. * PI) * n as f32).round() as usize) % n
+    }
+
+    /// Horizontal direction in which the camera looks for the i-th view
+    fn direction(i: usize, n: usize) -> Vector3 {
+        let angle = 2. * PI * i as f32 / n as f32;
+        Vector3::new(angle.cos(), angle.sin(), 0.)
+    }
+}
+
+/// How a chunk is drawn in the world
+#[derive(Clone, Copy, PartialEq)]
+enum ChunkView {
+    Faces,
+    /// The billboard of the impostor, with the given view
+    Impostor(usize),
+}
+
+/// Draws the chunks of a `VoxelWorld` further than a distance from the camera as impostors: a
+/// single billboard showing the chunk, captured beforehand with the offscreen renderer. The faces
+/// of a chunk are brought back when the camera comes closer.
+pub struct ChunkImpostors {
+    /// Horizontal distance from the center of a chunk to the camera beyond which it is an impostor
+    distance: f32,
+    angles: usize,
+    resolution: u32,
+    impostors: HashMap<(i32, i32), Impostor>,
+    /// Object of each chunk in the world, and how it is drawn
+    shown: HashMap<(i32, i32), (ObjectId, ChunkView)>,
+}
+
+impl ChunkImpostors {
+    pub fn new(distance: f32) -> Self {
+        Self { distance, angles: 8, resolution: 64, impostors: HashMap::new(), shown: HashMap::new() }
+    }
+
+    /// Number of directions from which the impostors are captured
+    pub fn with_angles(mut self, angles: usize) -> Self {
+        self.angles = angles.max(1);
+        self
+    }
+
+    /// Side of the images of the impostors, in pixels
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+
+    /// Adds the chunks to the world, or replaces their objects when the camera moved closer or
+    /// further than the distance, and returns the number of replaced chunks. The impostors are
+    /// captured the first time they are needed.
+    pub fn update(&mut self, world: &mut World, voxels: &VoxelWorld) -> usize {
+        let camera = *world.view_camera().pose().position();
+        let mut replaced = 0;
+        for key in voxels.chunks() {
+            let center = chunk_center(key);
+            let far = Vector3::new(center.x() - camera.x(), center.y() - camera.y(), 0.).norm() > self.distance;
+            if far && !self.impostors.contains_key(&key) {
+                if let Some(impostor) = Impostor::bake(voxels, key, self.angles, self.resolution) {
+                    self.impostors.insert(key, impostor);
+                }
+            }
+            let impostor = self.impostors.get(&key).filter(|_| far);
+            let view = impostor.map_or(ChunkView::Faces, |impostor| ChunkView::Impostor(impostor.view_towards(&camera)));
+            if self.shown.get(&key).is_some_and(|(_, shown)| *shown == view) {
+                continue;
+            }
+            if let Some((id, _)) = self.shown.remove(&key) {
+                world.remove(id);
+            }
+            let id = match impostor {
+                Some(impostor) => world.add_billboard(impostor.billboard(&camera)),
+                None => world.add_patch(Patch::new(voxels.chunk_faces(key))),
+            };
+            self.shown.insert(key, (id, view));
+            replaced += 1;
+        }
+        replaced
+    }
+
+    /// Forgets the impostor and the object of an edited chunk, which are built again by `update`
+    pub fn invalidate(&mut self, world: &mut World, key: (i32, i32)) {
+        self.impostors.remove(&key);
+        if let Some((id, _)) = self.shown.remove(&key) {
+            world.remove(id);
+        }
+    }
+
+    /// Returns true if the chunk is drawn as an impostor
+    pub fn is_impostor(&self, key: (i32, i32)) -> bool {
+        matches!(self.shown.get(&key), Some((_, ChunkView::Impostor(_))))
+    }
+}
+
+/// Center of the chunk, at the height 0
+fn chunk_center(key: (i32, i32)) -> Vector3 {
+    let half = CHUNK_SIZE as f32 / 2.;
+    Vector3::new((key.0 * CHUNK_SIZE) as f32 + half, (key.1 * CHUNK_SIZE) as f32 + half, 0.)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compare::{diff_images, render_offscreen, RenderMethod, DEFAULT_TOLERANCE};
+    use crate::primitives::camera::Camera;
+    use crate::primitives::position::Pose;
+    use crate::primitives::textures::colored::{ORANGE, YELLOW};
+    use crate::primitives::vector::Vector3;
+    use crate::voxels::impostors::{ChunkImpostors, Impostor};
+    use crate::voxels::{VoxelWorld, CHUNK_SIZE};
+    use crate::worlds::World;
+
+    fn hill() -> VoxelWorld {
+        let mut voxels = VoxelWorld::new(-4..2);
+        let grass = voxels.add_block_kind(&YELLOW, &ORANGE);
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                let top = 1 - ((x - 8).abs().min((y - 8).abs()) < 4) as i32 * 3;
+                for z in top..2 {
+                    voxels.set(x, y, z, Some(grass));
+                }
+            }
+        }
+        voxels
+    }
+
+    #[test]
+    fn test_bake_impostor() {
+        let voxels = hill();
+        assert!(Impostor::bake(&voxels, (5, 5), 4, 16).is_none());
+        let impostor = Impostor::bake(&voxels, (0, 0), 4, 32).unwrap();
+        assert_eq!(4, impostor.views.len());
+        // The views are picked from the side of the camera
+        assert_eq!(0, impostor.view_towards(&Vector3::new(-100., 8., 0.)));
+        assert_eq!(1, impostor.view_towards(&Vector3::new(8., -100., 0.)));
+        assert_eq!(2, impostor.view_towards(&Vector3::new(100., 8., 0.)));
+
+        // From far away, the impostor looks like the chunk
+        let mut world = World::new(Camera::default());
+        world.set_camera_pose(Pose::new(Vector3::new(-40., 8., -1.), 0.));
+        let mut impostors = ChunkImpostors::new(30.).with_angles(4).with_resolution(128);
+        assert_eq!(1, impostors.update(&mut world, &voxels));
+        assert!(impostors.is_impostor((0, 0)));
+        let drawn = render_offscreen(&mut world, RenderMethod::Painter);
+        let mut real = World::new(Camera::default());
+        real.set_camera_pose(Pose::new(Vector3::new(-40., 8., -1.), 0.));
+        voxels.add_to(&mut real);
+        let expected = render_offscreen(&mut real, RenderMethod::Painter);
+        // Only the perspective differs, since the impostor was captured from further away
+        let empty = render_offscreen(&mut World::new(Camera::default()), RenderMethod::Painter);
+        let mismatch = diff_images(&drawn, &expected, DEFAULT_TOLERANCE).mismatch_percentage();
+        assert!(mismatch < diff_images(&empty, &expected, DEFAULT_TOLERANCE).mismatch_percentage() / 3.);
+
+        // Nothing changes until the camera moves closer, or turns around the chunk
+        assert_eq!(0, impostors.update(&mut world, &voxels));
+        world.set_camera_pose(Pose::new(Vector3::new(-10., 8., -1.), 0.));
+        assert_eq!(1, impostors.update(&mut world, &voxels));
+        assert!(!impostors.is_impostor((0, 0)));
+        assert_eq!(1, world.objects().count());
+        world.set_camera_pose(Pose::new(Vector3::new(8., 50., -1.), 0.));
+        assert_eq!(1, impostors.update(&mut world, &voxels));
+        assert!(impostors.is_impostor((0, 0)));
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::object::ObjectId;
+use crate::primitives::tessellation::Patch;
+use crate::voxels::VoxelWorld;
+use crate::worlds::World;
+
+/// Faces of a chunk built by a worker, for the given request of this chunk
+struct MeshedChunk {
+    key: (i32, i32),
+    revision: u64,
+    faces: Vec<CubicFace3>,
+}
+
+/// Builds the faces of the chunks of a `VoxelWorld` on the threads of the rayon pool, so that
+/// meshing a large chunk does not make a frame last longer.
+///
+/// Each chunk is an object of the `World`. When a chunk is meshed again (for instance after a
+/// block was placed), its previous object is drawn until `apply` swaps in the new faces.
+pub struct ChunkMesher {
+    sender: Sender<MeshedChunk>,
+    results: Receiver<MeshedChunk>,
+    /// Last request of each chunk: the meshes of the older requests are outdated
+    revisions: HashMap<(i32, i32), u64>,
+    /// Requests whose faces were not applied yet
+    pending: usize,
+    /// Object of each chunk in the world
+    objects: HashMap<(i32, i32), ObjectId>,
+}
+
+impl ChunkMesher {
+    pub fn new() -> Self {
+        let (sender, results) = channel();
+        Self { sender, results, revisions: HashMap::new(), pending: 0, objects: HashMap::new() }
+    }
+
+    /// Meshes the chunk in the background. The blocks are copied, so the voxels can be edited
+    /// right away.
+    pub fn request(&mut self, voxels: &VoxelWorld, key: (i32, i32)) {
+        let revision = self.revisions.entry(key).or_insert(0);
+        *revision += 1;
+        let revision = *revision;
+        self.pending += 1;
+        let snapshot = voxels.snapshot(key);
+        let sender = self.sender.clone();
+        rayon::spawn(move || {
+            // The mesher may be dropped before the chunk is meshed
+            let _ = sender.send(MeshedChunk { key, revision, faces: snapshot.merged_chunk_faces(key) });
+        });
+    }
+
+    /// Meshes all the chunks holding blocks
+    pub fn request_all(&mut self, voxels: &VoxelWorld) {
+        for key in voxels.chunks() {
+            self.request(voxels, key);
+        }
+    }
+
+    /// Meshes again the chunks whose faces change with the block at these coordinates
+    pub fn block_changed(&mut self, voxels: &VoxelWorld, x: i32, y: i32) {
+        for key in VoxelWorld::chunks_around(x, y) {
+            if voxels.chunks.contains_key(&key) || self.objects.contains_key(&key) {
+                self.request(voxels, key);
+            }
+        }
+    }
+
+    /// Replaces the objects of the chunks meshed since the last call, and returns their number
+    pub fn apply(&mut self, world: &mut World) -> usize {
+        let mut applied = 0;
+        while let Ok(meshed) = self.results.try_recv() {
+            applied += usize::from(self.swap(world, meshed));
+        }
+        applied
+    }
+
+    /// Waits until all the requested chunks are meshed, and replaces their objects
+    pub fn finish(&mut self, world: &mut World) {
+        while self.pending > 0 {
+            let meshed = self.results.recv().expect("The mesher holds a sender");
+            self.swap(world, meshed);
+        }
+    }
+
+    /// Number of requested chunks whose faces are not in the world yet
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Object of the chunk in the world, if it has visible faces
+    pub fn object(&self, key: (i32, i32)) -> Option<ObjectId> {
+        self.objects.get(&key).copied()
+    }
+
+    /// Replaces the object of the chunk by its new faces, unless the chunk was requested again
+    /// since (the newer request is applied instead)
+    fn swap(&mut self, world: &mut World, meshed: MeshedChunk) -> bool {
+        self.pending -= 1;
+        if self.revisions.get(&meshed.key) != Some(&meshed.revision) {
+            return false;
+        }
+        if let Some(id) = self.objects.remove(&meshed.key) {
+            world.remove(id);
+        }
+        if !meshed.faces.is_empty() {
+            self.objects.insert(meshed.key, world.add_patch(Patch::new(meshed.faces)));
+        }
+        true
+    }
+}
+
+impl Default for ChunkMesher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::camera::Camera;
+    use crate::primitives::textures::colored::{ORANGE, YELLOW};
+    use crate::voxels::meshing::ChunkMesher;
+    use crate::voxels::{VoxelWorld, CHUNK_SIZE};
+    use crate::worlds::World;
+
+    fn face_count(world: &World) -> usize {
+        world.objects().flat_map(|(_, object)| object.get_all_faces()).count()
+    }
+
+    #[test]
+    fn test_background_meshing() {
+        let mut voxels = VoxelWorld::new(-2..2);
+        let grass = voxels.add_block_kind(&YELLOW, &ORANGE);
+        voxels.set(CHUNK_SIZE - 1, 0, 0, Some(grass));
+        voxels.set(2 * CHUNK_SIZE, 0, 0, Some(grass));
+        let mut world = World::new(Camera::default());
+        let mut mesher = ChunkMesher::new();
+        mesher.request_all(&voxels);
+        mesher.finish(&mut world);
+        assert_eq!(0, mesher.pending());
+        assert_eq!(12, face_count(&world));
+
+        // The old faces are drawn until the new ones are applied
+        voxels.set(CHUNK_SIZE, 0, 0, Some(grass));
+        mesher.block_changed(&voxels, CHUNK_SIZE, 0);
+        mesher.block_changed(&voxels, CHUNK_SIZE, 0);
+        assert_eq!(12, face_count(&world));
+        let (old, far) = (mesher.object((0, 0)).unwrap(), mesher.object((2, 0)).unwrap());
+        mesher.finish(&mut world);
+        // The new block hides a side of its neighbour, in the previous chunk, which is meshed again
+        assert_eq!(16, face_count(&world));
+        assert!(world.get(old).is_none());
+        assert_eq!(Some(far), mesher.object((2, 0)));
+        assert_eq!(3, world.objects().count());
+
+        // An emptied chunk has no object anymore
+        voxels.set(CHUNK_SIZE, 0, 0, None);
+        mesher.block_changed(&voxels, CHUNK_SIZE, 0);
+        mesher.finish(&mut world);
+        assert!(mesher.object((1, 0)).is_none());
+        assert_eq!(12, face_count(&world));
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+
+use serde::{Deserialize, Serialize};
+
+use crate::voxels::{BlockId, VoxelChunk, VoxelWorld, CHUNK_SIZE};
+
+/// Number of chunks along the x and y sides of a region
+pub const REGION_SIZE: i32 = 8;
+
+/// Blocks of a chunk, as runs of identical blocks in the order of their index
+#[derive(Serialize, Deserialize)]
+struct ChunkData {
+    key: (i32, i32),
+    height: i32,
+    runs: Vec<(usize, Option<BlockId>)>,
+}
+
+impl ChunkData {
+    fn capture(key: (i32, i32), chunk: &VoxelChunk) -> Self {
+        let mut runs: Vec<(usize, Option<BlockId>)> = Vec::new();
+        for z in 0..chunk.height {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let block = chunk.get(x, y, z);
+                    match runs.last_mut() {
+                        Some((count, last)) if *last == block => *count += 1,
+                        _ => runs.push((1, block)),
+                    }
+                }
+            }
+        }
+        Self { key, height: chunk.height, runs }
+    }
+
+    fn chunk(&self) -> Result<VoxelChunk, String> {
+        let mut chunk = VoxelChunk::new(self.height);
+        let mut i = 0;
+        for (count, block) in &self.runs {
+            if i + count > chunk.len() {
+                return Err(format!("The chunk {:?} holds too many blocks", self.key));
+            }
+            if block.is_some() {
+                for j in i..i + count {
+                    let j = j as i32;
+                    chunk.set(j % CHUNK_SIZE, j / CHUNK_SIZE % CHUNK_SIZE, j / (CHUNK_SIZE * CHUNK_SIZE), *block);
+                }
+            }
+            i += count;
+        }
+        Ok(chunk)
+    }
+}
+
+/// Content of a region file (in JSON): the chunks of a square of `REGION_SIZE` chunks which were
+/// saved
+#[derive(Serialize, Deserialize, Default)]
+struct RegionFile {
+    chunks: Vec<ChunkData>,
+}
+
+/// Saves the chunks of a `VoxelWorld` in a directory, grouped by regions of 8x8 chunks, so that
+/// the edits of the terrain are kept when the game is started again.
+///
+/// Only the regions of the chunks edited since the last save (see `VoxelWorld::dirty_chunks`)
+/// are written. The blocks are identified by their `BlockId`: the kinds of blocks must be added
+/// in the same order before loading the chunks.
+pub struct RegionStore {
+    directory: PathBuf,
+    /// Save running in the background, which returns the number of written regions
+    saving: Option<JoinHandle<Result<usize, String>>>,
+}
+
+impl RegionStore {
+    pub fn new(directory: &str) -> Self {
+        Self { directory: PathBuf::from(directory), saving: None }
+    }
+
+    /// Returns the region holding the chunk
+    pub fn region_of(key: (i32, i32)) -> (i32, i32) {
+        (key.0.div_euclid(REGION_SIZE), key.1.div_euclid(REGION_SIZE))
+    }
+
+    /// Writes the regions of the chunks edited since the last save, and returns their number
+    pub fn save(&mut self, voxels: &mut VoxelWorld) -> Result<usize, String> {
+        self.save_in_background(voxels)?;
+        self.wait()
+    }
+
+    /// Copies the chunks edited since the last save, and writes their regions on another thread
+    /// (see `wait`). The previous save is finished first.
+    pub fn save_in_background(&mut self, voxels: &mut VoxelWorld) -> Result<(), String> {
+        self.wait()?;
+        let mut regions: HashMap<(i32, i32), Vec<ChunkData>> = HashMap::new();
+        for key in voxels.take_dirty_chunks() {
+            let data = match voxels.chunks.get(&key) {
+                Some(chunk) => ChunkData::capture(key, chunk),
+                None => continue,
+            };
+            regions.entry(Self::region_of(key)).or_default().push(data);
+        }
+        let directory = self.directory.clone();
+        self.saving = Some(thread::spawn(move || {
+            std::fs::create_dir_all(&directory).map_err(|err| err.to_string())?;
+            for (region, chunks) in &mut regions {
+                write_region(&directory, *region, std::mem::take(chunks))?;
+            }
+            Ok(regions.len())
+        }));
+        Ok(())
+    }
+
+    /// Waits for the save running in the background, and returns the number of regions it wrote
+    pub fn wait(&mut self) -> Result<usize, String> {
+        match self.saving.take() {
+            Some(saving) => saving.join().map_err(|_| "The save of the regions panicked".to_string())?,
+            None => Ok(0),
+        }
+    }
+
+    /// Loads the chunks of the region holding the chunk, replacing the chunks of the world, and
+    /// returns their number (0 if the region was never saved)
+    pub fn load_region(&mut self, voxels: &mut VoxelWorld, key: (i32, i32)) -> Result<usize, String> {
+        self.wait()?;
+        let region = read_region(&self.path(Self::region_of(key)))?;
+        for data in &region.chunks {
+            if data.height != voxels.heights.len() as i32 {
+                return Err(format!("The chunk {:?} has a height of {} blocks instead of {}", data.key, data.height, voxels.heights.len()));
+            }
+            voxels.chunks.insert(data.key, data.chunk()?);
+        }
+        Ok(region.chunks.len())
+    }
+
+    /// Loads all the saved regions, and returns the number of chunks
+    pub fn load_all(&mut self, voxels: &mut VoxelWorld) -> Result<usize, String> {
+        let Ok(entries) = std::fs::read_dir(&self.directory) else {
+            return Ok(0);
+        };
+        let mut count = 0;
+        for entry in entries {
+            let name = entry.map_err(|err| err.to_string())?.file_name();
+            if let Some(region) = name.to_str().and_then(parse_region_name) {
+                count += self.load_region(voxels, (region.0 * REGION_SIZE, region.1 * REGION_SIZE))?;
+            }
+        }
+        Ok(count)
+    }
+
+    fn path(&self, region: (i32, i32)) -> PathBuf {
+        region_path(&self.directory, region)
+    }
+}
+
+fn region_path(directory: &Path, region: (i32, i32)) -> PathBuf {
+    directory.join(format!("r.{}.{}.json", region.0, region.1))
+}
+
+/// Returns the region of a file named as by `region_path`
+fn parse_region_name(name: &str) -> Option<(i32, i32)> {
+    let (x, y) = name.strip_prefix("r.")?.strip_suffix(".json")?.split_once('.')?;
+    Some((x.parse().ok()?, y.parse().ok()?))
+}
+
+/// Reads a region file, which is empty if it does not exist
+fn read_region(path: &Path) -> Result<RegionFile, String> {
+    if !path.exists() {
+        return Ok(RegionFile::default());
+    }
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&text).map_err(|err| format!("Invalid region {}: {err}", path.display()))
+}
+
+/// Replaces the chunks of the region file, keeping the other chunks of the file
+fn write_region(directory: &Path, region: (i32, i32), chunks: Vec<ChunkData>) -> Result<(), String> {
+    let path = region_path(directory, region);
+    let mut file = read_region(&path)?;
+    file.chunks.retain(|saved| chunks.iter().all(|chunk| chunk.key != saved.key));
+    file.chunks.extend(chunks);
+    let text = serde_json::to_string(&file).map_err(|err| err.to_string())?;
+    std::fs::write(path, text).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::textures::colored::{ORANGE, YELLOW};
+    use crate::voxels::regions::{RegionStore, REGION_SIZE};
+    use crate::voxels::{VoxelWorld, CHUNK_SIZE};
+
+    #[test]
+    fn test_save_and_load_regions() {
+        let directory = std::env::temp_dir().join("test_voxel_regions");
+        let _ = std::fs::remove_dir_all(&directory);
+        let mut store = RegionStore::new(directory.to_str().unwrap());
+
+        let terrain = || {
+            let mut voxels = VoxelWorld::new(-2..2);
+            voxels.add_block_kind(&YELLOW, &YELLOW);
+            voxels.add_block_kind(&ORANGE, &ORANGE);
+            voxels
+        };
+        let mut voxels = terrain();
+        voxels.set(3, 4, -1, Some(0));
+        voxels.set(-1, 4, 1, Some(1));
+        let far = REGION_SIZE * CHUNK_SIZE;
+        voxels.set(far, 0, 0, Some(1));
+        assert_eq!(3, voxels.dirty_chunks().len());
+        assert_eq!(3, store.save(&mut voxels).unwrap());
+        assert!(voxels.dirty_chunks().is_empty());
+        assert_eq!(0, store.save(&mut voxels).unwrap());
+
+        // Only the region of the edited chunk is written again, keeping its other chunk
+        voxels.set(3, 5, -1, Some(1));
+        store.save_in_background(&mut voxels).unwrap();
+        assert_eq!(1, store.wait().unwrap());
+
+        let mut loaded = terrain();
+        assert_eq!(1, store.load_region(&mut loaded, (0, 0)).unwrap());
+        assert_eq!(None, loaded.get(-1, 4, 1));
+        assert_eq!(3, store.load_all(&mut loaded).unwrap());
+        for (x, y, z) in [(3, 4, -1), (3, 5, -1), (-1, 4, 1), (far, 0, 0), (0, 0, 0)] {
+            assert_eq!(voxels.get(x, y, z), loaded.get(x, y, z));
+        }
+        assert!(loaded.dirty_chunks().is_empty());
+
+        // The heights of the chunks must be the ones of the world
+        assert!(store.load_region(&mut VoxelWorld::new(0..1), (0, 0)).is_err());
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
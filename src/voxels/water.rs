@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::voxels::{BlockId, VoxelWorld};
+
+/// Level of the water sources. The water loses a level at each block it flows sideways.
+pub const MAX_WATER_LEVEL: u8 = 8;
+
+/// Position of a block
+type Cell = (i32, i32, i32);
+
+const SIDES: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// Flow of the water between the blocks of a `VoxelWorld`, as a cellular automaton.
+///
+/// The water is a kind of block. At each step, it falls into the air below it, and spreads to the
+/// air around it when it lies on a block, losing a level at each block. The water which is not fed
+/// anymore by a source (because a block was placed, or the source was removed) dries up. Since the
+/// rules read the blocks of the world, digging next to some water lets it flow in the hole.
+pub struct WaterSimulation {
+    kind: BlockId,
+    levels: HashMap<Cell, u8>,
+    sources: HashSet<Cell>,
+}
+
+impl WaterSimulation {
+    /// Simulates the blocks of this kind (which should be transparent, see
+    /// `VoxelWorld::set_transparent`)
+    pub fn new(kind: BlockId) -> Self {
+        Self { kind, levels: HashMap::new(), sources: HashSet::new() }
+    }
+
+    /// Places a source of water, which never dries up
+    pub fn add_source(&mut self, voxels: &mut VoxelWorld, x: i32, y: i32, z: i32) {
+        voxels.set(x, y, z, Some(self.kind));
+        self.levels.insert((x, y, z), MAX_WATER_LEVEL);
+        self.sources.insert((x, y, z));
+    }
+
+    /// Removes the source, whose water dries up at the next steps
+    pub fn remove_source(&mut self, x: i32, y: i32, z: i32) {
+        self.sources.remove(&(x, y, z));
+    }
+
+    /// Returns the level of the water in this block, if any
+    pub fn level(&self, x: i32, y: i32, z: i32) -> Option<u8> {
+        self.levels.get(&(x, y, z)).copied()
+    }
+
+    /// Number of blocks of water
+    pub fn volume(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Moves the water by one block, and returns the chunks whose faces changed (to mesh again)
+    pub fn step(&mut self, voxels: &mut VoxelWorld) -> Vec<(i32, i32)> {
+        // The water replaced by other blocks is gone
+        self.levels.retain(|(x, y, z), _| voxels.get(*x, *y, *z) == Some(self.kind));
+        self.sources.retain(|cell| self.levels.contains_key(cell));
+
+        // Each block of water keeps the level given by its neighbours, and flows around
+        let mut next: HashMap<Cell, u8> = HashMap::new();
+        let mut raise = |cell: Cell, level: u8| {
+            let current = next.entry(cell).or_insert(0);
+            *current = (*current).max(level);
+        };
+        for (&(x, y, z), &level) in &self.levels {
+            let fed = if self.sources.contains(&(x, y, z)) {
+                MAX_WATER_LEVEL
+            } else {
+                let from_above = if self.levels.contains_key(&(x, y, z - 1)) { MAX_WATER_LEVEL - 1 } else { 0 };
+                let from_sides = SIDES
+                    .iter()
+                    .filter(|(dx, dy)| self.spreads(voxels, (x + dx, y + dy, z)))
+                    .filter_map(|(dx, dy)| self.levels.get(&(x + dx, y + dy, z)))
+                    .map(|level| level - 1)
+                    .max()
+                    .unwrap_or(0);
+                from_above.max(from_sides)
+            };
+            if fed > 0 {
+                raise((x, y, z), fed);
+            }
+            if Self::is_air(voxels, (x, y, z + 1)) {
+                raise((x, y, z + 1), MAX_WATER_LEVEL - 1);
+            } else if level > 1 && self.spreads(voxels, (x, y, z)) {
+                for (dx, dy) in SIDES {
+                    if Self::is_air(voxels, (x + dx, y + dy, z)) {
+                        raise((x + dx, y + dy, z), level - 1);
+                    }
+                }
+            }
+        }
+
+        let mut changed = HashSet::new();
+        for &(x, y, z) in self.levels.keys().filter(|cell| !next.contains_key(cell)) {
+            voxels.set(x, y, z, None);
+            changed.extend(VoxelWorld::chunks_around(x, y));
+        }
+        for &(x, y, z) in next.keys().filter(|cell| !self.levels.contains_key(cell)) {
+            voxels.set(x, y, z, Some(self.kind));
+            changed.extend(VoxelWorld::chunks_around(x, y));
+        }
+        self.levels = next;
+        changed.into_iter().collect()
+    }
+
+    /// Returns true if the water in this block spreads to the sides: it lies on a block which is
+    /// not water (or on the bottom of the world)
+    fn spreads(&self, voxels: &VoxelWorld, (x, y, z): Cell) -> bool {
+        !voxels.heights.contains(&(z + 1)) || voxels.get(x, y, z + 1).is_some_and(|block| block != self.kind)
+    }
+
+    fn is_air(voxels: &VoxelWorld, (x, y, z): Cell) -> bool {
+        voxels.heights.contains(&z) && voxels.get(x, y, z).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::voxels::water::{WaterSimulation, MAX_WATER_LEVEL};
+    use crate::voxels::VoxelWorld;
+
+    #[test]
+    fn test_water_flow() {
+        let mut voxels = VoxelWorld::new(-4..2);
+        let stone = voxels.add_block_kind(&YELLOW, &YELLOW);
+        let water = voxels.add_block_kind(&YELLOW, &YELLOW);
+        voxels.set_transparent(water, true);
+        // A floor at z = 1, with a source of water two blocks above it
+        for x in -5..20 {
+            for y in -5..20 {
+                voxels.set(x, y, 1, Some(stone));
+            }
+        }
+        let mut simulation = WaterSimulation::new(water);
+        simulation.add_source(&mut voxels, 5, 5, -2);
+
+        // The water falls down to the floor, then spreads on it
+        assert_eq!(vec![(0, 0)], simulation.step(&mut voxels));
+        assert_eq!(Some(water), voxels.get(5, 5, -1));
+        simulation.step(&mut voxels);
+        assert_eq!(Some(MAX_WATER_LEVEL - 1), simulation.level(5, 5, 0));
+        assert_eq!(None, simulation.level(6, 5, 0));
+        simulation.step(&mut voxels);
+        assert_eq!(Some(MAX_WATER_LEVEL - 2), simulation.level(6, 5, 0));
+        assert_eq!(None, simulation.level(6, 5, -1));
+        for _ in 0..20 {
+            simulation.step(&mut voxels);
+        }
+        // A diamond of 6 blocks of radius on the floor, under the column
+        assert_eq!(Some(1), simulation.level(11, 5, 0));
+        assert_eq!(None, simulation.level(12, 5, 0));
+        assert_eq!(2 + 1 + 2 * 6 * 7, simulation.volume());
+        assert!(simulation.step(&mut voxels).is_empty());
+
+        // Digging a hole next to the water lets it flow in
+        voxels.set(8, 5, 1, None);
+        simulation.step(&mut voxels);
+        assert_eq!(Some(MAX_WATER_LEVEL - 1), simulation.level(8, 5, 1));
+
+        // Without its source, the water dries up
+        simulation.remove_source(5, 5, -2);
+        for _ in 0..30 {
+            simulation.step(&mut voxels);
+        }
+        assert_eq!(0, simulation.volume());
+        assert_eq!(None, voxels.get(5, 5, 0));
+        assert_eq!(Some(stone), voxels.get(6, 5, 1));
+    }
+
+    #[test]
+    fn test_transparent_blocks() {
+        let mut voxels = VoxelWorld::new(0..2);
+        let stone = voxels.add_block_kind(&YELLOW, &YELLOW);
+        let water = voxels.add_block_kind(&YELLOW, &YELLOW);
+        voxels.set_transparent(water, true);
+        voxels.set(0, 0, 0, Some(stone));
+        voxels.set(1, 0, 0, Some(water));
+        voxels.set(2, 0, 0, Some(water));
+        // The side of the stone is seen through the water, but the water between two blocks of
+        // water is not drawn
+        assert_eq!(6 + 4 + 5, voxels.chunk_faces((0, 0)).len());
+    }
+}
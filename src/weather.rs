@@ -0,0 +1,257 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::frame::Frame;
+use crate::primitives::camera::Camera;
+use crate::primitives::vector::Vector3;
+use crate::tunables::tunables;
+
+/// Maximum number of particles, reached at full intensity
+const MAX_PARTICLES: usize = 3000;
+/// Particles live in a box centered on the camera, of this half size (horizontally and vertically)
+const HALF_EXTENT: f32 = 6.;
+const HALF_HEIGHT: f32 = 4.;
+/// Particles closer to the camera are not drawn, since they would cover large parts of the screen
+const MIN_DISTANCE: f32 = 0.3;
+
+/// Falling speed of the particles (the z-axis goes down)
+const RAIN_SPEED: f32 = 12.;
+const SNOW_SPEED: f32 = 0.8;
+/// Rain drops are drawn as the segment they travel during this duration
+const RAIN_STREAK_DURATION: f32 = 0.02;
+/// Amplitude (in m/s) and frequency of the sideways motion of the snow flakes
+const SNOW_DRIFT: f32 = 0.4;
+const SNOW_DRIFT_FREQUENCY: f32 = 1.5;
+
+const RAIN_COLOR: [u8; 4] = [150, 160, 190, 150];
+const SNOW_COLOR: [u8; 4] = [255, 255, 255, 220];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeatherKind {
+    Clear,
+    Rain,
+    Snow,
+}
+
+impl WeatherKind {
+    /// Returns the next kind of weather, to cycle through all of them
+    pub fn next(self) -> Self {
+        match self {
+            WeatherKind::Clear => WeatherKind::Rain,
+            WeatherKind::Rain => WeatherKind::Snow,
+            WeatherKind::Snow => WeatherKind::Clear,
+        }
+    }
+}
+
+struct Particle {
+    position: Vector3,
+    /// Random phase of the sideways motion of snow flakes
+    phase: f32,
+}
+
+/// Rain or snow falling around the camera.
+///
+/// The particles fill a box which follows the camera: a particle leaving the box (because it
+/// fell, or because the camera moved) re-enters it on the opposite side. Particles are therefore
+/// fixed in the world, but there is always the same amount of them around the camera.
+pub struct Weather {
+    kind: WeatherKind,
+    /// Between 0 (no particle) and 1 (all the particles)
+    intensity: f32,
+    particles: Vec<Particle>,
+    /// Time elapsed since the creation, to animate the snow flakes
+    time: f32,
+}
+
+impl Weather {
+    pub fn new() -> Self {
+        Self {
+            kind: WeatherKind::Clear,
+            intensity: 0.5,
+            particles: Vec::new(),
+            time: 0.,
+        }
+    }
+
+    pub fn kind(&self) -> WeatherKind {
+        self.kind
+    }
+
+    pub fn set_kind(&mut self, kind: WeatherKind) {
+        self.kind = kind;
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity.clamp(0., 1.);
+    }
+
+    /// Number of particles currently falling
+    fn active_particles(&self) -> usize {
+        match self.kind {
+            WeatherKind::Clear => 0,
+            _ => (self.intensity * MAX_PARTICLES as f32) as usize,
+        }
+    }
+
+    fn velocity(&self, particle: &Particle) -> Vector3 {
+        match self.kind {
+            WeatherKind::Clear => Vector3::empty(),
+            WeatherKind::Rain => Vector3::new(0., 0., RAIN_SPEED),
+            WeatherKind::Snow => {
+                let angle = SNOW_DRIFT_FREQUENCY * self.time + particle.phase;
+                Vector3::new(SNOW_DRIFT * angle.cos(), SNOW_DRIFT * angle.sin(), SNOW_SPEED)
+            }
+        }
+    }
+
+    /// Moves the particles by `dt` seconds, keeping them around the camera
+    pub fn update(&mut self, dt: f32, camera_position: &Vector3, rng: &mut StdRng) {
+        self.time += dt;
+        let n = self.active_particles();
+        while self.particles.len() < n {
+            let offset = Vector3::new(
+                rng.gen_range(-HALF_EXTENT..HALF_EXTENT),
+                rng.gen_range(-HALF_EXTENT..HALF_EXTENT),
+                rng.gen_range(-HALF_HEIGHT..HALF_HEIGHT),
+            );
+            let phase = rng.gen_range(0.0..std::f32::consts::TAU);
+            self.particles.push(Particle { position: camera_position + &offset, phase });
+        }
+        self.particles.truncate(n);
+
+        for i in 0..self.particles.len() {
+            let velocity = self.velocity(&self.particles[i]);
+            let particle = &mut self.particles[i];
+            let offset = particle.position + velocity * dt - *camera_position;
+            particle.position = camera_position
+                + &Vector3::new(
+                    wrap(offset.x(), HALF_EXTENT),
+                    wrap(offset.y(), HALF_EXTENT),
+                    wrap(offset.z(), HALF_HEIGHT),
+                );
+        }
+    }
+
+    /// Draws the particles on top of the frame, except those behind the faces whose distance at
+    /// each pixel is in the depth buffer (see `World::draw_depth`)
+    pub fn draw(&self, frame: &mut Frame, camera: &Camera, depth: &[u32]) {
+        let camera_position = camera.pose().position();
+        let viewport = camera.viewport();
+        let scale = tunables().distance_scale;
+        for particle in &self.particles {
+            let distance = (particle.position - *camera_position).norm();
+            if distance < MIN_DISTANCE {
+                continue;
+            }
+            let start = camera.project(&particle.position);
+            if !start.in_front() {
+                continue;
+            }
+            let (x, y) = (start.x(), start.y());
+            let on_screen = x >= 0. && y >= 0. && (x as u32) < viewport.width && (y as u32) < viewport.height;
+            if on_screen && depth.get(viewport.index(x as u32, y as u32)).is_some_and(|closest| (distance * scale) as u32 >= *closest) {
+                continue;
+            }
+            match self.kind {
+                WeatherKind::Clear => {}
+                WeatherKind::Rain => {
+                    let end = camera.project(&(particle.position + self.velocity(particle) * RAIN_STREAK_DURATION));
+                    if end.in_front() {
+                        frame.draw_line(start.x() as i32, start.y() as i32, end.x() as i32, end.y() as i32, RAIN_COLOR);
+                    }
+                }
+                WeatherKind::Snow => {
+                    let size = if distance < 2. { 2 } else { 1 };
+                    frame.fill_rect(start.x() as i32, start.y() as i32, size, size, SNOW_COLOR);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wraps the value in [-half_size, half_size)
+fn wrap(value: f32, half_size: f32) -> f32 {
+    (value + half_size).rem_euclid(2. * half_size) - half_size
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::frame::Frame;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::{Vector3, UNIT_X};
+    use crate::weather::{Weather, WeatherKind, HALF_EXTENT, HALF_HEIGHT, MAX_PARTICLES};
+    use crate::worlds::World;
+
+    #[test]
+    fn test_particles_follow_camera() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut weather = Weather::new();
+        weather.set_kind(WeatherKind::Rain);
+        weather.set_intensity(1.);
+
+        let mut camera_position = Vector3::empty();
+        weather.update(0.1, &camera_position, &mut rng);
+        assert_eq!(MAX_PARTICLES, weather.particles.len());
+
+        // The camera moves far away: the particles must still surround it
+        for _ in 0..10 {
+            camera_position += Vector3::new(3., -1., 0.5);
+            weather.update(0.1, &camera_position, &mut rng);
+        }
+        for particle in &weather.particles {
+            let offset = particle.position - camera_position;
+            assert!(offset.x().abs() <= HALF_EXTENT && offset.y().abs() <= HALF_EXTENT);
+            assert!(offset.z().abs() <= HALF_HEIGHT);
+        }
+
+        // The intensity controls the number of particles
+        weather.set_intensity(0.5);
+        weather.update(0.1, &camera_position, &mut rng);
+        assert_eq!(MAX_PARTICLES / 2, weather.particles.len());
+        weather.set_kind(WeatherKind::Clear);
+        weather.update(0.1, &camera_position, &mut rng);
+        assert!(weather.particles.is_empty());
+    }
+
+    #[test]
+    fn test_particles_hidden_by_faces() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut weather = Weather::new();
+        weather.set_kind(WeatherKind::Snow);
+        weather.set_intensity(1.);
+        weather.update(0.1, &Vector3::empty(), &mut rng);
+
+        let mut world = World::new(Camera::default());
+        let viewport = world.viewport();
+        let snow_pixels = |world: &World| {
+            let mut depth = vec![u32::MAX; viewport.pixel_count()];
+            world.draw_depth(&mut depth);
+            let mut buffer = vec![0; viewport.buffer_len()];
+            weather.draw(&mut Frame::new(&mut buffer, viewport), world.camera(), &depth);
+            buffer.chunks_exact(4).filter(|pixel| *pixel != [0, 0, 0, 0]).count()
+        };
+        let all = snow_pixels(&world);
+        assert!(all > 100);
+
+        // A wall right in front of the camera hides the particles behind it
+        let corners = [(-20, -20), (20, -20), (20, 20), (-20, 20)].map(|(y, z)| Vector3::new(0.6, y as f32, z as f32));
+        world.add_face(CubicFace3::new(corners, UNIT_X.opposite(), &YELLOW));
+        assert!(snow_pixels(&world) < all / 10);
+    }
+}
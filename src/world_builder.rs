@@ -0,0 +1,126 @@
+use std::ops::Range;
+
+use crate::fog::Fog;
+use crate::lights::Lighting;
+use crate::primitives::camera::Camera;
+use crate::primitives::cube::Cube3;
+use crate::primitives::cubic_face3::CubicFace3;
+use crate::primitives::position::Pose;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::vector::Vector3;
+use crate::sky::Sky;
+use crate::viewport::Viewport;
+use crate::worlds::World;
+
+/// Builds a world in a few chained calls, for the applications using the engine as a library.
+///
+/// ```
+/// use doom::prelude::*;
+///
+/// let world = WorldBuilder::new()
+///     .floor(-2..6, -3..4, 1, &YELLOW)
+///     .cube(Vector3::newi(2, 0, 0), &ORANGE)
+///     .camera_at(Vector3::new(-1., 0.5, -1.5), 0.)
+///     .sky(GradientSky::day())
+///     .build();
+/// assert_eq!(57, world.objects().count());
+/// ```
+pub struct WorldBuilder {
+    world: World,
+}
+
+impl WorldBuilder {
+    /// Starts from an empty world, seen by a default camera
+    pub fn new() -> Self {
+        Self { world: World::new(Camera::default()) }
+    }
+
+    /// Places the camera at the position, turned by `rotation` around the vertical axis
+    pub fn camera_at(mut self, position: Vector3, rotation: f32) -> Self {
+        self.world.set_camera_pose(Pose::new(position, rotation));
+        self
+    }
+
+    /// Adds a cube of side 1 with the same texture on all its faces, whose corner is at `from`
+    pub fn cube(self, from: Vector3, texture: &TextureHandle) -> Self {
+        self.cube_with_top(from, texture, texture)
+    }
+
+    /// Adds a cube of side 1 with a different texture on its top face
+    pub fn cube_with_top(mut self, from: Vector3, side: &TextureHandle, top: &TextureHandle) -> Self {
+        self.world.add_cube(Cube3::minecraft_like(from, side, top));
+        self
+    }
+
+    /// Adds a layer of cubes at the height `z`, covering the given ranges of x and y
+    pub fn floor(mut self, x: Range<i32>, y: Range<i32>, z: i32, texture: &TextureHandle) -> Self {
+        for i in x {
+            for j in y.clone() {
+                self.world.add_cube(Cube3::minecraft_like(Vector3::newi(i, j, z), texture, texture));
+            }
+        }
+        self
+    }
+
+    pub fn face(mut self, face: CubicFace3) -> Self {
+        self.world.add_face(face);
+        self
+    }
+
+    /// Registers the texture under a name, for the scene files
+    pub fn texture(mut self, name: &str, texture: &TextureHandle) -> Self {
+        self.world.register_texture(name, texture);
+        self
+    }
+
+    pub fn sky(mut self, sky: impl Sky + 'static) -> Self {
+        self.world.set_sky(Box::new(sky));
+        self
+    }
+
+    pub fn lighting(mut self, lighting: Lighting) -> Self {
+        self.world.set_lighting(lighting);
+        self
+    }
+
+    pub fn fog(mut self, fog: Fog) -> Self {
+        self.world.set_fog(Some(fog));
+        self
+    }
+
+    pub fn viewport(mut self, viewport: Viewport) -> Self {
+        self.world.set_viewport(viewport);
+        self
+    }
+
+    pub fn build(self) -> World {
+        self.world
+    }
+}
+
+impl Default for WorldBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitives::textures::colored::YELLOW;
+    use crate::primitives::vector::Vector3;
+    use crate::viewport::Viewport;
+    use crate::world_builder::WorldBuilder;
+
+    #[test]
+    fn test_world_builder() {
+        let world = WorldBuilder::new()
+            .floor(0..3, 0..2, 1, &YELLOW)
+            .cube(Vector3::newi(1, 1, 0), &YELLOW)
+            .camera_at(Vector3::new(-1., 0., -1.), 0.5)
+            .viewport(Viewport::new(160, 100))
+            .build();
+        assert_eq!(7, world.objects().count());
+        assert_eq!(Vector3::new(-1., 0., -1.), *world.camera().pose().position());
+        assert_eq!(160, world.viewport().width);
+    }
+}
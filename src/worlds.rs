@@ -1,222 +1,2404 @@
+use std::collections::HashMap;
+use std::hash::Hasher;
 use std::time::Instant;
 
+use rand::rngs::StdRng;
+use rayon::prelude::*;
+use rand::SeedableRng;
+use serde_json::{json, Value};
 use winit::event::VirtualKeyCode;
 
+use crate::assets::TextureLibrary;
+use crate::accel::tiles::ScreenTiles;
 use crate::bsp::tree::*;
+use crate::content_hash::ContentHasher;
+use crate::debug_paths::DebugPaths;
+use crate::draw_distance::{DistanceCulledFrame, DrawDistance};
 use crate::drawable::Drawable;
-use crate::frame::AbstractFrame;
-use crate::motion_model::{DEFAULT_ACC, MotionModel};
+use crate::entities::{EntityId, EntityLayer};
+use crate::fog::{Fog, FoggedFrame};
+use crate::frame::{AbstractFrame, Frame};
+use crate::input_map::{Action, InputMap};
+use crate::labels::{Label, LABEL_MARGIN};
+use crate::layers::Layers;
+use crate::lights::{Lighting, LitFrame};
+use crate::map::Map;
+use crate::motion_model::MotionModel;
+use crate::movement::{FootstepTracker, MovementEvent};
+use crate::pathfinding;
+use crate::primitives::aabb::Aabb;
+use crate::primitives::billboard::Billboard;
 use crate::primitives::camera::Camera;
 use crate::primitives::cube::Cube3;
 use crate::primitives::cubic_face2::CubicFace2;
 use crate::primitives::cubic_face3::CubicFace3;
-use crate::primitives::object::Object;
-use crate::primitives::point::Point2;
+use crate::primitives::mesh::Mesh;
+use crate::primitives::object::{Object, ObjectId, ObjectKind};
+use crate::primitives::position::Pose;
 use crate::primitives::projective_coordinates::ProjectionCoordinates;
-use crate::primitives::vector::Vector3;
-use crate::WIDTH;
+use crate::primitives::ray::{Hit, Intersectable, Ray, RaytraceObject};
+use crate::primitives::sphere::Sphere;
+use crate::primitives::tessellation::Patch;
+use crate::primitives::transformation::Transform;
+use crate::primitives::textures::TextureHandle;
+use crate::primitives::vector::{Vector3, UNIT_Z};
+use crate::profiler::{FrameProfiler, Stage, TimedFrame};
+use crate::scene_file::SceneFile;
+use crate::scene_graph::SceneGraph;
+use crate::sdf::{draw_raymarching, SignedDistance};
+use crate::sky::{SolidSky, Sky};
+use crate::spatial_hash::SpatialHashGrid;
+use crate::tunables::tunables;
+use crate::updatable::WorldQuery;
+use crate::ui::palette::DebugPalette;
+use crate::viewport::{Rect, Viewport};
+use crate::visibility::VisibilityStats;
+use crate::weather::Weather;
+
+/// Where a ray hits the world (see `World::raycast`)
+#[derive(Clone, Debug)]
+pub struct RaycastHit<'a> {
+    pub object: ObjectId,
+    pub face: &'a CubicFace3,
+    /// Distance from the origin of the ray, in meters
+    pub distance: f32,
+    pub point: Vector3,
+    /// Texture coordinates of the point on the face
+    pub uv: (f32, f32),
+}
+
+/// Radius of the sphere used to check collisions between the camera and the world
+const CAMERA_RADIUS: f32 = 0.2;
+/// Distance between the camera and the ground when walking.
+/// Note that the z-axis goes down (towards the ground).
+const EYE_HEIGHT: f32 = 1.5;
+/// The camera is standing on the ground when its distance to the ground is at most
+/// `EYE_HEIGHT + GROUND_TOLERANCE`
+const GROUND_TOLERANCE: f32 = 0.1;
+/// Faces whose normal is closer to the vertical than this are horizontal: the camera can stand
+/// on them
+const MIN_GROUND_VERTICALITY: f32 = 0.99;
+/// Objects closer than this distance (in meters) to the end of a line of sight do not block it, so
+/// that the points on the surface of the objects can be seen
+const LINE_OF_SIGHT_MARGIN: f32 = 1e-3;
+/// Resolution and range of the search for a free spot around a teleportation target
+const FREE_SPOT_STEP: f32 = 0.25;
+const FREE_SPOT_MAX_STEPS: i32 = 20;
+/// Size of the cells of the grid on which the paths are searched, and maximum number of cells
+/// visited by a search
+const PATH_STEP: f32 = 0.25;
+const PATH_MAX_CELLS: usize = 20_000;
+/// Size of the cells of the spatial grid used for collision queries
+const GRID_CELL_SIZE: f32 = 4.;
+/// Duration of the simulation step applied when single-stepping a paused world
+const SINGLE_STEP_DT: f32 = 1. / 60.;
+/// Time scale used by the slow motion
+const SLOW_MOTION_TIME_SCALE: f32 = 0.25;
+/// Factor by which the field of view narrows for each zoom key press or line of the mouse wheel
+const ZOOM_STEP: f32 = 1.1;
+/// Name of the debug path showing the frustum of the player's camera while spectating
+const PLAYER_FRUSTUM: &str = "Player frustum";
+/// Depth of the frustum of the player's camera shown while spectating, without draw distance
+const PLAYER_FRUSTUM_DEPTH: f32 = 10.;
+/// Distance (in meters) travelled by the spectator camera at each simulation step of a held key
+const SPECTATOR_STEP: f32 = 0.1;
+/// Relative difference between the area of a face and the area removed from the BSP, under which
+/// the face is considered removed
+const BSP_AREA_TOLERANCE: f32 = 1e-3;
+/// The BSP is built again when the objects moved in it made it this many times larger than when
+/// it was built
+const BSP_MAX_GROWTH: usize = 2;
+/// Minimum number of faces inserted into or removed from the BSP between two checks of its size
+const BSP_MIN_EDITS: usize = 64;
 
 /// Representation of the world in 3D coordinates
 /// A world simply contains several objects
 pub struct World {
     objects: Vec<Box<dyn Object>>,
+    /// Identifier of each object
+    ids: Vec<ObjectId>,
+    /// Identifier given to the next object added to the world
+    next_id: u64,
+    /// Spatial index of the objects, rebuilt at each update since objects can move
+    grid: SpatialHashGrid,
     bsp: Option<BSPNode>,
+    /// Whether the painter algorithm uses the BSP (when it was computed)
+    use_bsp: bool,
+    /// Number of nodes of the BSP when it was built, to detect when the incremental edits made it
+    /// grow too much
+    bsp_built_nodes: usize,
+    /// Number of faces inserted into or removed from the BSP since its size was last checked
+    bsp_edits: usize,
+    /// Set when some faces could not be removed from the BSP: it is built again
+    bsp_stale: bool,
+    /// Whether the faces of the BSP were merged, to merge them again when it is built again
+    bsp_merged: bool,
+    /// Names of the textures, used to save and load the scene
+    textures: TextureLibrary,
+    /// Lights of the world (the textures are drawn as is if None)
+    lighting: Option<Lighting>,
+    fog: Option<Fog>,
+    /// Shapes rendered by the (experimental) ray marching renderer
+    sdf: Option<Box<dyn SignedDistance>>,
+    /// Shapes rendered by the raytracer, on top of the faces of the objects
+    raytrace_objects: Vec<Box<dyn RaytraceObject>>,
     camera: Camera,
+    /// Free camera from which the world is drawn while spectating. The culling is still done
+    /// from the player's camera, so that it can be inspected from outside.
+    spectator: Option<Camera>,
+    /// Other cameras looking at the world (such as the one of a minimap), drawn with
+    /// `draw_painter_with`
+    cameras: Vec<Camera>,
     /// The motion model is the class responsible for smoothly updating the position
     motion_model: MotionModel,
-    /// Keep track for each time intervals to correctly update the motion model
-    last_time: Instant,
+    /// Position of the camera before the last simulation step
+    previous_position: Vector3,
+    /// While the camera is drawn between two simulation steps (see `interpolate_camera`), its
+    /// actual position in the simulation
+    simulated_position: Option<Vector3>,
     /// At each iteration, keep track whether a motion was applied
     motion_applied: bool,
+    /// Where the camera is placed when (re)spawning
+    spawn_point: Option<Pose>,
+    /// In walking mode, the camera stays on the ground
+    walking: bool,
+    /// Factor applied to the elapsed time to update the simulation (0 = paused)
+    time_scale: f32,
+    /// When set, the next update advances the simulation by exactly one step
+    single_step_requested: bool,
+    /// Random generator of the simulation, which can be seeded
+    rng: StdRng,
+    /// Actions triggered by the keys sent to the world
+    input_map: InputMap,
+    /// Angle by which the camera turns at each key press
+    turn_step: f32,
+    /// Angle by which the camera turns for each pixel moved by the mouse
+    mouse_sensitivity: f32,
+    /// Rain or snow falling around the camera
+    weather: Weather,
+    /// Background, seen where there is no object
+    sky: Box<dyn Sky>,
+    /// Objects further than this distance are not drawn (no limit if None)
+    draw_distance: Option<DrawDistance>,
+    /// For each object, whether it is within the draw distance
+    in_range: Vec<bool>,
+    /// Bounding sphere (center, radius) of each object, or None if the object may have changed
+    /// through `get_mut` since it was computed
+    bounds: Vec<Option<(Vector3, f32)>>,
+    /// Layers of each object: an object is only drawn if the camera sees one of its layers
+    layers: Vec<Layers>,
+    /// Text drawn above some of the objects
+    labels: HashMap<ObjectId, Label>,
+    /// Objects attached to other objects, moving with them
+    scene_graph: SceneGraph,
+    /// Game objects and the systems updating them
+    entities: EntityLayer,
+    /// Paths drawn over the world, for debugging
+    debug_paths: DebugPaths,
+    /// Movement events emitted during the last update
+    movement_events: Vec<MovementEvent>,
+    footsteps: FootstepTracker,
 }
 
 impl World {
     pub fn new(camera: Camera) -> Self {
+        let previous_position = *camera.pose().position();
         Self {
             objects: Vec::new(),
+            ids: Vec::new(),
+            next_id: 0,
+            grid: SpatialHashGrid::new(GRID_CELL_SIZE),
             bsp: None,
+            use_bsp: true,
+            bsp_built_nodes: 0,
+            bsp_edits: 0,
+            bsp_stale: false,
+            bsp_merged: false,
+            textures: TextureLibrary::new(),
+            lighting: None,
+            fog: None,
+            sdf: None,
+            raytrace_objects: Vec::new(),
             camera,
+            spectator: None,
+            cameras: Vec::new(),
             motion_model: MotionModel::new(),
-            last_time: Instant::now(),
+            previous_position,
+            simulated_position: None,
             motion_applied: false,
+            spawn_point: None,
+            walking: false,
+            time_scale: 1.,
+            single_step_requested: false,
+            rng: StdRng::from_entropy(),
+            input_map: InputMap::default(),
+            turn_step: std::f32::consts::PI / 16.,
+            mouse_sensitivity: 0.003,
+            weather: Weather::new(),
+            sky: Box::new(SolidSky::default()),
+            draw_distance: None,
+            in_range: Vec::new(),
+            bounds: Vec::new(),
+            layers: Vec::new(),
+            labels: HashMap::new(),
+            scene_graph: SceneGraph::new(),
+            entities: EntityLayer::new(),
+            debug_paths: DebugPaths::default(),
+            movement_events: Vec::new(),
+            footsteps: FootstepTracker::new(),
+        }
+    }
+
+    pub fn add_cube(&mut self, cube: Cube3) -> ObjectId {
+        self.add_object(Box::new(cube))
+    }
+
+    pub fn add_face(&mut self, face: CubicFace3) -> ObjectId {
+        self.add_object(Box::new(face))
+    }
+
+    pub fn add_mesh(&mut self, mesh: Mesh) -> ObjectId {
+        self.add_object(Box::new(mesh))
+    }
+
+    pub fn add_patch(&mut self, patch: Patch) -> ObjectId {
+        self.add_object(Box::new(patch))
+    }
+
+    /// Adds an object whose behavior is updated at each step of the simulation (see `Updatable`)
+    pub fn add_updatable(&mut self, object: impl Object + 'static) -> ObjectId {
+        self.add_object(Box::new(object))
+    }
+
+    /// Adds a billboard turned towards the camera, which turns again at each update of the world
+    pub fn add_billboard(&mut self, mut billboard: Billboard) -> ObjectId {
+        billboard.face_towards(self.view_camera().pose().position());
+        self.add_object(Box::new(billboard))
+    }
+
+    fn add_object(&mut self, object: Box<dyn Object>) -> ObjectId {
+        let id = ObjectId::new(self.next_id);
+        self.next_id += 1;
+        let (center, radius) = object.bounding_sphere();
+        self.grid.insert(self.objects.len(), center, radius);
+        self.objects.push(object);
+        self.ids.push(id);
+        self.in_range.push(true);
+        self.bounds.push(Some((center, radius)));
+        self.layers.push(Layers::GAMEPLAY);
+        self.update_in_range_of(self.objects.len() - 1);
+        self.insert_in_bsp(self.objects.len() - 1);
+        self.maintain_bsp();
+        id
+    }
+
+    /// Moves the object to other layers (all the objects are added to the gameplay layer).
+    /// Returns false if there is no such object.
+    pub fn set_layers(&mut self, id: ObjectId, layers: Layers) -> bool {
+        let Some(i) = self.index_of(id) else {
+            return false;
+        };
+        self.layers[i] = layers;
+        self.clear_bsp();
+        true
+    }
+
+    pub fn layers(&self, id: ObjectId) -> Option<Layers> {
+        self.index_of(id).map(|i| self.layers[i])
+    }
+
+    /// Layers of objects seen by the camera
+    pub fn visible_layers(&self) -> Layers {
+        self.camera.visible_layers()
+    }
+
+    /// Shows only the objects of these layers. The BSP is computed again the next time it is used.
+    pub fn set_visible_layers(&mut self, layers: Layers) {
+        if layers != self.camera.visible_layers() {
+            self.camera.set_visible_layers(layers);
+            self.clear_bsp();
+        }
+    }
+
+    /// Attaches a label to the object, drawn above it, replacing its previous label.
+    /// Returns false if there is no such object.
+    pub fn set_label(&mut self, id: ObjectId, label: Label) -> bool {
+        if self.index_of(id).is_none() {
+            return false;
+        }
+        self.labels.insert(id, label);
+        true
+    }
+
+    pub fn remove_label(&mut self, id: ObjectId) -> Option<Label> {
+        self.labels.remove(&id)
+    }
+
+    pub fn label(&self, id: ObjectId) -> Option<&Label> {
+        self.labels.get(&id)
+    }
+
+    /// Draws the labels of the visible objects on top of the frame, which must already contain
+    /// the world. Labels hidden behind objects are faded, and the closest labels are drawn last.
+    pub fn draw_labels(&self, frame: &mut Frame) {
+        let origin = self.view_camera().pose().position();
+        let mut anchors: Vec<(f32, Vector3, usize, &Label)> = self
+            .labels
+            .iter()
+            .filter_map(|(id, label)| self.index_of(*id).map(|i| (i, label)))
+            .filter(|(i, _)| self.in_range[*i] && self.is_layer_visible(*i))
+            .filter_map(|(i, label)| {
+                let points: Vec<Vector3> = self.objects[i].get_all_faces().iter().flat_map(|face| face.points()).collect();
+                let aabb = Aabb::from_points(&points)?;
+                // Centered above the object (the z-axis goes down)
+                let top = Vector3::new((aabb.min().x() + aabb.max().x()) / 2., (aabb.min().y() + aabb.max().y()) / 2., aabb.min().z() - LABEL_MARGIN);
+                Some((origin.line_to(&top).norm(), top, i, label))
+            })
+            .collect();
+        anchors.sort_by(|(d1, _, _, _), (d2, _, _, _)| d2.total_cmp(d1));
+        for (distance, anchor, i, label) in anchors {
+            // The line towards the anchor may graze the labelled object itself, which does not hide
+            // its own label
+            let ray = Ray::between(*origin, anchor);
+            let occluded = self.first_hit(&ray, distance, |j| j != i && self.is_layer_visible(j)).is_some();
+            label.draw(frame, self.view_camera(), &anchor, occluded);
+        }
+    }
+
+    /// Returns true if the object is in a layer seen by the camera
+    fn is_layer_visible(&self, i: usize) -> bool {
+        self.layers[i].intersects(self.camera.visible_layers())
+    }
+
+    /// Only the objects of the gameplay layer block the camera, whether they are shown or not
+    fn is_solid(&self, i: usize) -> bool {
+        self.layers[i].intersects(Layers::GAMEPLAY)
+    }
+
+    fn index_of(&self, id: ObjectId) -> Option<usize> {
+        self.ids.iter().position(|other| *other == id)
+    }
+
+    pub fn get(&self, id: ObjectId) -> Option<&dyn Object> {
+        self.index_of(id).map(|i| self.objects[i].as_ref())
+    }
+
+    /// Returns the object, to modify it. The BSP is computed again the next time it is used.
+    pub fn get_mut(&mut self, id: ObjectId) -> Option<&mut dyn Object> {
+        let i = self.index_of(id)?;
+        self.clear_bsp();
+        self.bounds[i] = None;
+        self.scene_graph.forget_rest(id);
+        Some(self.objects[i].as_mut())
+    }
+
+    /// Removes the object from the world and returns it (None if there is no such object)
+    pub fn remove(&mut self, id: ObjectId) -> Option<Box<dyn Object>> {
+        let i = self.index_of(id)?;
+        self.remove_from_bsp(i);
+        self.ids.remove(i);
+        self.in_range.remove(i);
+        self.bounds.remove(i);
+        self.layers.remove(i);
+        self.labels.remove(&id);
+        self.scene_graph.remove(id);
+        let object = self.objects.remove(i);
+        self.maintain_bsp();
+        // The indices of the next objects changed
+        self.grid.clear();
+        for i in 0..self.objects.len() {
+            let (center, radius) = self.bounds(i);
+            self.grid.insert(i, center, radius);
+        }
+        Some(object)
+    }
+
+    pub fn scene_graph(&self) -> &SceneGraph {
+        &self.scene_graph
+    }
+
+    pub fn entities(&self) -> &EntityLayer {
+        &self.entities
+    }
+
+    /// The entities and systems added here are updated with the world
+    pub fn entities_mut(&mut self) -> &mut EntityLayer {
+        &mut self.entities
+    }
+
+    /// Despawns the entity, and removes its object from the world. Returns false if there is no
+    /// such entity.
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        let Some(entity) = self.entities.entities_mut().despawn(id) else {
+            return false;
+        };
+        if let Some(object) = entity.render() {
+            self.remove(object);
+        }
+        true
+    }
+
+    /// Attaches the object to another one (or detaches it), so that it moves with its parent. The
+    /// object stays where it is.
+    pub fn set_parent(&mut self, id: ObjectId, parent: Option<ObjectId>) -> Result<(), String> {
+        for id in std::iter::once(id).chain(parent) {
+            self.index_of(id).ok_or(format!("There is no object {id:?}"))?;
+        }
+        self.scene_graph.set_parent(id, parent)
+    }
+
+    /// Moves the object relative to its parent (or to where it was added, without parent), with
+    /// all the objects attached to it. Returns false if there is no such object.
+    pub fn set_local_transform(&mut self, id: ObjectId, transform: Transform) -> bool {
+        if self.index_of(id).is_none() {
+            return false;
+        }
+        self.scene_graph.set_local_transform(id, transform);
+        for id in self.scene_graph.subtree(id) {
+            if let Some(i) = self.index_of(id) {
+                self.remove_from_bsp(i);
+                let faces = self.scene_graph.place_faces(id, &self.objects[i].get_all_faces());
+                self.objects[i].set_faces(&faces);
+                self.update_bounds(i);
+                self.insert_in_bsp(i);
+            }
+        }
+        self.maintain_bsp();
+        true
+    }
+
+    /// Iterates over the objects of the world, with their identifier
+    pub fn objects(&self) -> impl Iterator<Item = (ObjectId, &dyn Object)> + '_ {
+        self.ids.iter().copied().zip(self.objects.iter().map(|object| object.as_ref()))
+    }
+
+    /// Sets the maximum distance at which objects are drawn, or removes the limit
+    pub fn set_draw_distance(&mut self, distance: Option<f32>) {
+        self.draw_distance = distance.map(DrawDistance::new);
+        self.in_range.iter_mut().for_each(|visible| *visible = true);
+        self.update_in_range();
+    }
+
+    /// Updates which objects are within the draw distance of the camera
+    fn update_in_range(&mut self) {
+        for i in 0..self.objects.len() {
+            self.update_in_range_of(i);
         }
     }
 
-    pub fn add_cube(&mut self, cube: Cube3) {
-        self.objects.push(Box::new(cube));
+    /// Updates whether the object at index `i` is within the draw distance of the camera
+    fn update_in_range_of(&mut self, i: usize) {
+        let Some(draw_distance) = self.draw_distance else {
+            return;
+        };
+        let distance = self.distance_to(i, self.camera.pose().position());
+        self.in_range[i] = draw_distance.is_visible(distance, self.in_range[i]);
+    }
+
+    /// Returns whether the object at index `i` is within the draw distance of any camera. Without
+    /// knowing what this camera drew before, the objects are drawn up to the outer distance.
+    fn is_in_range_of(&self, i: usize, camera: &Camera) -> bool {
+        self.draw_distance.is_none_or(|draw_distance| draw_distance.is_visible(self.distance_to(i, camera.pose().position()), true))
+    }
+
+    /// Returns the distance from the position to the bounding sphere of the object at index `i`
+    fn distance_to(&self, i: usize, position: &Vector3) -> f32 {
+        let (center, radius) = self.bounds(i);
+        (center.line_to(position).norm() - radius).max(0.)
+    }
+
+    /// Returns the bounding sphere (center, radius) of the object at index `i`
+    fn bounds(&self, i: usize) -> (Vector3, f32) {
+        self.bounds[i].unwrap_or_else(|| self.objects[i].bounding_sphere())
+    }
+
+    /// Computes again the bounding sphere of the object at index `i` after it moved, and moves it
+    /// in the spatial grid
+    fn update_bounds(&mut self, i: usize) {
+        let (center, radius) = self.objects[i].bounding_sphere();
+        self.bounds[i] = Some((center, radius));
+        self.grid.insert(i, center, radius);
+    }
+
+    /// Moves the objects modified through `get_mut` in the spatial grid (the other objects are
+    /// moved as soon as they move)
+    fn update_grid(&mut self) {
+        for i in 0..self.objects.len() {
+            if self.bounds[i].is_none() {
+                self.update_bounds(i);
+            }
+        }
+    }
+
+    /// Returns the objects colliding with the sphere
+    pub fn objects_near(&self, position: &Vector3, radius: f32) -> Vec<ObjectId> {
+        self.grid
+            .query(*position, radius)
+            .into_iter()
+            .filter(|i| self.is_solid(*i) && self.objects[*i].collides_with(position, radius))
+            .map(|i| self.ids[i])
+            .collect()
+    }
+
+    /// Returns true if a solid object is hit by the ray before `max_distance`, except the object
+    /// at index `ignored`
+    fn is_blocked(&self, ray: &Ray, max_distance: f32, ignored: Option<usize>) -> bool {
+        self.first_hit(ray, max_distance, |i| self.is_solid(i) && Some(i) != ignored)
+            .is_some_and(|(_, _, hit, _)| hit.distance < max_distance)
+    }
+
+    /// Returns the first face hit by the ray, if it is at most `max_distance` away, among the
+    /// objects whose index is accepted by `filter`: (index of the object, face, hit, projection of
+    /// the hit on the face). Only the objects whose bounding sphere crosses the ray are intersected.
+    fn first_hit(
+        &self,
+        ray: &Ray,
+        max_distance: f32,
+        filter: impl Fn(usize) -> bool,
+    ) -> Option<(usize, &CubicFace3, Hit, ProjectionCoordinates)> {
+        (0..self.objects.len())
+            .filter(|i| filter(*i) && self.may_be_hit(*i, ray, max_distance))
+            .flat_map(|i| self.objects[i].get_all_faces().into_iter().map(move |face| (i, face)))
+            .filter_map(|(i, face)| {
+                let (_, projection) = face.line_projection(&ray.origin, &ray.direction)?;
+                if !face.contains_projection(&projection) {
+                    return None;
+                }
+                let point = face.point_at(&projection);
+                Some((i, face, Hit { distance: ray.origin.line_to(&point).norm(), point }, projection))
+            })
+            .filter(|(_, _, hit, _)| hit.distance <= max_distance)
+            .min_by(|(_, _, h1, _), (_, _, h2, _)| h1.distance.total_cmp(&h2.distance))
+    }
+
+    /// Returns true if the bounding sphere of the object at index `i` crosses the ray before
+    /// `max_distance`, so that only these objects are intersected
+    fn may_be_hit(&self, i: usize, ray: &Ray, max_distance: f32) -> bool {
+        let (center, radius) = self.bounds(i);
+        center.line_to(&ray.origin).norm() <= radius || Sphere::new(center, radius).hits_before(ray, max_distance)
+    }
+
+    /// Returns the first face hit by the ray going from `origin` towards `direction`, if it is at
+    /// most `max_distance` away. As for `pick`, the objects of the layers which are not seen by the
+    /// camera are ignored.
+    pub fn raycast(&self, origin: Vector3, direction: Vector3, max_distance: f32) -> Option<RaycastHit<'_>> {
+        let (i, face, hit, projection) = self.first_hit(&Ray::new(origin, direction), max_distance, |i| self.is_layer_visible(i))?;
+        Some(RaycastHit { object: self.ids[i], face, distance: hit.distance, point: hit.point, uv: face.uv_at(&projection) })
+    }
+
+    /// Returns true if no solid object is between the two points, for instance to know whether an
+    /// entity can see the player. The points can be on the surface of an object.
+    pub fn has_line_of_sight(&self, a: &Vector3, b: &Vector3) -> bool {
+        let distance = a.line_to(b).norm();
+        distance <= LINE_OF_SIGHT_MARGIN || !self.is_blocked(&Ray::between(*a, *b), distance - LINE_OF_SIGHT_MARGIN, None)
+    }
+
+    /// Returns the objects among `targets` which can be seen from the point: the line towards
+    /// their center reaches them before any other solid object.
+    pub fn visible_from(&self, point: &Vector3, targets: &[ObjectId]) -> Vec<ObjectId> {
+        targets
+            .iter()
+            .filter(|id| {
+                let Some(i) = self.index_of(**id) else {
+                    return false;
+                };
+                let (center, _) = self.bounds(i);
+                let ray = Ray::between(*point, center);
+                let distance = point.line_to(&center).norm();
+                let reached = self.first_hit(&ray, distance, |j| j == i).map_or(distance, |(_, _, hit, _)| hit.distance);
+                !self.is_blocked(&ray, reached - LINE_OF_SIGHT_MARGIN, Some(i))
+            })
+            .copied()
+            .collect()
     }
 
-    pub fn add_face(&mut self, face: CubicFace3) {
-        self.objects.push(Box::new(face));
+    /// Returns the closest object hit by the ray, if it is at most `max_distance` away. The
+    /// objects of the layers which are not seen by the camera can not be picked.
+    pub fn pick(&self, ray: &Ray, max_distance: f32) -> Option<ObjectId> {
+        self.first_hit(ray, max_distance, |i| self.is_layer_visible(i)).map(|(i, _, _, _)| self.ids[i])
     }
 
     pub fn set_camera_position(&mut self, position: Vector3) {
-        self.camera.set_position(position);
+        self.place_camera(position);
     }
 
     pub fn set_camera_rotation(&mut self, rot: f32) {
         self.camera.set_rotation(rot);
     }
 
+    /// Moves the camera to the pose, updating the objects within the draw distance
+    pub fn set_camera_pose(&mut self, pose: Pose) {
+        self.place_camera(*pose.position());
+        self.camera.set_pose(pose);
+        self.update_in_range();
+    }
+
+    /// Moves the camera to the position right away, without interpolating from where it was
+    fn place_camera(&mut self, position: Vector3) {
+        self.camera.set_position(position);
+        self.previous_position = position;
+        self.simulated_position = None;
+    }
+
+    /// Puts the camera back where the simulation left it, if it was interpolated
+    fn restore_simulated_position(&mut self) {
+        if let Some(position) = self.simulated_position.take() {
+            self.camera.set_position(position);
+        }
+    }
+
+    /// The simulation advances by fixed steps, which are not in sync with the frames: to move
+    /// smoothly, the camera is drawn between its positions before and after the last step.
+    /// `alpha` is the fraction of the step elapsed since then, from 0 (previous position) to 1.
+    pub fn interpolate_camera(&mut self, alpha: f32) {
+        self.restore_simulated_position();
+        let simulated = *self.camera.pose().position();
+        self.camera.set_position(self.previous_position + self.previous_position.line_to(&simulated) * alpha);
+        self.simulated_position = Some(simulated);
+    }
+
+    pub fn set_camera_fov(&mut self, fov: f32) {
+        self.camera.set_fov(fov);
+        if let Some(spectator) = &mut self.spectator {
+            spectator.set_fov(fov);
+        }
+    }
+
+    /// Sets the keys triggering the actions of the world
+    pub fn set_input_map(&mut self, input_map: InputMap) {
+        self.input_map = input_map;
+    }
+
+    pub fn set_turn_step(&mut self, turn_step: f32) {
+        self.turn_step = turn_step;
+    }
+
+    pub fn set_mouse_sensitivity(&mut self, sensitivity: f32) {
+        self.mouse_sensitivity = sensitivity;
+    }
+
     pub fn camera(&self) -> &Camera {
         &self.camera
     }
 
-    /// Computes the Binary Space Partitioning  using the current objects.
+    /// Detaches the view from the player (or attaches it back). The spectator camera starts where
+    /// the player is, then flies freely with the movement keys while the simulation continues.
+    pub fn set_spectating(&mut self, spectating: bool) {
+        self.spectator = spectating.then(|| self.camera.clone());
+        self.update_player_frustum();
+    }
+
+    /// While spectating, the frustum of the player's camera is shown as a debug path, up to the
+    /// draw distance
+    fn update_player_frustum(&mut self) {
+        if !self.is_spectating() {
+            self.debug_paths.remove(PLAYER_FRUSTUM);
+            return;
+        }
+        let depth = self.draw_distance.map_or(PLAYER_FRUSTUM_DEPTH, |distance| distance.outer());
+        self.debug_paths.set_frustum(PLAYER_FRUSTUM, &self.camera, depth);
+    }
+
+    pub fn is_spectating(&self) -> bool {
+        self.spectator.is_some()
+    }
+
+    /// Camera from which the world is drawn: the spectator camera, or else the player's one
+    pub fn view_camera(&self) -> &Camera {
+        self.spectator.as_ref().unwrap_or(&self.camera)
+    }
+
+    /// Adds a camera looking at the world, and returns its index in `cameras`
+    pub fn add_camera(&mut self, camera: Camera) -> usize {
+        self.cameras.push(camera);
+        self.cameras.len() - 1
+    }
+
+    /// The cameras added with `add_camera`
+    pub fn cameras(&self) -> &[Camera] {
+        &self.cameras
+    }
+
+    pub fn cameras_mut(&mut self) -> &mut [Camera] {
+        &mut self.cameras
+    }
+
+    /// Looks at the world through another camera, and returns the previous one
+    pub fn replace_camera(&mut self, camera: Camera) -> Camera {
+        self.restore_simulated_position();
+        let previous = std::mem::replace(&mut self.camera, camera);
+        self.previous_position = *self.camera.pose().position();
+        self.update_in_range();
+        previous
+    }
+
+    /// In walking mode, the gravity pulls the camera towards the ground, on which it can jump
+    pub fn set_walking_mode(&mut self, walking: bool) {
+        self.walking = walking;
+        self.motion_model.stop();
+    }
+
+    /// Returns true if the camera is standing on the ground (which only happens when walking)
+    pub fn is_on_ground(&self) -> bool {
+        self.walking
+            && self
+                .distance_to_ground(self.camera.pose().position())
+                .is_some_and(|distance| distance <= EYE_HEIGHT + GROUND_TOLERANCE)
+    }
+
+    pub fn set_spawn_point(&mut self, position: Vector3, rotation: f32) {
+        self.spawn_point = Some(Pose::new(position, rotation));
+    }
+
+    pub fn spawn_point(&self) -> Option<&Pose> {
+        self.spawn_point.as_ref()
+    }
+
+    /// Moves the camera to the spawn point (if there is one), making sure that the camera does
+    /// not end up inside an object.
+    pub fn respawn(&mut self) -> Option<Vector3> {
+        let spawn = self.spawn_point.clone()?;
+        self.camera.set_rotation(spawn.rotation_z());
+        self.camera.level();
+        self.teleport_camera(*spawn.position())
+    }
+
+    /// Moves the camera as close as possible to the requested position, without colliding with
+    /// any object. In walking mode, the camera is then placed on the ground below this position.
+    ///
+    /// Returns the new position of the camera, or None if no free spot was found around the
+    /// target (in which case the camera is not moved).
+    pub fn teleport_camera(&mut self, position: Vector3) -> Option<Vector3> {
+        let mut target = self.find_free_spot(position)?;
+        if self.walking {
+            if let Some(ground) = self.distance_to_ground(&target) {
+                target += UNIT_Z * (ground - EYE_HEIGHT);
+            }
+        }
+        self.place_camera(target);
+        Some(target)
+    }
+
+    /// Returns true if the camera could stand at this position without colliding with an object
+    pub fn is_free(&self, position: &Vector3) -> bool {
+        self.objects_near(position, CAMERA_RADIUS).is_empty()
+    }
+
+    /// Returns the shortest path (found with A*) for the camera to go from a position to another
+    /// one at the same height without colliding with any object, or None if there is no such path.
+    pub fn find_path(&self, from: Vector3, to: Vector3) -> Option<Vec<Vector3>> {
+        pathfinding::find_path(from, to, PATH_STEP, PATH_MAX_CELLS, |position| self.is_free(position))
+    }
+
+    /// Returns the closest free position around the provided one, searching on a grid of
+    /// increasingly larger shells.
+    fn find_free_spot(&self, position: Vector3) -> Option<Vector3> {
+        if self.is_free(&position) {
+            return Some(position);
+        }
+        let mut best: Option<(f32, Vector3)> = None;
+        for r in 1..=FREE_SPOT_MAX_STEPS {
+            // The next shells cannot contain a position closer than the best one
+            if best.is_some_and(|(d, _)| r as f32 * FREE_SPOT_STEP >= d) {
+                break;
+            }
+            for i in -r..=r {
+                for j in -r..=r {
+                    for k in -r..=r {
+                        // Only visit the shell of the cube of radius r
+                        if i.abs() != r && j.abs() != r && k.abs() != r {
+                            continue;
+                        }
+                        let offset = Vector3::newi(i, j, k) * FREE_SPOT_STEP;
+                        let candidate = position + offset;
+                        let distance = offset.norm();
+                        if best.is_none_or(|(d, _)| distance < d) && self.is_free(&candidate) {
+                            best = Some((distance, candidate));
+                        }
+                    }
+                }
+            }
+        }
+        best.map(|(_, candidate)| candidate)
+    }
+
+    /// Returns the distance between the position and the first face found below it.
+    fn distance_to_ground(&self, position: &Vector3) -> Option<f32> {
+        self.ground_below(position).map(|(distance, _)| distance)
+    }
+
+    /// Returns the first horizontal face hit by the ray going down from the position, with its
+    /// distance. Only the objects found below it in the spatial grid are intersected.
+    fn ground_below(&self, position: &Vector3) -> Option<(f32, &CubicFace3)> {
+        let ray = Ray::new(*position, UNIT_Z);
+        self.grid
+            .query_below(*position)
+            .into_iter()
+            .filter(|i| self.is_solid(*i))
+            .flat_map(|i| self.objects[i].get_all_faces())
+            .filter(|f| f.normal().z().abs() >= MIN_GROUND_VERTICALITY)
+            .filter_map(|f| f.intersect(&ray).map(|hit| (hit.distance, f)))
+            .min_by(|(d1, _), (d2, _)| d1.total_cmp(d2))
+    }
+
+    /// Events emitted by the motion of the camera during the last update
+    pub fn movement_events(&self) -> &[MovementEvent] {
+        &self.movement_events
+    }
+
+    /// Computes the Binary Space Partitioning  using the current objects of the visible layers.
     /// This function will be removed when BSP is validated.
     pub fn compute_bsp(&mut self) {
         let mut faces = Vec::new();
-        for o in &self.objects {
+        for (i, o) in self.objects.iter().enumerate() {
+            if !self.is_layer_visible(i) {
+                continue;
+            }
             for face in o.get_all_faces() {
                 faces.push(face.clone());
             }
         }
-        self.bsp = Some(binary_space_partionning(&faces))
+        let tree = binary_space_partionning(&faces);
+        self.bsp_built_nodes = tree.stats().nodes;
+        self.bsp_edits = 0;
+        self.bsp_stale = false;
+        self.bsp_merged = false;
+        self.bsp = Some(tree);
     }
 
+    /// Removes the BSP: the painter algorithm goes back to sorting the faces by depth.
+    pub fn clear_bsp(&mut self) {
+        self.bsp = None;
+        self.bsp_stale = false;
+    }
 
+    /// Builds the BSP again when some faces could not be removed from it, or when the objects moved
+    /// in it made it much larger than a tree built from scratch (their faces split the faces of the
+    /// leaves they cross). The size is only checked after a number of edits proportional to it.
+    fn maintain_bsp(&mut self) {
+        let Some(tree) = &self.bsp else {
+            return;
+        };
+        if !self.bsp_stale {
+            if self.bsp_edits < (self.bsp_built_nodes / 4).max(BSP_MIN_EDITS) {
+                return;
+            }
+            self.bsp_edits = 0;
+            if tree.stats().nodes <= BSP_MAX_GROWTH * self.bsp_built_nodes.max(1) {
+                return;
+            }
+        }
+        let merged = self.bsp_merged;
+        self.compute_bsp();
+        if merged {
+            self.merge_bsp_faces();
+        }
+    }
 
-    /// Debug function
-    pub fn save_current_image(&self) {
-        // TODO: look this up
-        // https://stackoverflow.com/a/38956995/13219173
+    /// Adds the faces of the object at index `i` to the BSP, if it was computed, without building
+    /// it again
+    fn insert_in_bsp(&mut self, i: usize) {
+        if !self.is_layer_visible(i) {
+            return;
+        }
+        if let Some(tree) = &mut self.bsp {
+            let options = BspBuildOptions::default();
+            for face in self.objects[i].get_all_faces() {
+                tree.insert(face.clone(), &options);
+                self.bsp_edits += 1;
+            }
+        }
     }
 
-    pub fn bsp(&self) -> &Option<BSPNode> {
-        &self.bsp
+    /// Removes the faces of the object at index `i` from the BSP, if it was computed. The BSP is
+    /// built again when some of the faces are not found in the tree.
+    fn remove_from_bsp(&mut self, i: usize) {
+        if self.bsp.is_some() && self.is_layer_visible(i) {
+            let faces: Vec<CubicFace3> = self.objects[i].get_all_faces().into_iter().cloned().collect();
+            self.remove_faces_from_bsp(&faces);
+        }
     }
-}
 
-impl Drawable for World {
-    fn draw_painter(&self, drawer: &mut dyn AbstractFrame) {
-        if let Some(tree) = &self.bsp {
-            // The tree is in charge of visiting itself and drawing in the proper order.
-            tree.painter_algorithm_traversal(&self.camera, drawer);
-        } else {
-            // Find the faces that are visible to the camera's perspective
-            let mut faces2: Vec<CubicFace2> = Vec::new();
-            for object in &self.objects {
-                let faces = object.get_visible_faces(&self.camera);
-                for face in faces {
-                    let face2d = face.projection(&self.camera);
-                    faces2.push(face2d);
+    /// Removes the faces from the BSP, if it was computed. The BSP is built again (by
+    /// `maintain_bsp`) when some of the faces are not found in the tree.
+    fn remove_faces_from_bsp(&mut self, faces: &[CubicFace3]) {
+        let Some(tree) = &mut self.bsp else {
+            return;
+        };
+        let epsilon = tunables().bsp_epsilon;
+        let removed = faces.iter().all(|face| {
+            let area = face.area();
+            (tree.remove(face, epsilon) - area).abs() <= BSP_AREA_TOLERANCE * area
+        });
+        self.bsp_edits += faces.len();
+        self.bsp_stale |= !removed;
+    }
+
+    /// Updates the behavior of the objects, and moves their faces in the BSP
+    fn tick_objects(&mut self, dt: f32) {
+        for i in 0..self.objects.len() {
+            if self.objects[i].as_updatable().is_none() {
+                continue;
+            }
+            let previous: Vec<CubicFace3> = match self.bsp.is_some() && self.is_layer_visible(i) {
+                true => self.objects[i].get_all_faces().into_iter().cloned().collect(),
+                false => Vec::new(),
+            };
+            let query = WorldQuery::new(&self.camera, &self.ids, &self.grid);
+            if let Some(behavior) = self.objects[i].as_updatable() {
+                behavior.tick(dt, &query);
+            }
+            self.scene_graph.forget_rest(self.ids[i]);
+            self.update_bounds(i);
+            if !previous.is_empty() {
+                self.remove_faces_from_bsp(&previous);
+                self.insert_in_bsp(i);
+            }
+        }
+        self.maintain_bsp();
+    }
+
+    /// Runs the entity layer, which moves the objects of the entities
+    fn update_entities(&mut self, dt: f32) {
+        let mut entities = std::mem::take(&mut self.entities);
+        entities.update(self, dt);
+        self.entities = entities;
+    }
+
+    /// What the objects and the systems can see of the world while they are updated
+    pub(crate) fn query(&self) -> WorldQuery<'_> {
+        WorldQuery::new(&self.camera, &self.ids, &self.grid)
+    }
+
+    /// Turns the billboards towards the camera from which the world is drawn, and moves their faces
+    /// in the BSP
+    fn face_billboards(&mut self) {
+        let position = *self.view_camera().pose().position();
+        for i in 0..self.objects.len() {
+            if self.objects[i].kind() != ObjectKind::Billboard {
+                continue;
+            }
+            let previous: Vec<CubicFace3> = self.objects[i].get_all_faces().into_iter().cloned().collect();
+            if !self.objects[i].face_camera(&position) {
+                continue;
+            }
+            self.update_bounds(i);
+            if self.is_layer_visible(i) {
+                self.remove_faces_from_bsp(&previous);
+                self.insert_in_bsp(i);
+            }
+        }
+        self.maintain_bsp();
+    }
+
+    /// Merges the adjacent coplanar faces of the BSP (if it was computed) into larger faces.
+    pub fn merge_bsp_faces(&mut self) {
+        if let Some(tree) = &mut self.bsp {
+            tree.merge_coplanar_faces(tunables().bsp_epsilon);
+            self.bsp_merged = true;
+        }
+    }
+
+    /// Selects whether the painter algorithm uses the BSP or sorts the faces by depth.
+    /// The BSP is computed the first time it is needed.
+    pub fn set_use_bsp(&mut self, use_bsp: bool) {
+        if use_bsp && self.bsp.is_none() {
+            self.compute_bsp();
+            self.merge_bsp_faces();
+        }
+        self.use_bsp = use_bsp;
+    }
+
+    /// Returns the BSP, if the painter algorithm should use it
+    fn active_bsp(&self) -> Option<&BSPNode> {
+        self.bsp.as_ref().filter(|_| self.use_bsp)
+    }
+
+
+
+    /// Returns the 2D projection of all the faces visible from the camera
+    fn projected_faces(&self) -> Vec<CubicFace2<'_>> {
+        self.project(self.visible_faces())
+    }
+
+    /// Returns the faces of the objects in range which are seen by the player's camera
+    fn visible_faces(&self) -> Vec<&CubicFace3> {
+        (0..self.objects.len())
+            .filter(|i| self.in_range[*i] && self.is_layer_visible(*i))
+            .flat_map(|i| self.objects[i].get_visible_faces(&self.camera))
+            .collect()
+    }
+
+    /// Performs a 2d projection of each face on the frame of the camera
+    fn project<'a>(&'a self, faces: Vec<&'a CubicFace3>) -> Vec<CubicFace2<'a>> {
+        self.project_with(self.view_camera(), faces)
+    }
+
+    /// Performs a 2d projection of each face on the frame of the given camera
+    fn project_with<'a>(&'a self, camera: &'a Camera, faces: Vec<&'a CubicFace3>) -> Vec<CubicFace2<'a>> {
+        faces
+            .into_iter()
+            .map(|face| {
+                let face2d = face.projection(camera);
+                let face2d = match &self.lighting {
+                    Some(lighting) => face2d.with_lighting(lighting),
+                    None => face2d,
+                };
+                match &self.fog {
+                    Some(fog) => face2d.with_fog(fog),
+                    None => face2d,
                 }
+            })
+            .collect()
+    }
+
+    /// Draws the faces of the BSP, seen through the fog of the world
+    fn draw_bsp(&self, tree: &BSPNode, drawer: &mut dyn AbstractFrame) {
+        match &self.fog {
+            Some(fog) => self.draw_bsp_lit(tree, &mut FoggedFrame::new(drawer, fog)),
+            None => self.draw_bsp_lit(tree, drawer),
+        }
+    }
+
+    /// Draws the faces of the BSP, lit by the lights of the world
+    fn draw_bsp_lit(&self, tree: &BSPNode, drawer: &mut dyn AbstractFrame) {
+        match &self.lighting {
+            Some(lighting) => self.draw_bsp_unlit(tree, &mut LitFrame::new(drawer, lighting)),
+            None => self.draw_bsp_unlit(tree, drawer),
+        }
+    }
+
+    /// Draws the faces of the BSP, except those beyond the draw distance. The faces of the tree
+    /// are not attached to the objects anymore, so they are culled individually at the outer
+    /// boundary of the draw distance.
+    fn draw_bsp_unlit(&self, tree: &BSPNode, drawer: &mut dyn AbstractFrame) {
+        match self.draw_distance {
+            Some(distance) => {
+                let mut culled = DistanceCulledFrame::new(drawer, &self.camera, distance.outer());
+                tree.painter_algorithm_traversal(self.view_camera(), &mut culled);
             }
+            None => tree.painter_algorithm_traversal(self.view_camera(), drawer),
+        }
+    }
+
+    /// Sort the faces by depth, from the closest polygon to the farthest polygon
+    fn sort_by_depth(faces2: &mut [CubicFace2], camera: &Camera) {
+        // The sorting iis done over i32, because f32 does not implements Ord.
+        let scale = tunables().distance_scale;
+        faces2.sort_by_key(|f| (f.distance_to(camera) * scale) as i32);
+    }
 
-            // Sort the faces by depth, from the farthest polygon to the closest polygon
-            // The sorting iis done over i32, because f32 does not implements Ord.
-            faces2.sort_by_key(|f| (f.distance_to(&self.camera) * 1000.) as i32);
+    /// Draws the faces sorted by depth, even if the painter algorithm uses the BSP
+    pub fn draw_painter_sorted(&self, drawer: &mut dyn AbstractFrame) {
+        // Find the faces that are visible to the camera's perspective
+        let mut faces2 = self.projected_faces();
+        Self::sort_by_depth(&mut faces2, self.view_camera());
+
+        // Paint the pixels, starting from the most distant ones
+        faces2.iter().rev().for_each(|f| drawer.draw_one_face(f));
+    }
 
-            // Paint the pixels, starting from the most distant ones
-            faces2.iter().rev().for_each(|f| drawer.draw_one_face(f));
+    /// Draws the world seen by a camera (for instance one of `cameras`) in a region of the frame,
+    /// so that several views can be composited into the same frame (split screen, minimap...).
+    /// The camera is given the size of the region, and the faces are sorted by depth (the BSP is
+    /// only used for the view camera).
+    pub fn draw_painter_with(&self, camera: &Camera, region: Rect, frame: &mut Frame) {
+        let mut camera = camera.clone();
+        camera.set_viewport(region.viewport());
+        let mut pixels = vec![0; region.viewport().buffer_len()];
+        self.sky.draw(&camera, &mut pixels);
+        let faces = (0..self.objects.len())
+            .filter(|i| self.is_in_range_of(*i, &camera) && self.layers[*i].intersects(camera.visible_layers()))
+            .flat_map(|i| self.objects[i].get_visible_faces(&camera))
+            .collect();
+        let mut faces2 = self.project_with(&camera, faces);
+        Self::sort_by_depth(&mut faces2, &camera);
+        let mut region_frame = Frame::new(&mut pixels, region.viewport());
+        faces2.iter().rev().for_each(|f| region_frame.draw_one_face(f));
+        frame.blit(region, &pixels);
+    }
+
+    /// Same as `draw_painter`, but the duration of each stage of the rendering is recorded.
+    pub fn draw_painter_profiled(&self, drawer: &mut dyn AbstractFrame, profiler: &mut FrameProfiler) {
+        if let Some(tree) = self.active_bsp() {
+            // The traversal both culls and orders the faces: only the drawing is measured apart
+            let start = Instant::now();
+            let mut timed_drawer = TimedFrame::new(drawer);
+            self.draw_bsp(tree, &mut timed_drawer);
+            let raster = timed_drawer.elapsed();
+            profiler.record(Stage::Raster, raster);
+            profiler.record(Stage::Bsp, start.elapsed().saturating_sub(raster));
+        } else {
+            let faces = profiler.time(Stage::Visibility, || self.visible_faces());
+            let mut faces2 = profiler.time(Stage::Projection, || self.project(faces));
+            profiler.time(Stage::Sort, || Self::sort_by_depth(&mut faces2, self.view_camera()));
+            profiler.time(Stage::Raster, || faces2.iter().rev().for_each(|f| drawer.draw_one_face(f)));
         }
+    }
 
+    /// Returns a function giving the color of a pixel of the frame, found by raytracing. The visible
+    /// faces are projected once, when creating the function, and bucketed into tiles of the screen:
+    /// the ray of a pixel is only traced through the faces of its tile. The shapes added with `add_raytrace_object` are
+    /// intersected one after the other.
+    pub fn raytracing_sampler(&self) -> impl Fn(i16, i16) -> [u8; 4] + Sync + '_ {
+        let sampler = self.subpixel_raytracing_sampler();
+        move |x, y| sampler(x as f32, y as f32)
     }
 
-    fn draw_raytracing(&self, frame: &mut [u8]) {
+    /// Same as `raytracing_sampler`, but the ray can go through any point of the pixels
+    pub fn subpixel_raytracing_sampler(&self) -> impl Fn(f32, f32) -> [u8; 4] + Sync + '_ {
         // Find the faces that are visible to the camera's perspective
-        let mut faces2: Vec<CubicFace2> = Vec::new();
-        for object in &self.objects {
-            // Get the visible 3d faces
-            let faces = object.get_visible_faces(&self.camera);
-            // For each face, perform a 2d projection on the camera frame
-            for face in faces {
-                let face2d = face.projection(&self.camera);
-                faces2.push(face2d);
-            }
-        }
-
-        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
-            let x = (i % WIDTH as usize) as i16;
-            let y = (i / WIDTH as usize) as i16;
-
-            // For each pixel, find
-            // * the closest face
-            // * the coordinate (in the frame's reference) of the raytracing intersection
-            let mut min_distance = u32::MAX;
-            let mut best_projection: Option<ProjectionCoordinates> = None;
-            let mut best_face: Option<&CubicFace2> = None;
-            for f2 in &faces2 {
-                if f2.contains(&Point2::new(x as f32, y as f32)) {
-                    if let Some(projection) = f2.raytracing(x, y) {
-                        if projection.0 < min_distance {
-                            min_distance = projection.0;
-                            best_face = Some(f2);
-                            best_projection = Some(projection.1)
+        let position = *self.view_camera().pose().position();
+        let tiles = ScreenTiles::new(self.projected_faces(), self.viewport(), &position);
+        let (origin, du, dv) = self.view_camera().ray_direction_derivatives();
+        let sky = self.sky.as_ref();
+        let shapes = &self.raytrace_objects;
+        let (lighting, fog) = (self.lighting.as_ref(), self.fog.as_ref());
+        // The shapes are lit and seen through the fog as the faces are
+        let shade = move |shape: &dyn RaytraceObject, hit: &Hit| {
+            let color = shape.color_at(&hit.point);
+            let color = match lighting {
+                Some(lighting) => lighting.shade(&color, &hit.point, &shape.normal_at(&hit.point)),
+                None => color,
+            };
+            match fog {
+                Some(fog) => fog.apply(&color, hit.distance),
+                None => color,
+            }
+        };
+
+        move |x, y| {
+            let direction = origin + du * x + dv * y;
+            let ray = Ray::new(position, direction);
+            let face = tiles.closest_hit(&ray, x.round() as u32, y.round() as u32);
+            // The closest shape, if it is in front of the face
+            let shape = shapes
+                .iter()
+                .filter_map(|shape| shape.intersect(&ray).map(|hit| (shape, hit)))
+                .filter(|(_, hit)| face.as_ref().is_none_or(|face| hit.distance < face.distance))
+                .min_by(|(_, h1), (_, h2)| h1.distance.total_cmp(&h2.distance));
+            match (shape, face) {
+                (Some((shape, hit)), _) => shade(shape.as_ref(), &hit).rgba(),
+                (None, Some(hit)) => tiles.face(hit.index).color_at_projection(&hit.projection).rgba(),
+                (None, None) => sky.color(&direction),
+            }
+        }
+    }
+
+    /// Draws the faces in any order, keeping for each pixel the closest one. The depth buffer holds
+    /// one distance per pixel, and must be filled with `u32::MAX` before drawing a new frame.
+    pub fn draw_depth_buffered(&self, frame: &mut [u8], depth: &mut [u32]) {
+        for face in self.projected_faces() {
+            face.draw_depth_tested(frame, depth);
+        }
+    }
+
+    /// Fills the depth buffer as `draw_depth_buffered`, without drawing the faces, for instance to
+    /// hide what is drawn on top of the world behind its faces
+    pub fn draw_depth(&self, depth: &mut [u32]) {
+        for face in self.projected_faces() {
+            face.draw_depth_only(depth);
+        }
+    }
+
+    /// Sets the lights of the world, used by all the renderers of the faces
+    pub fn set_lighting(&mut self, lighting: Lighting) {
+        self.lighting = Some(lighting);
+    }
+
+    /// Sets the fog of the world (or removes it), used by all the renderers of the faces
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        self.fog = fog;
+    }
+
+    /// Sets the shape rendered by `draw_raymarching`
+    pub fn set_sdf(&mut self, shape: Box<dyn SignedDistance>) {
+        self.sdf = Some(shape);
+    }
+
+    /// Adds a shape drawn by the raytracer (and ignored by the other renderers)
+    pub fn add_raytrace_object(&mut self, object: Box<dyn RaytraceObject>) {
+        self.raytrace_objects.push(object);
+    }
+
+    /// Experimental renderer: ray marching of the signed distance field of the world.
+    /// Only the shapes provided with `set_sdf` are rendered (and not the faces of the objects).
+    pub fn draw_raymarching(&self, frame: &mut [u8]) {
+        if let Some(shape) = &self.sdf {
+            draw_raymarching(shape.as_ref(), self.view_camera(), frame);
+        }
+    }
+
+    pub fn weather(&self) -> &Weather {
+        &self.weather
+    }
+
+    pub fn weather_mut(&mut self) -> &mut Weather {
+        &mut self.weather
+    }
+
+    /// Size of the frames drawn by the renderers
+    pub fn viewport(&self) -> Viewport {
+        self.camera.viewport()
+    }
+
+    /// Changes the size of the frames, for instance when the window is resized
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.camera.set_viewport(viewport);
+        if let Some(spectator) = &mut self.spectator {
+            spectator.set_viewport(viewport);
+        }
+    }
+
+    pub fn set_sky(&mut self, sky: Box<dyn Sky>) {
+        self.sky = sky;
+    }
+
+    /// Fills the frame with the sky, before drawing the objects
+    pub fn draw_sky(&self, frame: &mut [u8]) {
+        self.sky.draw(self.view_camera(), frame);
+    }
+
+    /// Draws the weather particles on top of the frame, which must already contain the world
+    /// Draws the weather on top of the frame, hidden by the faces of the depth buffer (see
+    /// `draw_depth`)
+    pub fn draw_weather(&self, frame: &mut Frame, depth: &[u32]) {
+        self.weather.draw(frame, self.view_camera(), depth);
+    }
+
+    pub fn debug_paths(&self) -> &DebugPaths {
+        &self.debug_paths
+    }
+
+    pub fn debug_paths_mut(&mut self) -> &mut DebugPaths {
+        &mut self.debug_paths
+    }
+
+    /// Draws the visible debug paths on top of the frame, with the colors of the palette
+    pub fn draw_debug_paths(&self, frame: &mut Frame, palette: DebugPalette) {
+        self.debug_paths.draw(frame, self.view_camera(), palette);
+    }
+
+    /// Sets the speed of the simulation: 1 is real time, 0.25 is slow motion, 0 pauses it.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.time_scale == 0.
+    }
+
+    /// While the world is paused, the next update will advance the simulation by exactly one
+    /// fixed time step.
+    pub fn request_single_step(&mut self) {
+        self.single_step_requested = true;
+    }
+
+    /// Seeds the random generator. Since the simulation only advances by the time steps given to
+    /// `update`, the world then produces the exact same frames at each run given the same inputs.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Random generator to use for anything random in the simulation
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Returns a structured description of the world: objects with their faces, textures,
+    /// camera and BSP statistics. This is helpful to attach a reproducible scene to a bug report.
+    ///
+    /// Textures are not serializable: they are identified by an id, shared by all the faces
+    /// using the same texture.
+    pub fn describe(&self) -> Value {
+        let mut textures: Vec<TextureHandle> = Vec::new();
+        let mut texture_id = |texture: &TextureHandle| -> usize {
+            if let Some(i) = textures.iter().position(|t| t.same_as(texture)) {
+                i
+            } else {
+                textures.push(texture.clone());
+                textures.len() - 1
+            }
+        };
+        let vec = |v: &Vector3| json!([v.x(), v.y(), v.z()]);
+
+        let mut texture_sizes = Vec::new();
+        let objects: Vec<Value> = self
+            .objects
+            .iter()
+            .map(|object| {
+                let faces: Vec<Value> = object
+                    .get_all_faces()
+                    .iter()
+                    .map(|face| {
+                        let id = texture_id(face.texture());
+                        if id == texture_sizes.len() {
+                            texture_sizes.push(json!({
+                                "id": id,
+                                "width": face.texture().width(),
+                                "height": face.texture().height(),
+                            }));
                         }
-                    }
+                        json!({
+                            "points": face.points().iter().map(vec).collect::<Vec<Value>>(),
+                            "uvs": face.uvs(),
+                            "normal": vec(face.normal()),
+                            "texture": id,
+                        })
+                    })
+                    .collect();
+                json!({ "faces": faces })
+            })
+            .collect();
+
+        let (px, py) = self.camera.optical_center();
+        let bsp = self.bsp.as_ref().map(|tree| {
+            let stats = tree.stats();
+            json!({
+                "nodes": stats.nodes,
+                "leaves": stats.leaves,
+                "convex_leaves": stats.convex_leaves,
+                "faces": stats.faces,
+                "depth": stats.depth,
+            })
+        });
+
+        json!({
+            "camera": {
+                "position": vec(self.camera.pose().position()),
+                "rotation_z": self.camera.pose().rotation_z(),
+                "pitch": self.camera.pose().pitch(),
+                "roll": self.camera.pose().roll(),
+                "focal": self.camera.focal(),
+                "optical_center": [px, py],
+            },
+            "objects": objects,
+            "textures": texture_sizes,
+            "bsp": bsp,
+        })
+    }
+
+    /// Returns a hash of the geometry of the objects seen by the camera, of their textures (by
+    /// their registered name, or else by their colors) and labels, of the raytraced shapes, of the
+    /// fog and of the camera from which the world is drawn. The lighting, the sky and the render
+    /// settings are not hashed: two worlds with the same hash hold the same faces, seen from the
+    /// same point of view.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+        // Each texture is only hashed once
+        let mut textures: Vec<(&TextureHandle, u64)> = Vec::new();
+        for (i, object) in self.objects.iter().enumerate() {
+            if !self.is_layer_visible(i) {
+                continue;
+            }
+            let faces = object.get_all_faces();
+            hasher.write_usize(faces.len());
+            for face in faces {
+                for point in face.points() {
+                    hasher.write_vector(&point);
+                }
+                for (u, v) in face.uvs() {
+                    hasher.write_f32(u);
+                    hasher.write_f32(v);
                 }
+                hasher.write_vector(face.normal());
+                let texture = match textures.iter().find(|(t, _)| t.same_as(face.texture())) {
+                    Some((_, hash)) => *hash,
+                    None => {
+                        let hash = self.texture_hash(face.texture());
+                        textures.push((face.texture(), hash));
+                        hash
+                    }
+                };
+                hasher.write_u64(texture);
+            }
+            if let Some(label) = self.labels.get(&self.ids[i]) {
+                hasher.write(label.text.as_bytes());
+                hasher.write(&label.color);
             }
+        }
+        hasher.write_usize(self.raytrace_objects.len());
+        for object in &self.raytrace_objects {
+            object.write_hash(&mut hasher);
+        }
+        hasher.write_u8(self.sdf.is_some() as u8);
+        if let Some(sdf) = &self.sdf {
+            sdf.write_hash(&mut hasher);
+        }
+        hasher.write_u8(self.fog.is_some() as u8);
+        if let Some(fog) = &self.fog {
+            fog.write_hash(&mut hasher);
+        }
+        let camera = self.view_camera();
+        let pose = camera.pose();
+        hasher.write_vector(pose.position());
+        for value in [pose.rotation_z(), pose.pitch(), pose.roll(), camera.focal()] {
+            hasher.write_f32(value);
+        }
+        let viewport = self.viewport();
+        hasher.write_u32(viewport.width);
+        hasher.write_u32(viewport.height);
+        hasher.finish()
+    }
 
-            // find the first face of this point (if it exists)
-            let rgba = if let Some(face) = best_face {
-                face.color_at_projection(&best_projection.unwrap()).rgba()
-            } else {
-                [214, 214, 194, 150]
-            };
+    /// Hashes a texture by its registered name, or else by its colors
+    fn texture_hash(&self, texture: &TextureHandle) -> u64 {
+        let mut hasher = ContentHasher::new();
+        match self.textures.name_of(texture) {
+            Some(name) => hasher.write(name.as_bytes()),
+            None => hasher.write_texture(&**texture),
+        }
+        hasher.finish()
+    }
+
+    /// Names a texture, so that the faces using it can be saved in a scene file
+    pub fn register_texture(&mut self, name: &str, texture: &TextureHandle) {
+        self.textures.insert(name, texture);
+    }
+
+    /// Textures which can be used by the scene files, by their name
+    pub fn textures(&self) -> &TextureLibrary {
+        &self.textures
+    }
+
+    pub fn textures_mut(&mut self) -> &mut TextureLibrary {
+        &mut self.textures
+    }
+
+    /// Saves the objects and the pose of the camera to a scene file (see `SceneFile`). All the
+    /// textures must have been registered.
+    pub fn save_scene(&self, path: &str) -> Result<(), String> {
+        let scene = SceneFile::capture(self.camera.pose(), &self.objects, &self.layers, &self.textures)?;
+        std::fs::write(path, scene.to_json()).map_err(|err| err.to_string())
+    }
+
+    /// Replaces the objects and the pose of the camera by those of a scene file. The textures of
+    /// the scene must have been registered.
+    pub fn load_scene(&mut self, path: &str) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let scene = SceneFile::from_json(&text)?;
+        let objects = scene.objects(&mut self.textures)?;
+        self.objects.clear();
+        self.ids.clear();
+        self.in_range.clear();
+        self.bounds.clear();
+        self.layers.clear();
+        self.labels.clear();
+        self.scene_graph.clear();
+        self.grid.clear();
+        self.clear_bsp();
+        for (object, layers) in objects {
+            let id = self.add_object(object);
+            self.set_layers(id, layers);
+        }
+        self.set_camera_pose(scene.camera().clone());
+        Ok(())
+    }
+
+    /// Writes the description of the world (see `describe`) to a JSON file
+    pub fn save_description(&self, path: &str) -> std::io::Result<()> {
+        let description = serde_json::to_string_pretty(&self.describe())?;
+        std::fs::write(path, description)
+    }
+
+    /// Computes how many faces pass each culling stage, and how many pixels they cover, when
+    /// looking from the given pose (see `VisibilityStats`).
+    pub fn visibility_stats(&self, pose: &Pose) -> VisibilityStats {
+        let mut camera = self.camera.clone();
+        camera.set_pose(pose.clone());
+        // The objects of the hidden layers have no face, so that the others keep their index
+        let objects: Vec<(Vec<&CubicFace3>, bool)> = (0..self.objects.len())
+            .map(|i| match self.is_layer_visible(i) {
+                true => (self.objects[i].get_all_faces(), self.in_range[i]),
+                false => (Vec::new(), self.in_range[i]),
+            })
+            .collect();
+        VisibilityStats::compute(&objects, &camera)
+    }
+
+    /// Renders the whole world seen from above (see `Map`), with `scale` pixels per meter
+    pub fn render_map(&self, scale: f32) -> Map {
+        let faces: Vec<&CubicFace3> = (0..self.objects.len())
+            .filter(|i| self.is_layer_visible(*i))
+            .flat_map(|i| self.objects[i].get_all_faces())
+            .collect();
+        Map::render(&faces, scale)
+    }
 
-            pixel.copy_from_slice(&rgba);
+    pub fn bsp(&self) -> &Option<BSPNode> {
+        &self.bsp
+    }
+}
+
+impl Drawable for World {
+    fn draw_painter(&self, drawer: &mut dyn AbstractFrame) {
+        if let Some(tree) = self.active_bsp() {
+            // The tree is in charge of visiting itself and drawing in the proper order.
+            self.draw_bsp(tree, drawer);
+        } else {
+            self.draw_painter_sorted(drawer);
         }
     }
 
+    /// The rows of the frame are traced in parallel, sharing the projected faces.
+    fn draw_raytracing(&self, frame: &mut [u8]) {
+        let sampler = self.raytracing_sampler();
+        frame.par_chunks_exact_mut(4 * self.viewport().width as usize).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                pixel.copy_from_slice(&sampler(x as i16, y as i16));
+            }
+        });
+    }
+
     fn left_mouse_pressed(&mut self, _x: i16, _y: i16) {
         println!("{_x}, {_y}");
     }
 
+    /// The keys trigger the actions they are bound to in the input map of the world
     fn key_pressed(&mut self, key: VirtualKeyCode) {
-        match key {
-            VirtualKeyCode::R => {
-                // Rotate the camera's
-                self.camera.apply_z_rot(std::f32::consts::PI / 16.);
+        if let Some(action) = self.input_map.action(key) {
+            self.action_pressed(action);
+        }
+    }
+
+    fn key_held(&mut self, key: VirtualKeyCode) {
+        if let Some(action) = self.input_map.action(key) {
+            self.action_held(action);
+        }
+    }
+
+    fn action_pressed(&mut self, action: Action) {
+        // While spectating, the keys turn the spectator camera
+        let turn_step = self.turn_step;
+        let camera = self.spectator.as_mut().unwrap_or(&mut self.camera);
+        match action {
+            Action::TurnLeft => camera.apply_z_rot(turn_step),
+            Action::TurnRight => camera.apply_z_rot(-turn_step),
+            Action::LookUp => camera.apply_pitch(turn_step),
+            Action::LookDown => camera.apply_pitch(-turn_step),
+            Action::RollLeft => camera.apply_roll(-turn_step),
+            Action::RollRight => camera.apply_roll(turn_step),
+            Action::ZoomIn => camera.zoom(ZOOM_STEP),
+            Action::ZoomOut => camera.zoom(1. / ZOOM_STEP),
+            // Time control
+            Action::Pause => {
+                self.set_time_scale(if self.is_paused() { 1. } else { 0. });
             }
-            VirtualKeyCode::E => {
-                self.camera.apply_z_rot(-std::f32::consts::PI / 16.);
+            Action::SlowMotion => {
+                self.set_time_scale(if self.time_scale == SLOW_MOTION_TIME_SCALE { 1. } else { SLOW_MOTION_TIME_SCALE });
             }
+            Action::SingleStep => self.request_single_step(),
+            Action::CycleWeather => self.weather.set_kind(self.weather.kind().next()),
+            Action::ToggleWalk => self.set_walking_mode(!self.walking),
+            Action::Jump if self.is_on_ground() => self.motion_model.jump(),
             _ => {}
         }
     }
 
-    fn key_held(&mut self, key: VirtualKeyCode) {
+    fn action_held(&mut self, action: Action) {
+        // The spectator camera flies without inertia nor collisions, and the player stays put
+        if let Some(spectator) = &mut self.spectator {
+            let orientation = spectator.orientation();
+            let direction = match action {
+                Action::MoveForward => orientation,
+                Action::MoveBackward => orientation.opposite(),
+                Action::StrafeRight => orientation.anticlockwise(),
+                Action::StrafeLeft => orientation.clockwise(),
+                Action::MoveDown => UNIT_Z,
+                Action::MoveUp => UNIT_Z.opposite(),
+                _ => return,
+            };
+            spectator.translate(&(direction * SPECTATOR_STEP));
+            return;
+        }
         self.motion_applied = true;
-        match key {
-            VirtualKeyCode::Up => self
+        let acc = tunables().acceleration;
+        match action {
+            Action::MoveForward => self
                 .motion_model
-                .increment_direction(self.camera.orientation(), DEFAULT_ACC),
-            VirtualKeyCode::Down => self
+                .increment_direction(self.camera.orientation(), acc),
+            Action::MoveBackward => self
                 .motion_model
-                .increment_direction(self.camera.orientation().opposite(), DEFAULT_ACC),
-            VirtualKeyCode::Right => self
+                .increment_direction(self.camera.orientation().opposite(), acc),
+            Action::StrafeRight => self
                 .motion_model
-                .increment_direction(self.camera.orientation().anticlockwise(), DEFAULT_ACC),
-            VirtualKeyCode::Left => self
+                .increment_direction(self.camera.orientation().anticlockwise(), acc),
+            Action::StrafeLeft => self
                 .motion_model
-                .increment_direction(self.camera.orientation().clockwise(), DEFAULT_ACC),
-            VirtualKeyCode::J => self.motion_model.apply(2, DEFAULT_ACC),
-            VirtualKeyCode::K => self.motion_model.apply(2, -DEFAULT_ACC),
+                .increment_direction(self.camera.orientation().clockwise(), acc),
+            // When walking, the height is only changed by the gravity and the jumps
+            Action::MoveDown if !self.walking => self.motion_model.apply(2, acc),
+            Action::MoveUp if !self.walking => self.motion_model.apply(2, -acc),
             _ => {}
         }
     }
 
-    /// Update is called at the end of each UI loop, right before rendering the screen
-    /// and calling the `draw` function.
-    fn update(&mut self) {
-        let elapsed = self.last_time.elapsed();
-        self.last_time = Instant::now();
+    /// Moving the mouse to the right turns right, and moving it down looks down
+    fn mouse_moved(&mut self, dx: f32, dy: f32) {
+        let sensitivity = self.mouse_sensitivity;
+        let camera = self.spectator.as_mut().unwrap_or(&mut self.camera);
+        camera.apply_z_rot(-dx * sensitivity);
+        camera.apply_pitch(-dy * sensitivity);
+    }
+
+    /// Turning the wheel away from the user zooms in
+    fn mouse_scrolled(&mut self, delta: f32) {
+        let camera = self.spectator.as_mut().unwrap_or(&mut self.camera);
+        camera.zoom(ZOOM_STEP.powf(delta));
+    }
+
+    /// Update is called at the end of each UI loop, once per simulation step (so possibly
+    /// several times or not at all before rendering the screen and calling the `draw` function).
+    fn update(&mut self, dt: f32) {
+        self.movement_events.clear();
+        self.restore_simulated_position();
+        self.previous_position = *self.camera.pose().position();
+        self.update_in_range();
+        self.update_player_frustum();
+
+        // Scale the time of the simulation
+        let dt = if self.is_paused() && self.single_step_requested {
+            SINGLE_STEP_DT
+        } else {
+            dt * self.time_scale
+        };
+        self.single_step_requested = false;
+        if dt == 0. {
+            // The simulation is paused: nothing must move, but the camera can still turn
+            self.motion_applied = false;
+            self.face_billboards();
+            return;
+        }
 
         // Obstacle detection
+        self.update_grid();
+        self.tick_objects(dt);
+        self.update_entities(dt);
 
         // If no key was pressed, slow down the motion
         if !self.motion_applied {
             self.motion_model.slow_down();
         }
 
-        // Update the camera position using the motion model
-        self.camera.set_position(
-            self.motion_model
-                .new_pos(self.camera.pose().position(), elapsed.as_secs_f32()),
-        );
+        // Update the camera position using the motion model, unless it moves into an object
+        let previous = *self.camera.pose().position();
+        if self.walking {
+            self.motion_model.apply_gravity(dt);
+        }
+        let mut position = self.motion_model.new_pos(&previous, dt);
+        if self.walking {
+            // The fall stops on the ground
+            if let Some(ground) = self.distance_to_ground(&previous) {
+                let standing = previous.z() + ground - EYE_HEIGHT;
+                if position.z() >= standing {
+                    position = Vector3::new(position.x(), position.y(), standing);
+                    self.motion_model.land();
+                }
+            }
+        }
+        // The objects do not stop the camera, but moving into one of them is reported
+        if self.is_free(&previous) && !self.is_free(&position) {
+            self.movement_events.push(MovementEvent::BumpedWall);
+        }
+        self.camera.set_position(position);
+
+        // Footsteps on the ground below the camera
+        let position = *self.camera.pose().position();
+        let moved = Vector3::new(position.x() - previous.x(), position.y() - previous.y(), 0.).norm();
+        let ground = self
+            .ground_below(&position)
+            .filter(|(distance, _)| *distance <= EYE_HEIGHT + GROUND_TOLERANCE)
+            .map(|(_, face)| face.texture().clone());
+        if let Some(event) = self.footsteps.update(moved, ground) {
+            self.movement_events.push(event);
+        }
+
+        self.weather.update(dt, self.camera.pose().position(), &mut self.rng);
+        self.face_billboards();
 
         // reset the temporary variable
         self.motion_applied = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use winit::event::VirtualKeyCode;
+
+    use crate::bsp::tree::BspStats;
+    use crate::compare::{diff_images, render_offscreen, render_to_buffer, RenderMethod, DEFAULT_TOLERANCE, MAX_MISMATCH_PERCENTAGE};
+    use crate::drawable::Drawable;
+    use crate::fog::Fog;
+    use crate::frame::Frame;
+    use crate::labels::Label;
+    use crate::layers::Layers;
+    use crate::motion_model::DEFAULT_ACC;
+    use crate::movement::MovementEvent;
+    use crate::primitives::aabb::Aabb;
+    use crate::primitives::billboard::Billboard;
+    use crate::primitives::camera::Camera;
+    use crate::primitives::color::Color;
+    use crate::primitives::cube::Cube3;
+    use crate::primitives::cubic_face3::CubicFace3;
+    use crate::primitives::object::{faces_ray_intersection, ObjectId};
+    use crate::primitives::position::Pose;
+    use crate::primitives::ray::Ray;
+    use crate::primitives::snapping::Grid;
+    use crate::primitives::sphere::Sphere;
+    use crate::primitives::textures::colored::{ColoredTexture, ORANGE, YELLOW};
+    use crate::primitives::textures::TextureHandle;
+    use crate::primitives::transformation::Transform;
+    use crate::primitives::vector::{Vector3, UNIT_Z};
+    use crate::profiler::{FrameProfiler, Stage};
+    use crate::sdf::SdfSphere;
+    use crate::viewport::{Rect, Viewport};
+    use crate::worlds::{World, PLAYER_FRUSTUM, SINGLE_STEP_DT};
+
+    fn world_with_cube() -> World {
+        let mut world = World::new(Camera::default());
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(0, 0, 0), &YELLOW, &YELLOW));
+        world
+    }
+
+    #[test]
+    fn test_teleport_to_free_position() {
+        let mut world = world_with_cube();
+        let target = Vector3::new(3., 3., -1.);
+        assert_eq!(Some(target), world.teleport_camera(target));
+        assert_eq!(target, *world.camera().pose().position());
+    }
+
+    #[test]
+    fn test_teleport_inside_cube() {
+        let mut world = world_with_cube();
+        let target = Vector3::new(0.5, 0.5, 0.5);
+        assert!(!world.is_free(&target));
+
+        let position = world.teleport_camera(target).unwrap();
+        assert!(world.is_free(&position));
+        // The closest free spot is right outside of the cube
+        assert!(position.line_to(&target).norm() <= 1.);
+    }
+
+    #[test]
+    fn test_teleport_in_walking_mode() {
+        let mut world = world_with_cube();
+        world.set_walking_mode(true);
+
+        // The camera is placed above the cube
+        let position = world.teleport_camera(Vector3::new(0.5, 0.5, -5.)).unwrap();
+        assert!(f32::abs(position.z() - (-1.5)) < 0.001);
+
+        // And it respawns on the ground as well
+        world.set_spawn_point(Vector3::new(0.5, 0.5, -3.), 0.);
+        let position = world.respawn().unwrap();
+        assert!(f32::abs(position.z() - (-1.5)) < 0.001);
+    }
+
+    #[test]
+    fn test_objects_near() {
+        let mut world = world_with_cube();
+        let far = world.add_cube(Cube3::minecraft_like(Vector3::newi(10, 0, 0), &YELLOW, &YELLOW));
+        assert_eq!(vec![far], world.objects_near(&Vector3::new(10.5, 0.5, 0.5), 0.1));
+        assert_eq!(vec![ObjectId::new(0)], world.objects_near(&Vector3::new(1.1, 0.5, 0.5), 0.2));
+        assert!(world.objects_near(&Vector3::new(5., 0.5, 0.5), 1.).is_empty());
+    }
+
+    #[test]
+    fn test_pick() {
+        let mut world = world_with_cube();
+        let far = world.add_cube(Cube3::minecraft_like(Vector3::newi(0, 3, 0), &YELLOW, &YELLOW));
+        let near = ObjectId::new(0);
+        let origin = Vector3::new(0.5, -2., 0.5);
+        assert_eq!(Some(near), world.pick(&Ray::new(origin, Vector3::new(0., 1., 0.)), 10.));
+        assert_eq!(None, world.pick(&Ray::new(origin, Vector3::new(0., 1., 0.)), 1.));
+        assert_eq!(None, world.pick(&Ray::new(origin, Vector3::new(0., -1., 0.)), 10.));
+        world.remove(near);
+        assert_eq!(Some(far), world.pick(&Ray::new(origin, Vector3::new(0., 1., 0.)), 10.));
+    }
+
+    #[test]
+    fn test_raycast() {
+        let mut world = world_with_cube();
+        let wall = world.add_cube(Cube3::minecraft_like(Vector3::newi(3, 0, 0), &ORANGE, &ORANGE));
+        let hit = world.raycast(Vector3::new(0.5, 0.25, 0.5), Vector3::new(2., 0., 0.), 10.).unwrap();
+        // The ray starts inside the first cube, and leaves it through its side
+        assert_eq!(ObjectId::new(0), hit.object);
+        assert!((hit.distance - 0.5).abs() < 1e-5);
+        assert_eq!(Vector3::new(1., 0., 0.), *hit.face.normal());
+        assert_eq!((0.75, 0.5), hit.uv);
+
+        let hit = world.raycast(Vector3::new(1.5, 0.25, 0.5), Vector3::new(1., 0., 0.), 10.).unwrap();
+        assert_eq!(wall, hit.object);
+        assert!((hit.point - Vector3::new(3., 0.25, 0.5)).norm() < 1e-5);
+        assert!(world.raycast(Vector3::new(1.5, 0.25, 0.5), Vector3::new(1., 0., 0.), 1.).is_none());
+        assert!(world.raycast(Vector3::new(1.5, 0.25, 0.5), Vector3::new(-1., 0., 0.), 0.1).is_none());
+
+        // The hidden layers are ignored, as when picking
+        world.set_layers(wall, Layers::DEBUG);
+        world.set_visible_layers(Layers::GAMEPLAY);
+        assert!(world.raycast(Vector3::new(1.5, 0.25, 0.5), Vector3::new(1., 0., 0.), 10.).is_none());
+        world.set_visible_layers(Layers::GAMEPLAY.with(Layers::DEBUG));
+        assert_eq!(wall, world.raycast(Vector3::new(1.5, 0.25, 0.5), Vector3::new(1., 0., 0.), 10.).unwrap().object);
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        // A wall between x = 2 and x = 3, from z = -2 (above the ground) to z = 1
+        let mut world = World::new(Camera::default());
+        let wall: Vec<ObjectId> = (-2..=0).map(|z| world.add_cube(Cube3::minecraft_like(Vector3::newi(2, 0, z), &YELLOW, &YELLOW))).collect();
+        let (a, b) = (Vector3::new(0., 0.5, 0.5), Vector3::new(5., 0.5, 0.5));
+        assert!(!world.has_line_of_sight(&a, &b));
+        assert!(world.has_line_of_sight(&a, &Vector3::new(0., 3., 0.5)));
+        // Over the wall, and up to its surface
+        assert!(world.has_line_of_sight(&Vector3::new(0., 0.5, -3.), &Vector3::new(5., 0.5, -3.)));
+        assert!(world.has_line_of_sight(&a, &Vector3::new(2., 0.5, 0.5)));
+
+        // A cube behind the wall is hidden, and the wall is seen
+        let hidden = world.add_cube(Cube3::minecraft_like(Vector3::newi(5, 0, 0), &ORANGE, &ORANGE));
+        let seen = world.add_cube(Cube3::minecraft_like(Vector3::newi(5, 3, 0), &ORANGE, &ORANGE));
+        assert_eq!(vec![seen, wall[2]], world.visible_from(&a, &[hidden, seen, wall[2], ObjectId::new(42)]));
+        // The debug objects do not block the sight
+        world.set_layers(wall[2], Layers::DEBUG);
+        assert_eq!(vec![hidden], world.visible_from(&a, &[hidden]));
+
+        // Moving a part of the wall out of the way opens the sight
+        world.set_layers(wall[2], Layers::GAMEPLAY);
+        assert!(!world.has_line_of_sight(&a, &b));
+        world.get_mut(wall[2]).unwrap().transform(&Transform::translation(Vector3::new(0., 5., 0.)));
+        assert!(world.has_line_of_sight(&a, &b));
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut world = world_with_cube();
+        world.set_camera_position(Vector3::new(0.5, -4., -1.));
+        world.set_camera_rotation(-std::f32::consts::PI / 2.);
+        let cube = world.objects().next().unwrap().0;
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(5, 0, 0), &ORANGE, &ORANGE));
+        let is_drawn = |world: &World| {
+            let mut buffer = vec![0; world.viewport().buffer_len()];
+            world.draw_raytracing(&mut buffer);
+            buffer.chunks_exact(4).any(|pixel| pixel == YELLOW.color_at(0., 0.).rgba())
+        };
+        let origin = Vector3::new(0.5, -2., 0.5);
+        assert!(is_drawn(&world));
+
+        // A debug object is hidden during the game, and can not be picked
+        assert!(world.set_layers(cube, Layers::DEBUG));
+        world.compute_bsp();
+        assert_eq!(6, world.bsp().as_ref().unwrap().stats().faces);
+        assert!(!is_drawn(&world));
+        assert_eq!(None, world.pick(&Ray::new(origin, Vector3::new(0., 1., 0.)), 10.));
+
+        // Until the debug layer is shown
+        world.set_visible_layers(world.visible_layers().with(Layers::DEBUG));
+        assert!(world.bsp().is_none());
+        assert!(is_drawn(&world));
+        assert_eq!(Some(cube), world.pick(&Ray::new(origin, Vector3::new(0., 1., 0.)), 10.));
+        assert!(!world.set_layers(ObjectId::new(42), Layers::DEBUG));
+
+        // Only the gameplay objects are solid
+        assert!(world.is_free(&Vector3::new(0.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn test_labels() {
+        let mut world = world_with_cube();
+        let cube = world.objects().next().unwrap().0;
+        let far = world.add_cube(Cube3::minecraft_like(Vector3::newi(4, 0, 0), &YELLOW, &YELLOW));
+        world.set_camera_pose(Pose::new(Vector3::new(-3., 0.5, 0.5), 0.));
+        assert!(world.set_label(far, Label::new("FAR", [255, 255, 255, 255])));
+        assert!(!world.set_label(ObjectId::new(42), Label::new("NONE", [255, 255, 255, 255])));
+
+        // Brightest red value of the labels
+        let brightest = |world: &World| {
+            let viewport = world.viewport();
+            let mut buffer = vec![0; viewport.buffer_len()];
+            world.draw_labels(&mut Frame::new(&mut buffer, viewport));
+            buffer.chunks(4).map(|pixel| pixel[0]).max().unwrap()
+        };
+        // The label of the far cube is hidden by the first cube, unlike the label of the first cube
+        let occluded = brightest(&world);
+        assert!(occluded > 0 && occluded < 255);
+        world.set_label(cube, Label::new("NEAR", [255, 255, 255, 255]));
+        assert_eq!(255, brightest(&world));
+
+        // The labels are removed with their object, which does not hide the far label anymore
+        world.remove(cube);
+        assert!(world.label(cube).is_none());
+        assert_eq!(255, brightest(&world));
+        // Seen from below, the line towards the label goes through the labelled cube
+        world.set_camera_pose(Pose::new(Vector3::new(1.5, 0.5, 1.5), 0.));
+        assert!(faces_ray_intersection(&world.objects[0].get_all_faces(), &Ray::between(Vector3::new(1.5, 0.5, 1.5), Vector3::new(4.5, 0.5, -0.2))).is_some());
+        assert_eq!(255, brightest(&world));
+        assert_eq!("FAR", world.remove_label(far).unwrap().text);
+        assert!(world.label(far).is_none());
+        assert_eq!(0, brightest(&world));
+    }
+
+    #[test]
+    fn test_transparent_faces() {
+        let mut world = World::new(Camera::default());
+        let glass = TextureHandle::new(ColoredTexture::new(Color::new(0, 0, 255, 51)));
+        // Added first, so that drawing the objects in order would hide the glass
+        world.add_cube(Cube3::from_aabb(Aabb::new(Vector3::new(5., -1., -1.), Vector3::new(6., 1., 1.)), &YELLOW, &YELLOW));
+        world.add_cube(Cube3::from_aabb(Aabb::new(Vector3::new(2., -0.5, -0.5), Vector3::new(3., 0.5, 0.5)), &glass, &glass));
+        let viewport = world.viewport();
+        let (px, py) = world.camera().optical_center();
+        let center = 4 * viewport.index(px as u32, py as u32);
+
+        // The yellow cube is seen through the glass, sorting the faces or using the BSP
+        let mut buffer = vec![0; viewport.buffer_len()];
+        world.draw_painter(&mut Frame::new(&mut buffer, viewport));
+        assert_eq!([204, 204, 51], buffer[center..center + 3]);
+        world.compute_bsp();
+        let mut buffer = vec![0; viewport.buffer_len()];
+        world.draw_painter(&mut Frame::new(&mut buffer, viewport));
+        assert_eq!([204, 204, 51], buffer[center..center + 3]);
+    }
+
+    #[test]
+    fn test_raytrace_sphere() {
+        let mut world = World::new(Camera::default());
+        let red = TextureHandle::new(ColoredTexture::new(Color::new(255, 0, 0, 255)));
+        world.add_cube(Cube3::from_aabb(Aabb::new(Vector3::new(5., -1., -1.), Vector3::new(6., 1., 1.)), &YELLOW, &YELLOW));
+        let viewport = world.viewport();
+        let (px, py) = world.camera().optical_center();
+        let center = 4 * viewport.index(px as u32, py as u32);
+        let draw = |world: &World| {
+            let mut buffer = vec![0; viewport.buffer_len()];
+            world.draw_raytracing(&mut buffer);
+            buffer[center..center + 4].to_vec()
+        };
+
+        // Behind the cube, the sphere is hidden
+        world.add_raytrace_object(Box::new(Sphere::new(Vector3::new(8., 0., 0.), 0.5).with_texture(&red)));
+        assert_eq!(YELLOW.color_at(0., 0.).rgba(), draw(&world)[..]);
+        world.add_raytrace_object(Box::new(Sphere::new(Vector3::new(3., 0., 0.), 0.5).with_texture(&red)));
+        assert_eq!([255, 0, 0, 255], draw(&world)[..]);
+    }
+
+    #[test]
+    fn test_scene_graph() {
+        let mut world = World::new(Camera::default());
+        let wall = world.add_cube(Cube3::from_aabb(Aabb::new(Vector3::new(0., 0., -2.), Vector3::new(0.2, 3., 0.)), &YELLOW, &YELLOW));
+        let door = world.add_cube(Cube3::from_aabb(Aabb::new(Vector3::new(0., 3., -2.), Vector3::new(0.1, 4., 0.)), &ORANGE, &ORANGE));
+        assert!(world.set_parent(door, Some(ObjectId::new(42))).is_err());
+        world.set_parent(door, Some(wall)).unwrap();
+        let center = |world: &World, id| world.get(id).unwrap().bounding_sphere().0;
+
+        // The door opens around its hinge, then moves with the wall
+        let hinge = Vector3::new(0., 3., 0.);
+        assert!(world.set_local_transform(door, Transform::rotation_around(&UNIT_Z, -std::f32::consts::FRAC_PI_2, &hinge)));
+        assert!((center(&world, door) - Vector3::new(0.5, 2.95, -1.)).norm() < 1e-4);
+        assert!(world.set_local_transform(wall, Transform::translation(Vector3::new(2., 0., 0.))));
+        assert!((center(&world, door) - Vector3::new(2.5, 2.95, -1.)).norm() < 1e-4);
+        assert!((center(&world, wall) - Vector3::new(2.1, 1.5, -1.)).norm() < 1e-4);
+        assert!(!world.set_local_transform(ObjectId::new(42), Transform::identity()));
+    }
+
+    #[test]
+    fn test_remove_objects() {
+        let mut world = world_with_cube();
+        let first = world.objects().next().unwrap().0;
+        let second = world.add_cube(Cube3::minecraft_like(Vector3::newi(10, 0, 0), &YELLOW, &YELLOW));
+        let third = world.add_face(CubicFace3::vface_from_line(Vector3::newi2(20, 0), Vector3::newi2(21, 0)));
+        world.compute_bsp();
+
+        // The other objects keep their identifier
+        assert!(world.remove(first).is_some());
+        assert!(world.remove(first).is_none());
+        assert!(world.get(first).is_none());
+        assert_eq!(vec![second, third], world.objects().map(|(id, _)| id).collect::<Vec<_>>());
+        assert_eq!(vec![second], world.objects_near(&Vector3::new(10.5, 0.5, 0.5), 0.1));
+        assert!(world.objects_near(&Vector3::new(0.5, 0.5, 0.5), 0.1).is_empty());
+        // The faces of the removed cube are taken out of the BSP, without rebuilding it
+        assert_eq!(7, world.bsp().as_ref().unwrap().stats().faces);
+
+        // The objects can be modified
+        world.get_mut(third).unwrap().snap(&Grid::new(10.));
+        assert_eq!(20., world.get(third).unwrap().get_all_faces()[0].points()[0].x());
+        assert_eq!(6, world.get(second).unwrap().get_all_faces().len());
+    }
+
+    #[test]
+    fn test_incremental_bsp() {
+        let mut world = World::new(Camera::default());
+        for i in 1..6 {
+            for j in -2..3 {
+                world.add_cube(Cube3::minecraft_like(Vector3::newi(i, j, 1), &YELLOW, &YELLOW));
+            }
+        }
+        world.set_camera_position(Vector3::new(-1., 0.5, -1.));
+        world.set_use_bsp(true);
+        let faces = world.bsp().as_ref().unwrap().stats().faces;
+
+        // The BSP is updated when a cube crossing the planes of the floor is added and removed
+        let cube = world.add_cube(Cube3::minecraft_like(Vector3::new(2.5, -0.5, 0.5), &ORANGE, &ORANGE));
+        assert!(world.bsp().as_ref().unwrap().stats().faces > faces);
+        let with_cube = render_offscreen(&mut world, RenderMethod::Bsp);
+        let raytraced = render_offscreen(&mut world, RenderMethod::Raytracer);
+        assert_eq!(0., diff_images(&with_cube, &raytraced, DEFAULT_TOLERANCE).mismatch_percentage());
+
+        world.remove(cube);
+        assert!(world.bsp().is_some());
+        let without_cube = render_offscreen(&mut world, RenderMethod::Bsp);
+        let raytraced = render_offscreen(&mut world, RenderMethod::Raytracer);
+        assert_eq!(0., diff_images(&without_cube, &raytraced, DEFAULT_TOLERANCE).mismatch_percentage());
+        assert!(diff_images(&with_cube, &without_cube, DEFAULT_TOLERANCE).mismatch_percentage() > 1.);
+    }
+
+    #[test]
+    fn test_moving_objects_do_not_grow_bsp() {
+        let mut world = World::new(Camera::default());
+        for i in 1..6 {
+            for j in -2..3 {
+                world.add_cube(Cube3::minecraft_like(Vector3::newi(i, j, 1), &YELLOW, &YELLOW));
+            }
+        }
+        let cube = world.add_cube(Cube3::minecraft_like(Vector3::new(2.5, -0.5, 0.5), &ORANGE, &ORANGE));
+        world.set_camera_position(Vector3::new(-1., 0.5, -1.));
+        world.set_use_bsp(true);
+        let built = world.bsp().as_ref().unwrap().stats();
+
+        // A cube turning on itself through the planes of its neighbours
+        for step in 1..=300 {
+            let angle = step as f32 * 0.1;
+            world.set_local_transform(cube, Transform::rotation_around(&UNIT_Z, angle, &Vector3::new(3., 0., 1.)));
+            let stats = world.bsp().as_ref().unwrap().stats();
+            assert!(stats.nodes <= 3 * built.nodes && stats.depth <= 3 * built.depth, "{stats:?} vs {built:?}");
+        }
+        let drawn = render_offscreen(&mut world, RenderMethod::Bsp);
+        let raytraced = render_offscreen(&mut world, RenderMethod::Raytracer);
+        assert!(diff_images(&drawn, &raytraced, DEFAULT_TOLERANCE).mismatch_percentage() <= MAX_MISMATCH_PERCENTAGE);
+    }
+
+    #[test]
+    fn test_billboards_face_camera() {
+        let mut world = world_with_cube();
+        let id = world.add_billboard(Billboard::new(Vector3::new(3., 0.5, 1.), 1., 1.5, &ORANGE));
+        world.set_use_bsp(true);
+        world.set_camera_position(Vector3::new(3., 4., -1.));
+        world.update(0.01);
+        let face = world.get(id).unwrap().get_all_faces()[0].clone();
+        assert_eq!(Vector3::new(0., 1., 0.), *face.normal());
+        // The BSP is kept: the turned face of the billboard replaces the old one, whose node was pruned
+        assert_eq!(BspStats { nodes: 3, leaves: 1, convex_leaves: 1, faces: 7, depth: 2 }, world.bsp().as_ref().unwrap().stats());
+        let drawn = render_offscreen(&mut world, RenderMethod::Bsp);
+        let raytraced = render_offscreen(&mut world, RenderMethod::Raytracer);
+        assert!(diff_images(&drawn, &raytraced, DEFAULT_TOLERANCE).mismatch_percentage() <= MAX_MISMATCH_PERCENTAGE);
+        assert_eq!(1, world.get(id).unwrap().get_visible_faces(world.camera()).len());
+    }
+
+    #[test]
+    fn test_split_screen() {
+        let mut world = world_with_cube();
+        world.set_camera_position(Vector3::new(-2., 0.5, 0.5));
+        let player = world.camera().clone();
+        let mut behind = player.clone();
+        behind.set_rotation(std::f32::consts::PI);
+        let index = world.add_camera(behind.clone());
+        let viewport = Viewport::new(320, 100);
+        let mut buffer = vec![0; viewport.buffer_len()];
+        let mut frame = Frame::new(&mut buffer, viewport);
+        world.draw_painter_with(&player, Rect::new(0, 0, 160, 100), &mut frame);
+        world.draw_painter_with(&world.cameras()[index], Rect::new(160, 0, 160, 100), &mut frame);
+
+        // The left half is the view of the player, and the right half only shows the sky
+        let player = render_to_buffer(&mut world, &player, 160, 100, RenderMethod::Painter);
+        let sky = render_to_buffer(&mut world, &behind, 160, 100, RenderMethod::Painter);
+        assert_ne!(player, sky);
+        for y in 0..100 {
+            let row = |x: usize| &buffer[4 * (y * 320 + x)..4 * (y * 320 + x + 160)];
+            assert_eq!(&player[4 * y * 160..4 * (y + 1) * 160], row(0));
+            assert_eq!(&sky[4 * y * 160..4 * (y + 1) * 160], row(160));
+        }
+    }
+
+    #[test]
+    fn test_split_screen_draw_distance() {
+        let mut world = world_with_cube();
+        // The player is too far to see the cube, but the other camera is close to it
+        world.set_camera_position(Vector3::new(-20., 0.5, 0.5));
+        world.set_draw_distance(Some(5.));
+        let mut near = world.camera().clone();
+        near.set_position(Vector3::new(-2., 0.5, 0.5));
+        let viewport = Viewport::new(320, 100);
+        let mut buffer = vec![0; viewport.buffer_len()];
+        let mut frame = Frame::new(&mut buffer, viewport);
+        world.draw_painter_with(&near, Rect::new(0, 0, 160, 100), &mut frame);
+        world.draw_painter_with(&world.camera().clone(), Rect::new(160, 0, 160, 100), &mut frame);
+
+        let near = render_to_buffer(&mut world, &near, 160, 100, RenderMethod::Painter);
+        for y in 0..100 {
+            let row = |x: usize| &buffer[4 * (y * 320 + x)..4 * (y * 320 + x + 160)];
+            assert_eq!(&near[4 * y * 160..4 * (y + 1) * 160], row(0));
+        }
+        assert!((0..100).any(|y| buffer[4 * (y * 320)..4 * (y * 320 + 160)] != buffer[4 * (y * 320 + 160)..4 * (y * 320 + 320)]));
+    }
+
+    #[test]
+    fn test_draw_painter_profiled() {
+        let mut world = world_with_cube();
+        world.set_camera_position(Vector3::new(0.5, -4., -1.));
+        world.set_camera_rotation(-std::f32::consts::PI / 2.);
+        let mut profiler = FrameProfiler::new(10);
+        let viewport = world.viewport();
+        let mut buffer = vec![0; viewport.buffer_len()];
+
+        world.draw_painter_profiled(&mut Frame::new(&mut buffer, viewport), &mut profiler);
+        world.compute_bsp();
+        world.draw_painter_profiled(&mut Frame::new(&mut buffer, viewport), &mut profiler);
+        profiler.end_frame();
+
+        let timings = profiler.history().next().unwrap();
+        assert!(timings[Stage::Raster as usize] > timings[Stage::Present as usize]);
+        assert!(buffer.iter().any(|c| *c != 0));
+    }
+
+    #[test]
+    fn test_describe() {
+        let mut world = world_with_cube();
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(3, 0, 0), &YELLOW, &YELLOW));
+        world.compute_bsp();
+
+        let description = world.describe();
+        assert_eq!(2, description["objects"].as_array().unwrap().len());
+        assert_eq!(6, description["objects"][0]["faces"].as_array().unwrap().len());
+        // All the faces share the same texture
+        assert_eq!(1, description["textures"].as_array().unwrap().len());
+        assert_eq!(0, description["objects"][1]["faces"][3]["texture"]);
+        assert_eq!(12, description["bsp"]["faces"]);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let hash = world_with_cube().content_hash();
+        assert_eq!(hash, world_with_cube().content_hash());
+
+        // Any change of the geometry, of the textures or of the camera changes the hash
+        let mut world = world_with_cube();
+        world.set_camera_rotation(0.1);
+        assert_ne!(hash, world.content_hash());
+        let mut world = world_with_cube();
+        world.add_face(CubicFace3::vface_from_line(Vector3::new(3., 0., 0.), Vector3::new(3., 1., 0.)));
+        assert_ne!(hash, world.content_hash());
+        let mut world = World::new(Camera::default());
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(0, 0, 0), &YELLOW, &ORANGE));
+        assert_ne!(hash, world.content_hash());
+
+        // The objects which are not seen do not change the hash
+        let mut world = world_with_cube();
+        let hidden = world.add_cube(Cube3::minecraft_like(Vector3::newi(3, 0, 0), &YELLOW, &YELLOW));
+        world.set_layers(hidden, Layers::DEBUG);
+        assert_eq!(hash, world.content_hash());
+
+        // The textures are hashed by their colors, not by their handle
+        let mut world = World::new(Camera::default());
+        let yellow = TextureHandle::new(ColoredTexture::new(Color::yellow()));
+        world.add_cube(Cube3::minecraft_like(Vector3::newi(0, 0, 0), &yellow, &yellow));
+        assert_eq!(hash, world.content_hash());
+        world.register_texture("yellow", &yellow);
+        assert_ne!(hash, world.content_hash());
+
+        // The labels, the raytraced shapes and the fog change the hash
+        let mut world = world_with_cube();
+        let id = world.objects().next().unwrap().0;
+        world.set_label(id, Label::new("cube", [255; 4]));
+        assert_ne!(hash, world.content_hash());
+        let mut world = world_with_cube();
+        world.add_raytrace_object(Box::new(Sphere::new(Vector3::new(3., 0., 0.), 1.)));
+        assert_ne!(hash, world.content_hash());
+        let mut world = world_with_cube();
+        world.set_sdf(Box::new(SdfSphere::new(Vector3::new(3., 0., 0.), 1., Color::yellow())));
+        assert_ne!(hash, world.content_hash());
+        let mut world = world_with_cube();
+        world.set_fog(Some(Fog::new(&Color::white(), 1., 10.)));
+        assert_ne!(hash, world.content_hash());
+    }
+
+    #[test]
+    fn test_pause_and_single_step() {
+        let mut world = world_with_cube();
+        let start = Vector3::new(3., 3., -1.);
+        world.set_camera_position(start);
+
+        // While paused, the camera does not move
+        world.set_time_scale(0.);
+        world.key_held(VirtualKeyCode::Up);
+        world.update(0.1);
+        assert_eq!(start, *world.camera().pose().position());
+
+        // A single step moves the camera by exactly one fixed time step
+        world.request_single_step();
+        world.key_held(VirtualKeyCode::Up);
+        world.update(0.1);
+        let moved = start.line_to(world.camera().pose().position()).norm();
+        assert!(f32::abs(moved - 2. * DEFAULT_ACC * SINGLE_STEP_DT * SINGLE_STEP_DT) < 0.0001);
+
+        // And the world is still paused afterwards
+        let position = *world.camera().pose().position();
+        world.update(0.1);
+        assert_eq!(position, *world.camera().pose().position());
+    }
+
+    #[test]
+    fn test_interpolate_camera() {
+        let mut world = world_with_cube();
+        let start = Vector3::new(3., 3., -1.);
+        world.set_camera_position(start);
+        world.key_held(VirtualKeyCode::Up);
+        world.update(0.1);
+        let end = *world.camera().pose().position();
+        assert_ne!(start, end);
+
+        // The camera is drawn between both positions, without changing the simulation
+        world.interpolate_camera(0.25);
+        let drawn = *world.camera().pose().position();
+        assert!((start.line_to(&drawn).norm() - 0.25 * start.line_to(&end).norm()).abs() < 1e-4);
+        world.interpolate_camera(1.);
+        assert_eq!(end, *world.camera().pose().position());
+        world.interpolate_camera(0.5);
+        world.set_time_scale(0.);
+        world.update(0.1);
+        assert_eq!(end, *world.camera().pose().position());
+
+        // Teleporting is not interpolated
+        world.set_camera_position(start);
+        world.interpolate_camera(0.5);
+        assert_eq!(start, *world.camera().pose().position());
+    }
+
+    #[test]
+    fn test_gravity_and_jump() {
+        let mut world = world_with_cube();
+        world.set_walking_mode(true);
+        world.set_camera_position(Vector3::new(0.5, 0.5, -4.));
+        let simulate = |world: &mut World, seconds: f32| {
+            for _ in 0..(seconds * 60.) as usize {
+                world.update(1. / 60.);
+            }
+        };
+
+        // The camera falls onto the cube, and stays there
+        assert!(!world.is_on_ground());
+        simulate(&mut world, 0.2);
+        assert!(world.camera().pose().position().z() > -4.);
+        simulate(&mut world, 2.);
+        assert!(world.is_on_ground());
+        assert!(f32::abs(world.camera().pose().position().z() - (-1.5)) < 0.001);
+
+        // A jump lifts it for a while, and cannot be repeated in the air
+        world.key_pressed(VirtualKeyCode::Space);
+        simulate(&mut world, 0.25);
+        let height = world.camera().pose().position().z();
+        assert!(height < -2.3);
+        world.key_pressed(VirtualKeyCode::Space);
+        world.key_held(VirtualKeyCode::K);
+        simulate(&mut world, 0.25);
+        assert!(world.camera().pose().position().z() > height - 0.5);
+        simulate(&mut world, 2.);
+        assert!(f32::abs(world.camera().pose().position().z() - (-1.5)) < 0.001);
+    }
+
+    #[test]
+    fn test_bump_into_wall() {
+        let mut world = world_with_cube();
+        let start = Vector3::new(1.3, 0.5, 0.5);
+        world.set_camera_position(start);
+        world.set_camera_rotation(std::f32::consts::PI);
+
+        // Moving into the cube is reported once, at the step where the camera reaches it
+        let mut bumps = 0;
+        for _ in 0..10 {
+            world.key_held(VirtualKeyCode::Up);
+            world.update(0.1);
+            bumps += world.movement_events().iter().filter(|event| matches!(event, MovementEvent::BumpedWall)).count();
+        }
+        assert_eq!(1, bumps);
+        assert!(world.camera().pose().position().x() < start.x());
+    }
+
+    #[test]
+    fn test_draw_distance_hysteresis() {
+        let mut world = world_with_cube();
+        world.set_camera_rotation(-std::f32::consts::PI / 2.);
+        world.set_draw_distance(Some(5.));
+        let mut is_drawn_from = |y: f32| {
+            world.set_camera_position(Vector3::new(0.5, y, 0.5));
+            world.update(0.);
+            !world.projected_faces().is_empty()
+        };
+
+        assert!(is_drawn_from(-5.2));
+        assert!(!is_drawn_from(-6.));
+        // Coming back slightly within the draw distance is not enough to show the cube again
+        assert!(!is_drawn_from(-5.2));
+        assert!(is_drawn_from(-4.5));
+    }
+
+    #[test]
+    fn test_mouse_look() {
+        let mut world = World::new(Camera::default());
+        world.set_mouse_sensitivity(0.01);
+        // Moving the mouse right turns as the "turn right" key, and moving it down looks down
+        world.mouse_moved(10., 20.);
+        assert!((world.camera().pose().rotation_z() + 0.1).abs() < 1e-6);
+        assert!((world.camera().pose().pitch() + 0.2).abs() < 1e-6);
+        // The pitch is limited to looking straight down
+        world.mouse_moved(0., 1000.);
+        assert!((world.camera().pose().pitch() + std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+
+        // The wheel and the zoom keys narrow the field of view
+        let fov = world.camera().fov();
+        world.mouse_scrolled(2.);
+        assert!(world.camera().fov() < fov);
+        world.key_pressed(VirtualKeyCode::Minus);
+        world.key_pressed(VirtualKeyCode::Minus);
+        assert!((world.camera().fov() - fov).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_spectator() {
+        let mut world = world_with_cube();
+        world.set_camera_rotation(-std::f32::consts::PI / 2.);
+        world.set_camera_position(Vector3::new(0.5, -3., 0.5));
+        world.set_draw_distance(Some(5.));
+        world.set_spectating(true);
+
+        // The spectator flies away, while the player does not move
+        for _ in 0..30 {
+            world.key_held(VirtualKeyCode::Down);
+            world.update(0.1);
+        }
+        assert!((world.view_camera().pose().position().y() + 6.).abs() < 1e-3);
+        assert_eq!(Vector3::new(0.5, -3., 0.5), *world.camera().pose().position());
+        // The cube is beyond the draw distance of the spectator, but it is culled from the player
+        assert!(!world.projected_faces().is_empty());
+
+        // The frustum of the player is shown while spectating
+        assert!(world.debug_paths().iter().any(|path| path.name() == PLAYER_FRUSTUM));
+        world.set_spectating(false);
+        assert!(!world.is_spectating());
+        assert!(world.debug_paths().iter().all(|path| path.name() != PLAYER_FRUSTUM));
+        assert_eq!(world.camera().pose().position(), world.view_camera().pose().position());
+    }
+
+    #[test]
+    fn test_visibility_stats_of_visible_layers() {
+        let mut world = world_with_cube();
+        world.set_viewport(Viewport::new(400, 100));
+        world.set_camera_rotation(-std::f32::consts::PI / 2.);
+        world.set_camera_position(Vector3::new(0.5, -3., 0.5));
+        let pose = world.camera().pose().clone();
+        let expected = world.visibility_stats(&pose).summary();
+        assert!(expected.ends_with("1 visible"), "{expected}");
+
+        // A marker of the editor between the camera and the cube hides nothing
+        let marker = world.add_cube(Cube3::minecraft_like(Vector3::newi(0, -2, 0), &ORANGE, &ORANGE));
+        world.set_layers(marker, Layers::EDITOR);
+        assert_eq!(expected, world.visibility_stats(&pose).summary());
+        assert_eq!(world_with_cube().render_map(10.).size(), world.render_map(10.).size());
+    }
+}